@@ -31,6 +31,7 @@ use std::rt::global_heap;
 use std::uint;
 use std::unstable::intrinsics::{TyDesc, get_tydesc};
 use std::unstable::intrinsics;
+use std::unstable::raw::Slice;
 use std::util;
 
 // The way arena uses arrays is really deeply awful. The arrays are
@@ -348,6 +349,10 @@ struct TypedArenaChunk {
     /// The number of elements that this chunk can hold.
     capacity: uint,
 
+    /// The number of elements allocated in this chunk. Only kept up to date
+    /// once the chunk is no longer the current one.
+    filled: uint,
+
     // Objects follow here, suitably aligned.
 }
 
@@ -369,6 +374,7 @@ impl TypedArenaChunk {
         };
 
         chunk.capacity = capacity;
+        chunk.filled = 0;
         chunk
     }
 
@@ -393,8 +399,8 @@ impl TypedArenaChunk {
         match next_opt {
             None => {}
             Some(mut next) => {
-                // We assume that the next chunk is completely filled.
-                next.destroy(next.capacity, opt_tydesc)
+                let filled = next.filled;
+                next.destroy(filled, opt_tydesc)
             }
         }
     }
@@ -447,7 +453,7 @@ impl<T> TypedArena<T> {
         unsafe {
             let this = cast::transmute_mut(self);
             if this.ptr == this.end {
-                this.grow()
+                this.grow(1)
             }
 
             let ptr: &'a mut T = cast::transmute(this.ptr);
@@ -458,11 +464,106 @@ impl<T> TypedArena<T> {
         }
     }
 
-    /// Grows the arena.
+    /// Allocates the objects yielded by `iter` contiguously into this arena,
+    /// returning them as a slice.
+    ///
+    /// The objects are gathered before any of them is moved into the arena,
+    /// so the iterator may itself allocate from this arena.
+    pub fn alloc_from_iter<'a, I: Iterator<T>>(&'a self, iter: I) -> &'a [T] {
+        let objects: ~[T] = iter.collect();
+        let len = objects.len();
+        unsafe {
+            let this = cast::transmute_mut(self);
+            if this.remaining() < len {
+                this.grow(len)
+            }
+
+            let start = this.ptr;
+            for object in objects.move_iter() {
+                let ptr: &mut T = cast::transmute(this.ptr);
+                intrinsics::move_val_init(ptr, object);
+                this.ptr = this.ptr.offset(1);
+            }
+            cast::transmute(Slice { data: start, len: len })
+        }
+    }
+
+    /// Returns the number of bytes of memory held by this arena, including
+    /// space that hasn't been allocated to objects yet.
+    pub fn allocated_bytes(&self) -> uint {
+        let elem_size = mem::size_of::<T>();
+        let mut total = 0;
+        let mut chunk = self.first.get_ref();
+        loop {
+            total += round_up(mem::size_of::<TypedArenaChunk>(),
+                              mem::min_align_of::<T>()) +
+                     chunk.capacity * elem_size;
+            match chunk.next {
+                Some(ref next) => chunk = next,
+                None => return total,
+            }
+        }
+    }
+
+    /// Returns the number of bytes occupied by the objects in this arena.
+    pub fn used_bytes(&self) -> uint {
+        let mut total = self.current_len();
+        let mut next = &self.first.get_ref().next;
+        loop {
+            match *next {
+                Some(ref chunk) => {
+                    total += chunk.filled;
+                    next = &chunk.next;
+                }
+                None => return total * mem::size_of::<T>(),
+            }
+        }
+    }
+
+    /// Destroys every object in this arena, keeping only its largest chunk
+    /// around to serve later allocations.
+    pub fn reset(&mut self) {
+        unsafe {
+            let len = self.current_len();
+            let opt_tydesc = self.opt_tydesc();
+            let chunk = self.first.get_mut_ref();
+            // Mark the chunk empty before running any destructors, so that if
+            // one of them fails, `drop` doesn't destroy the objects again.
+            self.ptr = chunk.start(self.tydesc) as *T;
+            self.end = chunk.end(self.tydesc) as *T;
+            chunk.destroy(len, opt_tydesc);
+        }
+    }
+
+    /// The number of objects allocated in the current chunk.
+    #[inline]
+    fn current_len(&self) -> uint {
+        let start = self.first.get_ref().start(self.tydesc) as uint;
+        (self.ptr as uint - start) / mem::size_of::<T>()
+    }
+
+    /// The number of objects that still fit in the current chunk.
+    #[inline]
+    fn remaining(&self) -> uint {
+        (self.end as uint - self.ptr as uint) / mem::size_of::<T>()
+    }
+
+    #[inline]
+    fn opt_tydesc(&self) -> Option<*TyDesc> {
+        unsafe {
+            if intrinsics::needs_drop::<T>() { Some(self.tydesc) } else { None }
+        }
+    }
+
+    /// Grows the arena so that the new chunk has room for at least
+    /// `additional` objects.
     #[inline(never)]
-    fn grow(&mut self) {
-        let chunk = self.first.take_unwrap();
-        let new_capacity = chunk.capacity.checked_mul(&2).unwrap();
+    fn grow(&mut self, additional: uint) {
+        let len = self.current_len();
+        let mut chunk = self.first.take_unwrap();
+        chunk.filled = len;
+        let new_capacity = num::max(chunk.capacity.checked_mul(&2).unwrap(),
+                                    additional);
         let chunk = TypedArenaChunk::new::<T>(Some(chunk), new_capacity);
         self.ptr = chunk.start(self.tydesc) as *T;
         self.end = chunk.end(self.tydesc) as *T;
@@ -470,22 +571,24 @@ impl<T> TypedArena<T> {
     }
 }
 
+impl<T: Clone> TypedArena<T> {
+    /// Allocates clones of the objects in `objects` contiguously into this
+    /// arena.
+    #[inline]
+    pub fn alloc_slice<'a>(&'a self, objects: &[T]) -> &'a [T] {
+        self.alloc_from_iter(objects.iter().map(|object| object.clone()))
+    }
+}
+
 #[unsafe_destructor]
 impl<T> Drop for TypedArena<T> {
     fn drop(&mut self) {
-        // Determine how much was filled.
-        let start = self.first.get_ref().start(self.tydesc) as uint;
-        let end = self.ptr as uint;
-        let diff = (end - start) / mem::size_of::<T>();
-
-        // Pass that to the `destroy` method.
+        // Determine how much was filled, and pass that to the `destroy`
+        // method.
+        let len = self.current_len();
         unsafe {
-            let opt_tydesc = if intrinsics::needs_drop::<T>() {
-                Some(self.tydesc)
-            } else {
-                None
-            };
-            self.first.get_mut_ref().destroy(diff, opt_tydesc)
+            let opt_tydesc = self.opt_tydesc();
+            self.first.get_mut_ref().destroy(len, opt_tydesc)
         }
     }
 }
@@ -494,6 +597,9 @@ impl<T> Drop for TypedArena<T> {
 mod test {
     use super::{Arena, TypedArena};
     use test::BenchHarness;
+    use std::cell::Cell;
+    use std::mem;
+    use std::rc::Rc;
 
     struct Point {
         x: int,
@@ -513,6 +619,102 @@ mod test {
         }
     }
 
+    struct DropCounter {
+        count: Rc<Cell<uint>>,
+    }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            let count = self.count.borrow();
+            count.set(count.get() + 1);
+        }
+    }
+
+    fn counter(count: &Rc<Cell<uint>>) -> DropCounter {
+        DropCounter { count: count.clone() }
+    }
+
+    #[test]
+    pub fn test_alloc_slice() {
+        let arena = TypedArena::with_capacity(4);
+        let a = arena.alloc_slice([1, 2, 3]);
+        // Does not fit in the rest of the first chunk
+        let b = arena.alloc_slice([4, 5, 6, 7, 8, 9]);
+        let c = arena.alloc_from_iter(range(0, 100));
+        assert_eq!(a, &[1, 2, 3]);
+        assert_eq!(b, &[4, 5, 6, 7, 8, 9]);
+        assert_eq!(c.len(), 100);
+        assert_eq!(c[99], 99);
+        assert_eq!(arena.alloc_slice([]).len(), 0);
+    }
+
+    #[test]
+    pub fn test_destructors() {
+        let count = Rc::new(Cell::new(0u));
+        {
+            let arena = TypedArena::with_capacity(2);
+            arena.alloc(counter(&count));
+            arena.alloc_from_iter(range(0, 5).map(|_| counter(&count)));
+            arena.alloc(counter(&count));
+            assert_eq!(count.borrow().get(), 0);
+        }
+        assert_eq!(count.borrow().get(), 7);
+    }
+
+    #[test]
+    pub fn test_reset() {
+        let count = Rc::new(Cell::new(0u));
+        let mut arena = TypedArena::with_capacity(2);
+        for _ in range(0, 10) {
+            arena.alloc(counter(&count));
+        }
+        let allocated = arena.allocated_bytes();
+        arena.reset();
+        assert_eq!(count.borrow().get(), 10);
+        assert_eq!(arena.used_bytes(), 0);
+        assert!(arena.allocated_bytes() < allocated);
+
+        // The remaining chunk is reused
+        let allocated = arena.allocated_bytes();
+        for _ in range(0, 8) {
+            arena.alloc(counter(&count));
+        }
+        assert_eq!(arena.allocated_bytes(), allocated);
+        drop(arena);
+        assert_eq!(count.borrow().get(), 18);
+    }
+
+    struct FailOnDrop(bool);
+
+    impl Drop for FailOnDrop {
+        fn drop(&mut self) {
+            let FailOnDrop(should_fail) = *self;
+            if should_fail { fail!() }
+        }
+    }
+
+    #[test]
+    #[should_fail]
+    pub fn test_reset_destructor_fails() {
+        let mut arena = TypedArena::with_capacity(2);
+        for i in range(0, 5) {
+            arena.alloc(FailOnDrop(i == 3));
+        }
+        arena.reset();
+    }
+
+    #[test]
+    pub fn test_memory_usage() {
+        let arena = TypedArena::with_capacity(4);
+        assert_eq!(arena.used_bytes(), 0);
+        for i in range(0, 5) {
+            arena.alloc(i as u64);
+        }
+        assert_eq!(arena.used_bytes(), 5 * mem::size_of::<u64>());
+        // Two chunks, of 4 and 8 objects
+        assert!(arena.allocated_bytes() >= 12 * mem::size_of::<u64>());
+    }
+
     #[bench]
     pub fn bench_pod(bh: &mut BenchHarness) {
         let arena = TypedArena::new();
@@ -162,17 +162,15 @@ impl <T> Container for CVec<T> {
 mod tests {
     use super::*;
 
-    use std::libc::*;
-    use std::libc;
     use std::ptr;
-    use std::rt::global_heap::malloc_raw;
+    use std::rt::global_heap::{malloc_raw, exchange_free};
 
     fn malloc(n: uint) -> CVec<u8> {
         unsafe {
             let mem = malloc_raw(n);
 
             CVec::new_with_dtor(mem as *mut u8, n,
-                proc() { libc::free(mem as *mut c_void); })
+                proc() { exchange_free(mem as *u8); })
         }
     }
 
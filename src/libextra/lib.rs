@@ -45,6 +45,7 @@ pub mod arc;
 pub mod comm;
 pub mod future;
 pub mod task_pool;
+pub mod par;
 
 // Collections
 
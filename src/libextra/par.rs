@@ -0,0 +1,781 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fork-join parallelism on top of a pool of work-stealing tasks
+//!
+//! A `ForkJoinPool` runs a fixed number of worker tasks, each of which owns a
+//! work-stealing deque from `std::sync::deque`. The basic primitive is `join`,
+//! which runs two procs, potentially in parallel: the second proc is pushed
+//! onto the current worker's deque, where idle workers can steal it, while the
+//! first one runs right away. On top of `join` the pool provides parallel
+//! `map`, `filter`, `reduce` and `for_each` over slices and ranges, which
+//! recursively split their input in half until the pieces are small enough
+//! to be processed sequentially.
+//!
+//! Everything which may run on another task is sendable: `join` and `install`
+//! take procs, and the parallel operations take bare functions, which can't
+//! capture any state. The items of a slice are shared between the workers
+//! while the operation runs, so they must be `Freeze` and `Send`.
+//!
+//! If any of the procs or functions fails, the failure is propagated with its
+//! original cause to the task which called into the pool, once the rest of the
+//! work of that call has finished. The worker which failed is replaced.
+//!
+//! # Example
+//!
+//! ```rust
+//! use extra::par;
+//! use extra::par::ForkJoinPool;
+//!
+//! fn fib(n: uint) -> uint {
+//!     if n < 2 { return n }
+//!     let (a, b) = par::join(proc() fib(n - 1), proc() fib(n - 2));
+//!     a + b
+//! }
+//!
+//! fn square(x: &uint) -> uint { *x * *x }
+//! fn add(a: uint, b: uint) -> uint { a + b }
+//!
+//! let pool = ForkJoinPool::new(4);
+//!
+//! assert_eq!(pool.install(proc() fib(20)), 6765);
+//! assert_eq!(pool.map(&[1u, 2, 3], square), ~[1, 4, 9]);
+//! assert_eq!(pool.reduce(par::range(0, 100), add), Some(4950));
+//! ```
+
+use std::cast;
+use std::cmp;
+use std::comm::Data;
+use std::iter;
+use std::local_data;
+use std::rt;
+use std::sync::arc::UnsafeArc;
+use std::sync::atomics::{AtomicUint, AtomicBool, SeqCst};
+use std::sync::deque;
+use std::sync::{Mutex, Condvar};
+use std::task;
+use std::unstable::raw;
+use std::unstable::sync::Exclusive;
+use std::vec;
+
+// The pool and index of the worker which the current task runs, if any
+local_data_key!(WORKER: (uint, uint))
+// Where the jobs which were running when the current worker failed are sent
+local_data_key!(FAILED_JOBS: Chan<FailedJob>)
+
+static PENDING: uint = 0;
+static DONE: uint = 1;
+static FAILED: uint = 2;
+
+// How many pieces per worker the parallel operations split their input into
+static PIECES_PER_WORKER: uint = 4;
+
+// The outcome of a job. It lives on the stack of the task which created the
+// job, and that task waits for `state` to be set before returning.
+struct Latch {
+    state: AtomicUint,
+    cause: Option<~Any>,
+}
+
+impl Latch {
+    fn new() -> Latch {
+        Latch { state: AtomicUint::new(PENDING), cause: None }
+    }
+
+    fn id(&self) -> uint { self as *Latch as uint }
+
+    // Fails with the cause of the failure of the job, if it failed
+    fn propagate(&mut self) {
+        if self.state.load(SeqCst) == DONE { return }
+        match self.cause.take() {
+            Some(cause) => rt::begin_unwind_any(cause, file!(), line!()),
+            // The job was only unwound because a job which its worker ran in
+            // the meantime failed, and that failure went to its own creator.
+            None => fail!("a job in a fork-join pool failed"),
+        }
+    }
+}
+
+// Records the outcome of the job with the given latch, then wakes up its
+// creator. The creator may return as soon as the state is set, so only data
+// owned by the job itself may be touched afterwards.
+unsafe fn complete(latch: uint, state: uint, cause: Option<~Any>,
+                   signal: Option<Chan<()>>) {
+    let latch = latch as *mut Latch;
+    (*latch).cause = cause;
+    (*latch).state.store(state, SeqCst);
+    match signal {
+        Some(chan) => { chan.try_send(()); }
+        None => {}
+    }
+}
+
+// A closure which may be run by any worker. The closure and its latch both
+// live on the stack of the task which created the job.
+struct Job {
+    code: uint,
+    env: uint,
+    latch: uint,
+    signal: Option<Chan<()>>,
+}
+
+impl Job {
+    unsafe fn new(f: ||, latch: &mut Latch, signal: Option<Chan<()>>) -> Job {
+        let f: raw::Closure = cast::transmute(f);
+        Job {
+            code: f.code as uint,
+            env: f.env as uint,
+            latch: latch.id(),
+            signal: signal,
+        }
+    }
+
+    fn call(&self) {
+        let f: || = unsafe {
+            cast::transmute(raw::Closure {
+                code: self.code as *(),
+                env: self.env as *(),
+            })
+        };
+        f();
+    }
+
+    // Runs a job for another task
+    fn run(mut self) {
+        let _finish = Finish { latch: self.latch, signal: self.signal.take() };
+        self.call();
+    }
+
+    // Runs a job on the task which created it. If the job fails, the failure
+    // unwinds through its creator directly, so the latch is only needed when
+    // the job succeeds.
+    fn run_inline(self) {
+        self.call();
+        unsafe { complete(self.latch, DONE, None, None) }
+    }
+}
+
+// Publishes the outcome of a job, even if it fails
+struct Finish {
+    latch: uint,
+    signal: Option<Chan<()>>,
+}
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        if !task::failing() {
+            unsafe { complete(self.latch, DONE, None, self.signal.take()) }
+            return
+        }
+        // The cause of the failure is only known once the worker has unwound
+        // completely, so the job is finished by the worker's supervisor.
+        let mut job = Some(FailedJob {
+            latch: self.latch,
+            signal: self.signal.take(),
+        });
+        local_data::get(FAILED_JOBS, |chan| chan.unwrap().send(job.take_unwrap()));
+    }
+}
+
+struct FailedJob {
+    latch: uint,
+    signal: Option<Chan<()>>,
+}
+
+// The state of a pool, which its workers and handle share through an
+// `UnsafeArc`. Each worker deque is only ever used by the worker which owns
+// it.
+struct Shared {
+    workers: ~[deque::Worker<Job>],
+    stealers: ~[deque::Stealer<Job>],
+    // Jobs submitted from outside of the pool
+    injector: Exclusive<~[Job]>,
+    sleepers: AtomicUint,
+    shutdown: AtomicBool,
+    lock: Mutex<()>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn push(&mut self, index: uint, job: Job) {
+        self.workers[index].push(job);
+        self.notify();
+    }
+
+    fn inject(&mut self, job: Job) {
+        unsafe { self.injector.with(|jobs| jobs.push(job)); }
+        self.notify();
+    }
+
+    fn notify(&self) {
+        if self.sleepers.load(SeqCst) > 0 {
+            let _guard = self.lock.lock();
+            self.wakeup.signal();
+        }
+    }
+
+    // Takes a job from the injector or from the deque of another worker
+    fn steal(&mut self, index: uint) -> Option<Job> {
+        match unsafe { self.injector.with(|jobs| jobs.shift()) } {
+            Some(job) => return Some(job),
+            None => {}
+        }
+        let n = self.stealers.len();
+        for i in iter::range(1, n) {
+            let victim = (index + i) % n;
+            loop {
+                match self.stealers[victim].steal() {
+                    deque::Data(job) => return Some(job),
+                    deque::Empty => break,
+                    deque::Abort => {}
+                }
+            }
+        }
+        None
+    }
+
+    fn find_work(&mut self, index: uint) -> Option<Job> {
+        match self.workers[index].pop() {
+            Some(job) => Some(job),
+            None => self.steal(index),
+        }
+    }
+
+    // Waits for the job with the given latch, which was pushed onto the deque
+    // of worker `index`, to finish. If the job hasn't been stolen it is popped
+    // back off and run inline, or discarded if `run` is false. While waiting
+    // for a thief, other jobs are run in the meantime if `run` is true, and
+    // once there are none left this blocks on `done`, which the job signals
+    // when it finishes.
+    fn reclaim(&mut self, index: uint, latch: &Latch, done: &Port<()>,
+               run: bool) {
+        match self.workers[index].pop() {
+            Some(job) => {
+                if job.latch == latch.id() {
+                    if run { job.run_inline() }
+                    return
+                }
+                // Ours was stolen, and this one belongs to an enclosing join
+                self.workers[index].push(job);
+            }
+            None => {}
+        }
+        while latch.state.load(SeqCst) == PENDING {
+            if run {
+                match self.steal(index) {
+                    Some(job) => { job.run(); continue }
+                    None => {}
+                }
+            }
+            // The job refers to this stack frame, so it must be waited for
+            // even if this task is cancelled.
+            task::uncancellable(|| done.recv_opt());
+        }
+    }
+}
+
+// Blocks until there may be new work, or the pool is shutting down. The
+// search for work is repeated under the lock so that a notification sent
+// after the last search can't be missed.
+unsafe fn sleep(shared: *mut Shared, index: uint) -> Option<Job> {
+    let mut guard = (*shared).lock.lock();
+    (*shared).sleepers.fetch_add(1, SeqCst);
+    let job = (*shared).find_work(index);
+    if job.is_none() && !(*shared).shutdown.load(SeqCst) {
+        (*shared).wakeup.wait(&mut guard);
+    }
+    (*shared).sleepers.fetch_sub(1, SeqCst);
+    job
+}
+
+// Makes sure that a forked job no longer refers to the stack of a join which
+// is unwinding
+struct JoinGuard<'a> {
+    shared: *mut Shared,
+    index: uint,
+    latch: &'a Latch,
+    done: &'a Port<()>,
+    armed: bool,
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for JoinGuard<'a> {
+    fn drop(&mut self) {
+        if self.armed {
+            unsafe {
+                (*self.shared).reclaim(self.index, self.latch, self.done, false);
+            }
+        }
+    }
+}
+
+fn current_worker() -> Option<(uint, uint)> {
+    local_data::get(WORKER, |worker| worker.map(|&worker| worker))
+}
+
+/// Runs `a` and `b`, potentially in parallel, and returns both of their
+/// results.
+///
+/// When called from a task of a `ForkJoinPool`, `b` is made available for
+/// other workers of the pool to steal while `a` runs. Otherwise the procs are
+/// simply run one after the other.
+///
+/// # Failure
+///
+/// If either proc fails, this fails with the same cause once both of them
+/// have finished.
+pub fn join<A: Send, B: Send>(a: proc() -> A, b: proc() -> B) -> (A, B) {
+    let (mut a, mut b) = (Some(a), Some(b));
+    unsafe { join_raw(|| (a.take_unwrap())(), || (b.take_unwrap())()) }
+}
+
+// Like `join`, but the closures may run on different tasks at the same time
+// even though nothing requires what they capture to be sendable, so the
+// caller has to make sure that this is safe.
+unsafe fn join_raw<A, B>(a: || -> A, b: || -> B) -> (A, B) {
+    match current_worker() {
+        Some((shared, index)) => join_on(shared as *mut Shared, index, a, b),
+        None => (a(), b()),
+    }
+}
+
+unsafe fn join_on<A, B>(shared: *mut Shared, index: uint,
+                        a: || -> A, b: || -> B) -> (A, B) {
+    let mut latch = Latch::new();
+    let (done, chan) = Chan::new();
+    let mut rb = None;
+    let ra;
+    {
+        let fork = || rb = Some(b());
+        (*shared).push(index, Job::new(fork, &mut latch, Some(chan)));
+        let mut guard = JoinGuard {
+            shared: shared, index: index, latch: &latch, done: &done,
+            armed: true,
+        };
+        ra = a();
+        (*shared).reclaim(index, &latch, &done, true);
+        guard.armed = false;
+    }
+    latch.propagate();
+    (ra, rb.unwrap())
+}
+
+// An input which can be processed in parallel by recursively splitting it.
+// This isn't public so that only slices and ranges, whose pieces are safe to
+// hand to other tasks given the bounds of the parallel operations, can be
+// used as inputs.
+trait Splittable<T> {
+    // Returns the number of items in this input
+    fn size(&self) -> uint;
+    // Splits this input into the first `mid` items and the rest
+    fn split_at(self, mid: uint) -> (Self, Self);
+    // Calls `f` on each item of this input, in order
+    fn each_item(self, f: |&T|);
+}
+
+impl<'a, T> Splittable<T> for &'a [T] {
+    fn size(&self) -> uint { self.len() }
+
+    fn split_at(self, mid: uint) -> (&'a [T], &'a [T]) {
+        (self.slice_to(mid), self.slice_from(mid))
+    }
+
+    fn each_item(self, f: |&T|) {
+        for x in self.iter() { f(x) }
+    }
+}
+
+/// The half-open range of integers `[lo, hi)`, which can be processed in
+/// parallel. See `range`.
+#[deriving(Clone, Eq)]
+pub struct Range {
+    priv lo: uint,
+    priv hi: uint,
+}
+
+/// Creates the range of integers `[lo, hi)`.
+pub fn range(lo: uint, hi: uint) -> Range {
+    assert!(lo <= hi);
+    Range { lo: lo, hi: hi }
+}
+
+impl Splittable<uint> for Range {
+    fn size(&self) -> uint { self.hi - self.lo }
+
+    fn split_at(self, mid: uint) -> (Range, Range) {
+        (Range { lo: self.lo, hi: self.lo + mid },
+         Range { lo: self.lo + mid, hi: self.hi })
+    }
+
+    fn each_item(self, f: |&uint|) {
+        for i in iter::range(self.lo, self.hi) { f(&i) }
+    }
+}
+
+// Recursively splits `input` in half, processing the pieces of at most `grain`
+// items with `leaf` and combining their results with `merge`. Both closures
+// are called in parallel, see `join_raw`.
+unsafe fn divide<T, I: Splittable<T>, R>(input: I, grain: uint,
+                                          leaf: |I| -> R,
+                                          merge: |R, R| -> R) -> R {
+    let size = input.size();
+    if size <= grain {
+        return leaf(input)
+    }
+    let (left, right) = input.split_at(size / 2);
+    let (mut left, mut right) = (Some(left), Some(right));
+    let (a, b) = join_raw(
+        || divide(left.take_unwrap(), grain, |i| leaf(i), |a, b| merge(a, b)),
+        || divide(right.take_unwrap(), grain, |i| leaf(i), |a, b| merge(a, b)));
+    merge(a, b)
+}
+
+/// A pool of worker tasks which run fork-join computations.
+pub struct ForkJoinPool {
+    priv shared: UnsafeArc<Shared>,
+    priv exited: Port<()>,
+    priv nworkers: uint,
+}
+
+impl ForkJoinPool {
+    /// Spawns a new pool with `nworkers` worker tasks.
+    pub fn new(nworkers: uint) -> ForkJoinPool {
+        assert!(nworkers > 0);
+        let mut pool = deque::BufferPool::new();
+        let (workers, stealers) =
+            vec::unzip(iter::range(0, nworkers).map(|_| pool.deque()));
+        let shared = UnsafeArc::new(Shared {
+            workers: workers,
+            stealers: stealers,
+            injector: Exclusive::new(~[]),
+            sleepers: AtomicUint::new(0),
+            shutdown: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            wakeup: Condvar::new(),
+        });
+        let (exited, chan) = SharedChan::new();
+        for index in iter::range(0, nworkers) {
+            supervise(shared.clone(), index, chan.clone());
+        }
+        ForkJoinPool { shared: shared, exited: exited, nworkers: nworkers }
+    }
+
+    /// Returns the number of worker tasks of this pool.
+    pub fn workers(&self) -> uint { self.nworkers }
+
+    /// Runs `f` on one of the workers of this pool and waits for its result.
+    /// Calls to `join` made by `f` will then run in parallel.
+    ///
+    /// # Failure
+    ///
+    /// If `f` fails, this fails with the same cause.
+    pub fn install<R: Send>(&self, f: proc() -> R) -> R {
+        let mut f = Some(f);
+        unsafe { self.install_raw(|| (f.take_unwrap())()) }
+    }
+
+    // Like `install`, but `f` and its result don't have to be sendable, so
+    // the caller has to make sure that running `f` on a worker is safe.
+    unsafe fn install_raw<R>(&self, f: || -> R) -> R {
+        let shared = self.shared.get();
+        match current_worker() {
+            Some((s, _)) if s == shared as uint => return f(),
+            _ => {}
+        }
+
+        let mut latch = Latch::new();
+        let mut ret = None;
+        {
+            let (port, chan) = Chan::new();
+            let body = || ret = Some(f());
+            (*shared).inject(Job::new(body, &mut latch, Some(chan)));
+            // The job refers to this stack frame, so it must be waited for
+            // even if this task is cancelled.
+            task::uncancellable(|| port.recv_opt());
+        }
+        latch.propagate();
+        ret.unwrap()
+    }
+
+    /// Runs `a` and `b` in parallel on this pool, returning both results. See
+    /// the free function `join`.
+    pub fn join<A: Send, B: Send>(&self, a: proc() -> A,
+                                  b: proc() -> B) -> (A, B) {
+        self.install(proc() join(a, b))
+    }
+
+    /// Calls `f` on each item of `input` in parallel. `input` is either a
+    /// slice or a `Range`.
+    pub fn for_each<T: Freeze + Send, I: Splittable<T>>(&self, input: I,
+                                                         f: fn(&T)) {
+        let grain = self.grain(input.size());
+        let mut input = Some(input);
+        unsafe {
+            self.install_raw(|| {
+                divide(input.take_unwrap(), grain,
+                       |piece| piece.each_item(|x| f(x)), |_, _| ())
+            })
+        }
+    }
+
+    /// Applies `f` to each item of `input` in parallel, returning the results
+    /// in the order of the input.
+    pub fn map<T: Freeze + Send, U: Send, I: Splittable<T>>(&self, input: I,
+                                                             f: fn(&T) -> U)
+                                                             -> ~[U] {
+        let grain = self.grain(input.size());
+        let mut input = Some(input);
+        unsafe {
+            self.install_raw(|| {
+                divide(input.take_unwrap(), grain, |piece| {
+                    let mut results = vec::with_capacity(piece.size());
+                    piece.each_item(|x| results.push(f(x)));
+                    results
+                }, concat)
+            })
+        }
+    }
+
+    /// Returns copies of the items of `input` for which `f` returns true, in
+    /// order. `f` is called on the items in parallel.
+    pub fn filter<T: Freeze + Send + Clone, I: Splittable<T>>(&self, input: I,
+                                                               f: fn(&T) -> bool)
+                                                               -> ~[T] {
+        let grain = self.grain(input.size());
+        let mut input = Some(input);
+        unsafe {
+            self.install_raw(|| {
+                divide(input.take_unwrap(), grain, |piece| {
+                    let mut results = ~[];
+                    piece.each_item(|x| if f(x) { results.push(x.clone()) });
+                    results
+                }, concat)
+            })
+        }
+    }
+
+    /// Combines copies of all of the items of `input` with `f`, in parallel.
+    /// `f` must be associative, as the order in which items are combined is
+    /// unspecified. Returns `None` if `input` is empty.
+    pub fn reduce<T: Freeze + Send + Clone, I: Splittable<T>>(&self, input: I,
+                                                               f: fn(T, T) -> T)
+                                                               -> Option<T> {
+        let grain = self.grain(input.size());
+        let mut input = Some(input);
+        unsafe {
+            self.install_raw(|| {
+                divide(input.take_unwrap(), grain, |piece| {
+                    let mut acc = None;
+                    piece.each_item(|x| {
+                        acc = Some(match acc.take() {
+                            Some(a) => f(a, x.clone()),
+                            None => x.clone(),
+                        });
+                    });
+                    acc
+                }, |a, b| {
+                    match (a, b) {
+                        (Some(a), Some(b)) => Some(f(a, b)),
+                        (a, None) => a,
+                        (None, b) => b,
+                    }
+                })
+            })
+        }
+    }
+
+    fn grain(&self, size: uint) -> uint {
+        cmp::max(1, size / (self.nworkers * PIECES_PER_WORKER))
+    }
+}
+
+fn concat<T>(a: ~[T], b: ~[T]) -> ~[T] {
+    let mut a = a;
+    a.push_all_move(b);
+    a
+}
+
+impl Drop for ForkJoinPool {
+    fn drop(&mut self) {
+        unsafe {
+            let shared = self.shared.get();
+            (*shared).shutdown.store(true, SeqCst);
+            let _guard = (*shared).lock.lock();
+            (*shared).wakeup.broadcast();
+        }
+        for _ in iter::range(0, self.nworkers) {
+            self.exited.recv();
+        }
+    }
+}
+
+// Spawns the task which runs worker `index`. A worker dies if one of the jobs
+// which it runs fails, in which case it is replaced by a new one.
+fn supervise(shared: UnsafeArc<Shared>, index: uint, exited: SharedChan<()>) {
+    let mut builder = task::task();
+    builder.name("<fork-join supervisor>");
+    do builder.spawn {
+        loop {
+            let shared = shared.clone();
+            let (failed, chan) = Chan::new();
+            let mut worker = task::task();
+            worker.name("<fork-join worker>");
+            let cause = match worker.try(proc() work(shared, index, chan)) {
+                Ok(()) => break,
+                Err(cause) => cause,
+            };
+            // The jobs arrive innermost first. The innermost one is the one
+            // which failed, the others only unwound because of it.
+            let mut cause = Some(cause);
+            loop {
+                match failed.try_recv() {
+                    Data(FailedJob { latch, signal }) => unsafe {
+                        complete(latch, FAILED, cause.take(), signal)
+                    },
+                    _ => break,
+                }
+            }
+        }
+        exited.send(());
+    }
+}
+
+fn work(shared: UnsafeArc<Shared>, index: uint, failed: Chan<FailedJob>) {
+    let s = shared.get();
+    local_data::set(WORKER, (s as uint, index));
+    local_data::set(FAILED_JOBS, failed);
+    unsafe {
+        while !(*s).shutdown.load(SeqCst) {
+            let job = match (*s).find_work(index) {
+                Some(job) => Some(job),
+                None => sleep(s, index),
+            };
+            match job {
+                Some(job) => job.run(),
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::iter;
+    use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+    use std::task;
+    use std::vec;
+
+    use super::{ForkJoinPool, join, range};
+
+    fn fib(n: uint) -> uint {
+        if n < 2 { return n }
+        let (a, b) = join(proc() fib(n - 1), proc() fib(n - 2));
+        a + b
+    }
+
+    fn double(x: &uint) -> uint { *x * 2 }
+    fn square(x: &uint) -> uint { *x * *x }
+    fn is_even(x: &uint) -> bool { *x % 2 == 0 }
+    fn add(a: uint, b: uint) -> uint { a + b }
+    fn max(a: uint, b: uint) -> uint { if a > b {a} else {b} }
+
+    #[test]
+    fn join_outside_pool() {
+        assert_eq!(join(proc() 1, proc() 2), (1, 2));
+        assert_eq!(fib(10), 55);
+    }
+
+    #[test]
+    fn join_smoke() {
+        let pool = ForkJoinPool::new(2);
+        assert_eq!(pool.join(proc() 1, proc() 2), (1, 2));
+        assert_eq!(pool.install(proc() fib(20)), 6765);
+    }
+
+    #[test]
+    fn map_slice() {
+        let pool = ForkJoinPool::new(4);
+        let v = vec::from_fn(1000, |i| i);
+        let doubled = pool.map(v.as_slice(), double);
+        assert_eq!(doubled, vec::from_fn(1000, |i| i * 2));
+    }
+
+    #[test]
+    fn map_range() {
+        let pool = ForkJoinPool::new(3);
+        assert_eq!(pool.map(range(5, 10), square), ~[25, 36, 49, 64, 81]);
+    }
+
+    #[test]
+    fn filter() {
+        let pool = ForkJoinPool::new(4);
+        let evens = pool.filter(range(0, 1000), is_even);
+        let expected: ~[uint] = iter::range_step(0u, 1000, 2).collect();
+        assert_eq!(evens, expected);
+        let v = [1u, 2, 3, 4, 5];
+        assert_eq!(pool.filter(v.as_slice(), is_even), ~[2, 4]);
+    }
+
+    #[test]
+    fn reduce() {
+        let pool = ForkJoinPool::new(4);
+        assert_eq!(pool.reduce(range(0, 10000), add), Some(49995000));
+        assert_eq!(pool.reduce(range(3, 3), add), None);
+        let v = [3u, 9, 1, 7];
+        assert_eq!(pool.reduce(v.as_slice(), max), Some(9));
+    }
+
+    static mut count: AtomicUint = INIT_ATOMIC_UINT;
+
+    fn count_up(i: &uint) {
+        unsafe { count.fetch_add(*i, SeqCst); }
+    }
+
+    #[test]
+    fn for_each() {
+        let pool = ForkJoinPool::new(4);
+        pool.for_each(range(0, 1000), count_up);
+        assert_eq!(unsafe { count.load(SeqCst) }, 499500);
+    }
+
+    fn fail_at_fifty(i: &uint) {
+        if *i == 50 { fail!("fifty") }
+    }
+
+    #[test]
+    fn failure_propagates() {
+        let ret = do task::try {
+            let pool = ForkJoinPool::new(2);
+            pool.for_each(range(0, 100), fail_at_fifty);
+        };
+        let cause = ret.unwrap_err();
+        assert_eq!(*cause.move::<&'static str>().unwrap(), "fifty");
+    }
+
+    #[test]
+    fn join_failure_propagates() {
+        let ret = do task::try {
+            let pool = ForkJoinPool::new(2);
+            pool.join(proc() fib(15), proc() -> uint { fail!(~"forked") });
+        };
+        let cause = ret.unwrap_err();
+        assert_eq!(*cause.move::<~str>().unwrap(), ~"forked");
+    }
+
+    #[test]
+    fn nested_join() {
+        let pool = ForkJoinPool::new(4);
+        let (a, (b, c)) = pool.join(proc() fib(12), proc() {
+            join(proc() iter::range(0u, 100).fold(0, |a, b| a + b),
+                 proc() fib(10))
+        });
+        assert_eq!((a, b, c), (144, 4950, 55));
+    }
+}
@@ -14,7 +14,7 @@ use std::cast::{transmute, transmute_mut_unsafe,
                 transmute_region, transmute_mut_region};
 use std::unstable::stack;
 
-use stack::StackSegment;
+use stack::{StackSegment, GuardPage, record_guard_page};
 
 // FIXME #7761: Registers is boxed so that it is 16-byte aligned, for storing
 // SSE regs.  It would be marginally better not to do this. In C++ we
@@ -29,6 +29,8 @@ pub struct Context {
     priv regs: ~Registers,
     /// Lower bound and upper bound for the stack
     priv stack_bounds: Option<(uint, uint)>,
+    /// Address range of the inaccessible page just below the stack
+    priv stack_guard: Option<(uint, uint)>,
 }
 
 impl Context {
@@ -37,6 +39,7 @@ impl Context {
             start: None,
             regs: new_regs(),
             stack_bounds: None,
+            stack_guard: None,
         }
     }
 
@@ -90,15 +93,16 @@ impl Context {
         // overflow). Additionally, their coroutine stacks are listed as being
         // zero-length, so that's how we detect what's what here.
         let stack_base: *uint = stack.start();
-        let bounds = if sp as uint == stack_base as uint {
-            None
+        let (bounds, guard) = if sp as uint == stack_base as uint {
+            (None, None)
         } else {
-            Some((stack_base as uint, sp as uint))
+            (Some((stack_base as uint, sp as uint)), Some(stack.guard_page()))
         };
         return Context {
             start: Some(start),
             regs: regs,
             stack_bounds: bounds,
+            stack_guard: guard,
         }
     }
 
@@ -107,8 +111,12 @@ impl Context {
     Suspend the current execution context and resume another by
     saving the registers values of the executing thread to a Context
     then loading the registers from a previously saved Context.
+
+    `guard` is the running thread's guard page record, as returned by
+    `stack::install_guard_handler`.
     */
-    pub fn swap(out_context: &mut Context, in_context: &Context) {
+    pub fn swap(out_context: &mut Context, in_context: &Context,
+                guard: *mut GuardPage) {
         rtdebug!("swapping contexts");
         let out_regs: &mut Registers = match out_context {
             &Context { regs: ~ref mut r, .. } => r
@@ -120,6 +128,14 @@ impl Context {
         rtdebug!("noting the stack limit and doing raw swap");
 
         unsafe {
+            // Let the fault handler know which guard page belongs to the
+            // context we're switching to, so that running off the end of the
+            // stack is reported as an overflow rather than a plain segfault.
+            match in_context.stack_guard {
+                Some((lo, hi)) => record_guard_page(guard, lo, hi),
+                None => record_guard_page(guard, 0, 0),
+            }
+
             // Right before we switch to the new context, set the new context's
             // stack limit in the OS-specified TLS slot. This also  means that
             // we cannot call any more rust functions after record_stack_bounds
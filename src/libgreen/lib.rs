@@ -43,8 +43,11 @@ use std::sync::arc::UnsafeArc;
 use sched::{Shutdown, Scheduler, SchedHandle, TaskFromFriend, NewNeighbor};
 use sleeper_list::SleeperList;
 use stack::StackPool;
+use stats::{StatsRegistry, StatsDumper};
 use task::GreenTask;
 
+pub use stats::SchedStats;
+
 mod macros;
 mod simple;
 
@@ -54,6 +57,7 @@ pub mod coroutine;
 pub mod sched;
 pub mod sleeper_list;
 pub mod stack;
+pub mod stats;
 pub mod task;
 
 #[lang = "start"]
@@ -160,6 +164,8 @@ pub struct SchedPool {
     priv factory: fn() -> ~rtio::EventLoop,
     priv task_state: TaskState,
     priv tasks_done: Port<()>,
+    priv stats: StatsRegistry,
+    priv dumper: Option<StatsDumper>,
 }
 
 /// This is an internal state shared among a pool of schedulers. This is used to
@@ -200,6 +206,8 @@ impl SchedPool {
             factory: factory,
             task_state: state,
             tasks_done: p,
+            stats: StatsRegistry::new(),
+            dumper: None,
         };
 
         // Create a work queue for each scheduler, ntimes. Create an extra
@@ -211,7 +219,8 @@ impl SchedPool {
         // Now that we've got all our work queues, create one scheduler per
         // queue, spawn the scheduler into a thread, and be sure to keep a
         // handle to the scheduler and the thread to keep them alive.
-        for worker in workers.move_iter() {
+        for (worker, stealer) in workers.move_iter().zip(pool.stealers.clone()
+                                                             .move_iter()) {
             rtdebug!("inserting a regular scheduler");
 
             let mut sched = ~Scheduler::new(pool.id,
@@ -220,11 +229,20 @@ impl SchedPool {
                                             pool.stealers.clone(),
                                             pool.sleepers.clone(),
                                             pool.task_state.clone());
+            pool.stats.register(sched.counters.clone(), stealer);
             pool.handles.push(sched.make_handle());
             let sched = sched;
             pool.threads.push(do Thread::start { sched.bootstrap(); });
         }
 
+        match rt::env::sched_stats() {
+            0 => {}
+            ms => {
+                pool.dumper = Some(StatsDumper::start(pool.id,
+                                                      pool.stats.clone(), ms));
+            }
+        }
+
         return pool;
     }
 
@@ -281,6 +299,7 @@ impl SchedPool {
                                         self.stealers.clone(),
                                         self.sleepers.clone(),
                                         self.task_state.clone());
+        self.stats.register(sched.counters.clone(), stealer);
         let ret = sched.make_handle();
         self.handles.push(sched.make_handle());
         let sched = sched;
@@ -289,6 +308,15 @@ impl SchedPool {
         return ret;
     }
 
+    /// Takes a snapshot of the runtime statistics of every scheduler in this
+    /// pool, in the order in which the schedulers were created.
+    ///
+    /// The counters of a running scheduler are updated without any
+    /// synchronization, so the snapshot may lag slightly behind.
+    pub fn stats(&self) -> ~[SchedStats] {
+        self.stats.snapshot()
+    }
+
     /// Consumes the pool of schedulers, waiting for all tasks to exit and all
     /// schedulers to shut down.
     ///
@@ -317,6 +345,10 @@ impl SchedPool {
         for thread in util::replace(&mut self.threads, ~[]).move_iter() {
             thread.join();
         }
+        match self.dumper.take() {
+            Some(dumper) => dumper.stop(),
+            None => {}
+        }
     }
 }
 
@@ -9,6 +9,7 @@
 // except according to those terms.
 
 use std::cast;
+use std::ptr;
 use std::rand::{XorShiftRng, Rng, Rand};
 use std::rt::local::Local;
 use std::rt::rtio::{RemoteCallback, PausableIdleCallback, Callback, EventLoop};
@@ -24,6 +25,8 @@ use context::Context;
 use coroutine::Coroutine;
 use sleeper_list::SleeperList;
 use stack::StackPool;
+use stack;
+use stats::SchedCounters;
 use task::{TypeSched, GreenTask, HomeSched, AnySched};
 
 /// A scheduler is responsible for coordinating the execution of Tasks
@@ -89,6 +92,11 @@ pub struct Scheduler {
     /// Bookeeping for the number of tasks which are currently running around
     /// inside this pool of schedulers
     task_state: TaskState,
+    /// Runtime statistics about this scheduler, shared with its pool
+    counters: SchedCounters,
+    /// This thread's guard page record, which is updated on every context
+    /// switch. Null until the scheduler is bootstrapped.
+    guard_page: *mut stack::GuardPage,
 
     // n.b. currently destructors of an object are run in top-to-bottom in order
     //      of field declaration. Due to its nature, the pausable idle callback
@@ -164,6 +172,8 @@ impl Scheduler {
             yield_check_count: 0,
             steal_for_yield: false,
             task_state: state,
+            counters: SchedCounters::new(),
+            guard_page: ptr::mut_null(),
         };
 
         sched.yield_check_count = reset_yield_check(&mut sched.rng);
@@ -180,6 +190,10 @@ impl Scheduler {
     // scheduler task and bootstrap into it.
     pub fn bootstrap(mut ~self) {
 
+        // Make sure that overflowing a task's stack onto its guard page is
+        // reported as such on this thread.
+        self.guard_page = stack::install_guard_handler();
+
         // Build an Idle callback.
         let cb = ~SchedRunner as ~Callback;
         self.idle_callback = Some(self.event_loop.pausable_idle_callback(cb));
@@ -218,6 +232,8 @@ impl Scheduler {
         rtassert!(match message { mpsc::Empty => true, _ => false });
 
         stask.task.get_mut_ref().destroyed = true;
+
+        stack::uninstall_guard_handler();
     }
 
     // This does not return a scheduler, as the scheduler is placed
@@ -296,6 +312,7 @@ impl Scheduler {
         if !sched.sleepy && !sched.no_sleep {
             rtdebug!("scheduler has no work to do, going to sleep");
             sched.sleepy = true;
+            sched.counters.sleep();
             let handle = sched.make_handle();
             sched.sleeper_list.push(handle);
             // Since we are sleeping, deactivate the idle callback.
@@ -366,6 +383,7 @@ impl Scheduler {
             }
             Some(Wake) => {
                 self.sleepy = false;
+                self.counters.wakeup();
                 stask.put_with_sched(self);
                 return None;
             }
@@ -467,15 +485,17 @@ impl Scheduler {
     // special schedulers.
     fn try_steals(&mut self) -> Option<~GreenTask> {
         let work_queues = &mut self.work_queues;
+        let counters = &self.counters;
         let len = work_queues.len();
         let start_index = self.rng.gen_range(0, len);
         for index in range(0, len).map(|i| (i + start_index) % len) {
             match work_queues[index].steal() {
                 deque::Data(task) => {
                     rtdebug!("found task by stealing");
+                    counters.steal(true);
                     return Some(task)
                 }
-                _ => ()
+                _ => counters.steal(false)
             }
         };
         rtdebug!("giving up on stealing");
@@ -581,6 +601,8 @@ impl Scheduler {
                                f: |&mut Scheduler, ~GreenTask|) -> ~GreenTask {
         let f_opaque = ClosureConverter::from_fn(f);
 
+        self.counters.context_switch(!next_task.is_sched());
+
         let current_task_dupe = unsafe {
             *cast::transmute::<&~GreenTask, &uint>(&current_task)
         };
@@ -623,7 +645,8 @@ impl Scheduler {
             // The raw context swap operation. The next action taken
             // will be running the cleanup job from the context of the
             // next task.
-            Context::swap(current_task_context, next_task_context);
+            Context::swap(current_task_context, next_task_context,
+                          sched.guard_page);
         }
 
         // When the context swaps back to this task we immediately
@@ -1486,4 +1509,29 @@ mod test {
         }
 
     }
+
+    #[test]
+    fn stats() {
+        let mut pool = pool();
+        let (port, chan) = Chan::new();
+        do pool.spawn(TaskOpts::new()) {
+            for _ in range(0, 10) {
+                do spawn {}
+            }
+            chan.send(());
+        }
+        port.recv();
+        let handle = pool.spawn_sched();
+
+        let stats = pool.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].id, 0);
+        assert_eq!(stats[1].id, 1);
+        assert!(stats[0].tasks_run > 0);
+        assert!(stats[0].context_switches >= stats[0].tasks_run);
+        assert!(stats[0].steals_succeeded <= stats[0].steals_attempted);
+
+        drop(handle);
+        pool.shutdown();
+    }
 }
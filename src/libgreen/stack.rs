@@ -8,43 +8,112 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::vec;
+use std::hashmap::HashMap;
 use std::libc::{c_uint, uintptr_t};
+use std::libc;
+use std::os::{MemoryMap, MapReadable, MapWritable};
+use std::os;
+use std::uint;
 
+/// The maximum number of stacks that a single `StackPool` will keep cached.
+/// Any segment returned to a full pool is unmapped immediately.
+pub static MAX_CACHED_STACKS: uint = 16;
+
+/// A stack segment for a green task.
+///
+/// The segment is backed by an anonymous memory mapping whose lowest page is
+/// left inaccessible. Stacks grow downwards, so a task (or, more likely, a
+/// foreign function that it called) which runs off the end of its stack will
+/// fault on the guard page instead of silently scribbling over whatever
+/// happens to live below it.
 pub struct StackSegment {
-    priv buf: ~[u8],
+    priv buf: MemoryMap,
+    priv size: uint,
     priv valgrind_id: c_uint
 }
 
 impl StackSegment {
+    /// Allocates a new stack segment which has at least `size` bytes of
+    /// usable stack, plus one guard page.
     pub fn new(size: uint) -> StackSegment {
-        unsafe {
-            // Crate a block of uninitialized values
-            let mut stack = vec::with_capacity(size);
-            stack.set_len(size);
+        let size = round_up(size, os::page_size());
+        let buf = match MemoryMap::new(size + os::page_size(),
+                                       [MapReadable, MapWritable]) {
+            Ok(map) => map,
+            Err(e) => fail!("mmap for stack of size {} failed: {}",
+                            size, e.to_str())
+        };
 
-            let mut stk = StackSegment {
-                buf: stack,
-                valgrind_id: 0
-            };
-
-            // XXX: Using the FFI to call a C macro. Slow
-            stk.valgrind_id = rust_valgrind_stack_register(stk.start(), stk.end());
-            return stk;
+        // Change the last page to be inaccessible. This won't catch every
+        // overflow (a large enough frame can skip right over it), but it
+        // catches the common case of deep recursion in FFI code which doesn't
+        // perform stack checks.
+        if !protect_last_page(&buf) {
+            fail!("could not memory-protect stack guard page, errno={}",
+                  os::errno());
         }
+
+        let mut stk = StackSegment {
+            buf: buf,
+            size: size,
+            valgrind_id: 0
+        };
+
+        // XXX: Using the FFI to call a C macro. Slow
+        stk.valgrind_id = unsafe {
+            rust_valgrind_stack_register(stk.start(), stk.end())
+        };
+        return stk;
     }
 
     /// Point to the low end of the allocated stack
     pub fn start(&self) -> *uint {
-        self.buf.as_ptr() as *uint
+        unsafe {
+            self.buf.data.offset(os::page_size() as int) as *uint
+        }
     }
 
     /// Point one word beyond the high end of the allocated stack
     pub fn end(&self) -> *uint {
         unsafe {
-            self.buf.as_ptr().offset(self.buf.len() as int) as *uint
+            self.buf.data.offset(self.buf.len as int) as *uint
         }
     }
+
+    /// The number of usable bytes in this segment, not counting the guard
+    /// page.
+    pub fn size(&self) -> uint { self.size }
+
+    /// The `(lo, hi)` address range of the inaccessible guard page which sits
+    /// just below the start of this stack.
+    pub fn guard_page(&self) -> (uint, uint) {
+        let lo = self.buf.data as uint;
+        (lo, lo + os::page_size())
+    }
+}
+
+#[cfg(unix)]
+fn protect_last_page(stack: &MemoryMap) -> bool {
+    unsafe {
+        // This may seem backwards: the start of the segment is the last page?
+        // Yes! The stack grows from higher addresses (the end of the allocated
+        // block) to lower addresses (the start of the allocated block).
+        let last_page = stack.data as *libc::c_void;
+        libc::mprotect(last_page, os::page_size() as libc::size_t,
+                       libc::PROT_NONE) != -1
+    }
+}
+
+#[cfg(windows)]
+fn protect_last_page(stack: &MemoryMap) -> bool {
+    unsafe {
+        // see above
+        let last_page = stack.data as *mut libc::c_void;
+        let mut old_prot: libc::DWORD = 0;
+        libc::VirtualProtect(last_page, os::page_size() as libc::SIZE_T,
+                             libc::PAGE_NOACCESS,
+                             &mut old_prot as libc::LPDWORD) != 0
+    }
 }
 
 impl Drop for StackSegment {
@@ -56,20 +125,169 @@ impl Drop for StackSegment {
     }
 }
 
-pub struct StackPool(());
+/// A per-scheduler cache of stack segments.
+///
+/// Segments are bucketed by size class (a power-of-two number of pages) so
+/// that a request for a stack can be satisfied by any cached segment of the
+/// same class. At most `MAX_CACHED_STACKS` segments are kept around in total.
+pub struct StackPool {
+    priv stacks: HashMap<uint, ~[StackSegment]>,
+    priv cached: uint,
+    priv max_cached: uint,
+}
 
 impl StackPool {
-    pub fn new() -> StackPool { StackPool(()) }
+    pub fn new() -> StackPool {
+        StackPool::with_capacity(MAX_CACHED_STACKS)
+    }
 
-    pub fn take_segment(&self, min_size: uint) -> StackSegment {
-        StackSegment::new(min_size)
+    /// Creates a new pool which will cache at most `max_cached` segments.
+    pub fn with_capacity(max_cached: uint) -> StackPool {
+        StackPool {
+            stacks: HashMap::new(),
+            cached: 0,
+            max_cached: max_cached,
+        }
     }
 
-    pub fn give_segment(&self, _stack: StackSegment) {
+    /// Returns the number of segments currently cached by this pool.
+    pub fn cached(&self) -> uint { self.cached }
+
+    pub fn take_segment(&mut self, min_size: uint) -> StackSegment {
+        let class = size_class(min_size);
+        let cached = match self.stacks.find_mut(&class) {
+            Some(stacks) => stacks.pop(),
+            None => None,
+        };
+        match cached {
+            Some(stack) => {
+                self.cached -= 1;
+                stack
+            }
+            None => StackSegment::new(class),
+        }
+    }
+
+    pub fn give_segment(&mut self, stack: StackSegment) {
+        // Zero-sized segments are only used for scheduler contexts, and full
+        // pools just let the segment be unmapped.
+        if stack.size() == 0 || self.cached >= self.max_cached { return }
+
+        let class = size_class(stack.size());
+        if class != stack.size() { return }
+        self.stacks.find_or_insert_with(class, |_| ~[]).push(stack);
+        self.cached += 1;
     }
 }
 
+/// Rounds `size` up to a power-of-two number of pages.
+fn size_class(size: uint) -> uint {
+    if size == 0 { return 0 }
+    let page = os::page_size();
+    uint::next_power_of_two(round_up(size, page) / page) * page
+}
+
+fn round_up(from: uint, to: uint) -> uint {
+    if from % to == 0 {
+        from
+    } else {
+        from + to - (from % to)
+    }
+}
+
+/// A thread's record of the guard page of the green stack it's currently
+/// running on, which is what the fault handler checks faulting addresses
+/// against.
+pub struct GuardPage {
+    priv lo: uintptr_t,
+    priv hi: uintptr_t,
+}
+
+/// Installs the process-wide handler which turns a fault on a stack guard
+/// page into a clean abort, along with an alternate signal stack for the
+/// calling thread (the faulting stack can't be used to run the handler).
+///
+/// This must be called on each thread which runs green tasks. The returned
+/// record belongs to the calling thread and must only be updated from it,
+/// with `record_guard_page`. It's null on platforms which don't need one.
+pub fn install_guard_handler() -> *mut GuardPage {
+    unsafe { rust_stack_guard_install() }
+}
+
+/// Tears down the alternate signal stack allocated for this thread by
+/// `install_guard_handler`.
+pub fn uninstall_guard_handler() {
+    unsafe { rust_stack_guard_uninstall() }
+}
+
+/// Records the guard page of the stack which the current thread is about to
+/// run on. Faults inside of `[lo, hi)` are reported as a stack overflow.
+///
+/// This is a couple of plain stores so that it's cheap enough to do on every
+/// context switch.
+#[inline(always)]
+pub unsafe fn record_guard_page(guard: *mut GuardPage, lo: uint, hi: uint) {
+    if guard.is_null() { return }
+    (*guard).lo = lo as uintptr_t;
+    (*guard).hi = hi as uintptr_t;
+}
+
 extern {
     fn rust_valgrind_stack_register(start: *uintptr_t, end: *uintptr_t) -> c_uint;
     fn rust_valgrind_stack_deregister(id: c_uint);
+
+    fn rust_stack_guard_install() -> *mut GuardPage;
+    fn rust_stack_guard_uninstall();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os;
+    use super::{StackPool, StackSegment};
+
+    #[test]
+    fn segment_has_guard_page() {
+        let stack = StackSegment::new(10);
+        let page = os::page_size();
+        assert_eq!(stack.size(), page);
+        assert_eq!(stack.end() as uint - stack.start() as uint, page);
+        let (lo, hi) = stack.guard_page();
+        assert_eq!(hi, stack.start() as uint);
+        assert_eq!(hi - lo, page);
+    }
+
+    #[test]
+    fn pool_reuses_segments() {
+        let mut pool = StackPool::new();
+        let page = os::page_size();
+
+        let stack = pool.take_segment(3 * page);
+        assert_eq!(stack.size(), 4 * page);
+        let start = stack.start();
+        pool.give_segment(stack);
+        assert_eq!(pool.cached(), 1);
+
+        // same size class, so we should get the same segment back
+        let stack = pool.take_segment(4 * page);
+        assert_eq!(stack.start(), start);
+        assert_eq!(pool.cached(), 0);
+
+        // a different size class allocates a fresh segment
+        let other = pool.take_segment(5 * page);
+        assert_eq!(other.size(), 8 * page);
+        pool.give_segment(stack);
+        pool.give_segment(other);
+        assert_eq!(pool.cached(), 2);
+    }
+
+    #[test]
+    fn pool_is_capped() {
+        let mut pool = StackPool::with_capacity(2);
+        let stacks = ~[pool.take_segment(1), pool.take_segment(1),
+                      pool.take_segment(1)];
+        for stack in stacks.move_iter() {
+            pool.give_segment(stack);
+        }
+        assert_eq!(pool.cached(), 2);
+    }
 }
@@ -0,0 +1,270 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runtime statistics for pools of green schedulers
+//!
+//! Each scheduler keeps a set of counters which it bumps as it runs tasks,
+//! steals work and goes to sleep. These can be snapshotted at any time via
+//! `SchedPool::stats`. If the `RUST_SCHED_STATS` environment variable is set to
+//! a number of milliseconds, each pool will also periodically print the
+//! statistics of all of its schedulers to stderr.
+
+use std::cmp;
+use std::libc;
+use std::rt::thread::Thread;
+use std::sync::arc::UnsafeArc;
+use std::sync::atomics::{AtomicUint, AtomicBool, Relaxed, SeqCst};
+use std::sync::deque;
+use std::unstable::sync::Exclusive;
+
+use task::GreenTask;
+
+/// A snapshot of the counters of a single scheduler.
+#[deriving(Clone, Eq)]
+pub struct SchedStats {
+    /// The index of this scheduler in its pool, in order of creation.
+    id: uint,
+    /// The number of times a task other than the scheduler was switched to.
+    tasks_run: uint,
+    /// The number of times this scheduler tried to steal from a work queue.
+    steals_attempted: uint,
+    /// The number of steals which actually yielded a task.
+    steals_succeeded: uint,
+    /// The number of times this scheduler went to sleep for lack of work.
+    sleeps: uint,
+    /// The number of times this scheduler was woken up by another.
+    wakeups: uint,
+    /// The total number of context switches performed by this scheduler.
+    context_switches: uint,
+    /// The number of tasks in this scheduler's work queue when the snapshot
+    /// was taken.
+    queue_len: uint,
+}
+
+struct Counters {
+    tasks_run: AtomicUint,
+    steals_attempted: AtomicUint,
+    steals_succeeded: AtomicUint,
+    sleeps: AtomicUint,
+    wakeups: AtomicUint,
+    context_switches: AtomicUint,
+}
+
+/// The live counters of one scheduler, shared with the pool that owns it.
+///
+/// Counters are only ever written by the scheduler's own thread, so they are
+/// bumped with plain relaxed loads and stores rather than with locked
+/// instructions. Readers may see slightly stale values.
+#[deriving(Clone)]
+pub struct SchedCounters {
+    priv inner: UnsafeArc<Counters>,
+}
+
+impl SchedCounters {
+    pub fn new() -> SchedCounters {
+        SchedCounters {
+            inner: UnsafeArc::new(Counters {
+                tasks_run: AtomicUint::new(0),
+                steals_attempted: AtomicUint::new(0),
+                steals_succeeded: AtomicUint::new(0),
+                sleeps: AtomicUint::new(0),
+                wakeups: AtomicUint::new(0),
+                context_switches: AtomicUint::new(0),
+            })
+        }
+    }
+
+    #[inline]
+    pub fn context_switch(&self, to_task: bool) {
+        unsafe {
+            let c = self.inner.get();
+            bump(&mut (*c).context_switches);
+            if to_task { bump(&mut (*c).tasks_run); }
+        }
+    }
+
+    #[inline]
+    pub fn steal(&self, succeeded: bool) {
+        unsafe {
+            let c = self.inner.get();
+            bump(&mut (*c).steals_attempted);
+            if succeeded { bump(&mut (*c).steals_succeeded); }
+        }
+    }
+
+    #[inline]
+    pub fn sleep(&self) {
+        unsafe { bump(&mut (*self.inner.get()).sleeps) }
+    }
+
+    #[inline]
+    pub fn wakeup(&self) {
+        unsafe { bump(&mut (*self.inner.get()).wakeups) }
+    }
+
+    /// Takes a snapshot of these counters.
+    pub fn snapshot(&self, id: uint, queue_len: uint) -> SchedStats {
+        let c = unsafe { &*self.inner.get() };
+        SchedStats {
+            id: id,
+            tasks_run: c.tasks_run.load(Relaxed),
+            steals_attempted: c.steals_attempted.load(Relaxed),
+            steals_succeeded: c.steals_succeeded.load(Relaxed),
+            sleeps: c.sleeps.load(Relaxed),
+            wakeups: c.wakeups.load(Relaxed),
+            context_switches: c.context_switches.load(Relaxed),
+            queue_len: queue_len,
+        }
+    }
+}
+
+#[inline]
+fn bump(counter: &mut AtomicUint) {
+    let n = counter.load(Relaxed);
+    counter.store(n + 1, Relaxed);
+}
+
+/// The set of schedulers in a pool, shared between the pool and its stats
+/// dumper (schedulers can be added to a pool after it has been created).
+#[deriving(Clone)]
+pub struct StatsRegistry {
+    priv scheds: Exclusive<~[(SchedCounters, deque::Stealer<~GreenTask>)]>,
+}
+
+impl StatsRegistry {
+    pub fn new() -> StatsRegistry {
+        StatsRegistry { scheds: Exclusive::new(~[]) }
+    }
+
+    /// Registers a new scheduler, along with a handle to its work queue.
+    pub fn register(&self, counters: SchedCounters,
+                    queue: deque::Stealer<~GreenTask>) {
+        unsafe {
+            self.scheds.with(|scheds| scheds.push((counters, queue)));
+        }
+    }
+
+    /// Takes a snapshot of all registered schedulers.
+    pub fn snapshot(&self) -> ~[SchedStats] {
+        unsafe {
+            self.scheds.with(|scheds| {
+                scheds.iter().enumerate().map(|(i, &(ref c, ref q))| {
+                    c.snapshot(i, q.len())
+                }).collect()
+            })
+        }
+    }
+}
+
+// How often the dumper checks whether it has been asked to exit
+static DUMP_TICK_MS: uint = 100;
+
+/// A native thread which periodically prints the statistics of a pool to
+/// stderr.
+pub struct StatsDumper {
+    priv done: UnsafeArc<AtomicBool>,
+    priv thread: Thread<()>,
+}
+
+impl StatsDumper {
+    /// Starts a dumper for the pool `pool_id` which prints every `interval`
+    /// milliseconds.
+    pub fn start(pool_id: uint, registry: StatsRegistry,
+                 interval: uint) -> StatsDumper {
+        assert!(interval > 0);
+        let done = UnsafeArc::new(AtomicBool::new(false));
+        let done2 = done.clone();
+        let thread = do Thread::start {
+            let mut elapsed = 0;
+            while !unsafe { (*done2.get()).load(SeqCst) } {
+                let tick = cmp::min(DUMP_TICK_MS, interval - elapsed);
+                sleep_ms(tick);
+                elapsed += tick;
+                if elapsed >= interval {
+                    dump(pool_id, &registry);
+                    elapsed = 0;
+                }
+            }
+            // Always leave behind the final numbers of the pool
+            dump(pool_id, &registry);
+        };
+        StatsDumper { done: done, thread: thread }
+    }
+
+    /// Stops the dumper, waiting for it to print one final report.
+    pub fn stop(self) {
+        let StatsDumper { done, thread } = self;
+        unsafe { (*done.get()).store(true, SeqCst); }
+        thread.join();
+    }
+}
+
+fn dump(pool_id: uint, registry: &StatsRegistry) {
+    for s in registry.snapshot().iter() {
+        rterrln!("sched-stats pool={} sched={} tasks_run={} \
+                  steals={}/{} sleeps={} wakeups={} switches={} queue={}",
+                 pool_id, s.id, s.tasks_run, s.steals_succeeded,
+                 s.steals_attempted, s.sleeps, s.wakeups,
+                 s.context_switches, s.queue_len);
+    }
+}
+
+#[cfg(unix)]
+fn sleep_ms(ms: uint) {
+    unsafe { libc::usleep((ms * 1000) as libc::c_uint); }
+}
+
+#[cfg(windows)]
+fn sleep_ms(ms: uint) {
+    extern "system" { fn Sleep(ms: libc::DWORD); }
+    unsafe { Sleep(ms as libc::DWORD); }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::deque::BufferPool;
+
+    use super::{SchedCounters, StatsRegistry};
+
+    #[test]
+    fn counters() {
+        let c = SchedCounters::new();
+        c.context_switch(true);
+        c.context_switch(false);
+        c.steal(false);
+        c.steal(true);
+        c.sleep();
+        c.wakeup();
+        let s = c.clone().snapshot(3, 7);
+        assert_eq!(s.id, 3);
+        assert_eq!(s.tasks_run, 1);
+        assert_eq!(s.context_switches, 2);
+        assert_eq!(s.steals_attempted, 2);
+        assert_eq!(s.steals_succeeded, 1);
+        assert_eq!(s.sleeps, 1);
+        assert_eq!(s.wakeups, 1);
+        assert_eq!(s.queue_len, 7);
+    }
+
+    #[test]
+    fn registry() {
+        let mut pool = BufferPool::new();
+        let registry = StatsRegistry::new();
+        let (_w1, s1) = pool.deque();
+        let (_w2, s2) = pool.deque();
+        registry.register(SchedCounters::new(), s1);
+        registry.register(SchedCounters::new(), s2);
+        let stats = registry.snapshot();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].id, 0);
+        assert_eq!(stats[1].id, 1);
+        assert_eq!(stats[1].queue_len, 0);
+    }
+}
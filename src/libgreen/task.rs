@@ -119,7 +119,7 @@ impl GreenTask {
         let TaskOpts {
             watched: _watched,
             notify_chan, name, stack_size,
-            stderr, stdout, logger,
+            stderr, stdout, logger, cancel,
         } = opts;
 
         let mut green = GreenTask::new(pool, stack_size, f);
@@ -129,6 +129,7 @@ impl GreenTask {
             task.logger = logger;
             task.stderr = stderr;
             task.stdout = stdout;
+            task.cancel = cancel;
             match notify_chan {
                 Some(chan) => {
                     let on_exit = proc(task_result) { chan.send(task_result) };
@@ -12,6 +12,8 @@
 
 use std::c_str::CString;
 use std::io::IoError;
+use std::io::net::SocketOption;
+use std::io::net::unix::Credentials;
 use std::io;
 use std::libc::{c_int, c_void};
 use std::libc;
@@ -22,6 +24,7 @@ use std::vec;
 
 use io::{IoResult, retry};
 
+#[cfg(unix)] use std::cmp;
 #[cfg(windows)] use std::os::win32::{as_utf16_p, fill_utf16_buf_and_decode};
 #[cfg(windows)] use std::ptr;
 #[cfg(windows)] use std::str;
@@ -53,6 +56,89 @@ pub fn keep_going(data: &[u8], f: |*u8, uint| -> i64) -> i64 {
     return (origamt - amt) as i64;
 }
 
+/// Reads into each of `bufs` in turn with a single `readv` call.
+#[cfg(unix)]
+pub fn readv(fd: fd_t, bufs: &mut [&mut [u8]]) -> IoResult<uint> {
+    let iov: ~[libc::iovec] = bufs.iter().take(IOV_MAX).map(|buf| {
+        libc::iovec {
+            iov_base: buf.as_ptr() as *mut c_void,
+            iov_len: buf.len() as libc::size_t,
+        }
+    }).collect();
+    let total = iov.iter().fold(0, |n, v| n + v.iov_len as uint);
+    let ret = retry_ssize(|| unsafe {
+        libc::readv(fd, iov.as_ptr(), iov.len() as c_int)
+    });
+    if ret == 0 && total > 0 {
+        Err(io::standard_error(io::EndOfFile))
+    } else if ret < 0 {
+        Err(super::last_error())
+    } else {
+        Ok(ret as uint)
+    }
+}
+
+/// Writes all of `bufs` with as few `writev` calls as possible.
+#[cfg(unix)]
+pub fn writev(fd: fd_t, bufs: &[&[u8]]) -> IoResult<()> {
+    // Empty buffers are dropped up front so that a return value of 0 always
+    // means that the descriptor accepted nothing.
+    let mut iov: ~[libc::iovec] = bufs.iter().filter(|buf| buf.len() > 0)
+                                      .map(|buf| {
+        libc::iovec {
+            iov_base: buf.as_ptr() as *mut c_void,
+            iov_len: buf.len() as libc::size_t,
+        }
+    }).collect();
+    let mut cur = 0;
+    while cur < iov.len() {
+        let cnt = cmp::min(iov.len() - cur, IOV_MAX);
+        let ret = retry_ssize(|| unsafe {
+            libc::writev(fd, iov.as_ptr().offset(cur as int), cnt as c_int)
+        });
+        if ret < 0 { return Err(super::last_error()) }
+        if ret == 0 {
+            return Err(IoError {
+                kind: io::OtherIoError,
+                desc: "writev made no progress",
+                detail: None,
+            })
+        }
+
+        // Skip past everything which was written, and trim the front off of a
+        // partially written buffer.
+        let mut n = ret as uint;
+        while cur < iov.len() && n >= iov[cur].iov_len as uint {
+            n -= iov[cur].iov_len as uint;
+            cur += 1;
+        }
+        if n > 0 {
+            let v = &mut iov[cur];
+            v.iov_base = unsafe { (v.iov_base as *mut u8).offset(n as int) }
+                            as *mut c_void;
+            v.iov_len -= n as libc::size_t;
+        }
+    }
+    Ok(())
+}
+
+// Like `retry`, but for calls returning a byte count which may not fit in a
+// c_int, such as vectored transfers of more than 2GB.
+#[cfg(unix)]
+fn retry_ssize(f: || -> libc::ssize_t) -> libc::ssize_t {
+    loop {
+        match f() {
+            -1 if os::errno() as int == libc::EINTR as int => {}
+            n => return n,
+        }
+    }
+}
+
+// The smallest limit on the number of iovecs in one call among supported
+// platforms (POSIX requires at least 16, but everyone allows 1024).
+#[cfg(unix)]
+static IOV_MAX: uint = 1024;
+
 pub type fd_t = libc::c_int;
 
 pub struct FileDesc {
@@ -107,6 +193,43 @@ impl FileDesc {
         }
     }
 
+    #[cfg(unix)]
+    pub fn inner_readv(&mut self, bufs: &mut [&mut [u8]]) -> IoResult<uint> {
+        readv(self.fd, bufs)
+    }
+    #[cfg(windows)]
+    pub fn inner_readv(&mut self, bufs: &mut [&mut [u8]]) -> IoResult<uint> {
+        // There's no readv on windows, so fill the buffers one at a time,
+        // moving on to the next one only when the previous one was filled.
+        let mut total = 0;
+        for buf in bufs.mut_iter().filter(|buf| buf.len() > 0) {
+            match self.inner_read(&mut **buf) {
+                Ok(n) => {
+                    total += n;
+                    if n < buf.len() { break }
+                }
+                Err(..) if total > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
+    }
+
+    #[cfg(unix)]
+    pub fn inner_writev(&mut self, bufs: &[&[u8]]) -> IoResult<()> {
+        writev(self.fd, bufs)
+    }
+    #[cfg(windows)]
+    pub fn inner_writev(&mut self, bufs: &[&[u8]]) -> IoResult<()> {
+        for buf in bufs.iter() {
+            match self.inner_write(*buf) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     pub fn fd(&self) -> fd_t { self.fd }
 }
 
@@ -129,6 +252,12 @@ impl rtio::RtioFileStream for FileDesc {
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError> {
         self.inner_write(buf)
     }
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError> {
+        self.inner_readv(bufs)
+    }
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError> {
+        self.inner_writev(bufs)
+    }
     fn pread(&mut self, buf: &mut [u8], offset: u64) -> Result<int, IoError> {
         return os_pread(self.fd, buf.as_ptr(), buf.len(), offset);
 
@@ -285,6 +414,46 @@ impl rtio::RtioFileStream for FileDesc {
             libc::ftruncate(self.fd, offset as libc::off_t)
         }))
     }
+
+    #[cfg(windows)]
+    fn lock(&mut self, kind: io::LockKind, block: bool) -> Result<(), IoError> {
+        let mut flags = match kind {
+            io::SharedLock => 0,
+            io::ExclusiveLock => libc::LOCKFILE_EXCLUSIVE_LOCK,
+        };
+        if !block { flags |= libc::LOCKFILE_FAIL_IMMEDIATELY; }
+        super::mkerr_winbool(unsafe {
+            let mut overlap: libc::OVERLAPPED = intrinsics::init();
+            let handle = libc::get_osfhandle(self.fd) as libc::HANDLE;
+            libc::LockFileEx(handle, flags, 0, 0xffffffff, 0xffffffff,
+                             &mut overlap)
+        })
+    }
+    #[cfg(unix)]
+    fn lock(&mut self, kind: io::LockKind, block: bool) -> Result<(), IoError> {
+        let mut op = match kind {
+            io::SharedLock => libc::LOCK_SH,
+            io::ExclusiveLock => libc::LOCK_EX,
+        };
+        if !block { op |= libc::LOCK_NB; }
+        super::mkerr_libc(retry(|| unsafe { libc::flock(self.fd, op) }))
+    }
+
+    #[cfg(windows)]
+    fn unlock(&mut self) -> Result<(), IoError> {
+        super::mkerr_winbool(unsafe {
+            let mut overlap: libc::OVERLAPPED = intrinsics::init();
+            let handle = libc::get_osfhandle(self.fd) as libc::HANDLE;
+            libc::UnlockFileEx(handle, 0, 0xffffffff, 0xffffffff, &mut overlap)
+        })
+    }
+    #[cfg(unix)]
+    fn unlock(&mut self) -> Result<(), IoError> {
+        super::mkerr_libc(retry(|| unsafe {
+            libc::flock(self.fd, libc::LOCK_UN)
+        }))
+    }
+    fn fd(&self) -> c_int { self.fd }
 }
 
 impl rtio::RtioPipe for FileDesc {
@@ -294,6 +463,36 @@ impl rtio::RtioPipe for FileDesc {
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError> {
         self.inner_write(buf)
     }
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        super::net::set_option(self.fd as super::net::sock_t, opt)
+    }
+    #[cfg(unix)]
+    fn peer_credentials(&mut self) -> Result<Credentials, IoError> {
+        super::pipe_unix::peer_credentials(self.fd)
+    }
+    #[cfg(windows)]
+    fn peer_credentials(&mut self) -> Result<Credentials, IoError> {
+        Err(super::unimpl())
+    }
+    #[cfg(unix)]
+    fn send_fds(&mut self, buf: &[u8], fds: &[c_int]) -> Result<(), IoError> {
+        super::pipe_unix::send_fds(self.fd, buf, fds)
+    }
+    #[cfg(windows)]
+    fn send_fds(&mut self, _buf: &[u8],
+                _fds: &[c_int]) -> Result<(), IoError> {
+        Err(super::unimpl())
+    }
+    #[cfg(unix)]
+    fn recv_fds(&mut self, buf: &mut [u8],
+                fds: &mut ~[c_int]) -> Result<(uint, bool), IoError> {
+        super::pipe_unix::recv_fds(self.fd, buf, fds)
+    }
+    #[cfg(windows)]
+    fn recv_fds(&mut self, _buf: &mut [u8],
+                _fds: &mut ~[c_int]) -> Result<(uint, bool), IoError> {
+        Err(super::unimpl())
+    }
 }
 
 impl rtio::RtioTTY for FileDesc {
@@ -382,6 +581,24 @@ impl rtio::RtioFileStream for CFile {
         }
     }
 
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError> {
+        // stdio has no vectored reads, and mixing in readv() on the
+        // descriptor would bypass the FILE's buffer.
+        match bufs.mut_iter().find(|buf| buf.len() > 0) {
+            Some(buf) => self.read(&mut **buf).map(|n| n as uint),
+            None => Ok(0),
+        }
+    }
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError> {
+        for buf in bufs.iter() {
+            match self.write(*buf) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     fn pread(&mut self, buf: &mut [u8], offset: u64) -> Result<int, IoError> {
         self.flush();
         self.fd.pread(buf, offset)
@@ -423,6 +640,14 @@ impl rtio::RtioFileStream for CFile {
         self.flush();
         self.fd.truncate(offset)
     }
+    fn lock(&mut self, kind: io::LockKind, block: bool) -> Result<(), IoError> {
+        self.fd.lock(kind, block)
+    }
+    fn unlock(&mut self) -> Result<(), IoError> {
+        self.flush();
+        self.fd.unlock()
+    }
+    fn fd(&self) -> c_int { self.fd.fd() }
 }
 
 impl Drop for CFile {
@@ -527,7 +752,7 @@ pub fn readdir(p: &CString) -> IoResult<~[Path]> {
         #[cfg(windows)]
         unsafe fn get_list(p: &CString) -> IoResult<~[Path]> {
             use std::libc::consts::os::extra::INVALID_HANDLE_VALUE;
-            use std::libc::{wcslen, free};
+            use std::libc::wcslen;
             use std::libc::funcs::extra::kernel32::{
                 FindFirstFileW,
                 FindNextFileW,
@@ -537,7 +762,7 @@ pub fn readdir(p: &CString) -> IoResult<~[Path]> {
             use os::win32::{
                 as_utf16_p
             };
-            use rt::global_heap::malloc_raw;
+            use rt::global_heap::{malloc_raw, exchange_free};
 
             #[nolink]
             extern {
@@ -567,7 +792,7 @@ pub fn readdir(p: &CString) -> IoResult<~[Path]> {
                         more_files = FindNextFileW(find_handle, wfd_ptr as HANDLE);
                     }
                     FindClose(find_handle);
-                    free(wfd_ptr as *mut c_void);
+                    exchange_free(wfd_ptr as *u8);
                     Ok(paths)
                 } else {
                     Err(super::last_error())
@@ -25,6 +25,7 @@ use std::c_str::CString;
 use std::comm::SharedChan;
 use std::io;
 use std::io::IoError;
+use std::io::net::SocketOption;
 use std::io::net::ip::SocketAddr;
 use std::io::process::ProcessConfig;
 use std::io::signal::Signum;
@@ -47,6 +48,12 @@ pub mod file;
 pub mod net;
 pub mod process;
 
+#[cfg(unix)]
+pub mod pipe_unix;
+
+#[cfg(unix)]
+pub mod signal;
+
 #[cfg(target_os = "macos")]
 #[cfg(target_os = "freebsd")]
 #[cfg(target_os = "android")]
@@ -63,6 +70,10 @@ pub mod timer;
 
 mod timer_helper;
 
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+pub mod poll;
+
 pub type IoResult<T> = Result<T, IoError>;
 
 fn unimpl() -> IoError {
@@ -87,6 +98,8 @@ fn translate_error(errno: i32, detail: bool) -> IoError {
             libc::WSAECONNABORTED => (io::ConnectionAborted, "connection aborted"),
             libc::WSAEADDRNOTAVAIL => (io::ConnectionRefused, "address not available"),
             libc::WSAEADDRINUSE => (io::ConnectionRefused, "address in use"),
+            libc::ERROR_LOCK_VIOLATION =>
+                (io::ResourceUnavailable, "file is locked by another process"),
 
             x => {
                 debug!("ignoring {}: {}", x, os::last_os_error());
@@ -188,21 +201,52 @@ impl IoFactory {
 
 impl rtio::IoFactory for IoFactory {
     // networking
-    fn tcp_connect(&mut self, addr: SocketAddr) -> IoResult<~RtioTcpStream> {
-        net::TcpStream::connect(addr).map(|s| ~s as ~RtioTcpStream)
+    fn tcp_connect(&mut self, addr: SocketAddr, local: Option<SocketAddr>,
+                   opts: &[SocketOption]) -> IoResult<~RtioTcpStream> {
+        net::TcpStream::connect_with(addr, local, opts).map(|s| {
+            ~s as ~RtioTcpStream
+        })
+    }
+    fn tcp_bind(&mut self, addr: SocketAddr,
+                opts: &[SocketOption]) -> IoResult<~RtioTcpListener> {
+        net::TcpListener::bind_with(addr, opts).map(|s| ~s as ~RtioTcpListener)
     }
-    fn tcp_bind(&mut self, addr: SocketAddr) -> IoResult<~RtioTcpListener> {
-        net::TcpListener::bind(addr).map(|s| ~s as ~RtioTcpListener)
+    fn udp_bind(&mut self, addr: SocketAddr,
+                opts: &[SocketOption]) -> IoResult<~RtioUdpSocket> {
+        net::UdpSocket::bind_with(addr, opts).map(|u| ~u as ~RtioUdpSocket)
     }
-    fn udp_bind(&mut self, addr: SocketAddr) -> IoResult<~RtioUdpSocket> {
-        net::UdpSocket::bind(addr).map(|u| ~u as ~RtioUdpSocket)
+    #[cfg(unix)]
+    fn unix_bind(&mut self, path: &CString) -> IoResult<~RtioUnixListener> {
+        pipe_unix::UnixListener::bind(path).map(|s| ~s as ~RtioUnixListener)
     }
+    #[cfg(windows)]
     fn unix_bind(&mut self, _path: &CString) -> IoResult<~RtioUnixListener> {
         Err(unimpl())
     }
+    #[cfg(unix)]
+    fn unix_connect(&mut self, path: &CString) -> IoResult<~RtioPipe> {
+        pipe_unix::connect(path).map(|s| ~s as ~RtioPipe)
+    }
+    #[cfg(windows)]
     fn unix_connect(&mut self, _path: &CString) -> IoResult<~RtioPipe> {
         Err(unimpl())
     }
+    #[cfg(unix)]
+    fn unix_bind_abstract(&mut self, name: &[u8]) -> IoResult<~RtioUnixListener> {
+        pipe_unix::UnixListener::bind_abstract(name).map(|s| ~s as ~RtioUnixListener)
+    }
+    #[cfg(windows)]
+    fn unix_bind_abstract(&mut self, _name: &[u8]) -> IoResult<~RtioUnixListener> {
+        Err(unimpl())
+    }
+    #[cfg(unix)]
+    fn unix_connect_abstract(&mut self, name: &[u8]) -> IoResult<~RtioPipe> {
+        pipe_unix::connect_abstract(name).map(|s| ~s as ~RtioPipe)
+    }
+    #[cfg(windows)]
+    fn unix_connect_abstract(&mut self, _name: &[u8]) -> IoResult<~RtioPipe> {
+        Err(unimpl())
+    }
     fn get_host_addresses(&mut self, host: Option<&str>, servname: Option<&str>,
                           hint: Option<ai::Hint>) -> IoResult<~[ai::Info]> {
         addrinfo::GetAddrInfoRequest::run(host, servname, hint)
@@ -289,6 +333,12 @@ impl rtio::IoFactory for IoFactory {
             })
         }
     }
+    #[cfg(unix)]
+    fn signal(&mut self, signum: Signum, channel: SharedChan<Signum>)
+        -> IoResult<~RtioSignal> {
+        signal::Signal::new(signum, channel).map(|s| ~s as ~RtioSignal)
+    }
+    #[cfg(windows)]
     fn signal(&mut self, _signal: Signum, _channel: SharedChan<Signum>)
         -> IoResult<~RtioSignal> {
         Err(unimpl())
@@ -10,6 +10,8 @@
 
 use std::cast;
 use std::io::net::ip;
+use std::io::net::SocketOption;
+use std::io::net;
 use std::io;
 use std::libc;
 use std::mem;
@@ -19,6 +21,9 @@ use std::unstable::intrinsics;
 use super::{IoResult, retry};
 use super::file::keep_going;
 
+/// The backlog of the listening sockets created through `rtio`
+pub static LISTEN_BACKLOG: int = 128;
+
 ////////////////////////////////////////////////////////////////////////////////
 // sockaddr and misc bindings
 ////////////////////////////////////////////////////////////////////////////////
@@ -112,6 +117,102 @@ fn setsockopt<T>(fd: sock_t, opt: libc::c_int, val: libc::c_int,
     }
 }
 
+#[cfg(windows)] type linger_t = libc::c_ushort;
+#[cfg(unix)]    type linger_t = libc::c_int;
+
+pub fn set_option(fd: sock_t, opt: SocketOption) -> IoResult<()> {
+    match opt {
+        net::ReuseAddr(on) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR,
+                       on as libc::c_int)
+        }
+        net::ReusePort(on) => set_reuseport(fd, on),
+        net::SendBufferSize(n) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDBUF, n as libc::c_int)
+        }
+        net::RecvBufferSize(n) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF, n as libc::c_int)
+        }
+        net::Ipv6Only(on) => {
+            setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY,
+                       on as libc::c_int)
+        }
+        net::Linger(secs) => {
+            let linger = libc::linger {
+                l_onoff: secs.is_some() as linger_t,
+                l_linger: secs.unwrap_or(0) as linger_t,
+            };
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, linger)
+        }
+        net::TcpCork(on) => set_cork(fd, on),
+    }
+}
+
+fn set_options(fd: sock_t, opts: &[SocketOption]) -> IoResult<()> {
+    for opt in opts.iter() {
+        match set_option(fd, *opt) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_reuseport(fd: sock_t, on: bool) -> IoResult<()> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT, on as libc::c_int)
+}
+#[cfg(windows)]
+fn set_reuseport(_fd: sock_t, _on: bool) -> IoResult<()> {
+    Err(super::unimpl())
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn set_cork(fd: sock_t, on: bool) -> IoResult<()> {
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_CORK, on as libc::c_int)
+}
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn set_cork(fd: sock_t, on: bool) -> IoResult<()> {
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_NOPUSH, on as libc::c_int)
+}
+#[cfg(windows)]
+fn set_cork(_fd: sock_t, _on: bool) -> IoResult<()> {
+    Err(super::unimpl())
+}
+
+fn bind(fd: sock_t, addr: ip::SocketAddr) -> IoResult<()> {
+    let (addr, len) = addr_to_sockaddr(addr);
+    let addrp = &addr as *libc::sockaddr_storage;
+    match unsafe {
+        libc::bind(fd, addrp as *libc::sockaddr, len as libc::socklen_t)
+    } {
+        -1 => Err(super::last_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn set_nonblocking(fd: sock_t, nonblocking: bool) -> IoResult<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags == -1 {
+        return Err(super::last_error())
+    }
+    let flags = if nonblocking {
+        flags | libc::O_NONBLOCK
+    } else {
+        flags & !libc::O_NONBLOCK
+    };
+    super::mkerr_libc(unsafe { libc::fcntl(fd, libc::F_SETFL, flags) })
+}
+
+#[cfg(windows)]
+fn set_nonblocking(fd: sock_t, nonblocking: bool) -> IoResult<()> {
+    let mut on = nonblocking as libc::c_ulong;
+    super::mkerr_libc(unsafe { libc::ioctlsocket(fd, libc::FIONBIO, &mut on) })
+}
+
 #[cfg(windows)] unsafe fn close(sock: sock_t) { libc::closesocket(sock); }
 #[cfg(unix)]    unsafe fn close(sock: sock_t) { libc::close(sock); }
 
@@ -222,11 +323,29 @@ pub struct TcpStream {
 
 impl TcpStream {
     pub fn connect(addr: ip::SocketAddr) -> IoResult<TcpStream> {
+        TcpStream::connect_with(addr, None, [])
+    }
+
+    /// Connects to `addr` after applying `opts` to the socket and binding it
+    /// to `local`, if given.
+    pub fn connect_with(addr: ip::SocketAddr, local: Option<ip::SocketAddr>,
+                        opts: &[SocketOption]) -> IoResult<TcpStream> {
         unsafe {
             socket(addr, libc::SOCK_STREAM).and_then(|fd| {
+                let ret = TcpStream { fd: fd };
+                match set_options(fd, opts) {
+                    Ok(()) => {}
+                    Err(e) => return Err(e),
+                }
+                match local {
+                    Some(local) => match bind(fd, local) {
+                        Ok(()) => {}
+                        Err(e) => return Err(e),
+                    },
+                    None => {}
+                }
                 let (addr, len) = addr_to_sockaddr(addr);
                 let addrp = &addr as *libc::sockaddr_storage;
-                let ret = TcpStream { fd: fd };
                 match retry(|| {
                     libc::connect(fd, addrp as *libc::sockaddr,
                                   len as libc::socklen_t)
@@ -306,6 +425,71 @@ impl rtio::RtioTcpStream for TcpStream {
             Ok(())
         }
     }
+    #[cfg(unix)]
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> IoResult<uint> {
+        super::file::readv(self.fd, bufs)
+    }
+    #[cfg(windows)]
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> IoResult<uint> {
+        use std::cmp;
+        use std::vec;
+
+        // A second recv could block while the data for the first buffer is
+        // already in hand, so receive once and spread the data over `bufs`.
+        let total = bufs.iter().fold(0, |n, buf| n + buf.len());
+        if total == 0 { return Ok(0) }
+        let mut tmp = vec::from_elem(total, 0u8);
+        let amt = match self.read(tmp) {
+            Ok(n) => n,
+            Err(e) => return Err(e),
+        };
+        let mut off = 0;
+        for buf in bufs.mut_iter() {
+            if off == amt { break }
+            let n = cmp::min(buf.len(), amt - off);
+            vec::bytes::copy_memory(buf.mut_slice_to(n),
+                                    tmp.slice(off, off + n));
+            off += n;
+        }
+        Ok(amt)
+    }
+    #[cfg(unix)]
+    fn writev(&mut self, bufs: &[&[u8]]) -> IoResult<()> {
+        super::file::writev(self.fd, bufs)
+    }
+    #[cfg(windows)]
+    fn writev(&mut self, bufs: &[&[u8]]) -> IoResult<()> {
+        for buf in bufs.iter() {
+            match self.write(*buf) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    fn sendfile(&mut self, fd: libc::c_int, offset: u64,
+                len: u64) -> IoResult<u64> {
+        // Linux transfers at most 0x7ffff000 bytes per call, so clamp here
+        // and let the caller loop.
+        let mut off = offset as libc::off_t;
+        let amt = if len > 0x7ffff000 { 0x7ffff000 } else { len };
+        let ret = retry(|| unsafe {
+            libc::sendfile(self.fd, fd, &mut off, amt as libc::size_t)
+                as libc::c_int
+        });
+        if ret < 0 {
+            Err(super::last_error())
+        } else {
+            Ok(ret as u64)
+        }
+    }
+    #[cfg(not(target_os = "linux"), not(target_os = "android"))]
+    fn sendfile(&mut self, _fd: libc::c_int, _offset: u64,
+                _len: u64) -> IoResult<u64> {
+        Err(super::unimpl())
+    }
     fn peer_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd, libc::getpeername)
     }
@@ -327,6 +511,13 @@ impl rtio::RtioSocket for TcpStream {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd, libc::getsockname)
     }
+    fn set_nonblocking(&mut self, nonblocking: bool) -> IoResult<()> {
+        set_nonblocking(self.fd, nonblocking)
+    }
+    fn set_option(&mut self, opt: SocketOption) -> IoResult<()> {
+        set_option(self.fd, opt)
+    }
+    fn fd(&self) -> libc::c_int { self.fd as libc::c_int }
 }
 
 impl Drop for TcpStream {
@@ -343,18 +534,16 @@ pub struct TcpListener {
 
 impl TcpListener {
     pub fn bind(addr: ip::SocketAddr) -> IoResult<TcpListener> {
-        unsafe {
-            socket(addr, libc::SOCK_STREAM).and_then(|fd| {
-                let (addr, len) = addr_to_sockaddr(addr);
-                let addrp = &addr as *libc::sockaddr_storage;
-                let ret = TcpListener { fd: fd };
-                match libc::bind(fd, addrp as *libc::sockaddr,
-                                 len as libc::socklen_t) {
-                    -1 => Err(super::last_error()),
-                    _ => Ok(ret),
-                }
-            })
-        }
+        TcpListener::bind_with(addr, [])
+    }
+
+    /// Binds to `addr` after applying `opts` to the socket.
+    pub fn bind_with(addr: ip::SocketAddr,
+                     opts: &[SocketOption]) -> IoResult<TcpListener> {
+        socket(addr, libc::SOCK_STREAM).and_then(|fd| {
+            let ret = TcpListener { fd: fd };
+            set_options(fd, opts).and_then(|()| bind(fd, addr)).map(|()| ret)
+        })
     }
 
     pub fn fd(&self) -> sock_t { self.fd }
@@ -369,7 +558,7 @@ impl TcpListener {
 
 impl rtio::RtioTcpListener for TcpListener {
     fn listen(~self) -> IoResult<~rtio::RtioTcpAcceptor> {
-        self.native_listen(128).map(|a| ~a as ~rtio::RtioTcpAcceptor)
+        self.native_listen(LISTEN_BACKLOG).map(|a| ~a as ~rtio::RtioTcpAcceptor)
     }
 }
 
@@ -377,6 +566,13 @@ impl rtio::RtioSocket for TcpListener {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd, libc::getsockname)
     }
+    fn set_nonblocking(&mut self, nonblocking: bool) -> IoResult<()> {
+        set_nonblocking(self.fd, nonblocking)
+    }
+    fn set_option(&mut self, opt: SocketOption) -> IoResult<()> {
+        set_option(self.fd, opt)
+    }
+    fn fd(&self) -> libc::c_int { self.fd as libc::c_int }
 }
 
 impl Drop for TcpListener {
@@ -412,6 +608,13 @@ impl rtio::RtioSocket for TcpAcceptor {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn set_nonblocking(&mut self, nonblocking: bool) -> IoResult<()> {
+        set_nonblocking(self.fd(), nonblocking)
+    }
+    fn set_option(&mut self, opt: SocketOption) -> IoResult<()> {
+        set_option(self.fd(), opt)
+    }
+    fn fd(&self) -> libc::c_int { self.fd() as libc::c_int }
 }
 
 impl rtio::RtioTcpAcceptor for TcpAcceptor {
@@ -433,18 +636,16 @@ pub struct UdpSocket {
 
 impl UdpSocket {
     pub fn bind(addr: ip::SocketAddr) -> IoResult<UdpSocket> {
-        unsafe {
-            socket(addr, libc::SOCK_DGRAM).and_then(|fd| {
-                let (addr, len) = addr_to_sockaddr(addr);
-                let addrp = &addr as *libc::sockaddr_storage;
-                let ret = UdpSocket { fd: fd };
-                match libc::bind(fd, addrp as *libc::sockaddr,
-                                 len as libc::socklen_t) {
-                    -1 => Err(super::last_error()),
-                    _ => Ok(ret),
-                }
-            })
-        }
+        UdpSocket::bind_with(addr, [])
+    }
+
+    /// Binds to `addr` after applying `opts` to the socket.
+    pub fn bind_with(addr: ip::SocketAddr,
+                     opts: &[SocketOption]) -> IoResult<UdpSocket> {
+        socket(addr, libc::SOCK_DGRAM).and_then(|fd| {
+            let ret = UdpSocket { fd: fd };
+            set_options(fd, opts).and_then(|()| bind(fd, addr)).map(|()| ret)
+        })
     }
 
     pub fn fd(&self) -> sock_t { self.fd }
@@ -485,6 +686,13 @@ impl rtio::RtioSocket for UdpSocket {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn set_nonblocking(&mut self, nonblocking: bool) -> IoResult<()> {
+        set_nonblocking(self.fd(), nonblocking)
+    }
+    fn set_option(&mut self, opt: SocketOption) -> IoResult<()> {
+        set_option(self.fd(), opt)
+    }
+    fn fd(&self) -> libc::c_int { self.fd() as libc::c_int }
 }
 
 #[cfg(windows)] type msglen_t = libc::c_int;
@@ -0,0 +1,484 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Blocking Unix domain sockets
+//!
+//! Streams are plain `FileDesc`s, which implement the credential and file
+//! descriptor passing operations of `RtioPipe` with the functions below.
+
+use std::c_str::CString;
+use std::cast;
+use std::io::IoError;
+use std::io::net::unix::Credentials;
+use std::io;
+use std::libc::{c_int, c_void};
+use std::libc;
+use std::mem;
+use std::ptr;
+use std::rt::rtio;
+use std::unstable::intrinsics;
+use std::vec;
+
+use super::{IoResult, retry};
+use super::file::{FileDesc, fd_t, keep_going};
+
+/// The most descriptors accepted in one message, matching Linux's SCM_MAX_FD
+static MAX_FDS: uint = 253;
+
+fn invalid_input(desc: &'static str) -> IoError {
+    IoError {
+        kind: io::InvalidInput,
+        desc: desc,
+        detail: None,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// sockaddr_un and misc bindings
+////////////////////////////////////////////////////////////////////////////////
+
+fn addr_to_sockaddr_un(addr: &CString)
+    -> IoResult<(libc::sockaddr_storage, uint)>
+{
+    let bytes = addr.as_bytes();
+    let bytes = bytes.slice_to(bytes.len() - 1); // chop off the trailing NUL
+    sockaddr_un(bytes, false)
+}
+
+// Abstract names are not nul-terminated, their length is given by the address
+// length alone. They are marked by a nul byte at the start of `sun_path`.
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn abstract_to_sockaddr_un(name: &[u8])
+    -> IoResult<(libc::sockaddr_storage, uint)>
+{
+    sockaddr_un(name, true)
+}
+#[cfg(not(target_os = "linux"), not(target_os = "android"))]
+fn abstract_to_sockaddr_un(_name: &[u8])
+    -> IoResult<(libc::sockaddr_storage, uint)>
+{
+    Err(IoError {
+        kind: io::IoUnavailable,
+        desc: "the abstract socket namespace is only available on linux",
+        detail: None,
+    })
+}
+
+fn sockaddr_un(bytes: &[u8], is_abstract: bool)
+    -> IoResult<(libc::sockaddr_storage, uint)>
+{
+    unsafe {
+        let mut storage: libc::sockaddr_storage = intrinsics::init();
+        let s: &mut libc::sockaddr_un = cast::transmute(&mut storage);
+        let offset = mem::size_of::<libc::sockaddr_un>() - s.sun_path.len();
+        // Either the leading nul of an abstract name or the trailing nul of
+        // a path has to fit as well.
+        if bytes.len() >= s.sun_path.len() {
+            return Err(invalid_input("path must be shorter than SUN_LEN"))
+        }
+        s.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let start = if is_abstract {1} else {0};
+        for (slot, &b) in s.sun_path.mut_slice_from(start).mut_iter()
+                                    .zip(bytes.iter()) {
+            *slot = b as libc::c_char;
+        }
+        let len = offset + bytes.len() + 1;
+        set_sun_len(s, len);
+        Ok((storage, len))
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn set_sun_len(s: &mut libc::sockaddr_un, len: uint) { s.sun_len = len as u8; }
+#[cfg(not(target_os = "macos"), not(target_os = "freebsd"))]
+fn set_sun_len(_s: &mut libc::sockaddr_un, _len: uint) {}
+
+fn unix_socket() -> IoResult<FileDesc> {
+    match unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0) } {
+        -1 => Err(super::last_error()),
+        fd => Ok(FileDesc::new(fd, true)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+type msglen_t = libc::size_t;
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+type msglen_t = libc::socklen_t;
+
+// Control messages are padded to the alignment of `cmsghdr` on every
+// platform but OSX, which only aligns them to 32 bits.
+#[cfg(target_os = "macos")]
+fn cmsg_align(len: uint) -> uint { (len + 3) & !3 }
+#[cfg(not(target_os = "macos"))]
+fn cmsg_align(len: uint) -> uint {
+    let align = mem::size_of::<libc::c_long>();
+    (len + align - 1) & !(align - 1)
+}
+
+fn cmsg_len(data: uint) -> uint {
+    cmsg_align(mem::size_of::<libc::cmsghdr>()) + data
+}
+
+fn cmsg_space(data: uint) -> uint {
+    cmsg_align(mem::size_of::<libc::cmsghdr>()) + cmsg_align(data)
+}
+
+// A zeroed buffer of at least `len` bytes aligned for a `cmsghdr`
+fn control_buffer(len: uint) -> ~[u64] {
+    vec::from_elem((len + 7) / 8, 0u64)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unix streams
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn connect(addr: &CString) -> IoResult<FileDesc> {
+    addr_to_sockaddr_un(addr).and_then(connect_sockaddr)
+}
+
+pub fn connect_abstract(name: &[u8]) -> IoResult<FileDesc> {
+    abstract_to_sockaddr_un(name).and_then(connect_sockaddr)
+}
+
+fn connect_sockaddr((addr, len): (libc::sockaddr_storage, uint))
+    -> IoResult<FileDesc>
+{
+    unix_socket().and_then(|sock| {
+        let addrp = &addr as *libc::sockaddr_storage;
+        match retry(|| unsafe {
+            libc::connect(sock.fd(), addrp as *libc::sockaddr,
+                          len as libc::socklen_t)
+        }) {
+            -1 => Err(super::last_error()),
+            _ => Ok(sock),
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+pub fn peer_credentials(fd: fd_t) -> IoResult<Credentials> {
+    let mut cred: libc::ucred = unsafe { intrinsics::init() };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_PEERCRED,
+                         &mut cred as *mut libc::ucred as *mut c_void,
+                         &mut len)
+    };
+    super::mkerr_libc(ret).map(|()| {
+        Credentials {
+            pid: Some(cred.pid),
+            uid: cred.uid as u32,
+            gid: cred.gid as u32,
+        }
+    })
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+pub fn peer_credentials(fd: fd_t) -> IoResult<Credentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+    let ret = unsafe { libc::getpeereid(fd, &mut uid, &mut gid) };
+    super::mkerr_libc(ret).map(|()| {
+        Credentials { pid: None, uid: uid as u32, gid: gid as u32 }
+    })
+}
+
+pub fn send_fds(fd: fd_t, buf: &[u8], fds: &[c_int]) -> IoResult<()> {
+    if fds.len() == 0 {
+        return FileDesc::new(fd, false).inner_write(buf)
+    }
+    // Stream sockets only deliver ancillary data along with at least one
+    // byte of regular data.
+    if buf.len() == 0 {
+        return Err(invalid_input("file descriptors must be sent with data"))
+    }
+    if fds.len() > MAX_FDS {
+        return Err(invalid_input("too many file descriptors in one message"))
+    }
+
+    let data_len = fds.len() * mem::size_of::<c_int>();
+    let space = cmsg_space(data_len);
+    let mut control = control_buffer(space);
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len() as libc::size_t,
+    };
+    let ret = unsafe {
+        let cmsg = control.as_mut_ptr() as *mut libc::cmsghdr;
+        (*cmsg).cmsg_len = cmsg_len(data_len) as msglen_t;
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        let data = (cmsg as *mut u8).offset(cmsg_len(0) as int);
+        ptr::copy_nonoverlapping_memory(data as *mut c_int, fds.as_ptr(),
+                                        fds.len());
+
+        let mut msg: libc::msghdr = intrinsics::init();
+        msg.msg_iov = &mut iov as *mut libc::iovec;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = space as msglen_t;
+        retry(|| libc::sendmsg(fd, &msg, 0) as c_int)
+    };
+    if ret < 0 {
+        return Err(super::last_error())
+    }
+
+    // The descriptors went out with the first chunk, the rest is plain data
+    let rest = buf.slice_from(ret as uint);
+    let ret = keep_going(rest, |buf, len| unsafe {
+        libc::write(fd, buf as *c_void, len as libc::size_t) as i64
+    });
+    if ret < 0 {
+        Err(super::last_error())
+    } else {
+        Ok(())
+    }
+}
+
+// Received descriptors are close-on-exec, like every other descriptor opened
+// by the runtime. Linux sets the flag atomically with MSG_CMSG_CLOEXEC, other
+// platforms set it right after receiving the descriptors.
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+static RECV_FLAGS: c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(target_os = "linux"), not(target_os = "android"))]
+static RECV_FLAGS: c_int = 0;
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn set_cloexec(_fd: c_int) -> IoResult<()> { Ok(()) }
+#[cfg(not(target_os = "linux"), not(target_os = "android"))]
+fn set_cloexec(fd: c_int) -> IoResult<()> {
+    extern { fn ioctl(fd: c_int, req: libc::c_ulong) -> c_int; }
+    static FIOCLEX: libc::c_ulong = 0x20006601;
+    match unsafe { ioctl(fd, FIOCLEX) } {
+        -1 => Err(super::last_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Reads into `buf`, appending the descriptors passed along with the data to
+/// `fds`. Returns the number of bytes read and whether the descriptors of the
+/// message were truncated, in which case none of them are received but the
+/// data still is.
+pub fn recv_fds(fd: fd_t, buf: &mut [u8],
+                fds: &mut ~[c_int]) -> IoResult<(uint, bool)> {
+    let space = cmsg_space(MAX_FDS * mem::size_of::<c_int>());
+    let mut control = control_buffer(space);
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len() as libc::size_t,
+    };
+    let mut msg: libc::msghdr = unsafe { intrinsics::init() };
+    msg.msg_iov = &mut iov as *mut libc::iovec;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = space as msglen_t;
+
+    let ret = retry(|| unsafe {
+        libc::recvmsg(fd, &mut msg, RECV_FLAGS) as c_int
+    });
+    if ret < 0 {
+        return Err(super::last_error())
+    }
+
+    let mut received = ~[];
+    unsafe {
+        let start = control.as_ptr() as *u8;
+        let end = start.offset(msg.msg_controllen as int);
+        let mut cur = start;
+        while cur.offset(mem::size_of::<libc::cmsghdr>() as int) <= end {
+            let cmsg = cur as *libc::cmsghdr;
+            let len = (*cmsg).cmsg_len as uint;
+            if len < cmsg_len(0) || cur.offset(len as int) > end { break }
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET &&
+               (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = cur.offset(cmsg_len(0) as int) as *c_int;
+                let n = (len - cmsg_len(0)) / mem::size_of::<c_int>();
+                for i in range(0, n) {
+                    received.push(*data.offset(i as int));
+                }
+            }
+            cur = cur.offset(cmsg_align(len) as int);
+        }
+    }
+
+    // The kernel closes the descriptors which didn't fit, so they can't be
+    // delivered faithfully. Close the ones which did fit as well rather than
+    // handing out part of what was sent, but keep the data: it has already
+    // been taken off of the stream.
+    let truncated = msg.msg_flags & libc::MSG_CTRUNC != 0;
+    if truncated {
+        close_all(received);
+        received = ~[];
+    }
+    for &fd in received.iter() {
+        match set_cloexec(fd) {
+            Ok(()) => {}
+            Err(e) => {
+                close_all(received);
+                return Err(e)
+            }
+        }
+    }
+    fds.push_all_move(received);
+
+    if ret == 0 && buf.len() > 0 {
+        Err(io::standard_error(io::EndOfFile))
+    } else {
+        Ok((ret as uint, truncated))
+    }
+}
+
+fn close_all(fds: &[c_int]) {
+    for &fd in fds.iter() {
+        unsafe { libc::close(fd); }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unix listeners
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct UnixListener {
+    priv sock: FileDesc,
+}
+
+impl UnixListener {
+    pub fn bind(addr: &CString) -> IoResult<UnixListener> {
+        addr_to_sockaddr_un(addr).and_then(UnixListener::bind_sockaddr)
+    }
+
+    pub fn bind_abstract(name: &[u8]) -> IoResult<UnixListener> {
+        abstract_to_sockaddr_un(name).and_then(UnixListener::bind_sockaddr)
+    }
+
+    fn bind_sockaddr((addr, len): (libc::sockaddr_storage, uint))
+        -> IoResult<UnixListener>
+    {
+        unix_socket().and_then(|sock| {
+            let addrp = &addr as *libc::sockaddr_storage;
+            match unsafe {
+                libc::bind(sock.fd(), addrp as *libc::sockaddr,
+                           len as libc::socklen_t)
+            } {
+                -1 => Err(super::last_error()),
+                _ => Ok(UnixListener { sock: sock }),
+            }
+        })
+    }
+
+    pub fn native_listen(self, backlog: int) -> IoResult<UnixAcceptor> {
+        match unsafe { libc::listen(self.sock.fd(), backlog as c_int) } {
+            -1 => Err(super::last_error()),
+            _ => Ok(UnixAcceptor { listener: self })
+        }
+    }
+}
+
+impl rtio::RtioUnixListener for UnixListener {
+    fn listen(~self) -> IoResult<~rtio::RtioUnixAcceptor> {
+        self.native_listen(super::net::LISTEN_BACKLOG)
+            .map(|a| ~a as ~rtio::RtioUnixAcceptor)
+    }
+}
+
+pub struct UnixAcceptor {
+    priv listener: UnixListener,
+}
+
+impl UnixAcceptor {
+    pub fn native_accept(&mut self) -> IoResult<FileDesc> {
+        let fd = self.listener.sock.fd();
+        match retry(|| unsafe {
+            libc::accept(fd, ptr::mut_null(), ptr::mut_null())
+        }) {
+            -1 => Err(super::last_error()),
+            fd => Ok(FileDesc::new(fd, true)),
+        }
+    }
+}
+
+impl rtio::RtioUnixAcceptor for UnixAcceptor {
+    fn accept(&mut self) -> IoResult<~rtio::RtioPipe> {
+        self.native_accept().map(|s| ~s as ~rtio::RtioPipe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::c_str::ToCStr;
+    use std::libc;
+    use std::rt::rtio::RtioPipe;
+    use super::{addr_to_sockaddr_un, cmsg_align, cmsg_space};
+    #[cfg(target_os = "linux")] use super::abstract_to_sockaddr_un;
+
+    #[test]
+    fn sockaddr_lengths() {
+        let (_, len) = addr_to_sockaddr_un(&"/tmp/sock".to_c_str()).unwrap();
+        assert_eq!(len, 2 + 9 + 1);
+
+        let long = "x".repeat(200);
+        assert!(addr_to_sockaddr_un(&long.to_c_str()).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_sockaddr() {
+        let (storage, len) = abstract_to_sockaddr_un(bytes!("name")).unwrap();
+        assert_eq!(len, 2 + 1 + 4);
+        let s: &libc::sockaddr_un = unsafe { ::std::cast::transmute(&storage) };
+        assert_eq!(s.sun_path[0], 0);
+        assert_eq!(s.sun_path[1], 'n' as libc::c_char);
+
+        // Paths starting with '@' are just paths
+        let (storage, len) = addr_to_sockaddr_un(&"@name".to_c_str()).unwrap();
+        assert_eq!(len, 2 + 5 + 1);
+        let s: &libc::sockaddr_un = unsafe { ::std::cast::transmute(&storage) };
+        assert_eq!(s.sun_path[0], '@' as libc::c_char);
+    }
+
+    #[test]
+    fn control_sizes() {
+        assert_eq!(cmsg_align(0), 0);
+        assert!(cmsg_align(1) >= 4);
+        assert!(cmsg_space(4) >= cmsg_align(4) + 4);
+    }
+
+    #[test]
+    fn pass_fds_over_socketpair() {
+        let mut fds = [0 as libc::c_int, ..2];
+        assert_eq!(unsafe {
+            libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0,
+                             fds.as_mut_ptr())
+        }, 0);
+        let mut a = super::FileDesc::new(fds[0], true);
+        let mut b = super::FileDesc::new(fds[1], true);
+
+        a.send_fds([1, 2, 3], [libc::STDIN_FILENO, libc::STDOUT_FILENO]).unwrap();
+        let mut buf = [0, ..3];
+        let mut received = ~[];
+        assert_eq!(b.recv_fds(buf, &mut received).unwrap(), (3, false));
+        assert_eq!(buf.to_owned(), ~[1, 2, 3]);
+        assert_eq!(received.len(), 2);
+        for fd in received.iter() {
+            unsafe { libc::close(*fd); }
+        }
+
+        let creds = a.peer_credentials().unwrap();
+        assert_eq!(creds.uid, unsafe { libc::getuid() } as u32);
+    }
+}
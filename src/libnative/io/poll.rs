@@ -0,0 +1,381 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Readiness notifications based on epoll(7)
+//!
+//! Native I/O otherwise blocks the calling OS thread for the duration of each
+//! operation, which means a server needs one thread per connection. A
+//! `Poller` instead waits on many sockets at once: handles are put into
+//! non-blocking mode (see `TcpStream::set_nonblocking` and friends) and
+//! registered with a poller, and `poll` then reports which of them have
+//! become readable or writable. A handful of threads, each with their own
+//! poller, can multiplex thousands of connections this way.
+//!
+//! Each registration carries a caller-chosen `token` which is handed back in
+//! the corresponding `Event`s, typically an index into a table of
+//! connections.
+//!
+//! Registrations are either level-triggered, in which case a handle is
+//! reported on every call to `poll` for as long as it is ready, or
+//! edge-triggered, in which case it's only reported when it transitions to
+//! being ready. With edge triggering, a handle must be drained until it
+//! raises a `ResourceUnavailable` error before it'll be reported again.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use native::io::poll::{Poller, Readable, LevelTriggered};
+//!
+//! let mut acceptor = TcpListener::bind(addr).listen().unwrap();
+//! acceptor.set_nonblocking(true);
+//!
+//! let mut poller = Poller::new().unwrap();
+//! poller.register(&acceptor, 0, Readable, LevelTriggered);
+//! loop {
+//!     for event in poller.poll(None).unwrap().iter() {
+//!         // event.token == 0, so accept the pending connection
+//!     }
+//! }
+//! ```
+
+use std::i32;
+use std::io::net::tcp;
+use std::io::net::udp;
+use std::libc;
+use std::os;
+use std::vec;
+
+use io::{IoResult, FileDesc};
+use io::file::fd_t;
+use io::net;
+
+/// Which kinds of readiness a registration is interested in.
+#[deriving(Eq)]
+pub enum Interest {
+    Readable,
+    Writable,
+    ReadWrite,
+}
+
+/// How readiness is reported for a registration, see the module
+/// documentation.
+#[deriving(Eq)]
+pub enum Trigger {
+    LevelTriggered,
+    EdgeTriggered,
+}
+
+/// A readiness notification returned from `Poller::poll`.
+#[deriving(Eq, Clone)]
+pub struct Event {
+    /// The token the handle was registered with.
+    token: uint,
+    /// Data (or a pending connection) can be read without blocking.
+    readable: bool,
+    /// Data can be written without blocking.
+    writable: bool,
+    /// The peer hung up. Any remaining data can still be read.
+    hangup: bool,
+    /// An error is pending on the handle.
+    error: bool,
+}
+
+/// Handles which are backed by a file descriptor and can be registered with a
+/// `Poller`.
+pub trait Evented {
+    fn evented_fd(&self) -> fd_t;
+}
+
+/// A set of registered handles which can be waited on for readiness.
+pub struct Poller {
+    priv epfd: FileDesc,
+    priv events: ~[imp::epoll_event],
+    priv ready: ~[Event],
+}
+
+impl Poller {
+    /// Creates a new poller with no registrations.
+    pub fn new() -> IoResult<Poller> {
+        Poller::with_capacity(1024)
+    }
+
+    /// Creates a new poller which reports at most `capacity` events from each
+    /// call to `poll`.
+    pub fn with_capacity(capacity: uint) -> IoResult<Poller> {
+        assert!(capacity > 0);
+        match unsafe { imp::epoll_create1(imp::EPOLL_CLOEXEC) } {
+            -1 => Err(super::last_error()),
+            fd => Ok(Poller {
+                epfd: FileDesc::new(fd, true),
+                events: vec::from_elem(capacity, imp::epoll_event::empty()),
+                ready: vec::with_capacity(capacity),
+            }),
+        }
+    }
+
+    /// Starts watching `handle` for the readiness described by `interest`,
+    /// reporting events for it with `token`.
+    pub fn register<E: Evented>(&mut self, handle: &E, token: uint,
+                                interest: Interest,
+                                trigger: Trigger) -> IoResult<()> {
+        self.ctl(imp::EPOLL_CTL_ADD, handle.evented_fd(),
+                 imp::epoll_event::new(token, interest, trigger))
+    }
+
+    /// Changes the token, interest or trigger mode of a handle which is
+    /// already registered.
+    pub fn reregister<E: Evented>(&mut self, handle: &E, token: uint,
+                                  interest: Interest,
+                                  trigger: Trigger) -> IoResult<()> {
+        self.ctl(imp::EPOLL_CTL_MOD, handle.evented_fd(),
+                 imp::epoll_event::new(token, interest, trigger))
+    }
+
+    /// Stops watching `handle`. Handles are also unregistered automatically
+    /// when they're closed.
+    pub fn deregister<E: Evented>(&mut self, handle: &E) -> IoResult<()> {
+        self.ctl(imp::EPOLL_CTL_DEL, handle.evented_fd(),
+                 imp::epoll_event::empty())
+    }
+
+    /// Blocks the calling thread until at least one registered handle is
+    /// ready, or until `timeout_ms` milliseconds have passed. Returns the
+    /// ready handles, which is empty on timeout or if the wait was
+    /// interrupted by a signal.
+    pub fn poll<'a>(&'a mut self, timeout_ms: Option<u64>)
+        -> IoResult<&'a [Event]>
+    {
+        let timeout = match timeout_ms {
+            Some(ms) if ms > (i32::MAX as u64) => i32::MAX as libc::c_int,
+            Some(ms) => ms as libc::c_int,
+            None => -1,
+        };
+        self.ready.truncate(0);
+        let n = unsafe {
+            imp::epoll_wait(self.epfd.fd(), self.events.as_mut_ptr(),
+                            self.events.len() as libc::c_int, timeout)
+        };
+        match n {
+            -1 if os::errno() == libc::EINTR as int => {}
+            -1 => return Err(super::last_error()),
+            n => {
+                for event in self.events.slice_to(n as uint).iter() {
+                    self.ready.push(event.to_event());
+                }
+            }
+        }
+        Ok(self.ready.as_slice())
+    }
+
+    fn ctl(&mut self, op: libc::c_int, fd: fd_t,
+           event: imp::epoll_event) -> IoResult<()> {
+        super::mkerr_libc(unsafe {
+            imp::epoll_ctl(self.epfd.fd(), op, fd, &event)
+        })
+    }
+}
+
+impl Evented for FileDesc {
+    fn evented_fd(&self) -> fd_t { self.fd() }
+}
+
+impl Evented for net::TcpStream {
+    fn evented_fd(&self) -> fd_t { self.fd() }
+}
+
+impl Evented for net::TcpAcceptor {
+    fn evented_fd(&self) -> fd_t { self.fd() }
+}
+
+impl Evented for net::UdpSocket {
+    fn evented_fd(&self) -> fd_t { self.fd() }
+}
+
+impl Evented for tcp::TcpStream {
+    fn evented_fd(&self) -> fd_t { self.raw_fd() }
+}
+
+impl Evented for tcp::TcpAcceptor {
+    fn evented_fd(&self) -> fd_t { self.raw_fd() }
+}
+
+impl Evented for udp::UdpSocket {
+    fn evented_fd(&self) -> fd_t { self.raw_fd() }
+}
+
+mod imp {
+    use std::libc;
+
+    use super::{Event, Interest, Trigger, Readable, Writable, ReadWrite,
+                EdgeTriggered};
+
+    pub static EPOLL_CLOEXEC: libc::c_int = 0x80000;
+    pub static EPOLL_CTL_ADD: libc::c_int = 1;
+    pub static EPOLL_CTL_DEL: libc::c_int = 2;
+    pub static EPOLL_CTL_MOD: libc::c_int = 3;
+    pub static EPOLLIN: u32 = 0x001;
+    pub static EPOLLOUT: u32 = 0x004;
+    pub static EPOLLERR: u32 = 0x008;
+    pub static EPOLLHUP: u32 = 0x010;
+    pub static EPOLLRDHUP: u32 = 0x2000;
+    pub static EPOLLET: u32 = 1 << 31;
+
+    // The 64-bit user data is split in two so that this struct has the same
+    // 12-byte layout as the C definition on x86 and x86_64 (where it's
+    // packed). ARM and MIPS align the data to 8 bytes instead.
+    #[cfg(target_arch = "x86")]
+    #[cfg(target_arch = "x86_64")]
+    #[deriving(Clone)]
+    pub struct epoll_event {
+        events: u32,
+        data: [u32, ..2],
+    }
+
+    #[cfg(target_arch = "arm")]
+    #[cfg(target_arch = "mips")]
+    #[deriving(Clone)]
+    pub struct epoll_event {
+        events: u32,
+        pad: u32,
+        data: [u32, ..2],
+    }
+
+    impl epoll_event {
+        #[cfg(target_arch = "x86")]
+        #[cfg(target_arch = "x86_64")]
+        pub fn empty() -> epoll_event {
+            epoll_event { events: 0, data: [0, 0] }
+        }
+        #[cfg(target_arch = "arm")]
+        #[cfg(target_arch = "mips")]
+        pub fn empty() -> epoll_event {
+            epoll_event { events: 0, pad: 0, data: [0, 0] }
+        }
+
+        pub fn new(token: uint, interest: Interest,
+                   trigger: Trigger) -> epoll_event {
+            let mut ret = epoll_event::empty();
+            let token = token as u64;
+            ret.data = [token as u32, (token >> 32) as u32];
+            ret.events = EPOLLRDHUP | match interest {
+                Readable => EPOLLIN,
+                Writable => EPOLLOUT,
+                ReadWrite => EPOLLIN | EPOLLOUT,
+            };
+            if trigger == EdgeTriggered {
+                ret.events |= EPOLLET;
+            }
+            ret
+        }
+
+        pub fn to_event(&self) -> Event {
+            let token = (self.data[1] as u64 << 32) | (self.data[0] as u64);
+            Event {
+                token: token as uint,
+                readable: self.events & EPOLLIN != 0,
+                writable: self.events & EPOLLOUT != 0,
+                hangup: self.events & (EPOLLHUP | EPOLLRDHUP) != 0,
+                error: self.events & EPOLLERR != 0,
+            }
+        }
+    }
+
+    extern {
+        pub fn epoll_create1(flags: libc::c_int) -> libc::c_int;
+        pub fn epoll_ctl(epfd: libc::c_int,
+                         op: libc::c_int,
+                         fd: libc::c_int,
+                         event: *epoll_event) -> libc::c_int;
+        pub fn epoll_wait(epfd: libc::c_int,
+                          events: *mut epoll_event,
+                          maxevents: libc::c_int,
+                          timeout: libc::c_int) -> libc::c_int;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::io::test::next_test_ip4;
+    use std::os;
+    use std::rt::rtio::{RtioSocket, RtioTcpStream};
+
+    use io::FileDesc;
+    use io::net::{TcpListener, TcpStream};
+    use super::{Poller, Readable, ReadWrite, LevelTriggered, EdgeTriggered};
+
+    #[test]
+    fn level_and_edge_triggered() {
+        let os::Pipe { input, out } = os::pipe();
+        let mut reader = FileDesc::new(input, true);
+        let mut writer = FileDesc::new(out, true);
+
+        let mut poller = Poller::new().unwrap();
+        poller.register(&reader, 7, Readable, LevelTriggered).unwrap();
+        assert_eq!(poller.poll(Some(0)).unwrap().len(), 0);
+
+        writer.inner_write([1, 2]).unwrap();
+        for _ in range(0, 2) {
+            let events = poller.poll(Some(0)).unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].token, 7);
+            assert!(events[0].readable);
+            assert!(!events[0].writable);
+        }
+
+        // An edge-triggered registration isn't reported again until more data
+        // arrives.
+        poller.reregister(&reader, 8, Readable, EdgeTriggered).unwrap();
+        assert_eq!(poller.poll(Some(0)).unwrap().len(), 1);
+        assert_eq!(poller.poll(Some(0)).unwrap().len(), 0);
+        writer.inner_write([3]).unwrap();
+        assert_eq!(poller.poll(Some(0)).unwrap()[0].token, 8);
+
+        let mut buf = [0, ..3];
+        assert_eq!(reader.inner_read(buf).unwrap(), 3);
+        poller.deregister(&reader).unwrap();
+        writer.inner_write([4]).unwrap();
+        assert_eq!(poller.poll(Some(0)).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn nonblocking_sockets() {
+        let addr = next_test_ip4();
+        let mut acceptor = TcpListener::bind(addr).unwrap()
+                                                  .native_listen(128).unwrap();
+        acceptor.set_nonblocking(true).unwrap();
+        match acceptor.native_accept() {
+            Err(e) => assert_eq!(e.kind, io::ResourceUnavailable),
+            Ok(..) => fail!("accepted a connection out of nowhere"),
+        }
+
+        let mut poller = Poller::new().unwrap();
+        poller.register(&acceptor, 0, Readable, LevelTriggered).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        assert_eq!(poller.poll(Some(5000)).unwrap()[0].token, 0);
+
+        let mut server = acceptor.native_accept().unwrap();
+        server.set_nonblocking(true).unwrap();
+        poller.register(&server, 1, ReadWrite, LevelTriggered).unwrap();
+        let mut buf = [0, ..1];
+        match server.read(buf) {
+            Err(e) => assert_eq!(e.kind, io::ResourceUnavailable),
+            Ok(..) => fail!("read data out of nowhere"),
+        }
+
+        client.write([5]).unwrap();
+        loop {
+            let events = poller.poll(Some(5000)).unwrap();
+            if events.iter().any(|e| e.token == 1 && e.readable) { break }
+        }
+        assert_eq!(server.read(buf).unwrap(), 1);
+        assert_eq!(buf[0], 5);
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Signal delivery for native tasks
+//!
+//! Signals are delivered with the "self-pipe trick". A process-wide pipe is
+//! created along with a helper thread which sits in a blocking read on the
+//! reading half of the pipe. The C signal handler installed for every signal
+//! which has a listener does nothing but write the signal number as a single
+//! byte to the writing half of the pipe (which is async-signal-safe). The
+//! helper thread then forwards each signal it reads to every listener
+//! registered for that signal number.
+//!
+//! Handlers are installed with SA_RESTART, so the system calls they interrupt
+//! are restarted. Once the last listener for a signal is gone, the action the
+//! signal had before its first listener was registered is restored (so that,
+//! for example, a SIGHUP ignored by nohup stays ignored).
+//!
+//! Registering and unregistering listeners is done by sending a request to the
+//! helper thread and then writing a 0 byte (which is never a valid signal
+//! number) to the pipe to wake it up, and waiting for the helper to reply. The
+//! helper thread owns all of the listeners, so no locking is needed.
+//!
+//! Much like the timer helper, the signal helper is lazily initialized and is
+//! only shut down once the entire program has finished.
+
+use std::cast;
+use std::comm::Data;
+use std::io::signal::Signum;
+use std::libc;
+use std::os;
+use std::rt;
+use std::rt::rtio;
+use std::sync::atomics;
+use std::unstable::mutex::{Once, ONCE_INIT};
+
+use bookkeeping;
+use io::IoResult;
+use io::file::FileDesc;
+use task;
+
+// As with the timer helper, these are initialized once before any listener is
+// registered and only torn down after everything else has exited, so they are
+// effectively read-only while in use. WRITE_FD is also read from the signal
+// handler, which is why it's a plain file descriptor.
+static mut HELPER_CHAN: *mut SharedChan<Req> = 0 as *mut SharedChan<Req>;
+static mut WRITE_FD: libc::c_int = -1;
+
+enum Req {
+    // Add a new listener with the given id, replying on the channel once the
+    // handler for the signal has been installed.
+    NewListener(uint, Signum, SharedChan<Signum>, Chan<IoResult<()>>),
+    // Remove the listener with the given id, replying on the channel once it
+    // won't be sent any more signals
+    RemoveListener(uint, Chan<()>),
+    // Uninstall all handlers and exit the helper thread
+    Shutdown,
+}
+
+pub struct Signal {
+    priv id: uint,
+}
+
+impl Signal {
+    pub fn new(signum: Signum, channel: SharedChan<Signum>)
+        -> IoResult<Signal>
+    {
+        static mut NEXT_ID: atomics::AtomicUint = atomics::INIT_ATOMIC_UINT;
+
+        boot();
+        let id = unsafe { NEXT_ID.fetch_add(1, atomics::SeqCst) };
+        let (port, chan) = Chan::new();
+        send(NewListener(id, signum, channel, chan));
+        port.recv().map(|()| Signal { id: id })
+    }
+}
+
+impl rtio::RtioSignal for Signal {}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        let (port, chan) = Chan::new();
+        send(RemoveListener(self.id, chan));
+        port.recv();
+    }
+}
+
+fn boot() {
+    static mut INIT: Once = ONCE_INIT;
+
+    unsafe {
+        INIT.doit(|| {
+            let (msgp, msgc) = SharedChan::new();
+            HELPER_CHAN = cast::transmute(~msgc);
+
+            // The signal handler must never block, so the writing half of the
+            // pipe is nonblocking. If the pipe is full then the helper thread
+            // has plenty of pending wakeups already.
+            let pipe = os::pipe();
+            imp::set_nonblocking(pipe.out);
+            WRITE_FD = pipe.out;
+            let input = pipe.input;
+
+            do task::spawn {
+                bookkeeping::decrement();
+                helper(input, msgp);
+            }
+
+            rt::at_exit(proc() { shutdown() });
+        })
+    }
+}
+
+fn send(req: Req) {
+    unsafe {
+        assert!(!HELPER_CHAN.is_null());
+        (*HELPER_CHAN).send(req);
+        imp::wakeup(WRITE_FD, 0);
+    }
+}
+
+fn shutdown() {
+    // See timer_helper::shutdown for why the task count is incremented here
+    bookkeeping::increment();
+
+    send(Shutdown);
+    bookkeeping::wait_for_other_tasks();
+
+    unsafe {
+        let _fd = FileDesc::new(WRITE_FD, true);
+        let _chan: ~SharedChan<Req> = cast::transmute(HELPER_CHAN);
+        HELPER_CHAN = 0 as *mut SharedChan<Req>;
+        WRITE_FD = -1;
+    }
+}
+
+fn helper(input: libc::c_int, messages: Port<Req>) {
+    let mut input = FileDesc::new(input, true);
+    let mut listeners: ~[(uint, Signum, SharedChan<Signum>)] = ~[];
+    // The actions replaced by our handler, to be restored on uninstall
+    let mut previous: ~[(Signum, imp::Action)] = ~[];
+    let mut buf = [0u8, ..128];
+
+    loop {
+        let amt = match input.inner_read(buf) {
+            Ok(n) => n,
+            Err(e) => fail!("failed to read the signal pipe: {}", e),
+        };
+
+        // Requests are always drained, not just when a 0 byte was read. A
+        // wakeup byte may have been dropped because the pipe was full, but in
+        // that case the request was queued before this read returned. They
+        // are handled before the signals which were read, so that no signal
+        // is sent to a listener after its removal has been acknowledged.
+        loop {
+            match messages.try_recv() {
+                Data(NewListener(id, signum, chan, ack)) => {
+                    let ret = if is_installed(listeners, signum) {
+                        Ok(())
+                    } else {
+                        imp::install(signum).map(|old| {
+                            previous.push((signum, old));
+                        })
+                    };
+                    if ret.is_ok() {
+                        listeners.push((id, signum, chan));
+                    }
+                    ack.send(ret);
+                }
+                Data(RemoveListener(id, ack)) => {
+                    match listeners.iter().position(|&(i, _, _)| i == id) {
+                        Some(i) => {
+                            let (_, signum, _) = listeners.remove(i).unwrap();
+                            if !is_installed(listeners, signum) {
+                                let j = previous.iter().position(|&(s, _)| {
+                                    s == signum
+                                }).unwrap();
+                                let (_, old) = previous.remove(j).unwrap();
+                                imp::uninstall(signum, &old);
+                            }
+                        }
+                        None => {}
+                    }
+                    ack.send(());
+                }
+                Data(Shutdown) => {
+                    for &(signum, ref old) in previous.iter() {
+                        imp::uninstall(signum, old);
+                    }
+                    return
+                }
+                _ => break,
+            }
+        }
+
+        for &byte in buf.slice_to(amt).iter() {
+            if byte == 0 { continue }
+            for &(_, signum, ref chan) in listeners.iter() {
+                if signum as u8 == byte {
+                    chan.try_send(signum);
+                }
+            }
+        }
+    }
+}
+
+fn is_installed(listeners: &[(uint, Signum, SharedChan<Signum>)],
+                signum: Signum) -> bool {
+    listeners.iter().any(|&(_, s, _)| s == signum)
+}
+
+mod imp {
+    use std::cast;
+    use std::io::signal::Signum;
+    use std::libc;
+    use std::os;
+    use std::ptr;
+    use std::unstable::intrinsics;
+
+    use io::IoResult;
+
+    pub use self::platform::Action;
+    use self::platform::SA_RESTART;
+
+    type sighandler_t = libc::size_t;
+
+    extern {
+        fn sigaction(signum: libc::c_int, act: *Action,
+                     oldact: *mut Action) -> libc::c_int;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    extern {
+        #[link_name = "__errno_location"]
+        fn errno_location() -> *mut libc::c_int;
+    }
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    extern {
+        #[link_name = "__error"]
+        fn errno_location() -> *mut libc::c_int;
+    }
+
+    #[cfg(target_os = "linux")]
+    mod platform {
+        use std::libc;
+
+        pub static SA_RESTART: libc::c_int = 0x10000000;
+
+        #[cfg(target_word_size = "32")]
+        pub type sigset_t = [u32, ..32];
+        #[cfg(target_word_size = "64")]
+        pub type sigset_t = [u64, ..16];
+
+        pub struct Action {
+            sa_handler: super::sighandler_t,
+            sa_mask: sigset_t,
+            sa_flags: libc::c_int,
+            sa_restorer: *libc::c_void,
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    mod platform {
+        use std::libc;
+
+        pub static SA_RESTART: libc::c_int = 0x10000000;
+
+        pub struct Action {
+            sa_handler: super::sighandler_t,
+            sa_mask: libc::c_ulong,
+            sa_flags: libc::c_int,
+            sa_restorer: *libc::c_void,
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    mod platform {
+        use std::libc;
+
+        pub static SA_RESTART: libc::c_int = 0x0002;
+
+        pub struct Action {
+            sa_handler: super::sighandler_t,
+            sa_mask: u32,
+            sa_flags: libc::c_int,
+        }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    mod platform {
+        use std::libc;
+
+        pub static SA_RESTART: libc::c_int = 0x0002;
+
+        pub struct Action {
+            sa_handler: super::sighandler_t,
+            sa_flags: libc::c_int,
+            sa_mask: [u32, ..4],
+        }
+    }
+
+    // The write to the pipe may clobber errno, which the interrupted code
+    // could be just about to read.
+    extern fn handler(signum: libc::c_int) {
+        unsafe {
+            let errno = os::errno();
+            wakeup(super::WRITE_FD, signum as u8);
+            *errno_location() = errno as libc::c_int;
+        }
+    }
+
+    fn handler_addr() -> sighandler_t {
+        unsafe { cast::transmute(handler) }
+    }
+
+    pub fn set_nonblocking(fd: libc::c_int) {
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            assert!(flags != -1);
+            assert!(libc::fcntl(fd, libc::F_SETFL,
+                                flags | libc::O_NONBLOCK) != -1);
+        }
+    }
+
+    // Only async-signal-safe functions may be called in here. A failed write
+    // (i.e. EAGAIN on a full pipe) is fine to ignore, see the helper loop.
+    pub unsafe fn wakeup(fd: libc::c_int, byte: u8) {
+        libc::write(fd, &byte as *u8 as *libc::c_void, 1);
+    }
+
+    /// Installs the handler for `signum`, returning the action it replaced
+    pub fn install(signum: Signum) -> IoResult<Action> {
+        unsafe {
+            let mut action: Action = intrinsics::init();
+            action.sa_handler = handler_addr();
+            action.sa_flags = SA_RESTART;
+            let mut old: Action = intrinsics::init();
+            match sigaction(signum as libc::c_int, &action, &mut old) {
+                0 => Ok(old),
+                _ => Err(super::super::last_error()),
+            }
+        }
+    }
+
+    /// Restores the action `old` replaced by `install`, unless somebody else
+    /// has installed another action for `signum` since.
+    pub fn uninstall(signum: Signum, old: &Action) {
+        unsafe {
+            let mut current: Action = intrinsics::init();
+            sigaction(signum as libc::c_int, ptr::null(), &mut current);
+            if current.sa_handler == handler_addr() {
+                sigaction(signum as libc::c_int, old, ptr::mut_null());
+            }
+        }
+    }
+}
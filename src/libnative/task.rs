@@ -59,7 +59,7 @@ pub fn spawn_opts(opts: TaskOpts, f: proc()) {
     let TaskOpts {
         watched: _watched,
         notify_chan, name, stack_size,
-        logger, stderr, stdout,
+        logger, stderr, stdout, cancel,
     } = opts;
 
     let mut task = ~Task::new();
@@ -67,6 +67,7 @@ pub fn spawn_opts(opts: TaskOpts, f: proc()) {
     task.logger = logger;
     task.stderr = stderr;
     task.stdout = stdout;
+    task.cancel = cancel;
     match notify_chan {
         Some(chan) => {
             let on_exit = proc(task_result) { chan.send(task_result) };
@@ -1270,7 +1270,9 @@ pub mod llvm {
                                   SingleThreaded: Bool)
                                   -> ValueRef;
 
-        pub fn LLVMBuildAtomicFence(B: BuilderRef, Order: AtomicOrdering);
+        pub fn LLVMBuildAtomicFence(B: BuilderRef,
+                                    Order: AtomicOrdering,
+                                    SingleThreaded: Bool);
 
 
         /* Selected entries from the downcasts. */
@@ -273,4 +273,7 @@ lets_do_this! {
     37, ManagedHeapLangItem,             "managed_heap",            managed_heap;
     38, ExchangeHeapLangItem,            "exchange_heap",           exchange_heap;
     39, GcLangItem,                      "gc",                      gc;
+
+    40, GlobalAllocatorLangItem,         "global_allocator",        global_allocator;
+    41, AllocatorTraitLangItem,          "allocator",               allocator_trait;
 }
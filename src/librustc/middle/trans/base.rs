@@ -2588,6 +2588,7 @@ pub fn decl_crate_map(sess: session::Session, mapmeta: LinkMeta,
         slicetype,          // child modules
         slicetype,          // sub crate-maps
         int_type.ptr_to(),  // event loop factory
+        int_type.ptr_to(),  // global allocator
     ], false);
     let map = sym_name.with_c_str(|buf| {
         unsafe {
@@ -2622,7 +2623,7 @@ pub fn fill_crate_map(ccx: @CrateContext, map: ValueRef) {
         subcrates.push(p2i(ccx, cr));
         i += 1;
     }
-    let event_loop_factory = match ccx.tcx.lang_items.event_loop_factory() {
+    let lang_item_fn = |item: Option<ast::DefId>| match item {
         Some(did) => unsafe {
             if is_local(did) {
                 llvm::LLVMConstPointerCast(get_item_val(ccx, did.node),
@@ -2637,6 +2638,8 @@ pub fn fill_crate_map(ccx: @CrateContext, map: ValueRef) {
         },
         None => C_null(ccx.int_type.ptr_to())
     };
+    let event_loop_factory = lang_item_fn(ccx.tcx.lang_items.event_loop_factory());
+    let global_allocator = lang_item_fn(ccx.tcx.lang_items.global_allocator());
     unsafe {
         let maptype = Type::array(&ccx.int_type, subcrates.len() as u64);
         let vec_elements = "_crate_map_child_vectors".with_c_str(|buf| {
@@ -2648,7 +2651,7 @@ pub fn fill_crate_map(ccx: @CrateContext, map: ValueRef) {
         let (mod_map, mod_count) = create_module_map(ccx);
 
         llvm::LLVMSetInitializer(map, C_struct(
-            [C_i32(2),
+            [C_i32(3),
              C_struct([
                 p2i(ccx, mod_map),
                 C_uint(ccx, mod_count)
@@ -2658,6 +2661,7 @@ pub fn fill_crate_map(ccx: @CrateContext, map: ValueRef) {
                 C_uint(ccx, subcrates.len())
              ], false),
             event_loop_factory,
+            global_allocator,
         ], false));
     }
 }
@@ -11,7 +11,7 @@
 use lib;
 use lib::llvm::llvm;
 use lib::llvm::{CallConv, AtomicBinOp, AtomicOrdering, AsmDialect};
-use lib::llvm::{Opcode, IntPredicate, RealPredicate, False};
+use lib::llvm::{Opcode, IntPredicate, RealPredicate, False, True};
 use lib::llvm::{ValueRef, BasicBlockRef, BuilderRef, ModuleRef};
 use middle::trans::base;
 use middle::trans::common::*;
@@ -950,9 +950,10 @@ impl Builder {
         }
     }
 
-    pub fn atomic_fence(&self, order: AtomicOrdering) {
+    pub fn atomic_fence(&self, order: AtomicOrdering, single_threaded: bool) {
         unsafe {
-            llvm::LLVMBuildAtomicFence(self.llbuilder, order);
+            let single_threaded = if single_threaded { True } else { False };
+            llvm::LLVMBuildAtomicFence(self.llbuilder, order, single_threaded);
         }
     }
 }
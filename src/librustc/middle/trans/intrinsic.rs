@@ -207,7 +207,11 @@ pub fn trans_intrinsic(ccx: @CrateContext,
                 RetVoid(bcx);
             }
             "fence" => {
-                AtomicFence(bcx, order);
+                AtomicFence(bcx, order, false);
+                RetVoid(bcx);
+            }
+            "singlethreadfence" => {
+                AtomicFence(bcx, order, true);
                 RetVoid(bcx);
             }
             op => {
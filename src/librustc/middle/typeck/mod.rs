@@ -449,6 +449,56 @@ fn check_for_entry_fn(ccx: &CrateCtxt) {
     }
 }
 
+// The function designated by the `global_allocator` lang item is called by the
+// runtime through the crate map, so nothing else would catch a wrong signature.
+fn check_global_allocator_fn_ty(ccx: &CrateCtxt) {
+    let tcx = ccx.tcx;
+    let fn_id = match tcx.lang_items.global_allocator() {
+        Some(did) if did.crate == ast::LOCAL_CRATE => did.node,
+        _ => return
+    };
+    let item = match tcx.items.find(fn_id) {
+        Some(ast_map::NodeItem(it, _)) => it,
+        _ => return
+    };
+    match item.node {
+        ast::ItemFn(_, _, _, ref ps, _) if ps.is_parameterized() => {
+            tcx.sess.span_err(
+                item.span,
+                "global allocator function is not allowed to have type parameters");
+            return;
+        }
+        _ => ()
+    }
+    let trait_id = match tcx.lang_items.allocator_trait() {
+        Some(did) => did,
+        None => {
+            tcx.sess.span_err(item.span,
+                              "global allocator function requires the `allocator` \
+                               lang item to be defined");
+            return;
+        }
+    };
+
+    let fn_t = ty::node_id_to_type(tcx, fn_id);
+    let se_ty = ty::mk_bare_fn(tcx, ty::BareFnTy {
+        purity: ast::ImpureFn,
+        abis: abi::AbiSet::Rust(),
+        sig: ty::FnSig {
+            binder_id: fn_id,
+            inputs: ~[],
+            output: ty::mk_trait(tcx, trait_id, ty::substs::empty(),
+                                 ty::RegionTraitStore(ty::ReStatic),
+                                 ast::MutImmutable, ty::EmptyBuiltinBounds()),
+            variadic: false
+        }
+    });
+
+    require_same_types(tcx, None, false, item.span, fn_t, se_ty,
+        || format!("global allocator function expects type: `{}`",
+                   ppaux::ty_to_str(ccx.tcx, se_ty)));
+}
+
 pub fn check_crate(tcx: ty::ctxt,
                    trait_map: resolve::TraitMap,
                    crate: &ast::Crate)
@@ -478,6 +528,7 @@ pub fn check_crate(tcx: ty::ctxt,
         check::check_item_types(ccx, crate));
 
     check_for_entry_fn(ccx);
+    check_global_allocator_fn_ty(ccx);
     tcx.sess.abort_if_errors();
     (ccx.method_map, ccx.vtable_map)
 }
@@ -12,12 +12,16 @@ use std::c_str::CString;
 use std::c_str;
 use std::cast::transmute;
 use std::cast;
+use std::cmp;
 use std::libc::{c_int, c_char, c_void, size_t};
 use std::libc;
+use std::os;
 use std::rt::task::BlockedTask;
 use std::io::{FileStat, IoError};
+use std::io::timer;
 use std::io;
 use std::rt::rtio;
+#[cfg(windows)] use std::unstable::intrinsics;
 
 use homing::{HomingIO, HomeHandle};
 use super::{Loop, UvError, uv_error_to_io_error, wait_until_woken_after, wakeup};
@@ -81,6 +85,16 @@ impl FsRequest {
         })
     }
 
+    pub fn sendfile(loop_: &Loop, out_fd: c_int, in_fd: c_int, offset: i64,
+                    len: uint) -> Result<uint, UvError> {
+        execute(|req, cb| unsafe {
+            uvll::uv_fs_sendfile(loop_.handle, req, out_fd, in_fd, offset,
+                                 len as size_t, cb)
+        }).map(|req| {
+            req.get_result() as uint
+        })
+    }
+
     pub fn read(loop_: &Loop, fd: c_int, buf: &mut [u8], offset: i64)
         -> Result<int, UvError>
     {
@@ -404,6 +418,31 @@ impl rtio::RtioFileStream for FileWatcher {
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError> {
         self.base_write(buf, -1)
     }
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError> {
+        // libuv's uv_fs_read only takes one buffer, so fill the buffers one
+        // at a time, moving on only once the previous one is full.
+        let mut total = 0;
+        for buf in bufs.mut_iter().filter(|buf| buf.len() > 0) {
+            match self.base_read(&mut **buf, -1) {
+                Ok(n) => {
+                    total += n as uint;
+                    if (n as uint) < buf.len() { break }
+                }
+                Err(..) if total > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
+    }
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError> {
+        for buf in bufs.iter() {
+            match self.base_write(*buf, -1) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
     fn pread(&mut self, buf: &mut [u8], offset: u64) -> Result<int, IoError> {
         self.base_read(buf, offset as i64)
     }
@@ -438,6 +477,96 @@ impl rtio::RtioFileStream for FileWatcher {
         let r = FsRequest::truncate(&self.loop_, self.fd, offset);
         r.map_err(uv_error_to_io_error)
     }
+    fn lock(&mut self, kind: io::LockKind, block: bool) -> Result<(), IoError> {
+        // libuv has no file locking, and a blocking flock() would stall every
+        // other task on this event loop. Instead the lock is only ever
+        // attempted without blocking, and blocking requests put the task to
+        // sleep on a timer (backing off up to 100ms) between attempts.
+        let mut backoff = 1;
+        loop {
+            match os_lock(self.fd, kind) {
+                Err(ref e) if block && e.kind == io::ResourceUnavailable => {
+                    timer::sleep(backoff);
+                    backoff = cmp::min(backoff * 2, 100);
+                }
+                r => return r,
+            }
+        }
+    }
+    fn unlock(&mut self) -> Result<(), IoError> {
+        os_unlock(self.fd)
+    }
+    fn fd(&self) -> c_int { self.fd }
+}
+
+#[cfg(unix)]
+fn os_lock(fd: c_int, kind: io::LockKind) -> Result<(), IoError> {
+    let op = match kind {
+        io::SharedLock => libc::LOCK_SH,
+        io::ExclusiveLock => libc::LOCK_EX,
+    };
+    match unsafe { libc::flock(fd, op | libc::LOCK_NB) } {
+        0 => Ok(()),
+        _ => Err(lock_error()),
+    }
+}
+
+#[cfg(windows)]
+fn os_lock(fd: c_int, kind: io::LockKind) -> Result<(), IoError> {
+    let flags = match kind {
+        io::SharedLock => 0,
+        io::ExclusiveLock => libc::LOCKFILE_EXCLUSIVE_LOCK,
+    };
+    match unsafe {
+        let mut overlap: libc::OVERLAPPED = intrinsics::init();
+        let handle = libc::get_osfhandle(fd) as libc::HANDLE;
+        libc::LockFileEx(handle, flags | libc::LOCKFILE_FAIL_IMMEDIATELY, 0,
+                         0xffffffff, 0xffffffff, &mut overlap)
+    } {
+        0 => Err(lock_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn os_unlock(fd: c_int) -> Result<(), IoError> {
+    match unsafe { libc::flock(fd, libc::LOCK_UN) } {
+        0 => Ok(()),
+        _ => Err(lock_error()),
+    }
+}
+
+#[cfg(windows)]
+fn os_unlock(fd: c_int) -> Result<(), IoError> {
+    match unsafe {
+        let mut overlap: libc::OVERLAPPED = intrinsics::init();
+        let handle = libc::get_osfhandle(fd) as libc::HANDLE;
+        libc::UnlockFileEx(handle, 0, 0xffffffff, 0xffffffff, &mut overlap)
+    } {
+        0 => Err(lock_error()),
+        _ => Ok(()),
+    }
+}
+
+fn lock_error() -> IoError {
+    #[cfg(unix)]
+    fn would_block(errno: int) -> bool {
+        errno == libc::EWOULDBLOCK as int || errno == libc::EAGAIN as int
+    }
+    #[cfg(windows)]
+    fn would_block(errno: int) -> bool {
+        errno == libc::ERROR_LOCK_VIOLATION as int
+    }
+
+    if would_block(os::errno()) {
+        io::standard_error(io::ResourceUnavailable)
+    } else {
+        IoError {
+            kind: io::OtherIoError,
+            desc: "failed to lock or unlock file",
+            detail: Some(os::last_os_error()),
+        }
+    }
 }
 
 #[cfg(test)]
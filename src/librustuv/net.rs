@@ -9,8 +9,12 @@
 // except according to those terms.
 
 use std::cast;
+use std::cmp;
 use std::io::IoError;
 use std::io::net::ip;
+use std::io::net::SocketOption;
+use std::io::net;
+use std::io;
 use std::libc::{size_t, ssize_t, c_int, c_void, c_uint};
 use std::libc;
 use std::mem;
@@ -18,7 +22,9 @@ use std::ptr;
 use std::rt::rtio;
 use std::rt::task::BlockedTask;
 use std::unstable::intrinsics;
+use std::vec;
 
+use file::FsRequest;
 use homing::{HomingIO, HomeHandle};
 use stream::StreamWatcher;
 use super::{Loop, Request, UvError, Buf, status_to_io_result,
@@ -31,6 +37,16 @@ use uvll;
 /// Generic functions related to dealing with sockaddr things
 ////////////////////////////////////////////////////////////////////////////////
 
+// Sockets are always driven asynchronously by the event loop, so tasks never
+// block on them in the first place.
+fn nonblocking_unavailable() -> IoError {
+    IoError {
+        kind: io::IoUnavailable,
+        desc: "non-blocking mode is not supported by libuv sockets",
+        detail: None,
+    }
+}
+
 pub fn htons(u: u16) -> u16 { intrinsics::to_be16(u as i16) as u16 }
 pub fn ntohs(u: u16) -> u16 { intrinsics::from_be16(u as i16) as u16 }
 
@@ -144,6 +160,148 @@ fn socket_name(sk: SocketNameKind,
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+/// Socket options
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(windows)] type linger_t = libc::c_ushort;
+#[cfg(unix)]    type linger_t = c_int;
+
+#[cfg(unix)]
+fn last_socket_error() -> UvError {
+    use std::os;
+    UvError(-(os::errno() as c_int))
+}
+#[cfg(windows)]
+fn last_socket_error() -> UvError { UvError(uvll::UNKNOWN) }
+
+#[cfg(unix)]
+unsafe fn close_socket(fd: uvll::uv_os_socket_t) { libc::close(fd); }
+#[cfg(windows)]
+unsafe fn close_socket(fd: uvll::uv_os_socket_t) { libc::closesocket(fd); }
+
+fn setsockopt<T>(fd: uvll::uv_os_socket_t, level: c_int, name: c_int,
+                 payload: T) -> Result<(), UvError> {
+    match unsafe {
+        libc::setsockopt(fd, level, name, &payload as *T as *c_void,
+                         mem::size_of::<T>() as libc::socklen_t)
+    } {
+        0 => Ok(()),
+        _ => Err(last_socket_error()),
+    }
+}
+
+#[cfg(unix)]
+fn set_reuseport(fd: uvll::uv_os_socket_t, on: bool) -> Result<(), UvError> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT, on as c_int)
+}
+#[cfg(windows)]
+fn set_reuseport(_fd: uvll::uv_os_socket_t, _on: bool) -> Result<(), UvError> {
+    Err(UvError(uvll::ENOSYS))
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn set_cork(fd: uvll::uv_os_socket_t, on: bool) -> Result<(), UvError> {
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_CORK, on as c_int)
+}
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn set_cork(fd: uvll::uv_os_socket_t, on: bool) -> Result<(), UvError> {
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_NOPUSH, on as c_int)
+}
+#[cfg(windows)]
+fn set_cork(_fd: uvll::uv_os_socket_t, _on: bool) -> Result<(), UvError> {
+    Err(UvError(uvll::ENOSYS))
+}
+
+fn set_socket_option(fd: uvll::uv_os_socket_t,
+                     opt: SocketOption) -> Result<(), UvError> {
+    match opt {
+        net::ReuseAddr(on) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, on as c_int)
+        }
+        net::ReusePort(on) => set_reuseport(fd, on),
+        net::SendBufferSize(n) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDBUF, n as c_int)
+        }
+        net::RecvBufferSize(n) => {
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF, n as c_int)
+        }
+        net::Ipv6Only(on) => {
+            setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, on as c_int)
+        }
+        net::Linger(secs) => {
+            let linger = libc::linger {
+                l_onoff: secs.is_some() as linger_t,
+                l_linger: secs.unwrap_or(0) as linger_t,
+            };
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, linger)
+        }
+        net::TcpCork(on) => set_cork(fd, on),
+    }
+}
+
+/// Sets an option on the socket underlying a uv handle.
+pub fn set_handle_option<T>(handle: *T,
+                            opt: SocketOption) -> Result<(), IoError> {
+    match unsafe { uvll::handle_fd(handle) } {
+        Ok(fd) => {
+            set_socket_option(fd as uvll::uv_os_socket_t, opt)
+                .map_err(uv_error_to_io_error)
+        }
+        Err(n) => Err(uv_error_to_io_error(UvError(n))),
+    }
+}
+
+// The descriptor behind a handle for `RtioSocket::fd`, or -1 if there isn't
+// one. Windows sockets don't fit in a c_int, so they're never handed out.
+#[cfg(unix)]
+fn raw_fd<T>(handle: *T) -> c_int {
+    match unsafe { uvll::handle_fd(handle) } {
+        Ok(fd) => fd,
+        Err(..) => -1,
+    }
+}
+#[cfg(windows)]
+fn raw_fd<T>(_handle: *T) -> c_int { -1 }
+
+// libuv only creates the socket for a handle once it's bound or connected, so
+// options which must be in place before then are applied to a socket which
+// is created here and then handed over to libuv with `open`.
+fn open_with_options<T>(handle: *T, addr: ip::SocketAddr, ty: c_int,
+                        opts: &[SocketOption],
+                        open: extern "C" unsafe fn(*T, uvll::uv_os_socket_t)
+                                                    -> c_int)
+    -> Result<(), UvError>
+{
+    if opts.len() == 0 { return Ok(()) }
+
+    let family = match addr.ip {
+        ip::Ipv4Addr(..) => libc::AF_INET,
+        ip::Ipv6Addr(..) => libc::AF_INET6,
+    };
+    let fd = unsafe { libc::socket(family, ty, 0) };
+    if fd == -1 as uvll::uv_os_socket_t {
+        return Err(last_socket_error())
+    }
+    let mut ret = Ok(());
+    for opt in opts.iter() {
+        ret = set_socket_option(fd, *opt);
+        if ret.is_err() { break }
+    }
+    if ret.is_ok() {
+        ret = match unsafe { open(handle, fd) } {
+            0 => Ok(()),
+            n => Err(UvError(n)),
+        };
+    }
+    if ret.is_err() {
+        unsafe { close_socket(fd) }
+    }
+    ret
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// TCP implementation
 ////////////////////////////////////////////////////////////////////////////////
@@ -188,10 +346,35 @@ impl TcpWatcher {
 
     pub fn connect(io: &mut UvIoFactory, address: ip::SocketAddr)
         -> Result<TcpWatcher, UvError>
+    {
+        TcpWatcher::connect_with(io, address, None, [])
+    }
+
+    pub fn connect_with(io: &mut UvIoFactory, address: ip::SocketAddr,
+                        local: Option<ip::SocketAddr>, opts: &[SocketOption])
+        -> Result<TcpWatcher, UvError>
     {
         struct Ctx { status: c_int, task: Option<BlockedTask> }
 
         let tcp = TcpWatcher::new(io);
+        match open_with_options(tcp.handle, address, libc::SOCK_STREAM, opts,
+                                uvll::uv_tcp_open) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
+        match local {
+            Some(local) => {
+                let (addr, _len) = addr_to_sockaddr(local);
+                match unsafe {
+                    let addr_p = &addr as *libc::sockaddr_storage;
+                    uvll::uv_tcp_bind(tcp.handle, addr_p as *libc::sockaddr)
+                } {
+                    0 => {}
+                    n => return Err(UvError(n)),
+                }
+            }
+            None => {}
+        }
         let (addr, _len) = addr_to_sockaddr(address);
         let mut req = Request::new(uvll::UV_CONNECT);
         let result = unsafe {
@@ -234,6 +417,14 @@ impl rtio::RtioSocket for TcpWatcher {
         let _m = self.fire_homing_missile();
         socket_name(Tcp, self.handle)
     }
+    fn set_nonblocking(&mut self, _nonblocking: bool) -> Result<(), IoError> {
+        Err(nonblocking_unavailable())
+    }
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        set_handle_option(self.handle, opt)
+    }
+    fn fd(&self) -> c_int { raw_fd(self.handle) }
 }
 
 impl rtio::RtioTcpStream for TcpWatcher {
@@ -247,6 +438,69 @@ impl rtio::RtioTcpStream for TcpWatcher {
         self.stream.write(buf).map_err(uv_error_to_io_error)
     }
 
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError> {
+        // uv_read_start hands us one buffer at a time, so read whatever is
+        // available into a single buffer and then spread it over `bufs`.
+        // Issuing one read per buffer could block on a later buffer while
+        // data for the earlier ones is already in hand.
+        let total = bufs.iter().fold(0, |n, buf| n + buf.len());
+        if total == 0 { return Ok(0) }
+        let mut tmp = vec::from_elem(total, 0u8);
+        let amt = match self.read(tmp) {
+            Ok(n) => n,
+            Err(e) => return Err(e),
+        };
+        let mut off = 0;
+        for buf in bufs.mut_iter() {
+            if off == amt { break }
+            let n = cmp::min(buf.len(), amt - off);
+            vec::bytes::copy_memory(buf.mut_slice_to(n),
+                                    tmp.slice(off, off + n));
+            off += n;
+        }
+        Ok(amt)
+    }
+
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        self.stream.write_bufs(bufs).map_err(uv_error_to_io_error)
+    }
+
+    fn sendfile(&mut self, fd: c_int, offset: u64,
+                len: u64) -> Result<u64, IoError> {
+        let _m = self.fire_homing_missile();
+        let sock = raw_fd(self.handle);
+        if sock < 0 {
+            return Err(IoError {
+                kind: io::IoUnavailable,
+                desc: "sendfile is not supported on this platform",
+                detail: None,
+            })
+        }
+        let loop_ = Loop::wrap(unsafe {
+            uvll::get_loop_for_uv_handle(self.handle)
+        });
+        let amt = cmp::min(len, 1 << 30) as uint;
+        match FsRequest::sendfile(&loop_, sock, fd, offset as i64, amt) {
+            Ok(n) => Ok(n as u64),
+
+            // libuv keeps its sockets non-blocking, so sendfile() bails out
+            // once the socket buffer is full. Push one chunk through the event
+            // loop instead, which waits for the socket to become writable.
+            Err(UvError(uvll::EAGAIN)) => {
+                let mut buf = vec::from_elem(cmp::min(amt, 64 * 1024), 0u8);
+                let n = match FsRequest::read(&loop_, fd, buf, offset as i64) {
+                    Ok(n) if n <= 0 => return Ok(0),
+                    Ok(n) => n as uint,
+                    Err(e) => return Err(uv_error_to_io_error(e)),
+                };
+                self.stream.write(buf.slice_to(n)).map(|()| n as u64)
+                    .map_err(uv_error_to_io_error)
+            }
+            Err(e) => Err(uv_error_to_io_error(e)),
+        }
+    }
+
     fn peer_name(&mut self) -> Result<ip::SocketAddr, IoError> {
         let _m = self.fire_homing_missile();
         socket_name(TcpPeer, self.handle)
@@ -298,6 +552,11 @@ impl Drop for TcpWatcher {
 impl TcpListener {
     pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr)
                 -> Result<~TcpListener, UvError> {
+        TcpListener::bind_with(io, address, [])
+    }
+
+    pub fn bind_with(io: &mut UvIoFactory, address: ip::SocketAddr,
+                     opts: &[SocketOption]) -> Result<~TcpListener, UvError> {
         let handle = unsafe { uvll::malloc_handle(uvll::UV_TCP) };
         assert_eq!(unsafe {
             uvll::uv_tcp_init(io.uv_loop(), handle)
@@ -310,6 +569,11 @@ impl TcpListener {
             outgoing: chan,
             incoming: port,
         };
+        match open_with_options(l.handle, address, libc::SOCK_STREAM, opts,
+                                uvll::uv_tcp_open) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
         let (addr, _len) = addr_to_sockaddr(address);
         let res = unsafe {
             let addr_p = &addr as *libc::sockaddr_storage;
@@ -335,6 +599,14 @@ impl rtio::RtioSocket for TcpListener {
         let _m = self.fire_homing_missile();
         socket_name(Tcp, self.handle)
     }
+    fn set_nonblocking(&mut self, _nonblocking: bool) -> Result<(), IoError> {
+        Err(nonblocking_unavailable())
+    }
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        set_handle_option(self.handle, opt)
+    }
+    fn fd(&self) -> c_int { raw_fd(self.handle) }
 }
 
 impl rtio::RtioTcpListener for TcpListener {
@@ -386,6 +658,14 @@ impl rtio::RtioSocket for TcpAcceptor {
         let _m = self.fire_homing_missile();
         socket_name(Tcp, self.listener.handle)
     }
+    fn set_nonblocking(&mut self, _nonblocking: bool) -> Result<(), IoError> {
+        Err(nonblocking_unavailable())
+    }
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        set_handle_option(self.listener.handle, opt)
+    }
+    fn fd(&self) -> c_int { raw_fd(self.listener.handle) }
 }
 
 impl rtio::RtioTcpAcceptor for TcpAcceptor {
@@ -420,6 +700,11 @@ pub struct UdpWatcher {
 impl UdpWatcher {
     pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr)
                 -> Result<UdpWatcher, UvError> {
+        UdpWatcher::bind_with(io, address, [])
+    }
+
+    pub fn bind_with(io: &mut UvIoFactory, address: ip::SocketAddr,
+                     opts: &[SocketOption]) -> Result<UdpWatcher, UvError> {
         let udp = UdpWatcher {
             handle: unsafe { uvll::malloc_handle(uvll::UV_UDP) },
             home: io.make_handle(),
@@ -427,6 +712,11 @@ impl UdpWatcher {
         assert_eq!(unsafe {
             uvll::uv_udp_init(io.uv_loop(), udp.handle)
         }, 0);
+        match open_with_options(udp.handle, address, libc::SOCK_DGRAM, opts,
+                                uvll::uv_udp_open) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
         let (addr, _len) = addr_to_sockaddr(address);
         let result = unsafe {
             let addr_p = &addr as *libc::sockaddr_storage;
@@ -452,6 +742,14 @@ impl rtio::RtioSocket for UdpWatcher {
         let _m = self.fire_homing_missile();
         socket_name(Udp, self.handle)
     }
+    fn set_nonblocking(&mut self, _nonblocking: bool) -> Result<(), IoError> {
+        Err(nonblocking_unavailable())
+    }
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        set_handle_option(self.handle, opt)
+    }
+    fn fd(&self) -> c_int { raw_fd(self.handle) }
 }
 
 impl rtio::RtioUdpSocket for UdpWatcher {
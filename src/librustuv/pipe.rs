@@ -10,11 +10,14 @@
 
 use std::c_str::CString;
 use std::io::IoError;
+use std::io::net::SocketOption;
+use std::io::net::unix::Credentials;
 use std::libc;
 use std::rt::rtio::{RtioPipe, RtioUnixListener, RtioUnixAcceptor};
 use std::rt::task::BlockedTask;
 
 use homing::{HomingIO, HomeHandle};
+use net;
 use stream::StreamWatcher;
 use super::{Loop, UvError, UvHandle, Request, uv_error_to_io_error,
             wait_until_woken_after, wakeup};
@@ -126,6 +129,27 @@ impl RtioPipe for PipeWatcher {
         let _m = self.fire_homing_missile();
         self.stream.write(buf).map_err(uv_error_to_io_error)
     }
+
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        net::set_handle_option(self.stream.handle, opt)
+    }
+
+    // libuv only exposes descriptor passing for its own IPC pipes, so these
+    // are left to the native runtime.
+    fn peer_credentials(&mut self) -> Result<Credentials, IoError> {
+        Err(uv_error_to_io_error(UvError(uvll::ENOSYS)))
+    }
+
+    fn send_fds(&mut self, _buf: &[u8],
+                _fds: &[libc::c_int]) -> Result<(), IoError> {
+        Err(uv_error_to_io_error(UvError(uvll::ENOSYS)))
+    }
+
+    fn recv_fds(&mut self, _buf: &mut [u8],
+                _fds: &mut ~[libc::c_int]) -> Result<(uint, bool), IoError> {
+        Err(uv_error_to_io_error(UvError(uvll::ENOSYS)))
+    }
 }
 
 impl HomingIO for PipeWatcher {
@@ -98,6 +98,12 @@ impl StreamWatcher {
     }
 
     pub fn write(&mut self, buf: &[u8]) -> Result<(), UvError> {
+        self.write_bufs([buf])
+    }
+
+    // Writes each of `bufs` in order with one uv_write request, letting libuv
+    // gather them into a single writev() where it can.
+    pub fn write_bufs(&mut self, bufs: &[&[u8]]) -> Result<(), UvError> {
         // The ownership of the write request is dubious if this function
         // unwinds. I believe that if the write_cb fails to re-schedule the task
         // then the write request will be leaked.
@@ -113,9 +119,11 @@ impl StreamWatcher {
         // Send off the request, but be careful to not block until we're sure
         // that the write reqeust is queued. If the reqeust couldn't be queued,
         // then we should return immediately with an error.
+        let uvbufs: ~[uvll::uv_buf_t] = bufs.iter().map(|buf| {
+            slice_to_uv_buf(*buf)
+        }).collect();
         match unsafe {
-            uvll::uv_write(req.handle, self.handle, [slice_to_uv_buf(buf)],
-                           write_cb)
+            uvll::uv_write(req.handle, self.handle, uvbufs, write_cb)
         } {
             0 => {
                 let mut wcx = WriteContext { result: 0, task: None, };
@@ -12,6 +12,7 @@ use std::c_str::CString;
 use std::cast;
 use std::comm::SharedChan;
 use std::io::IoError;
+use std::io::net::SocketOption;
 use std::io::net::ip::SocketAddr;
 use std::io::process::ProcessConfig;
 use std::io::signal::Signum;
@@ -139,28 +140,41 @@ impl UvIoFactory {
     }
 }
 
+fn abstract_unsupported() -> IoError {
+    IoError {
+        kind: io::IoUnavailable,
+        desc: "abstract unix socket names require the native runtime",
+        detail: None,
+    }
+}
+
 impl IoFactory for UvIoFactory {
     // Connect to an address and return a new stream
     // NB: This blocks the task waiting on the connection.
     // It would probably be better to return a future
-    fn tcp_connect(&mut self, addr: SocketAddr)
+    fn tcp_connect(&mut self, addr: SocketAddr, local: Option<SocketAddr>,
+                   opts: &[SocketOption])
         -> Result<~rtio::RtioTcpStream, IoError>
     {
-        match TcpWatcher::connect(self, addr) {
+        match TcpWatcher::connect_with(self, addr, local, opts) {
             Ok(t) => Ok(~t as ~rtio::RtioTcpStream),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
     }
 
-    fn tcp_bind(&mut self, addr: SocketAddr) -> Result<~rtio::RtioTcpListener, IoError> {
-        match TcpListener::bind(self, addr) {
+    fn tcp_bind(&mut self, addr: SocketAddr, opts: &[SocketOption])
+        -> Result<~rtio::RtioTcpListener, IoError>
+    {
+        match TcpListener::bind_with(self, addr, opts) {
             Ok(t) => Ok(t as ~rtio::RtioTcpListener),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
     }
 
-    fn udp_bind(&mut self, addr: SocketAddr) -> Result<~rtio::RtioUdpSocket, IoError> {
-        match UdpWatcher::bind(self, addr) {
+    fn udp_bind(&mut self, addr: SocketAddr, opts: &[SocketOption])
+        -> Result<~rtio::RtioUdpSocket, IoError>
+    {
+        match UdpWatcher::bind_with(self, addr, opts) {
             Ok(u) => Ok(~u as ~rtio::RtioUdpSocket),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
@@ -289,6 +303,19 @@ impl IoFactory for UvIoFactory {
         }
     }
 
+    // libuv has no support for the abstract namespace
+    fn unix_bind_abstract(&mut self, _name: &[u8])
+        -> Result<~rtio::RtioUnixListener, IoError>
+    {
+        Err(abstract_unsupported())
+    }
+
+    fn unix_connect_abstract(&mut self, _name: &[u8])
+        -> Result<~rtio::RtioPipe, IoError>
+    {
+        Err(abstract_unsupported())
+    }
+
     fn tty_open(&mut self, fd: c_int, readable: bool)
             -> Result<~rtio::RtioTTY, IoError> {
         match TtyWatcher::new(self, fd, readable) {
@@ -30,15 +30,16 @@
 #[allow(non_camel_case_types)]; // C types
 
 use std::libc::{size_t, c_int, c_uint, c_void, c_char, c_double};
-use std::libc::{ssize_t, sockaddr, free, addrinfo};
+use std::libc::{ssize_t, sockaddr, addrinfo};
 use std::libc;
-use std::rt::global_heap::malloc_raw;
+use std::rt::global_heap::{malloc_raw, exchange_free};
 
 #[cfg(test)]
 use std::libc::uintptr_t;
 
 pub use self::errors::{EACCES, ECONNREFUSED, ECONNRESET, EPIPE, ECONNABORTED,
-                       ECANCELED, EBADF, ENOTCONN, ENOENT, EADDRNOTAVAIL};
+                       ECANCELED, EBADF, ENOTCONN, ENOENT, EADDRNOTAVAIL,
+                       EAGAIN, ENOSYS};
 
 pub static OK: c_int = 0;
 pub static EOF: c_int = -4095;
@@ -61,6 +62,8 @@ pub mod errors {
     pub static ECANCELED: c_int = -4081;
     pub static EBADF: c_int = -4083;
     pub static EADDRNOTAVAIL: c_int = -4090;
+    pub static EAGAIN: c_int = -4088;
+    pub static ENOSYS: c_int = -4054;
 }
 #[cfg(not(windows))]
 pub mod errors {
@@ -77,6 +80,8 @@ pub mod errors {
     pub static ECANCELED : c_int = -libc::ECANCELED;
     pub static EBADF : c_int = -libc::EBADF;
     pub static EADDRNOTAVAIL : c_int = -libc::EADDRNOTAVAIL;
+    pub static EAGAIN : c_int = -libc::EAGAIN;
+    pub static ENOSYS : c_int = -libc::ENOSYS;
 }
 
 pub static PROCESS_SETUID: c_int = 1 << 0;
@@ -158,6 +163,16 @@ pub type uv_pipe_t = c_void;
 pub type uv_tty_t = c_void;
 pub type uv_signal_t = c_void;
 
+#[cfg(unix)]
+pub type uv_os_socket_t = c_int;
+#[cfg(windows)]
+pub type uv_os_socket_t = libc::SOCKET;
+
+#[cfg(unix)]
+pub type uv_os_fd_t = c_int;
+#[cfg(windows)]
+pub type uv_os_fd_t = libc::HANDLE;
+
 pub struct uv_timespec_t {
     tv_sec: libc::c_long,
     tv_nsec: libc::c_long
@@ -334,7 +349,7 @@ pub unsafe fn malloc_handle(handle: uv_handle_type) -> *c_void {
 }
 
 pub unsafe fn free_handle(v: *c_void) {
-    free(v as *mut c_void)
+    exchange_free(v as *u8)
 }
 
 pub unsafe fn malloc_req(req: uv_req_type) -> *c_void {
@@ -344,7 +359,7 @@ pub unsafe fn malloc_req(req: uv_req_type) -> *c_void {
 }
 
 pub unsafe fn free_req(v: *c_void) {
-    free(v as *mut c_void)
+    exchange_free(v as *u8)
 }
 
 #[test]
@@ -472,6 +487,13 @@ pub unsafe fn populate_stat(req_in: *uv_fs_t, stat_out: *uv_stat_t) {
 pub unsafe fn guess_handle(handle: c_int) -> c_int {
     rust_uv_guess_handle(handle)
 }
+pub unsafe fn handle_fd<T>(handle: *T) -> Result<uv_os_fd_t, c_int> {
+    let mut fd = 0 as uv_os_fd_t;
+    match uv_fileno(handle as *uv_handle_t, &mut fd) {
+        0 => Ok(fd),
+        n => Err(n),
+    }
+}
 
 
 // uv_support is the result of compiling rust_uv.cpp
@@ -528,6 +550,7 @@ extern {
     pub fn uv_req_size(ty: uv_req_type) -> size_t;
     pub fn uv_run(l: *uv_loop_t, mode: uv_run_mode) -> c_int;
     pub fn uv_close(h: *uv_handle_t, cb: uv_close_cb);
+    pub fn uv_fileno(h: *uv_handle_t, fd: *mut uv_os_fd_t) -> c_int;
     pub fn uv_walk(l: *uv_loop_t, cb: uv_walk_cb, arg: *c_void);
     pub fn uv_buf_init(base: *c_char, len: c_uint) -> uv_buf_t;
     pub fn uv_strerror(err: c_int) -> *c_char;
@@ -552,6 +575,7 @@ extern {
 
     // tcp bindings
     pub fn uv_tcp_init(l: *uv_loop_t, h: *uv_tcp_t) -> c_int;
+    pub fn uv_tcp_open(h: *uv_tcp_t, sock: uv_os_socket_t) -> c_int;
     pub fn uv_tcp_connect(c: *uv_connect_t, h: *uv_tcp_t,
                           addr: *sockaddr, cb: uv_connect_cb) -> c_int;
     pub fn uv_tcp_bind(t: *uv_tcp_t, addr: *sockaddr) -> c_int;
@@ -566,6 +590,7 @@ extern {
 
     // udp bindings
     pub fn uv_udp_init(l: *uv_loop_t, h: *uv_udp_t) -> c_int;
+    pub fn uv_udp_open(h: *uv_udp_t, sock: uv_os_socket_t) -> c_int;
     pub fn uv_udp_bind(h: *uv_udp_t, addr: *sockaddr, flags: c_uint) -> c_int;
     pub fn uv_udp_recv_start(server: *uv_udp_t,
                              on_alloc: uv_alloc_cb,
@@ -597,6 +622,9 @@ extern {
                        len: size_t, offset: i64, cb: uv_fs_cb) -> c_int;
     pub fn uv_fs_read(l: *uv_loop_t, req: *uv_fs_t, fd: c_int, buf: *c_void,
                       len: size_t, offset: i64, cb: uv_fs_cb) -> c_int;
+    pub fn uv_fs_sendfile(l: *uv_loop_t, req: *uv_fs_t, out_fd: c_int,
+                          in_fd: c_int, in_offset: i64, len: size_t,
+                          cb: uv_fs_cb) -> c_int;
     pub fn uv_fs_close(l: *uv_loop_t, req: *uv_fs_t, fd: c_int,
                        cb: uv_fs_cb) -> c_int;
     pub fn uv_fs_stat(l: *uv_loop_t, req: *uv_fs_t, path: *c_char,
@@ -0,0 +1,727 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Task-local reference-counted boxes with cycle collection (`Cc` type)
+
+The `Cc` type behaves like `Rc`: it provides shared ownership of an immutable value and destroys
+the value as soon as the last owner is gone. In addition, reference cycles between `Cc` boxes are
+reclaimed by a trial-deletion cycle collector (Bacon & Rajan, "Concurrent Cycle Collection in
+Reference Counted Systems", synchronous variant), so graph-shaped data does not need to be broken
+up manually with `Weak` pointers.
+
+Every type stored in a `Cc` box must implement the `Trace` trait, which reports the `Cc` pointers
+directly owned by a value. `Trace` can be derived with `#[deriving(Trace)]`.
+
+Whenever a `Cc` pointer is dropped without freeing its box, the box is remembered as a possible
+root of a garbage cycle. Candidates are examined when `collect_cycles` is called, or automatically
+from `Cc::new` once enough of them have been buffered.
+
+# Caveats
+
+* Values inside `Rc` or `@` boxes are not traced, so a cycle passing through one of them is never
+  collected (but is never freed incorrectly either).
+* A `RefCell` that is mutably borrowed while the collector runs is treated as if its contents
+  were referenced from outside, which keeps them alive until a later collection.
+* When a cycle is collected, the destructors of all of its values run before any box is freed,
+  in an unspecified order. Borrowing a box of the cycle from one of these destructors fails, as
+  its value may already have been destroyed. A `Cc` pointer which a destructor moves out of the
+  cycle keeps its box allocated, but the box can never be borrowed again.
+* A `Trace` implementation must report exactly the `Cc` pointers owned by the value. Reporting a
+  pointer the value does not own can make the collector destroy a box which is still in use.
+
+# Example
+
+```rust
+use std::cc::{Cc, collect_cycles};
+use std::cell::RefCell;
+
+#[deriving(Trace)]
+struct Node {
+    next: RefCell<Option<Cc<Node>>>
+}
+
+let a = Cc::new(Node { next: RefCell::new(None) });
+let b = Cc::new(Node { next: RefCell::new(Some(a.clone())) });
+a.borrow().next.set(Some(b.clone()));
+
+drop(a);
+drop(b);
+assert_eq!(collect_cycles(), 2);
+```
+
+*/
+
+use cast::transmute;
+use cell::{Cell, RefCell};
+use clone::Clone;
+use cmp::{Eq, Ord};
+use container::Container;
+use iter::Iterator;
+use kinds::Pod;
+use local_data;
+use ops::Drop;
+use option::{Option, Some, None};
+use ptr::read_ptr;
+use rc::Rc;
+use rt::global_heap::exchange_free;
+use vec::{ImmutableVector, OwnedVector};
+
+/// Number of buffered possible roots above which `Cc::new` runs a collection
+static COLLECT_THRESHOLD: uint = 500;
+
+#[deriving(Eq)]
+enum Color {
+    /// In use or free
+    Black,
+    /// Possible member of a cycle
+    Gray,
+    /// Member of a garbage cycle
+    White,
+    /// Possible root of a cycle
+    Purple,
+    /// Being freed by the collector
+    Red,
+    /// Value destroyed by the collector, allocation kept alive by pointers which escaped the cycle
+    Dead,
+}
+
+struct Header {
+    strong: uint,
+    color: Color,
+    buffered: bool,
+    trace: unsafe fn(*mut Header, &mut Tracer),
+    drop_value: unsafe fn(*mut Header),
+}
+
+// The header must stay the first field: boxes are handled as `*mut Header` by the collector.
+struct CcBox<T> {
+    header: Header,
+    value: T
+}
+
+local_data_key!(possible_roots: ~[*mut Header])
+
+/// Collects the `Cc` pointers reported by `Trace` implementations
+pub struct Tracer {
+    priv nodes: ~[*mut Header]
+}
+
+/// A type whose `Cc` pointers can be enumerated by the cycle collector
+///
+/// Implementations must report every `Cc` pointer they own exactly once, and nothing else; the
+/// safest way to do so is `#[deriving(Trace)]`.
+pub trait Trace {
+    /// Report every `Cc` pointer directly owned by `self` to the tracer, by calling `trace` on
+    /// the fields that may contain one.
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+/// Immutable reference counted pointer type with cycle collection
+#[unsafe_no_drop_flag]
+#[no_send]
+pub struct Cc<T> {
+    priv ptr: *mut CcBox<T>
+}
+
+impl<T: Trace> Cc<T> {
+    /// Construct a new cycle-collected box
+    pub fn new(value: T) -> Cc<T> {
+        let buffered = local_data::get(possible_roots, |roots| {
+            roots.map_or(0, |roots| roots.len())
+        });
+        if buffered >= COLLECT_THRESHOLD {
+            collect_cycles();
+        }
+
+        unsafe {
+            let header = Header {
+                strong: 1,
+                color: Black,
+                buffered: false,
+                trace: trace_box::<T>,
+                drop_value: drop_box::<T>,
+            };
+            Cc { ptr: transmute(~CcBox { header: header, value: value }) }
+        }
+    }
+}
+
+impl<T> Cc<T> {
+    /// Borrow the value contained in the box
+    ///
+    /// # Failure
+    ///
+    /// Fails if the value was destroyed by the cycle collector, which can only be observed from the
+    /// destructor of a value in a garbage cycle.
+    #[inline(always)]
+    pub fn borrow<'a>(&'a self) -> &'a T {
+        unsafe {
+            match (*self.ptr).header.color {
+                Red | Dead => fail!("borrowed a Cc box destroyed by the cycle collector"),
+                _ => &(*self.ptr).value
+            }
+        }
+    }
+}
+
+#[unsafe_destructor]
+impl<T> Drop for Cc<T> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.ptr == 0 as *mut CcBox<T> {
+                return
+            }
+            let h = self.ptr as *mut Header;
+            match (*h).color {
+                // The collector is destroying this box and frees it if no pointer escapes
+                Red => {
+                    if (*h).strong > 0 {
+                        (*h).strong -= 1;
+                    }
+                    return
+                }
+                // Only the allocation is left
+                Dead => {
+                    (*h).strong -= 1;
+                    if (*h).strong == 0 {
+                        exchange_free(h as *u8);
+                    }
+                    return
+                }
+                _ => {}
+            }
+            (*h).strong -= 1;
+            if (*h).strong == 0 {
+                release(h);
+            } else {
+                possible_root(h);
+            }
+        }
+    }
+}
+
+impl<T> Clone for Cc<T> {
+    #[inline]
+    fn clone(&self) -> Cc<T> {
+        unsafe {
+            (*self.ptr).header.strong += 1;
+            match (*self.ptr).header.color {
+                Red | Dead => {}
+                _ => (*self.ptr).header.color = Black
+            }
+            Cc { ptr: self.ptr }
+        }
+    }
+}
+
+impl<T: Eq> Eq for Cc<T> {
+    #[inline(always)]
+    fn eq(&self, other: &Cc<T>) -> bool { *self.borrow() == *other.borrow() }
+
+    #[inline(always)]
+    fn ne(&self, other: &Cc<T>) -> bool { *self.borrow() != *other.borrow() }
+}
+
+impl<T: Ord> Ord for Cc<T> {
+    #[inline(always)]
+    fn lt(&self, other: &Cc<T>) -> bool { *self.borrow() < *other.borrow() }
+
+    #[inline(always)]
+    fn le(&self, other: &Cc<T>) -> bool { *self.borrow() <= *other.borrow() }
+
+    #[inline(always)]
+    fn gt(&self, other: &Cc<T>) -> bool { *self.borrow() > *other.borrow() }
+
+    #[inline(always)]
+    fn ge(&self, other: &Cc<T>) -> bool { *self.borrow() >= *other.borrow() }
+}
+
+/// Reclaim the garbage cycles among the boxes buffered as possible roots in this task, returning
+/// the number of values that were destroyed.
+pub fn collect_cycles() -> uint {
+    let roots = match local_data::pop(possible_roots) {
+        Some(roots) => roots,
+        None => return 0
+    };
+
+    unsafe {
+        let mut candidates = ~[];
+        for &h in roots.iter() {
+            if (*h).color == Purple {
+                mark_gray(h);
+                candidates.push(h);
+            } else {
+                (*h).buffered = false;
+                // Released while buffered, only the allocation is left
+                if (*h).color == Black && (*h).strong == 0 {
+                    exchange_free(h as *u8);
+                }
+            }
+        }
+
+        for &h in candidates.iter() {
+            scan(h);
+        }
+
+        let mut garbage = ~[];
+        for &h in candidates.iter() {
+            (*h).buffered = false;
+            collect_white(h, &mut garbage);
+        }
+
+        // Edges from garbage were subtracted by `mark_gray` and never added back. Restore them:
+        // destroying the garbage values then releases live boxes exactly once, and brings the
+        // count of every garbage box back to zero unless a pointer to it escapes.
+        for &h in garbage.iter() {
+            for &child in edges(h).iter() {
+                if (*child).color != Dead {
+                    (*child).strong += 1;
+                }
+            }
+        }
+
+        // Run every destructor before freeing anything, as the destructors may still hold
+        // pointers to the other boxes of the cycle.
+        for &h in garbage.iter() {
+            ((*h).drop_value)(h);
+        }
+        for &h in garbage.iter() {
+            if (*h).strong == 0 {
+                exchange_free(h as *u8);
+            } else {
+                (*h).color = Dead;
+            }
+        }
+        garbage.len()
+    }
+}
+
+unsafe fn trace_box<T: Trace>(h: *mut Header, tracer: &mut Tracer) {
+    let b = h as *mut CcBox<T>;
+    (*b).value.trace(tracer);
+}
+
+unsafe fn drop_box<T>(h: *mut Header) {
+    let b = h as *mut CcBox<T>;
+    read_ptr(&(*b).value); // destroy the contained object
+}
+
+unsafe fn release(h: *mut Header) {
+    (*h).color = Black;
+    ((*h).drop_value)(h);
+    // A buffered box is freed by the collector once it is taken out of the buffer
+    if !(*h).buffered {
+        exchange_free(h as *u8);
+    }
+}
+
+unsafe fn possible_root(h: *mut Header) {
+    if (*h).color != Purple {
+        (*h).color = Purple;
+        if !(*h).buffered {
+            (*h).buffered = true;
+            let pushed = local_data::get_mut(possible_roots, |roots| {
+                match roots {
+                    Some(roots) => { roots.push(h); true }
+                    None => false
+                }
+            });
+            if !pushed {
+                local_data::set(possible_roots, ~[h]);
+            }
+        }
+    }
+}
+
+unsafe fn edges(h: *mut Header) -> ~[*mut Header] {
+    let mut tracer = Tracer { nodes: ~[] };
+    ((*h).trace)(h, &mut tracer);
+    tracer.nodes
+}
+
+// The boxes a collection may walk to from `h`. Boxes destroyed by the collector are no longer part
+// of the graph, even if a pointer to them escaped into a live value.
+unsafe fn children(h: *mut Header) -> ~[*mut Header] {
+    let mut nodes = edges(h);
+    nodes.retain(|&child| (*child).color != Red && (*child).color != Dead);
+    nodes
+}
+
+// Subtract the internal references of the subgraph reachable from `root`
+unsafe fn mark_gray(root: *mut Header) {
+    if (*root).color == Gray {
+        return
+    }
+    (*root).color = Gray;
+    let mut stack = ~[root];
+    loop {
+        let h = match stack.pop() { Some(h) => h, None => break };
+        for &child in children(h).iter() {
+            (*child).strong -= 1;
+            if (*child).color != Gray {
+                (*child).color = Gray;
+                stack.push(child);
+            }
+        }
+    }
+}
+
+// Separate the gray subgraph into boxes that are still referenced from outside and garbage
+unsafe fn scan(root: *mut Header) {
+    let mut stack = ~[root];
+    loop {
+        let h = match stack.pop() { Some(h) => h, None => break };
+        if (*h).color == Gray {
+            if (*h).strong > 0 {
+                scan_black(h);
+            } else {
+                (*h).color = White;
+                stack.push_all_move(children(h));
+            }
+        }
+    }
+}
+
+// Restore the internal references of a subgraph found to be alive
+unsafe fn scan_black(root: *mut Header) {
+    (*root).color = Black;
+    let mut stack = ~[root];
+    loop {
+        let h = match stack.pop() { Some(h) => h, None => break };
+        for &child in children(h).iter() {
+            (*child).strong += 1;
+            if (*child).color != Black {
+                (*child).color = Black;
+                stack.push(child);
+            }
+        }
+    }
+}
+
+unsafe fn collect_white(root: *mut Header, garbage: &mut ~[*mut Header]) {
+    let mut stack = ~[root];
+    loop {
+        let h = match stack.pop() { Some(h) => h, None => break };
+        if (*h).color == White && !(*h).buffered {
+            (*h).color = Red;
+            garbage.push(h);
+            stack.push_all_move(children(h));
+        }
+    }
+}
+
+impl<T> Trace for Cc<T> {
+    #[inline]
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.nodes.push(self.ptr as *mut Header);
+    }
+}
+
+macro_rules! trace_nothing(
+    ($($ty:ty),+) => (
+        $(
+            impl Trace for $ty {
+                #[inline(always)]
+                fn trace(&self, _: &mut Tracer) {}
+            }
+        )+
+    )
+)
+
+trace_nothing!((), bool, char, int, i8, i16, i32, i64, uint, u8, u16, u32, u64, f32, f64, ~str)
+
+impl<'a> Trace for &'a str {
+    #[inline(always)]
+    fn trace(&self, _: &mut Tracer) {}
+}
+
+impl<T: Pod> Trace for Cell<T> {
+    #[inline(always)]
+    fn trace(&self, _: &mut Tracer) {}
+}
+
+// The contents of an `Rc` may be shared with owners the collector cannot see
+impl<T> Trace for Rc<T> {
+    #[inline(always)]
+    fn trace(&self, _: &mut Tracer) {}
+}
+
+impl<T: Trace> Trace for RefCell<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self.try_borrow() {
+            Some(r) => r.get().trace(tracer),
+            // Mutably borrowed: leave the contents alive
+            None => {}
+        }
+    }
+}
+
+impl<T: Trace> Trace for ~T {
+    #[inline]
+    fn trace(&self, tracer: &mut Tracer) {
+        (**self).trace(tracer)
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    #[inline]
+    fn trace(&self, tracer: &mut Tracer) {
+        match *self {
+            Some(ref value) => value.trace(tracer),
+            None => {}
+        }
+    }
+}
+
+impl<T: Trace> Trace for ~[T] {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self.iter() {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<A: Trace, B: Trace> Trace for (A, B) {
+    #[inline]
+    fn trace(&self, tracer: &mut Tracer) {
+        let (ref a, ref b) = *self;
+        a.trace(tracer);
+        b.trace(tracer);
+    }
+}
+
+impl<A: Trace, B: Trace, C: Trace> Trace for (A, B, C) {
+    #[inline]
+    fn trace(&self, tracer: &mut Tracer) {
+        let (ref a, ref b, ref c) = *self;
+        a.trace(tracer);
+        b.trace(tracer);
+        c.trace(tracer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::*;
+    use cell::{Cell, RefCell};
+    use local_data;
+    use rc::Rc;
+
+    struct Node {
+        edges: RefCell<~[Cc<Node>]>,
+        dropped: Rc<Cell<uint>>
+    }
+
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.edges.trace(tracer);
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            self.dropped.borrow().set(self.dropped.borrow().get() + 1);
+        }
+    }
+
+    fn node(dropped: &Rc<Cell<uint>>) -> Cc<Node> {
+        Cc::new(Node { edges: RefCell::new(~[]), dropped: dropped.clone() })
+    }
+
+    fn link(from: &Cc<Node>, to: &Cc<Node>) {
+        from.borrow().edges.with_mut(|edges| edges.push(to.clone()));
+    }
+
+    #[test]
+    fn test_clone() {
+        let x = Cc::new(RefCell::new(5));
+        let y = x.clone();
+        x.borrow().with_mut(|inner| {
+            *inner = 20;
+        });
+        assert_eq!(y.borrow().with(|v| *v), 20);
+    }
+
+    #[test]
+    fn test_acyclic() {
+        let dropped = Rc::new(Cell::new(0u));
+        let a = node(&dropped);
+        let b = node(&dropped);
+        link(&a, &b);
+        drop(b);
+        assert_eq!(dropped.borrow().get(), 0);
+        drop(a);
+        assert_eq!(dropped.borrow().get(), 2);
+        assert_eq!(collect_cycles(), 0);
+    }
+
+    #[test]
+    fn test_self_cycle() {
+        let dropped = Rc::new(Cell::new(0u));
+        let a = node(&dropped);
+        link(&a, &a);
+        drop(a);
+        assert_eq!(dropped.borrow().get(), 0);
+        assert_eq!(collect_cycles(), 1);
+        assert_eq!(dropped.borrow().get(), 1);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let dropped = Rc::new(Cell::new(0u));
+        let a = node(&dropped);
+        let b = node(&dropped);
+        let c = node(&dropped);
+        link(&a, &b);
+        link(&b, &c);
+        link(&c, &a);
+        drop(a);
+        drop(b);
+        drop(c);
+        assert_eq!(collect_cycles(), 3);
+        assert_eq!(dropped.borrow().get(), 3);
+        assert_eq!(collect_cycles(), 0);
+    }
+
+    #[test]
+    fn test_live_cycle() {
+        let dropped = Rc::new(Cell::new(0u));
+        let a = node(&dropped);
+        let b = node(&dropped);
+        link(&a, &b);
+        link(&b, &a);
+        drop(b);
+        assert_eq!(collect_cycles(), 0);
+        assert_eq!(dropped.borrow().get(), 0);
+
+        // The counts must survive a collection that found nothing
+        drop(a);
+        assert_eq!(collect_cycles(), 2);
+        assert_eq!(dropped.borrow().get(), 2);
+    }
+
+    #[test]
+    fn test_cycle_holding_live_box() {
+        let dropped = Rc::new(Cell::new(0u));
+        let live = node(&dropped);
+        let a = node(&dropped);
+        let b = node(&dropped);
+        link(&a, &b);
+        link(&b, &a);
+        link(&b, &live);
+        drop(a);
+        drop(b);
+        assert_eq!(collect_cycles(), 2);
+        assert_eq!(dropped.borrow().get(), 2);
+
+        // The cycle held one of the two references to `live`
+        assert_eq!(live.borrow().edges.with(|edges| edges.len()), 0);
+        drop(live);
+        assert_eq!(dropped.borrow().get(), 3);
+    }
+
+    #[test]
+    fn test_garbage_reachable_from_cycle() {
+        let dropped = Rc::new(Cell::new(0u));
+        let a = node(&dropped);
+        let b = node(&dropped);
+        let c = node(&dropped);
+        link(&a, &b);
+        link(&b, &a);
+        link(&b, &c);
+        drop(c);
+        drop(a);
+        drop(b);
+        // `c` is only reachable from the cycle, so it is garbage as well
+        assert_eq!(collect_cycles(), 3);
+        assert_eq!(dropped.borrow().get(), 3);
+    }
+
+    local_data_key!(escaped: Cc<Escape>)
+
+    struct Escape {
+        next: RefCell<Option<Cc<Escape>>>
+    }
+
+    impl Trace for Escape {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.next.trace(tracer);
+        }
+    }
+
+    // Moves the pointer it holds out of the cycle being destroyed
+    impl Drop for Escape {
+        fn drop(&mut self) {
+            match self.next.with_mut(|next| next.take()) {
+                Some(next) => local_data::set(escaped, next),
+                None => {}
+            }
+        }
+    }
+
+    fn escaping_cycle() -> Cc<Escape> {
+        let a = Cc::new(Escape { next: RefCell::new(None) });
+        a.borrow().next.set(Some(a.clone()));
+        drop(a);
+        assert_eq!(collect_cycles(), 1);
+        local_data::pop(escaped).unwrap()
+    }
+
+    #[test]
+    fn test_escape_from_destructor() {
+        // The box stays allocated until the escaped pointer is gone
+        let a = escaping_cycle();
+        let b = a.clone();
+        drop(a);
+        drop(b);
+        assert_eq!(collect_cycles(), 0);
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_borrow_destroyed() {
+        let a = escaping_cycle();
+        a.borrow();
+    }
+
+    struct Peek {
+        other: RefCell<Option<Cc<Peek>>>
+    }
+
+    impl Trace for Peek {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.other.trace(tracer);
+        }
+    }
+
+    // Looks at the other box of the cycle, whose value may already be gone
+    impl Drop for Peek {
+        fn drop(&mut self) {
+            self.other.with(|other| {
+                match *other {
+                    Some(ref other) => { other.borrow(); }
+                    None => {}
+                }
+            });
+        }
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_borrow_from_garbage_destructor() {
+        let a = Cc::new(Peek { other: RefCell::new(None) });
+        let b = Cc::new(Peek { other: RefCell::new(Some(a.clone())) });
+        a.borrow().other.set(Some(b.clone()));
+        drop(a);
+        drop(b);
+        collect_cycles();
+    }
+}
@@ -11,6 +11,8 @@
 #[doc(hidden)];
 
 use ptr;
+use rt::env;
+use rt::local_heap;
 use unstable::intrinsics::TyDesc;
 use unstable::raw;
 
@@ -74,6 +76,12 @@ pub unsafe fn annihilate() {
         n_bytes_freed: 0
     };
 
+    // The boxes which are still alive at this point are only kept alive by
+    // cycles, so report them as leaks if asked to.
+    if env::heap_stats() {
+        local_heap::report_leaks();
+    }
+
     // Pass 1: Make all boxes immortal.
     //
     // In this pass, nothing gets freed, so it does not matter whether
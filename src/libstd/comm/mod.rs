@@ -15,11 +15,12 @@
 //! communication between concurrent tasks. The primitives defined in this
 //! module are the building blocks for synchronization in rust.
 //!
-//! This module currently provides three main types:
+//! This module currently provides four main types:
 //!
 //! * `Chan`
 //! * `Port`
 //! * `SharedChan`
+//! * `BoundedChan`
 //!
 //! The `Chan` and `SharedChan` types are used to send data to a `Port`. A
 //! `SharedChan` is clone-able such that many tasks can send simultaneously to
@@ -31,6 +32,12 @@
 //! this means is that the `send` operation will never block. `Port`s, on the
 //! other hand, will block the task if there is no data to be received.
 //!
+//! The exception to this rule is `BoundedChan`, a clone-able channel whose
+//! buffer holds at most a fixed number of messages. Sending on a full
+//! `BoundedChan` blocks the sender until the receiving port makes room, which
+//! provides backpressure for producers which would otherwise outrun their
+//! consumer.
+//!
 //! ## Failure Propagation
 //!
 //! In addition to being a core primitive for communicating in rust, channels
@@ -202,6 +209,24 @@
 // a mutex and the "wakeup" blocks involve grabbing a mutex and signaling on a
 // condition variable.
 //
+// ## Bounded channels
+//
+// A bounded channel is a shared channel whose packet additionally carries a
+// `Bound`. The bound counts the messages which have been sent but not yet
+// received, and senders must reserve a slot (with a CAS loop, so the count
+// never overshoots the capacity) before pushing onto the MPSC queue. Every
+// successful receive releases a slot. The send/recv protocol described above
+// is otherwise untouched, which is also why bounded ports can be used with
+// select() just like any other port.
+//
+// Senders which find the buffer full block on a list of waiters guarded by a
+// native mutex (it's only ever held for a handful of instructions, and never
+// across a context switch). The lost-wakeup race between a sender deciding to
+// block and the receiver releasing a slot is closed by the sender bumping the
+// `waiting` count and then re-checking the slot count under the lock, while
+// the receiver decrements the slot count before checking `waiting`. Dropping
+// the port wakes up every blocked sender so it can observe the disconnect.
+//
 // ## Select
 //
 // Being able to support selection over channels has greatly influenced this
@@ -233,11 +258,15 @@ use iter::Iterator;
 use kinds::Send;
 use ops::Drop;
 use option::{Option, Some, None};
-use result::{Ok, Err};
+use result::{Result, Ok, Err};
 use rt::local::Local;
 use rt::task::{Task, BlockedTask};
 use rt::thread::Thread;
 use sync::atomics::{AtomicInt, AtomicBool, SeqCst, Relaxed};
+use task::CancelToken;
+use task;
+use unstable::mutex::Mutex;
+use util;
 use vec::OwnedVector;
 
 use spsc = sync::spsc_queue;
@@ -324,6 +353,32 @@ pub struct SharedChan<T> {
     priv queue: mpsc::Producer<T, Packet>,
 }
 
+/// The sending-half of a bounded channel. Like `SharedChan`, this half can be
+/// cloned and shared among many tasks, but at most `capacity` messages can be
+/// buffered at once. Senders block (whether they are green or native tasks)
+/// while the buffer is full.
+///
+/// The receiving half of a bounded channel is an ordinary `Port`, so it can be
+/// added to a `Select` set like any other port. Receiving through a `Handle`
+/// frees up room in the buffer just as receiving on the port directly does.
+#[no_freeze]
+pub struct BoundedChan<T> {
+    priv inner: SharedChan<T>,
+}
+
+/// The possible outcomes of a `try_send` on a `BoundedChan`. The value which
+/// was attempted to be sent is handed back if it could not be sent.
+#[deriving(Eq, Clone)]
+pub enum TrySendResult<T> {
+    /// The value was placed into the channel's buffer
+    Sent,
+    /// The channel's buffer is full, so the value could not be sent without
+    /// blocking
+    Full(T),
+    /// The receiving port has hung up, so the value will never be received
+    Closed(T),
+}
+
 /// This enumeration is the list of the possible reasons that try_recv could not
 /// return data when called.
 #[deriving(Eq, Clone)]
@@ -361,6 +416,17 @@ struct Packet {
     select_next: *mut Packet,
     select_prev: *mut Packet,
     recv_cnt: int,
+
+    // Only present for bounded channels
+    bound: Option<Bound>,
+}
+
+struct Bound {
+    cap: int,
+    inflight: AtomicInt, // How many messages are sent but not yet received
+    waiting: AtomicInt,  // How many senders are in `waiters`
+    lock: Mutex,         // Protects `waiters`
+    waiters: ~[BlockedTask],
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -383,6 +449,111 @@ impl Packet {
             select_next: 0 as *mut Packet,
             select_prev: 0 as *mut Packet,
             recv_cnt: 0,
+
+            bound: None,
+        }
+    }
+
+    fn bounded(cap: uint) -> Packet {
+        let mut p = Packet::new();
+        p.bound = Some(Bound {
+            cap: cap as int,
+            inflight: AtomicInt::new(0),
+            waiting: AtomicInt::new(0),
+            lock: unsafe { Mutex::new() },
+            waiters: ~[],
+        });
+        p
+    }
+
+    // Tests whether the port has hung up (or is in the process of hanging up).
+    // See the comment in `try_send` for a shared channel for the range check.
+    fn port_dropped(&self) -> bool {
+        self.cnt.load(Relaxed) < DISCONNECTED + 1024
+    }
+
+    // Reserves a slot in a bounded channel's buffer, returning whether there
+    // was room for another message.
+    fn acquire_slot(&mut self) -> bool {
+        let bound = self.bound.get_mut_ref();
+        loop {
+            let n = bound.inflight.load(SeqCst);
+            if n >= bound.cap { return false }
+            if bound.inflight.compare_and_swap(n, n + 1, SeqCst) == n {
+                return true
+            }
+        }
+    }
+
+    // Releases a slot reserved by `acquire_slot`, waking up one sender which
+    // is blocked waiting for room (if there is one).
+    fn release_slot(&mut self) {
+        let task = {
+            let bound = self.bound.get_mut_ref();
+            bound.inflight.fetch_sub(1, SeqCst);
+            if bound.waiting.load(SeqCst) == 0 { return }
+            unsafe {
+                bound.lock.lock();
+                let task = bound.waiters.shift();
+                if task.is_some() {
+                    bound.waiting.fetch_sub(1, SeqCst);
+                }
+                bound.lock.unlock();
+                task
+            }
+        };
+        match task {
+            Some(task) => match task.wake() {
+                Some(task) => task.reawaken(true),
+                None => {}
+            },
+            None => {}
+        }
+    }
+
+    // Blocks the current task until a slot in a bounded channel may have
+    // become available or the port has hung up. Callers must retry their
+    // `acquire_slot` after this returns.
+    fn wait_for_slot(&mut self) {
+        let this: *mut Packet = self;
+        let task: ~Task = Local::take();
+        task.deschedule(1, |task| {
+            unsafe {
+                let bound = (*this).bound.get_mut_ref();
+                bound.lock.lock();
+                bound.waiting.fetch_add(1, SeqCst);
+                let ret = if bound.inflight.load(SeqCst) < bound.cap ||
+                             (*this).port_dropped() {
+                    bound.waiting.fetch_sub(1, SeqCst);
+                    Err(task)
+                } else {
+                    bound.waiters.push(task);
+                    Ok(())
+                };
+                bound.lock.unlock();
+                ret
+            }
+        });
+    }
+
+    // Wakes up all senders blocked on a bounded channel. Called when the port
+    // hangs up.
+    fn wake_senders(&mut self) {
+        let waiters = {
+            let bound = self.bound.get_mut_ref();
+            unsafe {
+                bound.lock.lock();
+                let waiters = util::replace(&mut bound.waiters, ~[]);
+                bound.waiting.store(0, SeqCst);
+                bound.lock.unlock();
+                waiters
+            }
+        };
+        for task in waiters.move_iter() {
+            match task.wake() {
+                Some(task) => task.reawaken(true),
+                None => {}
+            }
         }
     }
 
@@ -536,6 +707,13 @@ impl Drop for Packet {
     }
 }
 
+impl Drop for Bound {
+    fn drop(&mut self) {
+        assert!(self.waiters.is_empty());
+        unsafe { self.lock.destroy(); }
+    }
+}
+
 impl<T: Send> Chan<T> {
     /// Creates a new port/channel pair. All data send on the channel returned
     /// will become available on the port as well. See the documentation of
@@ -681,13 +859,22 @@ impl<T: Send> SharedChan<T> {
             // received". Once we get beyond this check, we have permanently
             // entered the realm of "this may be received"
             let packet = self.queue.packet();
-            if (*packet).cnt.load(Relaxed) < DISCONNECTED + 1024 {
+            if (*packet).port_dropped() {
                 return false
             }
+            self.push(t);
+            true
+        }
+    }
 
+    // Pushes a value onto the queue once it's been determined that the port
+    // has not yet hung up.
+    fn push(&self, t: T) {
+        unsafe {
             let this = cast::transmute_mut(self);
             this.queue.push(t);
 
+            let packet = this.queue.packet();
             match (*packet).increment() {
                 DISCONNECTED => {} // oh well, we tried
                 -1 => { (*packet).wakeup(true); }
@@ -698,7 +885,6 @@ impl<T: Send> SharedChan<T> {
                     }
                 }
             }
-            true
         }
     }
 }
@@ -717,6 +903,79 @@ impl<T: Send> Drop for SharedChan<T> {
     }
 }
 
+impl<T: Send> BoundedChan<T> {
+    /// Creates a new bounded channel and port pair. At most `capacity` messages
+    /// can be buffered in the channel at once, after which senders will block
+    /// until the port receives some data.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if `capacity` is 0. See
+    /// `extra::comm::rendezvous` for a channel without any buffering.
+    pub fn new(capacity: uint) -> (Port<T>, BoundedChan<T>) {
+        assert!(capacity > 0, "bounded channels must have a non-zero capacity");
+        let (c, p) = mpsc::queue(Packet::bounded(capacity));
+        let c = MPSC(c);
+        (Port { queue: c }, BoundedChan { inner: SharedChan { queue: p } })
+    }
+
+    /// Returns the maximum number of messages which can be buffered by this
+    /// channel.
+    pub fn capacity(&self) -> uint {
+        unsafe { (*self.inner.queue.packet()).bound.get_ref().cap as uint }
+    }
+
+    /// Sends a value along this channel, blocking the current task until there
+    /// is room in the channel's buffer.
+    ///
+    /// # Failure
+    ///
+    /// Like `Chan::send`, this function will fail if the receiving port has
+    /// hung up, including if it hangs up while this task is blocked.
+    pub fn send(&self, t: T) {
+        if self.send_opt(t).is_err() {
+            fail!("sending on a closed channel");
+        }
+    }
+
+    /// Sends a value along this channel, blocking the current task until there
+    /// is room in the channel's buffer. Unlike `send`, this function will not
+    /// fail if the port hangs up, but instead hand back the value in `Err`.
+    ///
+    /// As with `Chan::try_send`, a return value of `Ok` does *not* mean that
+    /// the data will be received.
+    pub fn send_opt(&self, t: T) -> Result<(), T> {
+        let mut t = t;
+        loop {
+            match self.try_send(t) {
+                Sent => return Ok(()),
+                Closed(t) => return Err(t),
+                Full(val) => { t = val; }
+            }
+            unsafe { (*self.inner.queue.packet()).wait_for_slot(); }
+        }
+    }
+
+    /// Attempts to send a value on this channel without blocking. If the
+    /// channel's buffer is full or the port has hung up, the value is handed
+    /// back to the caller.
+    pub fn try_send(&self, t: T) -> TrySendResult<T> {
+        unsafe {
+            let packet = self.inner.queue.packet();
+            if (*packet).port_dropped() { return Closed(t) }
+            if !(*packet).acquire_slot() { return Full(t) }
+            self.inner.push(t);
+            Sent
+        }
+    }
+}
+
+impl<T: Send> Clone for BoundedChan<T> {
+    fn clone(&self) -> BoundedChan<T> {
+        BoundedChan { inner: self.inner.clone() }
+    }
+}
+
 impl<T: Send> Port<T> {
     /// Blocks waiting for a value on this port
     ///
@@ -822,6 +1081,9 @@ impl<T: Send> Port<T> {
         if increment && ret.is_some() {
             unsafe { (*this.queue.packet()).steals += 1; }
         }
+        if ret.is_some() {
+            this.release_slot();
+        }
         match ret {
             Some(t) => Data(t),
             None => {
@@ -846,7 +1108,7 @@ impl<T: Send> Port<T> {
                     }
                 };
                 match ret {
-                    Some(data) => Data(data),
+                    Some(data) => { this.release_slot(); Data(data) }
                     None => Disconnected,
                 }
             }
@@ -865,6 +1127,12 @@ impl<T: Send> Port<T> {
     ///
     /// If the channel has hung up, then `None` is returned. Otherwise `Some` of
     /// the value found on the port is returned.
+    ///
+    /// # Failure
+    ///
+    /// If the current task has a cancellation token, this function will fail
+    /// with the `CancelReason` if the token is cancelled while the task is
+    /// blocked.
     pub fn recv_opt(&self) -> Option<T> {
         // optimistic preflight check (scheduling is expensive)
         match self.try_recv() {
@@ -873,6 +1141,11 @@ impl<T: Send> Port<T> {
             Data(t) => return Some(t),
         }
 
+        match task::cancel_token() {
+            Some(token) => return self.recv_cancellable(&token),
+            None => {}
+        }
+
         let packet;
         let this;
         unsafe {
@@ -897,11 +1170,140 @@ impl<T: Send> Port<T> {
         }
     }
 
+    /// Blocks waiting for a value on this port for at most `msecs`
+    /// milliseconds.
+    ///
+    /// This function has the same return values as `try_recv`, except that it
+    /// will block the current task until data arrives, the channel hangs up,
+    /// or the timeout expires (in which case `Empty` is returned). A timeout
+    /// of 0 is equivalent to calling `try_recv`.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if a timer could not be created in the current
+    /// runtime.
+    pub fn recv_timeout(&mut self, msecs: u64) -> TryRecvResult<T> {
+        match self.try_recv() {
+            Empty => {}
+            ret => return ret,
+        }
+        if msecs == 0 { return Empty }
+
+        let sel = Select::new();
+        let ready = {
+            let _handle = sel.add(self);
+            sel.wait_timeout(msecs)
+        };
+        match ready {
+            Some(..) => self.try_recv(),
+            None => Empty,
+        }
+    }
+
     /// Returns an iterator which will block waiting for messages, but never
     /// `fail!`. It will return `None` when the channel has hung up.
     pub fn iter<'a>(&'a self) -> Messages<'a, T> {
         Messages { port: self }
     }
+
+    // Blocks waiting for either a value on this port or the cancellation of
+    // `token`. Data which is available takes precedence over cancellation.
+    //
+    // The task blocks on the packet and on the token at the same time, in the
+    // same way that `Select::wait` blocks on several packets, so whichever
+    // wakes the task up first wins. This doesn't go through `Select` itself,
+    // so the port may also be a member of a `Select` set.
+    fn recv_cancellable(&self, token: &CancelToken) -> Option<T> {
+        loop {
+            match self.try_recv() {
+                Data(t) => return Some(t),
+                Disconnected => return None,
+                Empty => {}
+            }
+            token.check();
+
+            unsafe {
+                let packet = self.queue.packet();
+                let mut blocked = false;
+                let mut registered = None;
+                let task: ~Task = Local::take();
+                task.deschedule(2, |task| {
+                    if !blocked {
+                        assert!((*packet).to_wake.is_none());
+                        (*packet).to_wake = Some(task);
+                        (*packet).selecting.store(true, SeqCst);
+                        if (*packet).decrement() {
+                            blocked = true;
+                            Ok(())
+                        } else {
+                            // see Select::wait for why to_wake is emptied
+                            // first
+                            let task = (*packet).to_wake.take_unwrap();
+                            (*packet).abort_selection(false);
+                            (*packet).selecting.store(false, SeqCst);
+                            Err(task)
+                        }
+                    } else {
+                        let waker = WakeTask { task: Some(task.cast_to_uint()) };
+                        match token.register(waker) {
+                            Ok(id) => { registered = Some(id); Ok(()) }
+                            Err(mut waker) => {
+                                let task = waker.task.take_unwrap();
+                                Err(BlockedTask::cast_from_uint(task))
+                            }
+                        }
+                    }
+                });
+
+                match registered {
+                    Some(id) => { token.unregister(id); }
+                    None => {}
+                }
+                if blocked && (*packet).abort_selection(true) {
+                    while (*packet).selecting.load(Relaxed) {
+                        task::deschedule();
+                    }
+                }
+            }
+        }
+    }
+
+    // Frees up room in the buffer of a bounded channel after a message has
+    // been received.
+    fn release_slot(&mut self) {
+        unsafe {
+            let packet = self.queue.packet();
+            if (*packet).bound.is_some() {
+                (*packet).release_slot();
+            }
+        }
+    }
+}
+
+// Wakes up a task blocked in `recv_cancellable` when its token is cancelled.
+// The task is held as a `BlockedTask` cast to a uint.
+struct WakeTask {
+    task: Option<uint>,
+}
+
+impl task::Canceller for WakeTask {
+    fn cancel(~self) {
+        let mut this = self;
+        let task = unsafe { BlockedTask::cast_from_uint(this.task.take_unwrap()) };
+        match task.wake() {
+            Some(task) => task.reawaken(true),
+            None => {}
+        }
+    }
+}
+
+impl Drop for WakeTask {
+    fn drop(&mut self) {
+        match self.task.take() {
+            Some(task) => unsafe { BlockedTask::cast_from_uint(task).trash() },
+            None => {}
+        }
+    }
 }
 
 impl<'a, T: Send> Iterator<T> for Messages<'a, T> {
@@ -915,7 +1317,11 @@ impl<T: Send> Drop for Port<T> {
         // half has already disconnected, then we'll just deallocate everything
         // when the shared packet is deallocated.
         unsafe {
-            (*self.queue.packet()).cnt.store(DISCONNECTED, SeqCst);
+            let packet = self.queue.packet();
+            (*packet).cnt.store(DISCONNECTED, SeqCst);
+            if (*packet).bound.is_some() {
+                (*packet).wake_senders();
+            }
         }
     }
 }
@@ -1393,4 +1799,117 @@ mod test {
         p2.recv();
         assert_eq!(p.try_recv(), Disconnected);
     })
+
+    test!(fn recv_timeout() {
+        let (mut p, c) = Chan::<int>::new();
+        assert_eq!(p.recv_timeout(0), Empty);
+        assert_eq!(p.recv_timeout(10), Empty);
+        c.send(1);
+        assert_eq!(p.recv_timeout(10), Data(1));
+        drop(c);
+        assert_eq!(p.recv_timeout(10), Disconnected);
+    })
+
+    test!(fn recv_timeout_woken() {
+        let (mut p, c) = SharedChan::<int>::new();
+        let (p1, c1) = Chan::<()>::new();
+        do spawn {
+            p1.recv();
+            c.send(1);
+        }
+        c1.send(());
+        assert_eq!(p.recv_timeout(100000), Data(1));
+        assert_eq!(p.recv_timeout(100000), Disconnected);
+    })
+
+    test!(fn bounded_smoke() {
+        let (p, c) = BoundedChan::new(1);
+        assert_eq!(c.capacity(), 1);
+        c.send(1);
+        assert_eq!(c.try_send(2), Full(2));
+        assert_eq!(p.recv(), 1);
+        assert_eq!(c.try_send(2), Sent);
+        assert_eq!(p.recv(), 2);
+    })
+
+    test!(fn bounded_zero_capacity() {
+        let (_p, _c) = BoundedChan::<int>::new(0);
+    } #[should_fail])
+
+    test!(fn bounded_port_gone() {
+        let (p, c) = BoundedChan::new(1);
+        drop(p);
+        assert_eq!(c.try_send(1), Closed(1));
+        assert_eq!(c.send_opt(2), Err(2));
+    })
+
+    test!(fn bounded_port_gone_fails_send() {
+        let (p, c) = BoundedChan::new(1);
+        drop(p);
+        c.send(1);
+    } #[should_fail])
+
+    test!(fn bounded_chan_gone() {
+        let (p, c) = BoundedChan::new(2);
+        c.send(1);
+        drop(c);
+        assert_eq!(p.recv_opt(), Some(1));
+        assert_eq!(p.recv_opt(), None);
+    })
+
+    test!(fn bounded_blocks_until_recv() {
+        let (p, c) = BoundedChan::new(2);
+        let (donep, donec) = Chan::new();
+        do spawn {
+            for i in range(0, 10) {
+                c.send(i);
+            }
+            donec.send(());
+        }
+        for _ in range(0, 20) { task::deschedule(); }
+        // The sender can't have gotten any further than filling the buffer
+        assert_eq!(donep.try_recv(), Empty);
+        for i in range(0, 10) {
+            assert_eq!(p.recv(), i);
+        }
+        donep.recv();
+    })
+
+    test!(fn bounded_port_gone_wakes_sender() {
+        let (p, c) = BoundedChan::new(1);
+        let (donep, donec) = Chan::new();
+        c.send(1);
+        do spawn {
+            donec.send(c.send_opt(2));
+        }
+        for _ in range(0, 20) { task::deschedule(); }
+        drop(p);
+        assert_eq!(donep.recv(), Err(2));
+    })
+
+    test!(fn bounded_stress_shared() {
+        static AMT: uint = 10000;
+        static NTHREADS: uint = 8;
+        let (p, c) = BoundedChan::<int>::new(4);
+        let (p1, c1) = Chan::new();
+
+        do spawn {
+            for _ in range(0, AMT * NTHREADS) {
+                assert_eq!(p.recv(), 1);
+            }
+            match p.try_recv() {
+                Data(..) => fail!(),
+                _ => {}
+            }
+            c1.send(());
+        }
+
+        for _ in range(0, NTHREADS) {
+            let c = c.clone();
+            do spawn {
+                for _ in range(0, AMT) { c.send(1); }
+            }
+        }
+        p1.recv();
+    })
 }
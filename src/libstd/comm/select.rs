@@ -41,13 +41,33 @@
 //!     }
 //! )
 //! ```
+//!
+//! # Timeouts
+//!
+//! A set can be waited on with a deadline through `Select::wait_timeout`,
+//! which returns `None` if no port became ready in time. Timer ports are
+//! ordinary ports, so a port created by `Timer::oneshot` can also be added to
+//! the set directly when one deadline should span several waits.
+//!
+//! ```rust,ignore
+//! let (mut p, c) = Chan::<int>::new();
+//! let sel = Select::new();
+//! let h = sel.add(&mut p);
+//! match sel.wait_timeout(100) {
+//!     Some(id) if id == h.id => { /* data (or a hangup) on p */ }
+//!     Some(..) => unreachable!(),
+//!     None => { /* nothing arrived within 100ms */ }
+//! }
+//! ```
 
 #[allow(dead_code)];
 
 use cast;
 use comm;
+use io::timer::Timer;
 use iter::Iterator;
 use kinds::Send;
+use local_data;
 use ops::Drop;
 use option::{Some, None, Option};
 use ptr::RawPtr;
@@ -59,6 +79,11 @@ use sync::atomics::{Relaxed, SeqCst};
 use task;
 use uint;
 
+// The timer behind `Select::wait_timeout` for the current task. Creating a
+// timer is comparatively expensive (a libuv handle, or a round trip to the
+// timer helper thread), so it's kept around between timed waits.
+local_data_key!(TIMER_KEY: Timer)
+
 macro_rules! select {
     (
         $name1:pat = $port1:ident.$meth1:ident() => $code1:expr,
@@ -251,6 +276,46 @@ impl Select {
         }
     }
 
+    /// Waits for an event on this port set, giving up after `msecs`
+    /// milliseconds have elapsed.
+    ///
+    /// If a port becomes ready in time then `Some` of its handle's id is
+    /// returned (see `wait`), and otherwise `None` is returned. A timeout of 0
+    /// only checks whether any port is ready without blocking.
+    ///
+    /// The deadline is implemented by temporarily adding a `Timer` oneshot port
+    /// to this set, so this works with both green and native tasks. Each task
+    /// creates its timer on its first timed wait and reuses it afterwards.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if a timer could not be created in the current
+    /// runtime.
+    pub fn wait_timeout(&self, msecs: u64) -> Option<uint> {
+        unsafe {
+            for p in self.iter() {
+                if (*p).can_recv() {
+                    return Some((*p).selection_id);
+                }
+            }
+        }
+        if msecs == 0 { return None }
+
+        let mut timer = match local_data::pop(TIMER_KEY) {
+            Some(timer) => timer,
+            None => Timer::new().expect("Select::wait_timeout: could not \
+                                         create a Timer"),
+        };
+        let ret = {
+            let mut timeout = timer.oneshot(msecs);
+            let handle = self.add(&mut timeout);
+            let id = self.wait();
+            if id == handle.id {None} else {Some(id)}
+        };
+        local_data::set(TIMER_KEY, timer);
+        ret
+    }
+
     unsafe fn remove(&self, packet: *mut Packet) {
         let this = cast::transmute_mut(self);
         assert!(!(*packet).selecting.load(Relaxed));
@@ -421,6 +486,100 @@ mod test {
         c3.send(());
     })
 
+    test!(fn bounded() {
+        let (mut p1, c1) = BoundedChan::<int>::new(1);
+        let (mut p2, _c2) = Chan::<int>::new();
+        c1.send(1);
+        assert_eq!(c1.try_send(2), Full(2));
+        select! (
+            a = p1.recv() => { assert_eq!(a, 1); },
+            _b = p2.recv() => { fail!() }
+        )
+        assert_eq!(c1.try_send(2), Sent);
+        select! (
+            a = p1.recv() => { assert_eq!(a, 2); },
+            _b = p2.recv() => { fail!() }
+        )
+    })
+
+    test!(fn bounded_unblocks_sender() {
+        let (mut p1, c1) = BoundedChan::<int>::new(1);
+        let (mut p2, _c2) = Chan::<int>::new();
+        let (p3, c3) = Chan::<()>::new();
+
+        do spawn {
+            c1.send(1);
+            c1.send(2);
+            c3.send(());
+        }
+
+        select! (
+            a = p1.recv() => { assert_eq!(a, 1); },
+            _b = p2.recv() => { fail!() }
+        )
+        p3.recv();
+        select! (
+            a = p1.recv() => { assert_eq!(a, 2); },
+            _b = p2.recv() => { fail!() }
+        )
+    })
+
+    test!(fn timeout() {
+        let (mut p1, _c1) = Chan::<int>::new();
+        let (mut p2, _c2) = Chan::<int>::new();
+        let sel = Select::new();
+        let _h1 = sel.add(&mut p1);
+        let _h2 = sel.add(&mut p2);
+        assert_eq!(sel.wait_timeout(0), None);
+        assert_eq!(sel.wait_timeout(10), None);
+    })
+
+    test!(fn ready_before_timeout() {
+        let (mut p1, _c1) = Chan::<int>::new();
+        let (mut p2, c2) = Chan::<int>::new();
+        let sel = Select::new();
+        let _h1 = sel.add(&mut p1);
+        let mut h2 = sel.add(&mut p2);
+        c2.send(2);
+        assert_eq!(sel.wait_timeout(0), Some(h2.id));
+        assert_eq!(sel.wait_timeout(100000), Some(h2.id));
+        assert_eq!(h2.recv(), 2);
+    })
+
+    test!(fn woken_before_timeout() {
+        let (mut p1, c1) = Chan::<int>::new();
+        let (p2, c2) = Chan::<()>::new();
+        let (p3, c3) = Chan::<Chan<int>>::new();
+
+        do spawn {
+            p2.recv();
+            c1.send(1);
+            // Hand the sender back so the port isn't disconnected when this
+            // task exits, which would wake up the final select.
+            c3.send(c1);
+        }
+
+        let sel = Select::new();
+        let mut h1 = sel.add(&mut p1);
+        c2.send(());
+        assert_eq!(sel.wait_timeout(100000), Some(h1.id));
+        assert_eq!(h1.recv(), 1);
+        let _c1 = p3.recv();
+        assert_eq!(sel.wait_timeout(1), None);
+    })
+
+    test!(fn timer_port() {
+        use io::timer::Timer;
+
+        let (mut p1, _c1) = Chan::<int>::new();
+        let mut timer = Timer::new().unwrap();
+        let mut timeout = timer.oneshot(10);
+        select! (
+            _a = p1.recv() => { fail!() },
+            _t = timeout.recv() => {}
+        )
+    })
+
     test!(fn stress() {
         static AMT: int = 10000;
         let (mut p1, c1) = Chan::<int>::new();
@@ -46,15 +46,26 @@ particular bits of it, etc.
 */
 
 use c_str::ToCStr;
+use cast;
 use clone::Clone;
 use iter::Iterator;
+use libc;
+use ops::Drop;
+use os;
+use ptr;
+use ptr::RawPtr;
+use to_str::ToStr;
+use uint;
+use unstable::raw::Slice;
+use util;
 use super::{Reader, Writer, Seek};
 use super::{SeekStyle, Read, Write, Open, IoError, Truncate,
-            FileMode, FileAccess, FileStat, io_error, FilePermission};
+            FileMode, FileAccess, FileStat, io_error, FilePermission,
+            LockKind};
 use rt::rtio::{RtioFileStream, IoFactory, LocalIo};
 use io;
 use option::{Some, None, Option};
-use result::{Ok, Err};
+use result::{Result, Ok, Err};
 use path;
 use path::{Path, GenericPath};
 use vec::{OwnedVector, ImmutableVector};
@@ -206,6 +217,137 @@ impl File {
         self.fd.truncate(size).map_err(|e| io_error::cond.raise(e));
     }
 
+    /// Places a shared advisory lock on this file, blocking the current task
+    /// until the lock can be acquired.
+    ///
+    /// Any number of shared locks may be held at once, but a shared lock
+    /// cannot be held while another handle holds an exclusive lock. Locks are
+    /// advisory: they only exclude other handles which also lock the file.
+    /// The lock is released by `unlock` or when this file is closed.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    pub fn lock_shared(&mut self) {
+        self.lock(io::SharedLock, true);
+    }
+
+    /// Places an exclusive advisory lock on this file, blocking the current
+    /// task until the lock can be acquired.
+    ///
+    /// See `lock_shared` for more information about advisory locks.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    pub fn lock_exclusive(&mut self) {
+        self.lock(io::ExclusiveLock, true);
+    }
+
+    /// Attempts to place a shared advisory lock on this file without
+    /// blocking. Returns whether the lock was acquired.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure. The
+    /// lock being held elsewhere is not considered a failure.
+    pub fn try_lock_shared(&mut self) -> bool {
+        self.lock(io::SharedLock, false)
+    }
+
+    /// Attempts to place an exclusive advisory lock on this file without
+    /// blocking. Returns whether the lock was acquired.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure. The
+    /// lock being held elsewhere is not considered a failure.
+    pub fn try_lock_exclusive(&mut self) -> bool {
+        self.lock(io::ExclusiveLock, false)
+    }
+
+    /// Releases any advisory lock held on this file by this handle.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    pub fn unlock(&mut self) {
+        self.fd.unlock().map_err(|e| io_error::cond.raise(e));
+    }
+
+    // Exposes the underlying descriptor to `TcpStream::send_file`.
+    #[doc(hidden)]
+    pub fn raw_fd(&self) -> libc::c_int { self.fd.fd() }
+
+    fn lock(&mut self, kind: LockKind, block: bool) -> bool {
+        match self.fd.lock(kind, block) {
+            Ok(()) => true,
+            Err(ref e) if !block && e.kind == io::ResourceUnavailable => false,
+            Err(e) => { io_error::cond.raise(e); false }
+        }
+    }
+
+    /// Maps the entire contents of this file into memory for reading.
+    ///
+    /// The returned `MappedFile` borrows this file, and its contents can be
+    /// accessed as a byte slice without copying them through `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition if the file could
+    /// not be mapped, for example because it was not opened for reading.
+    pub fn map<'a>(&'a mut self) -> Option<MappedFile<'a>> {
+        match file_size(&mut self.fd) {
+            Ok(size) => self.map_range(0, size as uint),
+            Err(e) => { io_error::cond.raise(e); None }
+        }
+    }
+
+    /// Maps the entire contents of this file into memory for reading and
+    /// writing. Writes to the map are carried through to the file.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition if the file could
+    /// not be mapped, for example because it was not opened with `ReadWrite`
+    /// access.
+    pub fn map_mut<'a>(&'a mut self) -> Option<MappedFileMut<'a>> {
+        match file_size(&mut self.fd) {
+            Ok(size) => self.map_range_mut(0, size as uint),
+            Err(e) => { io_error::cond.raise(e); None }
+        }
+    }
+
+    /// Maps `len` bytes of this file starting at byte `offset` into memory
+    /// for reading. The region must lie entirely within the file.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition if the region
+    /// could not be mapped.
+    pub fn map_range<'a>(&'a mut self, offset: u64,
+                         len: uint) -> Option<MappedFile<'a>> {
+        MapRegion::new(self, offset, len, false).map(|region| {
+            MappedFile { region: region }
+        })
+    }
+
+    /// Maps `len` bytes of this file starting at byte `offset` into memory
+    /// for reading and writing. Writes to the map are carried through to the
+    /// file, and the file is first extended with zeroes if it is shorter than
+    /// `offset + len`.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition if the region
+    /// could not be mapped.
+    pub fn map_range_mut<'a>(&'a mut self, offset: u64,
+                             len: uint) -> Option<MappedFileMut<'a>> {
+        MapRegion::new(self, offset, len, true).map(|region| {
+            MappedFileMut { region: region }
+        })
+    }
+
     /// Tests whether this stream has reached EOF.
     ///
     /// If true, then this file will no longer continue to return data via
@@ -374,6 +516,92 @@ pub fn copy(from: &Path, to: &Path) {
     chmod(to, from.stat().perm)
 }
 
+/// Atomically replaces the contents of the file at `path`.
+///
+/// The closure is handed a freshly created temporary file in the same
+/// directory as `path`. Once the closure returns, the temporary file is
+/// flushed to permanent storage and then renamed over `path`, so other
+/// readers of `path` will see either its old contents or its new contents in
+/// full, but never a partially written file. If `path` already exists, its
+/// permission bits are carried over to the new file.
+///
+/// # Example
+///
+///     use std::io::fs;
+///
+///     fs::write_atomically(&Path::new("cache.bin"), |file| {
+///         file.write(bytes!("new contents"));
+///     });
+///
+/// # Errors
+///
+/// If an `io_error` condition is raised while the temporary file is being
+/// created or written, the temporary file is removed, `path` is left
+/// untouched, and the first such error is raised again. Errors from the final
+/// rename are raised as for `fs::rename`, also after removing the temporary
+/// file. If the closure fails, the temporary file is removed as the task
+/// unwinds.
+pub fn write_atomically(path: &Path, f: |&mut File|) {
+    let mut tmp = TempFileGuard { path: atomic_temp_path(path), armed: true };
+    let ret = io::result(|| {
+        match File::open_mode(&tmp.path, Truncate, Write) {
+            Some(mut file) => {
+                if path.exists() {
+                    chmod(&tmp.path, path.stat().perm);
+                }
+                f(&mut file);
+                file.fsync();
+            }
+            None => {}
+        }
+    }).and_then(|()| io::result(|| rename(&tmp.path, path)));
+    match ret {
+        Ok(()) => {
+            tmp.armed = false;
+            sync_dir(&path.dir_path());
+        }
+        Err(e) => {
+            util::drop(tmp);
+            io_error::cond.raise(e);
+        }
+    }
+
+    // Removes the temporary file unless the rename went through, including
+    // when the closure fails and this is dropped during unwinding.
+    struct TempFileGuard {
+        path: Path,
+        armed: bool,
+    }
+
+    impl Drop for TempFileGuard {
+        fn drop(&mut self) {
+            if self.armed {
+                let _ = io::result(|| unlink(&self.path));
+            }
+        }
+    }
+
+    // Persisting the rename itself requires syncing the containing directory
+    // on unix. Not every filesystem supports this, so it's best-effort.
+    #[cfg(unix)]
+    fn sync_dir(dir: &Path) {
+        let _ = io::result(|| {
+            File::open(dir).map(|mut d| d.fsync());
+        });
+    }
+    #[cfg(windows)]
+    fn sync_dir(_dir: &Path) {}
+}
+
+fn atomic_temp_path(path: &Path) -> Path {
+    use rand;
+
+    let mut name = bytes!(".").to_owned();
+    name.push_all(path.filename().unwrap_or(bytes!("file")));
+    name.push_all(format!(".{}.tmp", rand::random::<u32>()).as_bytes());
+    path.with_filename(name)
+}
+
 /// Changes the permission mode bits found on a file or a directory. This
 /// function takes a mask from the `io` module
 ///
@@ -605,6 +833,24 @@ impl Reader for File {
             }
         }
     }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Option<uint> {
+        match self.fd.readv(bufs) {
+            Ok(read) => {
+                self.last_nread = read as int;
+                Some(read)
+            }
+            Err(ioerr) => {
+                // EOF is indicated by returning None
+                if ioerr.kind != io::EndOfFile {
+                    io_error::cond.raise(ioerr);
+                } else {
+                    self.last_nread = 0;
+                }
+                None
+            }
+        }
+    }
 }
 
 impl Writer for File {
@@ -616,6 +862,13 @@ impl Writer for File {
             }
         }
     }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) {
+        match self.fd.writev(bufs) {
+            Ok(()) => (),
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
 }
 
 impl Seek for File {
@@ -644,6 +897,302 @@ impl Seek for File {
     }
 }
 
+/// A read-only region of a file which has been mapped into memory, created
+/// with `File::map` or `File::map_range`.
+///
+/// The map borrows the `File` it was created from, so the file cannot be
+/// truncated or closed while it is mapped. Note, however, that nothing
+/// prevents other processes from modifying the file in the meantime. Changes
+/// they make may show up in the map, and shrinking the file underneath a map
+/// will cause accesses past the new end of the file to crash the process.
+pub struct MappedFile<'a> {
+    priv region: MapRegion<'a>,
+}
+
+/// A region of a file which has been mapped into memory for reading and
+/// writing, created with `File::map_mut` or `File::map_range_mut`. The same
+/// caveats as for `MappedFile` apply.
+pub struct MappedFileMut<'a> {
+    priv region: MapRegion<'a>,
+}
+
+/// Hints about how the contents of a `MappedFile` will be accessed, which
+/// the operating system may use to tune paging behavior.
+pub enum MapAdvice {
+    /// No particular access pattern, the default.
+    AdviseNormal,
+    /// Pages will be accessed in random order, so read-ahead is unhelpful.
+    AdviseRandom,
+    /// Pages will be accessed in sequential order, so they may be read ahead
+    /// aggressively and freed soon after being accessed.
+    AdviseSequential,
+    /// The map will be accessed soon, so it may be read in ahead of time.
+    AdviseWillNeed,
+    /// The map will not be accessed in the near future.
+    AdviseDontNeed,
+}
+
+impl<'a> MappedFile<'a> {
+    /// Returns the number of bytes in this map.
+    pub fn len(&self) -> uint { self.region.len }
+
+    /// Returns the offset in the file at which this map starts.
+    pub fn offset(&self) -> u64 { self.region.offset }
+
+    /// Returns the contents of this map as a byte slice.
+    pub fn as_slice<'b>(&'b self) -> &'b [u8] { self.region.as_slice() }
+
+    /// Tells the operating system how this map is going to be accessed.
+    /// Advice is purely a hint and may be ignored, in particular it has no
+    /// effect on Windows.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    pub fn advise(&mut self, advice: MapAdvice) { self.region.advise(advice) }
+
+    /// Changes the length of this map to `len` bytes, keeping its starting
+    /// offset. The map must still lie entirely within the file.
+    ///
+    /// The file is mapped anew, so the contents may move in memory; slices
+    /// previously obtained from this map cannot outlive this call.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure, in
+    /// which case the map is left empty.
+    pub fn remap(&mut self, len: uint) { self.region.remap(len) }
+}
+
+impl<'a> MappedFileMut<'a> {
+    /// Returns the number of bytes in this map.
+    pub fn len(&self) -> uint { self.region.len }
+
+    /// Returns the offset in the file at which this map starts.
+    pub fn offset(&self) -> u64 { self.region.offset }
+
+    /// Returns the contents of this map as a byte slice.
+    pub fn as_slice<'b>(&'b self) -> &'b [u8] { self.region.as_slice() }
+
+    /// Returns the contents of this map as a mutable byte slice. Writes to
+    /// the slice are carried through to the underlying file.
+    pub fn as_mut_slice<'b>(&'b mut self) -> &'b mut [u8] {
+        let slice = Slice { data: self.region.data() as *u8,
+                            len: self.region.len };
+        unsafe { cast::transmute(slice) }
+    }
+
+    /// Synchronously writes all modifications made through this map back to
+    /// the file. This does not flush file metadata, see `File::fsync` for
+    /// that.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    pub fn flush(&mut self) {
+        let len = self.region.len;
+        self.flush_range(0, len)
+    }
+
+    /// Synchronously writes back modifications made to `len` bytes of this
+    /// map starting at `offset` bytes from the start of the map.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure.
+    ///
+    /// # Failure
+    ///
+    /// Fails if the range is not contained within the map.
+    pub fn flush_range(&mut self, offset: uint, len: uint) {
+        let region = &self.region;
+        assert!(offset <= region.len && len <= region.len - offset,
+                "flush range out of bounds of the map");
+        if len == 0 { return }
+        // msync() requires a page-aligned start address
+        let start = region.skew + offset;
+        let aligned = start - start % os::page_size();
+        let ret = unsafe {
+            os_flush(region.data().offset((aligned - region.skew) as int),
+                     start + len - aligned)
+        };
+        if !ret {
+            io_error::cond.raise(map_error("failed to flush file map"));
+        }
+
+        #[cfg(unix)]
+        unsafe fn os_flush(addr: *mut u8, len: uint) -> bool {
+            libc::msync(addr as *libc::c_void, len as libc::size_t,
+                        libc::MS_SYNC) == 0
+        }
+        #[cfg(windows)]
+        unsafe fn os_flush(addr: *mut u8, len: uint) -> bool {
+            libc::FlushViewOfFile(addr as libc::LPCVOID,
+                                  len as libc::SIZE_T) != 0
+        }
+    }
+
+    /// Tells the operating system how this map is going to be accessed. See
+    /// `MappedFile::advise`.
+    pub fn advise(&mut self, advice: MapAdvice) { self.region.advise(advice) }
+
+    /// Changes the length of this map to `len` bytes, keeping its starting
+    /// offset. The map may grow past the end of the file, in which case the
+    /// file is extended with zeroes first.
+    ///
+    /// The file is mapped anew, so the contents may move in memory; slices
+    /// previously obtained from this map cannot outlive this call.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise on the `io_error` condition on failure, in
+    /// which case the map is left empty.
+    pub fn remap(&mut self, len: uint) { self.region.remap(len) }
+}
+
+// The state shared by read-only and writable maps
+struct MapRegion<'a> {
+    file: &'a mut File,
+    map: Option<os::MemoryMap>,
+    offset: u64,
+    // distance from the start of the OS mapping, which must be aligned to
+    // `MemoryMap::granularity`, to the requested offset
+    skew: uint,
+    len: uint,
+    writable: bool,
+}
+
+impl<'a> MapRegion<'a> {
+    fn new(file: &'a mut File, offset: u64, len: uint,
+           writable: bool) -> Option<MapRegion<'a>> {
+        let granularity = os::MemoryMap::granularity() as u64;
+        let mut region = MapRegion {
+            file: file,
+            map: None,
+            offset: offset,
+            skew: (offset % granularity) as uint,
+            len: 0,
+            writable: writable,
+        };
+        match region.map_len(len) {
+            Ok(()) => Some(region),
+            Err(e) => { io_error::cond.raise(e); None }
+        }
+    }
+
+    fn as_slice<'b>(&'b self) -> &'b [u8] {
+        let slice = Slice { data: self.data() as *u8, len: self.len };
+        unsafe { cast::transmute(slice) }
+    }
+
+    fn advise(&mut self, advice: MapAdvice) {
+        let map = match self.map { Some(ref map) => map, None => return };
+        if !os_advise(map, advice) {
+            io_error::cond.raise(map_error("failed to advise file map"));
+        }
+
+        #[cfg(unix)]
+        fn os_advise(map: &os::MemoryMap, advice: MapAdvice) -> bool {
+            let advice = match advice {
+                AdviseNormal => libc::MADV_NORMAL,
+                AdviseRandom => libc::MADV_RANDOM,
+                AdviseSequential => libc::MADV_SEQUENTIAL,
+                AdviseWillNeed => libc::MADV_WILLNEED,
+                AdviseDontNeed => libc::MADV_DONTNEED,
+            };
+            unsafe {
+                libc::madvise(map.data as *libc::c_void,
+                              map.len as libc::size_t, advice) == 0
+            }
+        }
+        #[cfg(windows)]
+        fn os_advise(_map: &os::MemoryMap, _advice: MapAdvice) -> bool { true }
+    }
+
+    fn remap(&mut self, len: uint) {
+        self.map_len(len).map_err(|e| io_error::cond.raise(e));
+    }
+
+    fn data(&self) -> *mut u8 {
+        match self.map {
+            Some(ref map) => unsafe { map.data.offset(self.skew as int) },
+            None => ptr::mut_null(),
+        }
+    }
+
+    fn map_len(&mut self, len: uint) -> Result<(), IoError> {
+        // Unmap first: the file may be resized below, and a failure should
+        // leave no stale map behind.
+        self.map = None;
+        self.len = 0;
+
+        let end = self.offset + len as u64;
+        let size = match file_size(&mut self.file.fd) {
+            Ok(size) => size,
+            Err(e) => return Err(e),
+        };
+        if size < end {
+            if !self.writable {
+                return Err(IoError {
+                    kind: io::InvalidInput,
+                    desc: "read-only map extends past the end of the file",
+                    detail: None,
+                });
+            }
+            match self.file.fd.truncate(end as i64) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Zero-length maps are not supported by the OS, but an empty map of a
+        // file is perfectly reasonable (e.g. of a freshly created file).
+        if len > 0 {
+            // The OS takes the offset as a uint, which can't represent every
+            // file offset on 32-bit platforms.
+            let aligned = self.offset - self.skew as u64;
+            if aligned > uint::max_value as u64 {
+                return Err(IoError {
+                    kind: io::InvalidInput,
+                    desc: "map offset is too large for this platform",
+                    detail: None,
+                });
+            }
+            let mut options = ~[os::MapReadable,
+                                os::MapShared,
+                                os::MapFd(self.file.fd.fd()),
+                                os::MapOffset(aligned as uint)];
+            if self.writable {
+                options.push(os::MapWritable);
+            }
+            match os::MemoryMap::new(self.skew + len, options) {
+                Ok(map) => self.map = Some(map),
+                Err(e) => return Err(IoError {
+                    kind: io::OtherIoError,
+                    desc: "failed to map file into memory",
+                    detail: Some(e.to_str()),
+                }),
+            }
+        }
+        self.len = len;
+        Ok(())
+    }
+}
+
+fn file_size(fd: &mut ~RtioFileStream) -> Result<u64, IoError> {
+    let pos = match fd.tell() { Ok(pos) => pos, Err(e) => return Err(e) };
+    let size = match fd.seek(0, io::SeekEnd) { Ok(s) => s, Err(e) => return Err(e) };
+    fd.seek(pos as i64, io::SeekSet).map(|_| size)
+}
+
+fn map_error(desc: &'static str) -> IoError {
+    IoError {
+        kind: io::OtherIoError,
+        desc: desc,
+        detail: Some(os::last_os_error()),
+    }
+}
+
 impl path::Path {
     /// Get information on the file, directory, etc at this path.
     ///
@@ -704,7 +1253,8 @@ mod test {
     use str;
     use io::fs::{File, rmdir, mkdir, readdir, rmdir_recursive,
                  mkdir_recursive, copy, unlink, stat, symlink, link,
-                 readlink, chmod, lstat, change_file_times};
+                 readlink, chmod, lstat, change_file_times,
+                 write_atomically};
     use util;
     use path::Path;
     use io;
@@ -1232,6 +1782,148 @@ mod test {
         assert_eq!(stat(&tmpdir.join("h")).size, 3);
     })
 
+    iotest!(fn advisory_locks() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("lock");
+        File::create(&path);
+
+        let mut a = File::open_mode(&path, io::Open, io::ReadWrite).unwrap();
+        let mut b = File::open_mode(&path, io::Open, io::ReadWrite).unwrap();
+
+        assert!(a.try_lock_shared());
+        assert!(b.try_lock_shared());
+        assert!(!b.try_lock_exclusive());
+        a.unlock();
+        b.unlock();
+
+        a.lock_exclusive();
+        assert!(!b.try_lock_shared());
+        assert!(!b.try_lock_exclusive());
+        a.unlock();
+        assert!(b.try_lock_exclusive());
+        drop(b);
+
+        // closing the file drops its lock
+        assert!(a.try_lock_exclusive());
+    })
+
+    iotest!(fn write_atomically_replaces() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("data");
+        File::create(&path).write(bytes!("old"));
+        chmod(&path, io::UserRWX);
+
+        write_atomically(&path, |f| f.write(bytes!("new contents")));
+        assert_eq!(File::open(&path).read_to_end(),
+                   bytes!("new contents").to_owned());
+        assert_eq!(path.stat().perm & io::UserRWX, io::UserRWX);
+        assert_eq!(readdir(tmpdir.path()).len(), 1);
+
+        write_atomically(&tmpdir.join("fresh"), |f| f.write(bytes!("a")));
+        assert_eq!(File::open(&tmpdir.join("fresh")).read_to_end(),
+                   bytes!("a").to_owned());
+    })
+
+    iotest!(fn write_atomically_error_keeps_original() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("data");
+        File::create(&path).write(bytes!("old"));
+
+        let ret = io::result(|| {
+            write_atomically(&path, |f| {
+                f.write(bytes!("partial"));
+                io_error::cond.raise(io::standard_error(io::OtherIoError));
+            })
+        });
+        assert!(ret.is_err());
+        assert_eq!(File::open(&path).read_to_end(), bytes!("old").to_owned());
+        assert_eq!(readdir(tmpdir.path()).len(), 1);
+    })
+
+    iotest!(fn write_atomically_failure_removes_temp_file() {
+        use task;
+
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("data");
+        File::create(&path).write(bytes!("old"));
+
+        let child_path = path.clone();
+        let ret = do task::try {
+            write_atomically(&child_path, |f| {
+                f.write(bytes!("partial"));
+                fail!();
+            })
+        };
+        assert!(ret.is_err());
+        assert_eq!(File::open(&path).read_to_end(), bytes!("old").to_owned());
+        assert_eq!(readdir(tmpdir.path()).len(), 1);
+    })
+
+    iotest!(fn map_read_only() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("map");
+        File::create(&path).write(bytes!("hello, mapped world"));
+
+        let mut file = File::open(&path).unwrap();
+        {
+            let mut map = file.map().unwrap();
+            assert_eq!(map.len(), 19);
+            assert_eq!(map.as_slice(), bytes!("hello, mapped world"));
+            map.advise(super::AdviseSequential);
+        }
+        {
+            let map = file.map_range(7, 6).unwrap();
+            assert_eq!(map.offset(), 7);
+            assert_eq!(map.as_slice(), bytes!("mapped"));
+        }
+        match io::result(|| file.map_range(10, 100)) {
+            Ok(..) => fail!("mapped past the end of a read-only file"),
+            Err(..) => {}
+        }
+    })
+
+    iotest!(fn map_empty_file() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("empty");
+        File::create(&path);
+
+        let mut file = File::open(&path).unwrap();
+        let map = file.map().unwrap();
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.as_slice().len(), 0);
+    })
+
+    iotest!(fn map_write_and_grow() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("map");
+        File::create(&path).write(bytes!("abc"));
+
+        let mut file = File::open_mode(&path, io::Open, io::ReadWrite).unwrap();
+        {
+            let mut map = file.map_mut().unwrap();
+            map.as_mut_slice()[0] = 'x' as u8;
+            map.flush();
+
+            map.remap(8);
+            assert_eq!(map.len(), 8);
+            assert_eq!(map.as_slice(), bytes!("xbc", 0, 0, 0, 0, 0));
+            map.as_mut_slice().mut_slice_from(3).copy_from(bytes!("defgh"));
+            map.flush_range(3, 5);
+        }
+        assert_eq!(stat(&path).size, 8);
+        assert_eq!(File::open(&path).read_to_end(),
+                   bytes!("xbcdefgh").to_owned());
+
+        // an unaligned offset into the middle of the file
+        {
+            let mut map = file.map_range_mut(5, 2).unwrap();
+            map.as_mut_slice().copy_from(bytes!("GG"));
+            map.flush();
+        }
+        assert_eq!(File::open(&path).read_to_end(),
+                   bytes!("xbcdeGGh").to_owned());
+    })
+
     #[test]
     fn utime() {
         let tmpdir = tmpdir();
@@ -294,11 +294,14 @@ use condition::Guard;
 use container::Container;
 use int;
 use iter::Iterator;
+use libc;
+use libc::c_int;
 use option::{Option, Some, None};
 use path::Path;
 use result::{Ok, Err, Result};
 use str;
 use str::{StrSlice, OwnedStr};
+use task;
 use to_str::ToStr;
 use uint;
 use unstable::finally::Finally;
@@ -418,6 +421,7 @@ pub enum IoErrorKind {
     ResourceUnavailable,
     IoUnavailable,
     InvalidInput,
+    Cancelled,
 }
 
 // FIXME: #8242 implementing manually because deriving doesn't work for some reason
@@ -442,6 +446,7 @@ impl ToStr for IoErrorKind {
             ResourceUnavailable => ~"ResourceUnavailable",
             ConnectionAborted => ~"ConnectionAborted",
             InvalidInput => ~"InvalidInput",
+            Cancelled => ~"Cancelled",
         }
     }
 }
@@ -506,6 +511,25 @@ pub trait Reader {
     /// Is it actually possible for 0 bytes to be read successfully?
     fn read(&mut self, buf: &mut [u8]) -> Option<uint>;
 
+    /// Read bytes into each buffer in `bufs` in turn, as a single operation
+    /// if the underlying stream supports it (`readv` on unix). Returns the
+    /// total number of bytes read, which may be less than the combined
+    /// length of the buffers. Returns `None` on EOF.
+    ///
+    /// The default implementation reads into the first non-empty buffer.
+    ///
+    /// # Failure
+    ///
+    /// Raises the same conditions as the `read` method.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Option<uint> {
+        for buf in bufs.mut_iter() {
+            if buf.len() > 0 {
+                return self.read(&mut **buf);
+            }
+        }
+        Some(0)
+    }
+
     // Convenient helper methods based on the above methods
 
     /// Reads a single byte. Returns `None` on EOF.
@@ -848,10 +872,16 @@ pub trait Reader {
 
 impl Reader for ~Reader {
     fn read(&mut self, buf: &mut [u8]) -> Option<uint> { self.read(buf) }
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Option<uint> {
+        self.read_vectored(bufs)
+    }
 }
 
 impl<'a> Reader for &'a mut Reader {
     fn read(&mut self, buf: &mut [u8]) -> Option<uint> { self.read(buf) }
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Option<uint> {
+        self.read_vectored(bufs)
+    }
 }
 
 fn extend_sign(val: u64, nbytes: uint) -> i64 {
@@ -867,6 +897,20 @@ pub trait Writer {
     /// Raises the `io_error` condition on error
     fn write(&mut self, buf: &[u8]);
 
+    /// Write each buffer in `bufs` in turn, as a single operation if the
+    /// underlying stream supports it (`writev` on unix).
+    ///
+    /// The default implementation calls `write` for each buffer.
+    ///
+    /// # Failure
+    ///
+    /// Raises the `io_error` condition on error
+    fn write_vectored(&mut self, bufs: &[&[u8]]) {
+        for buf in bufs.iter() {
+            self.write(*buf);
+        }
+    }
+
     /// Flush this output stream, ensuring that all intermediately buffered
     /// contents reach their destination.
     ///
@@ -1036,11 +1080,13 @@ pub trait Writer {
 
 impl Writer for ~Writer {
     fn write(&mut self, buf: &[u8]) { self.write(buf) }
+    fn write_vectored(&mut self, bufs: &[&[u8]]) { self.write_vectored(bufs) }
     fn flush(&mut self) { self.flush() }
 }
 
 impl<'a> Writer for &'a mut Writer {
     fn write(&mut self, buf: &[u8]) { self.write(buf) }
+    fn write_vectored(&mut self, bufs: &[&[u8]]) { self.write_vectored(bufs) }
     fn flush(&mut self) { self.flush() }
 }
 
@@ -1284,6 +1330,7 @@ pub fn standard_error(kind: IoErrorKind) -> IoError {
         EndOfFile => "end of file",
         IoUnavailable => "I/O is unavailable",
         InvalidInput => "invalid input",
+        Cancelled => "operation was cancelled",
         _ => fail!()
     };
     IoError {
@@ -1293,6 +1340,64 @@ pub fn standard_error(kind: IoErrorKind) -> IoError {
     }
 }
 
+// Blocking I/O operations call this before entering the runtime. If the
+// current task has been cancelled, a `Cancelled` error is raised and true is
+// returned.
+fn raise_if_cancelled() -> bool {
+    if task::cancelled() {
+        io_error::cond.raise(standard_error(Cancelled));
+        true
+    } else {
+        false
+    }
+}
+
+// Runs the blocking operation `f` on the socket `fd`. If the current task is
+// cancelled before or while the operation runs, a `Cancelled` error is
+// returned instead of its result.
+//
+// To interrupt an operation which is already blocked, the socket is shut down
+// by the task which cancels the token. This makes the operation return in
+// both runtimes, but it also leaves the socket unusable. A negative `fd` (the
+// runtime doesn't expose the socket) means the operation can't be
+// interrupted, so cancellation is only checked before it starts.
+fn cancellable_socket_op<T>(fd: c_int,
+                            f: || -> Result<T, IoError>) -> Result<T, IoError> {
+    let token = match task::cancel_token() {
+        Some(token) => token,
+        None => return f(),
+    };
+    if token.is_cancelled() { return Err(standard_error(Cancelled)) }
+    if fd < 0 { return f() }
+
+    let id = match token.register(ShutdownSocket(fd)) {
+        Ok(id) => id,
+        Err(..) => return Err(standard_error(Cancelled)),
+    };
+    let ret = f();
+    if token.unregister(id) {
+        ret
+    } else {
+        Err(standard_error(Cancelled))
+    }
+}
+
+struct ShutdownSocket(c_int);
+
+impl task::Canceller for ShutdownSocket {
+    #[cfg(unix)]
+    fn cancel(~self) {
+        let ShutdownSocket(fd) = *self;
+        unsafe { libc::shutdown(fd, libc::SHUT_RDWR); }
+    }
+
+    #[cfg(windows)]
+    fn cancel(~self) {
+        let ShutdownSocket(fd) = *self;
+        unsafe { libc::shutdown(fd as libc::SOCKET, libc::SD_BOTH); }
+    }
+}
+
 pub fn placeholder_error() -> IoError {
     IoError {
         kind: OtherIoError,
@@ -1321,6 +1426,21 @@ pub enum FileAccess {
     ReadWrite,
 }
 
+/// Kinds of advisory locks which can be placed on an open file with
+/// `File::lock_shared` and friends.
+///
+/// Advisory locks are only honored by other processes which also use them;
+/// they do not prevent plain reads and writes of the locked file.
+#[deriving(Eq)]
+pub enum LockKind {
+    /// Any number of shared locks may be held on a file at once, but not
+    /// while an exclusive lock is held.
+    SharedLock,
+    /// Only one exclusive lock may be held on a file at once, and it excludes
+    /// all shared locks.
+    ExclusiveLock,
+}
+
 /// Different kinds of files which can be identified by a call to stat
 #[deriving(Eq)]
 pub enum FileType {
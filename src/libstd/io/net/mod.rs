@@ -16,3 +16,33 @@ pub mod udp;
 pub mod ip;
 #[cfg(unix)]
 pub mod unix;
+
+/// Options which can be set on sockets with `set_option`, or passed to the
+/// `*_with` constructors of `TcpStream`, `TcpListener` and `UdpSocket` to be
+/// applied before the socket is bound or connected.
+///
+/// Not every option is supported on every platform. Setting an unsupported
+/// option fails with an `IoUnavailable` error.
+#[deriving(Eq, Clone)]
+pub enum SocketOption {
+    /// Allow binding to an address which is still in `TIME_WAIT`
+    /// (`SO_REUSEADDR`). This must be set before binding.
+    ReuseAddr(bool),
+    /// Allow several sockets to bind to the same address and port
+    /// (`SO_REUSEPORT`). This must be set before binding. Unavailable on
+    /// windows.
+    ReusePort(bool),
+    /// The size of the kernel's send buffer, in bytes (`SO_SNDBUF`).
+    SendBufferSize(uint),
+    /// The size of the kernel's receive buffer, in bytes (`SO_RCVBUF`).
+    RecvBufferSize(uint),
+    /// Restrict an IPv6 socket to IPv6 traffic only (`IPV6_V6ONLY`). This
+    /// must be set before binding.
+    Ipv6Only(bool),
+    /// How long closing the socket should block while unsent data remains,
+    /// in seconds, or `None` to close in the background (`SO_LINGER`).
+    Linger(Option<uint>),
+    /// Hold back partial frames until uncorked (`TCP_CORK` on linux,
+    /// `TCP_NOPUSH` on BSDs). Unavailable on windows.
+    TcpCork(bool),
+}
@@ -11,8 +11,11 @@
 use option::{Option, Some, None};
 use result::{Ok, Err};
 use io::net::ip::SocketAddr;
-use io::{Reader, Writer, Listener, Acceptor};
-use io::{io_error, EndOfFile};
+use io::net::SocketOption;
+use io::{Reader, Writer, Listener, Acceptor, File, Seek, SeekSet, SeekEnd};
+use io::{io_error, EndOfFile, IoUnavailable, cancellable_socket_op};
+use io;
+use libc;
 use rt::rtio::{IoFactory, LocalIo, RtioSocket, RtioTcpListener};
 use rt::rtio::{RtioTcpAcceptor, RtioTcpStream};
 
@@ -26,11 +29,103 @@ impl TcpStream {
     }
 
     pub fn connect(addr: SocketAddr) -> Option<TcpStream> {
+        TcpStream::connect_with(addr, None, [])
+    }
+
+    /// Connects to `addr` after applying `opts` to the new socket and, if
+    /// `local` is given, binding it to that local address.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if any option couldn't be set or the connection
+    /// couldn't be made.
+    pub fn connect_with(addr: SocketAddr, local: Option<SocketAddr>,
+                        opts: &[SocketOption]) -> Option<TcpStream> {
         LocalIo::maybe_raise(|io| {
-            io.tcp_connect(addr).map(TcpStream::new)
+            io.tcp_connect(addr, local, opts).map(TcpStream::new)
         })
     }
 
+    /// Sets an option on the underlying socket.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the option couldn't be set, or with
+    /// `IoUnavailable` if it isn't supported on this platform.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        match self.obj.set_option(opt) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    /// Puts this stream into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, reads and writes which can't make progress
+    /// immediately raise `io_error` with a `ResourceUnavailable` error
+    /// instead of blocking the calling task. A write which is interrupted in
+    /// this way may already have sent part of its buffer. This is intended to
+    /// be paired with a readiness poller, such as libnative's `io::poll`.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the runtime doesn't support non-blocking sockets
+    /// (libuv sockets never block the task in the first place).
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        match self.obj.set_nonblocking(nonblocking) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn raw_fd(&self) -> libc::c_int { self.obj.fd() }
+
+    /// Writes the rest of `file`, from its current position to its end, to
+    /// this stream without copying the data through userspace (using
+    /// `sendfile` where the platform has it). On success the file is left
+    /// positioned after the last byte written.
+    ///
+    /// Returns the number of bytes written, or `None` without touching the
+    /// file if the runtime can't transfer files this way, in which case the
+    /// caller should fall back to `io::util::copy`.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the transfer fails part way through.
+    pub fn send_file(&mut self, file: &mut File) -> Option<u64> {
+        // If the file can't be positioned (e.g. it's really a pipe), leave it
+        // to the caller to fall back to plain reads.
+        let (start, end) = match io::result(|| {
+            let start = file.tell();
+            file.seek(0, SeekEnd);
+            let end = file.tell();
+            file.seek(start as i64, SeekSet);
+            (start, end)
+        }) {
+            Ok(pos) => pos,
+            Err(..) => return None,
+        };
+
+        let mut sent = 0;
+        while start + sent < end {
+            match self.obj.sendfile(file.raw_fd(), start + sent,
+                                    end - start - sent) {
+                Ok(0) => break,
+                Ok(n) => sent += n,
+                Err(ref e) if sent == 0 && e.kind == IoUnavailable => {
+                    return None
+                }
+                Err(e) => {
+                    io_error::cond.raise(e);
+                    break
+                }
+            }
+        }
+        file.seek((start + sent) as i64, SeekSet);
+        Some(sent)
+    }
+
     pub fn peer_name(&mut self) -> Option<SocketAddr> {
         match self.obj.peer_name() {
             Ok(pn) => Some(pn),
@@ -56,7 +151,22 @@ impl TcpStream {
 
 impl Reader for TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
-        match self.obj.read(buf) {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.read(buf)) {
+            Ok(read) => Some(read),
+            Err(ioerr) => {
+                // EOF is indicated by returning None
+                if ioerr.kind != EndOfFile {
+                    io_error::cond.raise(ioerr);
+                }
+                return None;
+            }
+        }
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Option<uint> {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.readv(bufs)) {
             Ok(read) => Some(read),
             Err(ioerr) => {
                 // EOF is indicated by returning None
@@ -71,7 +181,16 @@ impl Reader for TcpStream {
 
 impl Writer for TcpStream {
     fn write(&mut self, buf: &[u8]) {
-        match self.obj.write(buf) {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.write(buf)) {
+            Ok(_) => (),
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.writev(bufs)) {
             Ok(_) => (),
             Err(ioerr) => io_error::cond.raise(ioerr),
         }
@@ -84,11 +203,36 @@ pub struct TcpListener {
 
 impl TcpListener {
     pub fn bind(addr: SocketAddr) -> Option<TcpListener> {
+        TcpListener::bind_with(addr, [])
+    }
+
+    /// Binds to `addr` after applying `opts` to the new socket. Options such
+    /// as `ReuseAddr` only take effect when set this way.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if any option couldn't be set or the address
+    /// couldn't be bound.
+    pub fn bind_with(addr: SocketAddr,
+                     opts: &[SocketOption]) -> Option<TcpListener> {
         LocalIo::maybe_raise(|io| {
-            io.tcp_bind(addr).map(|l| TcpListener { obj: l })
+            io.tcp_bind(addr, opts).map(|l| TcpListener { obj: l })
         })
     }
 
+    /// Sets an option on the underlying socket.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the option couldn't be set, or with
+    /// `IoUnavailable` if it isn't supported on this platform.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        match self.obj.set_option(opt) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
     pub fn socket_name(&mut self) -> Option<SocketAddr> {
         match self.obj.socket_name() {
             Ok(sn) => Some(sn),
@@ -117,9 +261,42 @@ pub struct TcpAcceptor {
     priv obj: ~RtioTcpAcceptor
 }
 
+impl TcpAcceptor {
+    /// Sets an option on the underlying socket.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the option couldn't be set, or with
+    /// `IoUnavailable` if it isn't supported on this platform.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        match self.obj.set_option(opt) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    /// Puts this acceptor into or out of non-blocking mode, in which `accept`
+    /// raises `io_error` with a `ResourceUnavailable` error when no
+    /// connection is pending. Accepted streams are always blocking.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the runtime doesn't support non-blocking sockets.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        match self.obj.set_nonblocking(nonblocking) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn raw_fd(&self) -> libc::c_int { self.obj.fd() }
+}
+
 impl Acceptor<TcpStream> for TcpAcceptor {
     fn accept(&mut self) -> Option<TcpStream> {
-        match self.obj.accept() {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.accept()) {
             Ok(s) => Some(TcpStream::new(s)),
             Err(ioerr) => {
                 io_error::cond.raise(ioerr);
@@ -609,4 +786,114 @@ mod test {
         c.write([1]);
         p.recv();
     })
+
+    iotest!(fn vectored_io() {
+        let addr = next_test_ip4();
+        let (p, c) = Chan::new();
+        do spawn {
+            let mut srv = TcpListener::bind(addr).listen();
+            c.send(());
+            let mut cl = srv.accept().unwrap();
+            cl.write_vectored([&[1u8, 2], &[], &[3u8, 4, 5]]);
+        }
+
+        p.recv();
+        let mut c = TcpStream::connect(addr).unwrap();
+        let mut data = ~[];
+        loop {
+            let mut a = [0u8, ..2];
+            let mut b = [0u8, ..8];
+            match c.read_vectored([a.as_mut_slice(), b.as_mut_slice()]) {
+                Some(n) => {
+                    let n2 = if n > 2 { n - 2 } else { 0 };
+                    data.push_all(a.slice_to(n - n2));
+                    data.push_all(b.slice_to(n2));
+                }
+                None => break,
+            }
+        }
+        assert_eq!(data, ~[1, 2, 3, 4, 5]);
+    })
+
+    iotest!(fn socket_options() {
+        use io::net::{ReuseAddr, SendBufferSize, RecvBufferSize, Linger};
+
+        let addr = next_test_ip4();
+        let local = next_test_ip4();
+        let (p, c) = Chan::new();
+        do spawn {
+            let listener = TcpListener::bind_with(addr, [ReuseAddr(true)]);
+            let mut acceptor = listener.listen();
+            c.send(());
+            let mut stream = acceptor.accept().unwrap();
+            assert_eq!(stream.peer_name(), Some(local));
+            stream.set_option(RecvBufferSize(16 * 1024));
+            stream.set_option(Linger(None));
+            stream.write([1]);
+        }
+
+        p.recv();
+        let mut stream = TcpStream::connect_with(addr, Some(local),
+                                                 [ReuseAddr(true)]).unwrap();
+        assert_eq!(stream.socket_name(), Some(local));
+        stream.set_option(SendBufferSize(16 * 1024));
+        stream.set_option(Linger(Some(1)));
+        assert_eq!(stream.read_byte(), Some(1));
+    })
+
+    iotest!(fn copy_file_to_socket() {
+        use io::fs;
+        use io::util;
+        use os;
+        use rand;
+        use vec;
+
+        let path = os::tmpdir().join(format!("rust-{}", rand::random::<u32>()));
+        let contents = vec::from_fn(100000, |i| i as u8);
+        {
+            let mut f = File::create(&path).unwrap();
+            f.write(contents);
+        }
+
+        let addr = next_test_ip4();
+        let (p, c) = Chan::new();
+        let path2 = path.clone();
+        do spawn {
+            let mut srv = TcpListener::bind(addr).listen();
+            c.send(());
+            let mut cl = srv.accept().unwrap();
+            let mut f = File::open(&path2).unwrap();
+            f.seek(10, SeekSet);
+            match cl.send_file(&mut f) {
+                Some(..) => {}
+                None => util::copy(&mut f, &mut cl),
+            }
+            assert_eq!(f.tell(), 100000);
+        }
+
+        p.recv();
+        let mut c = TcpStream::connect(addr).unwrap();
+        assert!(c.read_to_end() == contents.slice_from(10).to_owned());
+        fs::unlink(&path);
+    })
+    iotest!(fn cancel_blocked_read() {
+        use task;
+
+        let addr = next_test_ip4();
+        let mut acceptor = TcpListener::bind(addr).listen();
+        let (connected, connect) = Chan::new();
+        let child = do task::task().spawn_cancellable {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            connect.send(());
+            let mut kind = None;
+            io_error::cond.trap(|e| kind = Some(e.kind)).inside(|| {
+                assert!(stream.read([0]).is_none());
+            });
+            assert_eq!(kind, Some(Cancelled));
+        };
+        let _stream = acceptor.accept();
+        connected.recv();
+        child.cancel();
+        assert!(child.join().is_ok());
+    } #[ignore(cfg(windows))])
 }
@@ -11,8 +11,10 @@
 use option::{Option, Some, None};
 use result::{Ok, Err};
 use io::net::ip::SocketAddr;
+use io::net::SocketOption;
 use io::{Reader, Writer};
-use io::{io_error, EndOfFile};
+use io::{io_error, EndOfFile, cancellable_socket_op};
+use libc;
 use rt::rtio::{RtioSocket, RtioUdpSocket, IoFactory, LocalIo};
 
 pub struct UdpSocket {
@@ -21,13 +23,39 @@ pub struct UdpSocket {
 
 impl UdpSocket {
     pub fn bind(addr: SocketAddr) -> Option<UdpSocket> {
+        UdpSocket::bind_with(addr, [])
+    }
+
+    /// Binds to `addr` after applying `opts` to the new socket. Options such
+    /// as `ReuseAddr` only take effect when set this way.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if any option couldn't be set or the address
+    /// couldn't be bound.
+    pub fn bind_with(addr: SocketAddr,
+                     opts: &[SocketOption]) -> Option<UdpSocket> {
         LocalIo::maybe_raise(|io| {
-            io.udp_bind(addr).map(|s| UdpSocket { obj: s })
+            io.udp_bind(addr, opts).map(|s| UdpSocket { obj: s })
         })
     }
 
+    /// Sets an option on the underlying socket.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the option couldn't be set, or with
+    /// `IoUnavailable` if it isn't supported on this platform.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        match self.obj.set_option(opt) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
     pub fn recvfrom(&mut self, buf: &mut [u8]) -> Option<(uint, SocketAddr)> {
-        match self.obj.recvfrom(buf) {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.recvfrom(buf)) {
             Ok((nread, src)) => Some((nread, src)),
             Err(ioerr) => {
                 // EOF is indicated by returning None
@@ -40,12 +68,30 @@ impl UdpSocket {
     }
 
     pub fn sendto(&mut self, buf: &[u8], dst: SocketAddr) {
-        match self.obj.sendto(buf, dst) {
+        let fd = self.obj.fd();
+        match cancellable_socket_op(fd, || self.obj.sendto(buf, dst)) {
             Ok(_) => (),
             Err(ioerr) => io_error::cond.raise(ioerr),
         }
     }
 
+    /// Puts this socket into or out of non-blocking mode, in which
+    /// `recvfrom` and `sendto` raise `io_error` with a `ResourceUnavailable`
+    /// error instead of blocking the calling task.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the runtime doesn't support non-blocking sockets.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        match self.obj.set_nonblocking(nonblocking) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn raw_fd(&self) -> libc::c_int { self.obj.fd() }
+
     pub fn connect(self, other: SocketAddr) -> UdpStream {
         UdpStream { socket: self, connectedTo: other }
     }
@@ -20,6 +20,18 @@ These pipes are similar to TCP in the sense that you can have both a stream to a
 server and a server itself. The server provided accepts other `UnixStream`
 instances as clients.
 
+On Linux under the native runtime, sockets can also live in the abstract
+namespace, see `UnixListener::bind_abstract` and `UnixStream::connect_abstract`.
+Such a socket is identified by a name rather than by a path, nothing is created
+on the filesystem, and the socket goes away when its last descriptor is closed.
+Elsewhere, and under the green runtime, these raise `io_error` with
+`IoUnavailable`.
+
+Unix domain sockets can also report the credentials of the peer process and
+pass open file descriptors between processes, see
+`UnixStream::peer_credentials` and `UnixStream::send_fds`. These are currently
+only supported by the native runtime.
+
 */
 
 use prelude::*;
@@ -27,8 +39,22 @@ use prelude::*;
 use c_str::ToCStr;
 use rt::rtio::{IoFactory, LocalIo, RtioUnixListener};
 use rt::rtio::{RtioUnixAcceptor, RtioPipe};
+use io::net::SocketOption;
 use io::pipe::PipeStream;
-use io::{io_error, Listener, Acceptor, Reader, Writer};
+use io::{io_error, Listener, Acceptor, Reader, Writer, raise_if_cancelled};
+use libc;
+
+/// The identity of the process on the other end of a Unix domain socket, as
+/// recorded by the kernel when the connection was established.
+#[deriving(Eq, Clone)]
+pub struct Credentials {
+    /// Process ID of the peer, on platforms which report it
+    pid: Option<libc::pid_t>,
+    /// Effective user ID of the peer
+    uid: u32,
+    /// Effective group ID of the peer
+    gid: u32,
+}
 
 /// A stream which communicates over a named pipe.
 pub struct UnixStream {
@@ -63,6 +89,71 @@ impl UnixStream {
             io.unix_connect(&path.to_c_str()).map(UnixStream::new)
         })
     }
+
+    /// Connect to the socket named `name` in the abstract namespace. See the
+    /// module documentation.
+    ///
+    /// # Failure
+    ///
+    /// This function will raise on the `io_error` condition if the connection
+    /// could not be made, or with `IoUnavailable` if the abstract namespace
+    /// isn't supported.
+    pub fn connect_abstract(name: &[u8]) -> Option<UnixStream> {
+        LocalIo::maybe_raise(|io| {
+            io.unix_connect_abstract(name).map(UnixStream::new)
+        })
+    }
+
+    /// Sets an option on the underlying socket, such as its buffer sizes.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the option couldn't be set.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        self.obj.set_option(opt)
+    }
+
+    /// Returns the credentials of the process on the other end of this
+    /// stream. The process ID is only reported on Linux.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the credentials couldn't be retrieved.
+    pub fn peer_credentials(&mut self) -> Option<Credentials> {
+        self.obj.peer_credentials()
+    }
+
+    /// Writes `buf` to the stream, passing duplicates of the file descriptors
+    /// `fds` to the peer along with it. The peer receives them with
+    /// `recv_fds`; the descriptors in `fds` remain owned by the caller.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if the write failed, or with `InvalidInput` if `fds`
+    /// is not empty but `buf` is.
+    pub fn send_fds(&mut self, buf: &[u8], fds: &[libc::c_int]) {
+        self.obj.send_fds(buf, fds)
+    }
+
+    /// Reads into `buf` like `read`, appending any file descriptors passed
+    /// by the peer along with that data to `fds`. The caller takes ownership
+    /// of the received descriptors and is responsible for closing them. The
+    /// received descriptors are close-on-exec.
+    ///
+    /// At most 253 descriptors can be received with a single read. If the
+    /// peer sent more, none of the descriptors of the message are received,
+    /// but its data is still read into `buf`.
+    ///
+    /// # Failure
+    ///
+    /// Returns `None` on end of file and raises `io_error` if the read
+    /// failed. If the descriptors were truncated, `io_error` is raised with
+    /// `OtherIoError` and, if the condition is handled, the number of bytes
+    /// read is returned so that the stream stays in sync.
+    pub fn recv_fds(&mut self, buf: &mut [u8],
+                    fds: &mut ~[libc::c_int]) -> Option<uint> {
+        self.obj.recv_fds(buf, fds)
+    }
 }
 
 impl Reader for UnixStream {
@@ -105,6 +196,20 @@ impl UnixListener {
             io.unix_bind(&path.to_c_str()).map(|s| UnixListener { obj: s })
         })
     }
+
+    /// Creates a new listener on the socket named `name` in the abstract
+    /// namespace. See the module documentation.
+    ///
+    /// # Failure
+    ///
+    /// This function will raise on the `io_error` condition if the name
+    /// could not be bound, or with `IoUnavailable` if the abstract namespace
+    /// isn't supported.
+    pub fn bind_abstract(name: &[u8]) -> Option<UnixListener> {
+        LocalIo::maybe_raise(|io| {
+            io.unix_bind_abstract(name).map(|s| UnixListener { obj: s })
+        })
+    }
 }
 
 impl Listener<UnixStream, UnixAcceptor> for UnixListener {
@@ -125,6 +230,7 @@ pub struct UnixAcceptor {
 
 impl Acceptor<UnixStream> for UnixAcceptor {
     fn accept(&mut self) -> Option<UnixStream> {
+        if raise_if_cancelled() { return None }
         match self.obj.accept() {
             Ok(s) => Some(UnixStream::new(s)),
             Err(ioerr) => {
@@ -141,6 +247,9 @@ mod tests {
     use super::*;
     use io::*;
     use io::test::*;
+    use io::pipe::PipeStream;
+    use libc;
+    use os;
 
     fn smalltest(server: proc(UnixStream), client: proc(UnixStream)) {
         let path1 = next_test_unix();
@@ -256,4 +365,92 @@ mod tests {
         let _acceptor = UnixListener::bind(&path).listen();
         assert!(path.exists());
     }
+
+    // Credential and descriptor passing are only implemented natively
+    #[cfg(unix)]
+    fn native_smalltest(server: proc(UnixStream), client: proc(UnixStream)) {
+        use native;
+        let (p, c) = Chan::new();
+        do native::task::spawn {
+            smalltest(server, client);
+            c.send(());
+        }
+        p.recv();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn peer_credentials() {
+        native_smalltest(proc(mut server) {
+            let creds = server.peer_credentials().unwrap();
+            assert_eq!(creds.uid, unsafe { libc::getuid() } as u32);
+            assert_eq!(creds.gid, unsafe { libc::getgid() } as u32);
+            if cfg!(target_os = "linux") {
+                assert_eq!(creds.pid, Some(unsafe { libc::getpid() }));
+            }
+        }, proc(mut client) {
+            client.write([1]);
+        })
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn pass_fds() {
+        native_smalltest(proc(mut server) {
+            let mut buf = [0];
+            let mut fds = ~[];
+            assert_eq!(server.recv_fds(buf, &mut fds), Some(1));
+            assert_eq!(buf[0], 7);
+            assert_eq!(fds.len(), 1);
+
+            let mut input = PipeStream::open(fds[0]);
+            let mut data = [0, ..5];
+            assert_eq!(input.read(data), Some(5));
+            assert_eq!(data.to_owned(), ~[1, 2, 3, 4, 5]);
+        }, proc(mut client) {
+            let os::Pipe { input, out } = os::pipe();
+            client.send_fds([7], [input]);
+            unsafe { libc::close(input); }
+            let mut out = PipeStream::open(out);
+            out.write([1, 2, 3, 4, 5]);
+        })
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn send_fds_without_data() {
+        native_smalltest(proc(_server) {}, proc(mut client) {
+            let mut called = false;
+            io_error::cond.trap(|e| {
+                assert_eq!(e.kind, InvalidInput);
+                called = true;
+            }).inside(|| {
+                client.send_fds([], [libc::STDIN_FILENO]);
+            });
+            assert!(called);
+        })
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_namespace() {
+        use native;
+        let (p, c) = Chan::new();
+        do native::task::spawn {
+            let name = format!("rust-test-{}", next_test_unix().filename_str().unwrap());
+            let mut acceptor = UnixListener::bind_abstract(name.as_bytes()).listen();
+            assert!(!Path::new(name.as_slice()).exists());
+
+            let name2 = name.clone();
+            do spawn {
+                let mut stream = UnixStream::connect_abstract(name2.as_bytes());
+                stream.write([42]);
+            }
+            let mut buf = [0];
+            acceptor.accept().read(buf);
+            assert_eq!(buf[0], 42);
+            c.send(());
+        }
+        p.recv();
+    }
 }
@@ -14,7 +14,9 @@
 //! enough so that pipes can be created to child processes.
 
 use prelude::*;
-use io::{io_error, EndOfFile};
+use io::{io_error, EndOfFile, IoError, OtherIoError, raise_if_cancelled};
+use io::net::SocketOption;
+use io::net::unix::Credentials;
 use libc;
 use rt::rtio::{RtioPipe, LocalIo};
 
@@ -51,10 +53,88 @@ impl PipeStream {
     pub fn new(inner: ~RtioPipe) -> PipeStream {
         PipeStream { obj: inner }
     }
+
+    /// Sets an option on the socket underlying this pipe.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if this pipe isn't a socket or the option couldn't
+    /// be set.
+    pub fn set_option(&mut self, opt: SocketOption) {
+        match self.obj.set_option(opt) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    /// Returns the credentials of the process on the other end of the Unix
+    /// domain socket underlying this pipe.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if this pipe isn't a Unix domain socket or the
+    /// credentials couldn't be retrieved.
+    pub fn peer_credentials(&mut self) -> Option<Credentials> {
+        match self.obj.peer_credentials() {
+            Ok(creds) => Some(creds),
+            Err(ioerr) => {
+                io_error::cond.raise(ioerr);
+                None
+            }
+        }
+    }
+
+    /// Writes `buf`, passing the file descriptors `fds` to the peer along
+    /// with it. See `UnixStream::send_fds`.
+    ///
+    /// # Failure
+    ///
+    /// Raises `io_error` if this pipe isn't a Unix domain socket or the write
+    /// failed.
+    pub fn send_fds(&mut self, buf: &[u8], fds: &[libc::c_int]) {
+        if raise_if_cancelled() { return }
+        match self.obj.send_fds(buf, fds) {
+            Ok(()) => {}
+            Err(ioerr) => io_error::cond.raise(ioerr),
+        }
+    }
+
+    /// Reads into `buf`, appending any file descriptors passed by the peer
+    /// along with the data to `fds`. See `UnixStream::recv_fds`.
+    ///
+    /// # Failure
+    ///
+    /// Returns `None` on end of file. Raises `io_error` if this pipe isn't a
+    /// Unix domain socket or the read failed. If the descriptors were
+    /// truncated, the data has still been read: `io_error` is raised with
+    /// `OtherIoError`, and the number of bytes read is returned if the
+    /// condition is handled.
+    pub fn recv_fds(&mut self, buf: &mut [u8],
+                    fds: &mut ~[libc::c_int]) -> Option<uint> {
+        if raise_if_cancelled() { return None }
+        match self.obj.recv_fds(buf, fds) {
+            Ok((read, false)) => Some(read),
+            Ok((read, true)) => {
+                io_error::cond.raise(IoError {
+                    kind: OtherIoError,
+                    desc: "file descriptors were truncated",
+                    detail: Some(format!("{} bytes were read", read)),
+                });
+                Some(read)
+            }
+            Err(ioerr) => {
+                if ioerr.kind != EndOfFile {
+                    io_error::cond.raise(ioerr);
+                }
+                None
+            }
+        }
+    }
 }
 
 impl Reader for PipeStream {
     fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        if raise_if_cancelled() { return None }
         match self.obj.read(buf) {
             Ok(read) => Some(read),
             Err(ioerr) => {
@@ -70,6 +150,7 @@ impl Reader for PipeStream {
 
 impl Writer for PipeStream {
     fn write(&mut self, buf: &[u8]) {
+        if raise_if_cancelled() { return }
         match self.obj.write(buf) {
             Ok(_) => (),
             Err(ioerr) => {
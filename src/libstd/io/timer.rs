@@ -41,6 +41,7 @@ loop {
 use comm::Port;
 use option::Option;
 use rt::rtio::{IoFactory, LocalIo, RtioTimer};
+use task;
 
 pub struct Timer {
     priv obj: ~RtioTimer
@@ -65,8 +66,17 @@ impl Timer {
     ///
     /// Note that this function will cause any other ports for this timer to be
     /// invalidated (the other end will be closed).
+    ///
+    /// If the current task has a cancellation token and it is cancelled while
+    /// sleeping, this function will fail with the `CancelReason`.
     pub fn sleep(&mut self, msecs: u64) {
-        self.obj.sleep(msecs);
+        // A cancellable task waits on a port instead so that the sleep can be
+        // interrupted by its cancellation token.
+        if task::cancel_token().is_some() {
+            self.oneshot(msecs).recv_opt();
+        } else {
+            self.obj.sleep(msecs);
+        }
     }
 
     /// Creates a oneshot port which will have a notification sent when `msecs`
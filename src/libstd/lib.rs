@@ -124,6 +124,7 @@ pub mod owned;
 pub mod managed;
 pub mod borrow;
 pub mod rc;
+pub mod cc;
 pub mod gc;
 
 
@@ -213,6 +214,7 @@ pub mod rt;
 // can be resolved within libstd.
 #[doc(hidden)]
 mod std {
+    pub use cc;
     pub use clone;
     pub use cmp;
     pub use comm;
@@ -265,9 +265,17 @@ pub mod types {
                 }
 
                 pub enum timezone {}
+
+                pub struct iovec {
+                    iov_base: *mut c_void,
+                    iov_len: size_t,
+                }
             }
             pub mod bsd44 {
-                use libc::types::os::arch::c95::{c_char, c_int, c_uint};
+                use libc::types::common::c95::c_void;
+                use libc::types::os::arch::c95::{c_char, c_int, c_uint, size_t};
+                use libc::types::os::arch::posix88::{pid_t, uid_t, gid_t};
+                use libc::types::os::common::posix01::iovec;
 
                 pub type socklen_t = u32;
                 pub type sa_family_t = u16;
@@ -309,6 +317,10 @@ pub mod types {
                     ipv6mr_multiaddr: in6_addr,
                     ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    l_onoff: c_int,
+                    l_linger: c_int,
+                }
                 pub struct addrinfo {
                     ai_flags: c_int,
                     ai_family: c_int,
@@ -319,6 +331,29 @@ pub mod types {
                     ai_canonname: *c_char,
                     ai_next: *addrinfo
                 }
+                pub struct sockaddr_un {
+                    sun_family: sa_family_t,
+                    sun_path: [c_char, ..108]
+                }
+                pub struct msghdr {
+                    msg_name: *mut c_void,
+                    msg_namelen: socklen_t,
+                    msg_iov: *mut iovec,
+                    msg_iovlen: size_t,
+                    msg_control: *mut c_void,
+                    msg_controllen: size_t,
+                    msg_flags: c_int,
+                }
+                pub struct cmsghdr {
+                    cmsg_len: size_t,
+                    cmsg_level: c_int,
+                    cmsg_type: c_int,
+                }
+                pub struct ucred {
+                    pid: pid_t,
+                    uid: uid_t,
+                    gid: gid_t,
+                }
             }
         }
 
@@ -632,9 +667,16 @@ pub mod types {
                 }
 
                 pub enum timezone {}
+
+                pub struct iovec {
+                    iov_base: *mut c_void,
+                    iov_len: size_t,
+                }
             }
             pub mod bsd44 {
+                use libc::types::common::c95::c_void;
                 use libc::types::os::arch::c95::{c_char, c_int, c_uint};
+                use libc::types::os::common::posix01::iovec;
 
                 pub type socklen_t = u32;
                 pub type sa_family_t = u8;
@@ -681,6 +723,10 @@ pub mod types {
                     ipv6mr_multiaddr: in6_addr,
                     ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    l_onoff: c_int,
+                    l_linger: c_int,
+                }
                 pub struct addrinfo {
                     ai_flags: c_int,
                     ai_family: c_int,
@@ -691,6 +737,25 @@ pub mod types {
                     ai_addr: *sockaddr,
                     ai_next: *addrinfo
                 }
+                pub struct sockaddr_un {
+                    sun_len: u8,
+                    sun_family: sa_family_t,
+                    sun_path: [c_char, ..104]
+                }
+                pub struct msghdr {
+                    msg_name: *mut c_void,
+                    msg_namelen: socklen_t,
+                    msg_iov: *mut iovec,
+                    msg_iovlen: c_int,
+                    msg_control: *mut c_void,
+                    msg_controllen: socklen_t,
+                    msg_flags: c_int,
+                }
+                pub struct cmsghdr {
+                    cmsg_len: socklen_t,
+                    cmsg_level: c_int,
+                    cmsg_type: c_int,
+                }
             }
         }
 
@@ -831,7 +896,8 @@ pub mod types {
             }
 
             pub mod bsd44 {
-                use libc::types::os::arch::c95::{c_char, c_int, c_uint, size_t};
+                use libc::types::os::arch::c95::{c_char, c_int, c_uint, c_ushort,
+                                                 size_t};
 
                 pub type SOCKET = c_uint;
                 pub type socklen_t = c_int;
@@ -874,6 +940,10 @@ pub mod types {
                     ipv6mr_multiaddr: in6_addr,
                     ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    l_onoff: c_ushort,
+                    l_linger: c_ushort,
+                }
                 pub struct addrinfo {
                     ai_flags: c_int,
                     ai_family: c_int,
@@ -1148,10 +1218,17 @@ pub mod types {
                 }
 
                 pub enum timezone {}
+
+                pub struct iovec {
+                    iov_base: *mut c_void,
+                    iov_len: size_t,
+                }
             }
 
             pub mod bsd44 {
+                use libc::types::common::c95::c_void;
                 use libc::types::os::arch::c95::{c_char, c_int, c_uint};
+                use libc::types::os::common::posix01::iovec;
 
                 pub type socklen_t = c_int;
                 pub type sa_family_t = u8;
@@ -1198,6 +1275,10 @@ pub mod types {
                     ipv6mr_multiaddr: in6_addr,
                     ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    l_onoff: c_int,
+                    l_linger: c_int,
+                }
                 pub struct addrinfo {
                     ai_flags: c_int,
                     ai_family: c_int,
@@ -1208,6 +1289,25 @@ pub mod types {
                     ai_addr: *sockaddr,
                     ai_next: *addrinfo
                 }
+                pub struct sockaddr_un {
+                    sun_len: u8,
+                    sun_family: sa_family_t,
+                    sun_path: [c_char, ..104]
+                }
+                pub struct msghdr {
+                    msg_name: *mut c_void,
+                    msg_namelen: socklen_t,
+                    msg_iov: *mut iovec,
+                    msg_iovlen: c_int,
+                    msg_control: *mut c_void,
+                    msg_controllen: socklen_t,
+                    msg_flags: c_int,
+                }
+                pub struct cmsghdr {
+                    cmsg_len: socklen_t,
+                    cmsg_level: c_int,
+                    cmsg_type: c_int,
+                }
             }
         }
 
@@ -1526,7 +1626,7 @@ pub mod consts {
         pub mod posix08 {
         }
         pub mod bsd44 {
-            use libc::types::os::arch::c95::c_int;
+            use libc::types::os::arch::c95::{c_int, c_long};
 
             pub static AF_INET: c_int = 2;
             pub static AF_INET6: c_int = 23;
@@ -1547,6 +1647,15 @@ pub mod consts {
             pub static SOL_SOCKET: c_int = 0xffff;
             pub static SO_KEEPALIVE: c_int = 8;
             pub static SO_BROADCAST: c_int = 32;
+            pub static SO_REUSEADDR: c_int = 4;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static IPV6_V6ONLY: c_int = 27;
+            pub static SD_BOTH: c_int = 2;
+
+            // 0x8004667e, _IOW('f', 126, u_long)
+            pub static FIONBIO: c_long = -0x7ffb9982;
         }
         pub mod extra {
             use libc::types::os::arch::c95::c_int;
@@ -1561,6 +1670,7 @@ pub mod consts {
 
             pub static ERROR_SUCCESS : c_int = 0;
             pub static ERROR_INVALID_HANDLE : c_int = 6;
+            pub static ERROR_LOCK_VIOLATION : c_int = 33;
             pub static ERROR_DISK_FULL : c_int = 112;
             pub static ERROR_INSUFFICIENT_BUFFER : c_int = 122;
             pub static ERROR_ALREADY_EXISTS : c_int = 183;
@@ -1664,6 +1774,9 @@ pub mod consts {
 
             pub static SYMBOLIC_LINK_FLAG_DIRECTORY: DWORD = 1;
 
+            pub static LOCKFILE_EXCLUSIVE_LOCK: DWORD = 0x2;
+            pub static LOCKFILE_FAIL_IMMEDIATELY: DWORD = 0x1;
+
             pub static FILE_SHARE_DELETE: DWORD = 0x4;
             pub static FILE_SHARE_READ: DWORD = 0x1;
             pub static FILE_SHARE_WRITE: DWORD = 0x2;
@@ -1764,6 +1877,9 @@ pub mod consts {
             pub static O_CREAT : c_int = 64;
             pub static O_EXCL : c_int = 128;
             pub static O_TRUNC : c_int = 512;
+            pub static O_NONBLOCK : c_int = 2048;
+            pub static F_GETFL : c_int = 3;
+            pub static F_SETFL : c_int = 4;
             pub static S_IFIFO : c_int = 4096;
             pub static S_IFCHR : c_int = 8192;
             pub static S_IFBLK : c_int = 24576;
@@ -1975,6 +2091,9 @@ pub mod consts {
             pub static O_CREAT : c_int = 256;
             pub static O_EXCL : c_int = 1024;
             pub static O_TRUNC : c_int = 512;
+            pub static O_NONBLOCK : c_int = 128;
+            pub static F_GETFL : c_int = 3;
+            pub static F_SETFL : c_int = 4;
             pub static S_IFIFO : c_int = 4096;
             pub static S_IFCHR : c_int = 8192;
             pub static S_IFBLK : c_int = 24576;
@@ -2266,6 +2385,25 @@ pub mod consts {
             pub static SOL_SOCKET: c_int = 1;
             pub static SO_KEEPALIVE: c_int = 9;
             pub static SO_BROADCAST: c_int = 6;
+            pub static SO_REUSEADDR: c_int = 2;
+            pub static SO_SNDBUF: c_int = 7;
+            pub static SO_RCVBUF: c_int = 8;
+            pub static SO_LINGER: c_int = 13;
+            pub static SO_REUSEPORT: c_int = 15;
+            pub static IPV6_V6ONLY: c_int = 26;
+            pub static SHUT_RDWR: c_int = 2;
+            pub static TCP_CORK: c_int = 3;
+
+            pub static AF_UNIX: c_int = 1;
+            pub static SO_PEERCRED: c_int = 17;
+            pub static SCM_RIGHTS: c_int = 1;
+            pub static MSG_CTRUNC: c_int = 0x8;
+            pub static MSG_CMSG_CLOEXEC: c_int = 0x40000000;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         #[cfg(target_arch = "x86")]
         #[cfg(target_arch = "x86_64")]
@@ -2446,6 +2584,9 @@ pub mod consts {
             pub static O_CREAT : c_int = 512;
             pub static O_EXCL : c_int = 2048;
             pub static O_TRUNC : c_int = 1024;
+            pub static O_NONBLOCK : c_int = 4;
+            pub static F_GETFL : c_int = 3;
+            pub static F_SETFL : c_int = 4;
             pub static S_IFIFO : c_int = 4096;
             pub static S_IFCHR : c_int = 8192;
             pub static S_IFBLK : c_int = 24576;
@@ -2707,6 +2848,23 @@ pub mod consts {
             pub static SOL_SOCKET: c_int = 0xffff;
             pub static SO_KEEPALIVE: c_int = 0x0008;
             pub static SO_BROADCAST: c_int = 0x0020;
+            pub static SO_REUSEADDR: c_int = 0x0004;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static SO_REUSEPORT: c_int = 0x0200;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static IPV6_V6ONLY: c_int = 27;
+            pub static SHUT_RDWR: c_int = 2;
+            pub static TCP_NOPUSH: c_int = 4;
+
+            pub static AF_UNIX: c_int = 1;
+            pub static SCM_RIGHTS: c_int = 1;
+            pub static MSG_CTRUNC: c_int = 0x20;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         pub mod extra {
             use libc::types::os::arch::c95::c_int;
@@ -2816,6 +2974,9 @@ pub mod consts {
             pub static O_CREAT : c_int = 512;
             pub static O_EXCL : c_int = 2048;
             pub static O_TRUNC : c_int = 1024;
+            pub static O_NONBLOCK : c_int = 4;
+            pub static F_GETFL : c_int = 3;
+            pub static F_SETFL : c_int = 4;
             pub static S_IFIFO : c_int = 4096;
             pub static S_IFCHR : c_int = 8192;
             pub static S_IFBLK : c_int = 24576;
@@ -3082,6 +3243,23 @@ pub mod consts {
             pub static SOL_SOCKET: c_int = 0xffff;
             pub static SO_KEEPALIVE: c_int = 0x0008;
             pub static SO_BROADCAST: c_int = 0x0020;
+            pub static SO_REUSEADDR: c_int = 0x0004;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static SO_REUSEPORT: c_int = 0x0200;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static IPV6_V6ONLY: c_int = 27;
+            pub static SHUT_RDWR: c_int = 2;
+            pub static TCP_NOPUSH: c_int = 4;
+
+            pub static AF_UNIX: c_int = 1;
+            pub static SCM_RIGHTS: c_int = 1;
+            pub static MSG_CTRUNC: c_int = 0x20;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         pub mod extra {
             use libc::types::os::arch::c95::c_int;
@@ -3500,7 +3678,7 @@ pub mod funcs {
                 pub fn open(path: *c_char, oflag: c_int, mode: c_int)
                             -> c_int;
                 pub fn creat(path: *c_char, mode: mode_t) -> c_int;
-                pub fn fcntl(fd: c_int, cmd: c_int) -> c_int;
+                pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
             }
         }
 
@@ -3672,6 +3850,7 @@ pub mod funcs {
         pub mod unistd {
             use libc::types::os::arch::c95::{c_char, c_int, size_t};
             use libc::types::os::arch::posix88::{ssize_t, off_t};
+            use libc::types::os::common::posix01::iovec;
 
             extern {
                 pub fn readlink(path: *c_char,
@@ -3693,6 +3872,9 @@ pub mod funcs {
                 pub fn symlink(path1: *c_char, path2: *c_char) -> c_int;
 
                 pub fn ftruncate(fd: c_int, length: off_t) -> c_int;
+
+                pub fn readv(fd: c_int, iov: *iovec, iovcnt: c_int) -> ssize_t;
+                pub fn writev(fd: c_int, iov: *iovec, iovcnt: c_int) -> ssize_t;
             }
         }
 
@@ -3765,7 +3947,7 @@ pub mod funcs {
     #[cfg(not(windows))]
     pub mod bsd43 {
         use libc::types::common::c95::{c_void};
-        use libc::types::os::common::bsd44::{socklen_t, sockaddr};
+        use libc::types::os::common::bsd44::{socklen_t, sockaddr, msghdr};
         use libc::types::os::arch::c95::{c_int, size_t};
         use libc::types::os::arch::posix88::ssize_t;
 
@@ -3784,6 +3966,12 @@ pub mod funcs {
                                address_len: *mut socklen_t) -> c_int;
             pub fn setsockopt(socket: c_int, level: c_int, name: c_int,
                               value: *c_void, option_len: socklen_t) -> c_int;
+            pub fn getsockopt(socket: c_int, level: c_int, name: c_int,
+                              value: *mut c_void,
+                              option_len: *mut socklen_t) -> c_int;
+            pub fn socketpair(domain: c_int, ty: c_int, protocol: c_int,
+                              sv: *mut c_int) -> c_int;
+            pub fn shutdown(socket: c_int, how: c_int) -> c_int;
             pub fn recv(socket: c_int, buf: *mut c_void, len: size_t,
                         flags: c_int) -> ssize_t;
             pub fn send(socket: c_int, buf: *mut c_void, len: size_t,
@@ -3794,6 +3982,10 @@ pub mod funcs {
             pub fn sendto(socket: c_int, buf: *c_void, len: size_t,
                           flags: c_int, addr: *sockaddr,
                           addrlen: socklen_t) -> ssize_t;
+            pub fn sendmsg(socket: c_int, msg: *msghdr,
+                           flags: c_int) -> ssize_t;
+            pub fn recvmsg(socket: c_int, msg: *mut msghdr,
+                           flags: c_int) -> ssize_t;
         }
     }
 
@@ -3801,7 +3993,7 @@ pub mod funcs {
     pub mod bsd43 {
         use libc::types::common::c95::{c_void};
         use libc::types::os::common::bsd44::{socklen_t, sockaddr, SOCKET};
-        use libc::types::os::arch::c95::c_int;
+        use libc::types::os::arch::c95::{c_int, c_long, c_ulong};
         use libc::types::os::arch::posix88::ssize_t;
 
         extern "system" {
@@ -3820,6 +4012,9 @@ pub mod funcs {
             pub fn setsockopt(socket: SOCKET, level: c_int, name: c_int,
                               value: *c_void, option_len: socklen_t) -> c_int;
             pub fn closesocket(socket: SOCKET) -> c_int;
+            pub fn shutdown(socket: SOCKET, how: c_int) -> c_int;
+            pub fn ioctlsocket(socket: SOCKET, cmd: c_long,
+                               argp: *mut c_ulong) -> c_int;
             pub fn recv(socket: SOCKET, buf: *mut c_void, len: c_int,
                         flags: c_int) -> c_int;
             pub fn send(socket: SOCKET, buf: *mut c_void, len: c_int,
@@ -3838,6 +4033,7 @@ pub mod funcs {
     pub mod bsd44 {
         use libc::types::common::c95::{c_void};
         use libc::types::os::arch::c95::{c_char, c_uchar, c_int, c_uint, size_t};
+        use libc::types::os::arch::posix88::{uid_t, gid_t};
 
         extern {
             pub fn sysctl(name: *c_int,
@@ -3862,6 +4058,9 @@ pub mod funcs {
                            -> c_int;
             pub fn mincore(addr: *c_void, len: size_t, vec: *c_uchar)
                            -> c_int;
+            pub fn flock(fd: c_int, operation: c_int) -> c_int;
+            pub fn getpeereid(socket: c_int, euid: *mut uid_t,
+                              egid: *mut gid_t) -> c_int;
         }
     }
 
@@ -3878,6 +4077,7 @@ pub mod funcs {
                            -> c_int;
             pub fn mincore(addr: *c_void, len: size_t, vec: *c_uchar)
                            -> c_int;
+            pub fn flock(fd: c_int, operation: c_int) -> c_int;
         }
     }
 
@@ -3904,6 +4104,13 @@ pub mod funcs {
     #[cfg(target_os = "linux")]
     #[cfg(target_os = "android")]
     pub mod extra {
+        use libc::types::os::arch::c95::{c_int, size_t};
+        use libc::types::os::arch::posix88::{off_t, ssize_t};
+
+        extern {
+            pub fn sendfile(out_fd: c_int, in_fd: c_int, offset: *mut off_t,
+                            count: size_t) -> ssize_t;
+        }
     }
 
 
@@ -4028,6 +4235,8 @@ pub mod funcs {
                                      dwNumberOfBytesToMap: SIZE_T)
                                      -> LPVOID;
                 pub fn UnmapViewOfFile(lpBaseAddress: LPCVOID) -> BOOL;
+                pub fn FlushViewOfFile(lpBaseAddress: LPCVOID,
+                                       dwNumberOfBytesToFlush: SIZE_T) -> BOOL;
                 pub fn MoveFileExW(lpExistingFileName: LPCWSTR,
                                    lpNewFileName: LPCWSTR,
                                    dwFlags: DWORD) -> BOOL;
@@ -4065,6 +4274,17 @@ pub mod funcs {
                                         lpNewFilePointer: PLARGE_INTEGER,
                                         dwMoveMethod: DWORD) -> BOOL;
                 pub fn SetEndOfFile(hFile: HANDLE) -> BOOL;
+                pub fn LockFileEx(hFile: HANDLE,
+                                  dwFlags: DWORD,
+                                  dwReserved: DWORD,
+                                  nNumberOfBytesToLockLow: DWORD,
+                                  nNumberOfBytesToLockHigh: DWORD,
+                                  lpOverlapped: LPOVERLAPPED) -> BOOL;
+                pub fn UnlockFileEx(hFile: HANDLE,
+                                    dwReserved: DWORD,
+                                    nNumberOfBytesToUnlockLow: DWORD,
+                                    nNumberOfBytesToUnlockHigh: DWORD,
+                                    lpOverlapped: LPOVERLAPPED) -> BOOL;
 
                 pub fn GetSystemTimeAsFileTime(
                             lpSystemTimeAsFileTime: LPFILETIME);
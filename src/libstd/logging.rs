@@ -35,6 +35,18 @@ following macro:
 
 * `log_enabled!(level)` - returns true if logging of the given level is enabled
 
+## Structured fields
+
+Each of the logging macros optionally takes a set of key/value pairs before the
+format string. The keys must be string literals, and the values can be anything
+which implements `fmt::Default`. They are handed to the logger along with the
+message:
+
+```rust
+let user = "alice";
+info!({"user" => user, "attempt" => 3}, "login failed");
+```
+
 ## Enabling logging
 
 Log levels are controlled on a per-module basis, and by default all logging is
@@ -72,15 +84,40 @@ hello=debug          // turns on debug logging for 'hello'
 hello=3              // turns on info logging for 'hello'
 hello,std::hashmap   // turns on hello, and std's hashmap logging
 error,hello=warn     // turn on global error logging and also warn for hello
+hello/timeout        // turns on hello, but only messages containing "timeout"
+hello//^conn.*lost$/ // turns on hello, but only messages matching the regex
 ```
 
+Any value may end with `/filter`, which only lets through the messages that
+contain the text `filter`. This is a plain, case-sensitive substring match.
+If the filter is itself enclosed in slashes, as in `/^conn.*lost$/`, it is a
+regular expression which must match somewhere in the message instead. The
+supported syntax is deliberately small:
+
+* `.` matches any character and `[...]` any character of a class, which may
+  contain ranges (`[a-z0-9_]`) and be negated (`[^ ]`)
+* `*`, `+` and `?` repeat the preceding item zero or more times, one or more
+  times, or at most once
+* `^` and `$` anchor the expression at the start and end of the message
+* `\` makes the character after it match literally
+
+Grouping and alternation are not supported. A malformed expression is
+reported with a warning and no filter is applied.
+
 ## Performance and Side Effects
 
 Each of these macros will expand to code similar to:
 
 ```rust,ignore
 if log_level <= my_module_log_level() {
-    ::std::logging::log(log_level, format!(...));
+    ::std::logging::log(&LogRecord {
+        level: log_level,
+        module_path: module_path!(),
+        file: file!(),
+        line: line!(),
+        args: format_args!(...),
+        fields: &[...],
+    });
 }
 ```
 
@@ -88,6 +125,36 @@ What this means is that each of these macros are very cheap at runtime if
 they're turned off (just a load and an integer comparison). This also means that
 if logging is disabled, none of the components of the log will be executed.
 
+## Loggers
+
+Messages are handed to the logger of the current task if one was installed
+with `set_logger`, and otherwise to the process-wide logger installed with
+`set_global_logger`. If neither is set, messages are printed to stderr on
+their own, one per line.
+
+Loggers written against `log` only receive the level and the message. Those
+which also want the metadata and fields of a message implement `log_record`.
+
+A few loggers are provided by this module:
+
+* `StderrLogger` - prints one line per message to stderr, optionally prefixed
+                   with a timestamp
+* `FileLogger` - appends to a file, rotating it once it grows too large
+* `JsonLogger` - writes one JSON object per message to any writer
+
+```rust,ignore
+use std::logging::{set_global_logger, FileLogger, Logger};
+
+set_global_logger(~FileLogger::new(&Path::new("app.log"), 1 << 20, 5)
+                  as ~Logger:Send);
+```
+
+## Changing the configuration at runtime
+
+The levels set by `RUST_LOG` can be replaced while the program runs with
+`std::rt::logging::set_log_spec`, which takes a specification of the same
+form, including an optional `/filter`.
+
 ## Useful Values
 
 For convenience, if a value of `::help` is set for `RUST_LOG`, a program will
@@ -95,16 +162,30 @@ start, print out all modules registered for logging, and then exit.
 
 */
 
+use cast;
+use container::Container;
 use fmt;
 use io::LineBufferedWriter;
 use io;
 use io::Writer;
+use io::fs;
+use io::fs::File;
+use iter::{Iterator, DoubleEndedIterator, range};
+use local_data;
 use ops::Drop;
 use option::{Some, None, Option};
+use path::Path;
 use prelude::drop;
+use rt;
 use rt::local::Local;
 use rt::task::Task;
+use str::StrSlice;
+use sync::atomics::{AtomicBool, INIT_ATOMIC_BOOL, SeqCst};
+use sync::mutex::{Mutex, MutexGuard, Condvar};
+use sync::once::{Once, ONCE_INIT};
+use task;
 use util;
+use vec::{CopyableVector, ImmutableVector, OwnedCopyableVector};
 
 /// Debug log level
 pub static DEBUG: u32 = 4;
@@ -115,13 +196,48 @@ pub static WARN: u32 = 2;
 /// Error log level
 pub static ERROR: u32 = 1;
 
-/// A trait used to represent an interface to a task-local logger. Each task
-/// can have its own custom logger which can respond to logging messages
-/// however it likes.
+/// A single message emitted by one of the logging macros.
+pub struct LogRecord<'a> {
+    /// The level of the message, `ERROR` through `DEBUG` or a custom level.
+    level: u32,
+    /// The path of the module in which the message was logged.
+    module_path: &'static str,
+    /// The file in which the message was logged.
+    file: &'static str,
+    /// The line on which the message was logged.
+    line: uint,
+    /// The message itself.
+    args: &'a fmt::Arguments<'a>,
+    /// The key/value pairs given to the macro, with the values formatted.
+    fields: &'a [(&'static str, ~str)],
+}
+
+/// A trait used to represent an interface to a logger. Each task can have its
+/// own custom logger which can respond to logging messages however it likes,
+/// and a single logger can be shared by the whole process.
 pub trait Logger {
     /// Logs a single message described by the `args` structure. The level is
     /// provided in case you want to do things like color the message, etc.
     fn log(&mut self, level: u32, args: &fmt::Arguments);
+
+    /// Logs a single message described by the `record`. By default the
+    /// metadata and fields of the message are ignored and it is passed on to
+    /// `log`.
+    fn log_record(&mut self, record: &LogRecord) {
+        self.log(record.level, record.args);
+    }
+}
+
+// Passes a message which was logged without any metadata to `f` as a record
+fn bare_record(level: u32, args: &fmt::Arguments, f: |&LogRecord|) {
+    f(&LogRecord {
+        level: level,
+        module_path: "",
+        file: "",
+        line: 0,
+        args: args,
+        fields: &[],
+    })
 }
 
 struct DefaultLogger {
@@ -141,6 +257,165 @@ impl Drop for DefaultLogger {
     }
 }
 
+/// A logger which prints one line per message to stderr, made of the level,
+/// the module path, the message and its fields.
+pub struct StderrLogger {
+    priv handle: LineBufferedWriter<io::stdio::StdWriter>,
+    priv timestamps: bool,
+}
+
+impl StderrLogger {
+    /// Creates a logger printing to the stderr of the process.
+    pub fn new() -> StderrLogger {
+        StderrLogger {
+            handle: LineBufferedWriter::new(io::stderr()),
+            timestamps: false,
+        }
+    }
+
+    /// Creates a logger printing to stderr which prefixes every line with the
+    /// current time in UTC.
+    pub fn timestamped() -> StderrLogger {
+        StderrLogger {
+            handle: LineBufferedWriter::new(io::stderr()),
+            timestamps: true,
+        }
+    }
+}
+
+impl Logger for StderrLogger {
+    fn log(&mut self, level: u32, args: &fmt::Arguments) {
+        bare_record(level, args, |record| self.log_record(record));
+    }
+
+    fn log_record(&mut self, record: &LogRecord) {
+        write_line(&mut self.handle, record, self.timestamps);
+    }
+}
+
+impl Drop for StderrLogger {
+    fn drop(&mut self) {
+        self.handle.flush();
+    }
+}
+
+/// A logger which appends timestamped lines to a file.
+///
+/// Once the file reaches `max_size` bytes it is rotated: `path` is renamed to
+/// `path.1`, `path.1` to `path.2` and so on, keeping at most `max_files` old
+/// files. I/O errors are ignored so that logging never fails a task.
+pub struct FileLogger {
+    priv path: Path,
+    priv file: Option<File>,
+    priv size: u64,
+    priv max_size: u64,
+    priv max_files: uint,
+}
+
+impl FileLogger {
+    /// Creates a logger appending to the file at `path`, which is created if
+    /// it doesn't exist.
+    pub fn new(path: &Path, max_size: u64, max_files: uint) -> FileLogger {
+        let mut logger = FileLogger {
+            path: path.clone(),
+            file: None,
+            size: 0,
+            max_size: max_size,
+            max_files: max_files,
+        };
+        io::io_error::cond.trap(|_| {}).inside(|| logger.open());
+        logger
+    }
+
+    fn open(&mut self) {
+        self.file = File::open_mode(&self.path, io::Append, io::Write);
+        self.size = if self.file.is_some() { fs::stat(&self.path).size } else { 0 };
+    }
+
+    // The path of the `n`th rotated file
+    fn rotated(&self, n: uint) -> Path {
+        let mut name = self.path.as_vec().to_owned();
+        name.push_all(format!(".{}", n).as_bytes());
+        Path::new(name)
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        if self.max_files == 0 {
+            fs::unlink(&self.path);
+        } else {
+            let oldest = self.rotated(self.max_files);
+            if oldest.exists() {
+                fs::unlink(&oldest);
+            }
+            for n in range(1, self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(&from, &self.rotated(n + 1));
+                }
+            }
+            fs::rename(&self.path, &self.rotated(1));
+        }
+        self.open();
+    }
+}
+
+impl Logger for FileLogger {
+    fn log(&mut self, level: u32, args: &fmt::Arguments) {
+        bare_record(level, args, |record| self.log_record(record));
+    }
+
+    fn log_record(&mut self, record: &LogRecord) {
+        io::io_error::cond.trap(|_| {}).inside(|| {
+            if self.file.is_some() && self.size >= self.max_size {
+                self.rotate();
+            }
+            match self.file {
+                Some(ref mut file) => {
+                    let mut line = io::MemWriter::new();
+                    write_line(&mut line, record, true);
+                    let line = line.unwrap();
+                    file.write(line);
+                    self.size += line.len() as u64;
+                }
+                None => {}
+            }
+        })
+    }
+}
+
+/// A logger which writes every message as a JSON object on its own line, with
+/// the keys `time`, `level`, `module`, `file`, `line`, `message` and `fields`.
+pub struct JsonLogger {
+    priv out: ~Writer:Send,
+}
+
+impl JsonLogger {
+    /// Creates a logger writing to `out`.
+    pub fn new(out: ~Writer:Send) -> JsonLogger {
+        JsonLogger { out: out }
+    }
+}
+
+impl Logger for JsonLogger {
+    fn log(&mut self, level: u32, args: &fmt::Arguments) {
+        bare_record(level, args, |record| self.log_record(record));
+    }
+
+    fn log_record(&mut self, record: &LogRecord) {
+        let mut line = io::MemWriter::new();
+        write_json(&mut line, record, now());
+        self.out.write(line.unwrap());
+        self.out.flush();
+    }
+}
+
+local_data_key!(in_global_logger: ())
+
+// Whether a global logger is installed, which spares tasks from taking the
+// global lock when there isn't one
+static mut HAS_GLOBAL_LOGGER: AtomicBool = INIT_ATOMIC_BOOL;
+
 /// This function is called directly by the compiler when using the logging
 /// macros. This function does not take into account whether the log level
 /// specified is active or not, it will always log something if this method is
@@ -148,7 +423,11 @@ impl Drop for DefaultLogger {
 ///
 /// It is not recommended to call this function directly, rather it should be
 /// invoked through the logging family of macros.
-pub fn log(level: u32, args: &fmt::Arguments) {
+pub fn log(record: &LogRecord) {
+    if !rt::logging::filter_matches(record.args) {
+        return
+    }
+
     // See io::stdio::with_task_stdout for why there's a few dances here. The
     // gist of it is that arbitrary code can run during logging (and set an
     // arbitrary logging handle into the task) so we need to be careful that the
@@ -158,12 +437,27 @@ pub fn log(level: u32, args: &fmt::Arguments) {
         task.get().logger.take()
     };
 
+    // Messages logged by the global logger itself go to the task's logger, as
+    // the global one is busy.
+    if logger.is_none() && unsafe { HAS_GLOBAL_LOGGER.load(SeqCst) } &&
+       local_data::get(in_global_logger, |b| b.is_none()) {
+        match GlobalCheckout::new() {
+            Some(mut global) => {
+                local_data::set(in_global_logger, ());
+                global.logger.get_mut_ref().log_record(record);
+                local_data::pop(in_global_logger);
+                return
+            }
+            None => {}
+        }
+    }
+
     if logger.is_none() {
         logger = Some(~DefaultLogger {
             handle: LineBufferedWriter::new(io::stderr()),
         } as ~Logger);
     }
-    logger.get_mut_ref().log(level, args);
+    logger.get_mut_ref().log_record(record);
 
     let mut task = Local::borrow(None::<Task>);
     let prev = util::replace(&mut task.get().logger, logger);
@@ -177,3 +471,321 @@ pub fn set_logger(logger: ~Logger) -> Option<~Logger> {
     let mut task = Local::borrow(None::<Task>);
     util::replace(&mut task.get().logger, Some(logger))
 }
+
+/// Replaces the process-wide logger, returning the old one. The global logger
+/// receives the messages of all tasks which don't have a logger of their own.
+///
+/// If the global logger fails while logging a message, the task which logged
+/// the message fails and the global logger is removed, so that messages go to
+/// the loggers of the tasks which log them (stderr by default) until a new
+/// one is set.
+pub fn set_global_logger(logger: ~Logger:Send) -> Option<~Logger:Send> {
+    let state = global_state();
+    let mut guard = state.lock_idle();
+    unsafe { HAS_GLOBAL_LOGGER.store(true, SeqCst); }
+    util::replace(&mut guard.get_mut().logger, Some(logger))
+}
+
+/// Removes the process-wide logger, returning it if one was set.
+pub fn take_global_logger() -> Option<~Logger:Send> {
+    let state = global_state();
+    let mut guard = state.lock_idle();
+    unsafe { HAS_GLOBAL_LOGGER.store(false, SeqCst); }
+    guard.get_mut().logger.take()
+}
+
+// The global logger isn't run with the lock held, so that a logger which
+// fails doesn't poison the lock. Instead the logger is taken out while it
+// runs, and `busy` keeps other tasks from using or replacing it meanwhile.
+struct GlobalLogger {
+    logger: Option<~Logger:Send>,
+    busy: bool,
+}
+
+struct GlobalState {
+    lock: Mutex<GlobalLogger>,
+    idle: Condvar,
+}
+
+impl GlobalState {
+    fn lock_idle<'a>(&'a self) -> MutexGuard<'a, GlobalLogger> {
+        let mut guard = self.lock.lock();
+        while guard.get().busy {
+            self.idle.wait(&mut guard);
+        }
+        guard
+    }
+}
+
+// The global logger, while the current task runs it
+struct GlobalCheckout {
+    logger: Option<~Logger:Send>,
+}
+
+impl GlobalCheckout {
+    fn new() -> Option<GlobalCheckout> {
+        let state = global_state();
+        let mut guard = state.lock_idle();
+        let logger = guard.get_mut().logger.take();
+        if logger.is_none() { return None }
+        guard.get_mut().busy = true;
+        Some(GlobalCheckout { logger: logger })
+    }
+}
+
+impl Drop for GlobalCheckout {
+    fn drop(&mut self) {
+        let state = global_state();
+        // A logger which failed is dropped rather than put back, after the
+        // lock has been released.
+        let failed = {
+            let mut guard = state.lock.lock();
+            guard.get_mut().busy = false;
+            if task::failing() {
+                unsafe { HAS_GLOBAL_LOGGER.store(false, SeqCst); }
+                self.logger.take()
+            } else {
+                guard.get_mut().logger = self.logger.take();
+                None
+            }
+        };
+        state.idle.broadcast();
+        drop(failed);
+    }
+}
+
+fn global_state() -> &'static GlobalState {
+    static mut INIT: Once = ONCE_INIT;
+    static mut GLOBAL: *GlobalState = 0 as *GlobalState;
+
+    unsafe {
+        INIT.doit(|| {
+            let global = ~GlobalState {
+                lock: Mutex::new(GlobalLogger { logger: None, busy: false }),
+                idle: Condvar::new(),
+            };
+            GLOBAL = cast::transmute(global);
+        });
+        &*GLOBAL
+    }
+}
+
+fn level_name(level: u32) -> Option<&'static str> {
+    match level {
+        ERROR => Some("ERROR"),
+        WARN => Some("WARN"),
+        INFO => Some("INFO"),
+        DEBUG => Some("DEBUG"),
+        _ => None,
+    }
+}
+
+// Formats a record as `[time ]LEVEL:module: message key=value...`
+fn write_line(w: &mut Writer, record: &LogRecord, timestamp: bool) {
+    if timestamp {
+        write_timestamp(w, now());
+        w.write(bytes!(" "));
+    }
+    match level_name(record.level) {
+        Some(name) => write!(w, "{}:{}: ", name, record.module_path),
+        None => write!(w, "{}:{}: ", record.level, record.module_path),
+    }
+    fmt::write(w, record.args);
+    for &(key, ref value) in record.fields.iter() {
+        write!(w, " {}={}", key, *value);
+    }
+    w.write(bytes!("\n"));
+}
+
+fn write_json(w: &mut Writer, record: &LogRecord, time: (i64, u32)) {
+    w.write(bytes!("{\"time\":\""));
+    write_timestamp(w, time);
+    w.write(bytes!("\",\"level\":"));
+    match level_name(record.level) {
+        Some(name) => write_json_str(w, name),
+        None => write!(w, "{}", record.level),
+    }
+    w.write(bytes!(",\"module\":"));
+    write_json_str(w, record.module_path);
+    w.write(bytes!(",\"file\":"));
+    write_json_str(w, record.file);
+    write!(w, ",\"line\":{},\"message\":", record.line);
+    write_json_str(w, fmt::format(record.args));
+    w.write(bytes!(",\"fields\":{"));
+    for (i, &(key, ref value)) in record.fields.iter().enumerate() {
+        if i > 0 {
+            w.write(bytes!(","));
+        }
+        write_json_str(w, key);
+        w.write(bytes!(":"));
+        write_json_str(w, *value);
+    }
+    w.write(bytes!("}}\n"));
+}
+
+fn write_json_str(w: &mut Writer, s: &str) {
+    w.write(bytes!("\""));
+    for c in s.chars() {
+        match c {
+            '"' => w.write(bytes!("\\\"")),
+            '\\' => w.write(bytes!("\\\\")),
+            '\n' => w.write(bytes!("\\n")),
+            '\r' => w.write(bytes!("\\r")),
+            '\t' => w.write(bytes!("\\t")),
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32),
+            c => write!(w, "{}", c),
+        }
+    }
+    w.write(bytes!("\""));
+}
+
+// Formats seconds and nanoseconds since the epoch as an RFC 3339 UTC time
+// with millisecond precision
+fn write_timestamp(w: &mut Writer, (sec, nsec): (i64, u32)) {
+    let days = if sec >= 0 { sec / 86400 } else { (sec - 86399) / 86400 };
+    let secs = (sec - days * 86400) as uint;
+    let (year, month, day) = civil_from_days(days);
+    write!(w, "{:04d}-{:02u}-{:02u}T{:02u}:{:02u}:{:02u}.{:03u}Z",
+           year, month, day, secs / 3600, secs / 60 % 60, secs % 60,
+           nsec / 1000000);
+}
+
+// Converts a number of days since 1970-01-01 to a (year, month, day) date in
+// the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, uint, uint) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as uint, day as uint)
+}
+
+// The current time as seconds and nanoseconds since the epoch. This is also
+// the clock which task deadlines are measured with.
+#[cfg(unix)]
+#[doc(hidden)]
+pub fn now() -> (i64, u32) {
+    use libc::{c_int, c_void, timeval};
+    use ptr;
+
+    extern {
+        fn gettimeofday(tp: *mut timeval, tzp: *mut c_void) -> c_int;
+    }
+
+    let mut tv = timeval { tv_sec: 0, tv_usec: 0 };
+    unsafe { gettimeofday(&mut tv, ptr::mut_null()); }
+    (tv.tv_sec as i64, tv.tv_usec as u32 * 1000)
+}
+
+#[cfg(windows)]
+#[doc(hidden)]
+pub fn now() -> (i64, u32) {
+    use libc;
+
+    // The number of 100ns intervals between 1601-01-01 and 1970-01-01
+    static EPOCH_OFFSET: u64 = 116444736000000000;
+
+    let mut time = libc::FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+    unsafe { libc::GetSystemTimeAsFileTime(&mut time); }
+    let ticks = ((time.dwHighDateTime as u64 << 32) |
+                 time.dwLowDateTime as u64) - EPOCH_OFFSET;
+    ((ticks / 10000000) as i64, (ticks % 10000000) as u32 * 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::{LogRecord, FileLogger, Logger, write_line, write_json,
+                write_timestamp, civil_from_days, INFO};
+    use io;
+    use io::MemWriter;
+    use io::fs;
+    use io::fs::File;
+    use os;
+    use rand;
+    use str;
+
+    fn record_str(f: |&LogRecord| -> ~[u8]) -> ~str {
+        let fields: &[(&'static str, ~str)] = &[("user", ~"alice"), ("id", ~"7")];
+        format_args!(|args| {
+            let record = LogRecord {
+                level: INFO,
+                module_path: "foo::bar",
+                file: "foo.rs",
+                line: 3,
+                args: args,
+                fields: fields,
+            };
+            str::from_utf8_owned(f(&record)).unwrap()
+        }, "hello {}", "world")
+    }
+
+    #[test]
+    fn line_format() {
+        let s = record_str(|r| {
+            let mut w = MemWriter::new();
+            write_line(&mut w, r, false);
+            w.unwrap()
+        });
+        assert_eq!(s, ~"INFO:foo::bar: hello world user=alice id=7\n");
+    }
+
+    #[test]
+    fn json_format() {
+        let s = record_str(|r| {
+            let mut w = MemWriter::new();
+            write_json(&mut w, r, (0, 0));
+            w.unwrap()
+        });
+        assert_eq!(s, ~"{\"time\":\"1970-01-01T00:00:00.000Z\",\"level\":\"INFO\",\
+                        \"module\":\"foo::bar\",\"file\":\"foo.rs\",\"line\":3,\
+                        \"message\":\"hello world\",\
+                        \"fields\":{\"user\":\"alice\",\"id\":\"7\"}}\n");
+    }
+
+    #[test]
+    fn json_escapes() {
+        let mut w = MemWriter::new();
+        super::write_json_str(&mut w, "a\"b\\c\nd\x01é");
+        assert_eq!(str::from_utf8_owned(w.unwrap()).unwrap(), ~"\"a\\\"b\\\\c\\nd\\u0001é\"");
+    }
+
+    #[test]
+    fn timestamps() {
+        let format = |t: (i64, u32)| {
+            let mut w = MemWriter::new();
+            write_timestamp(&mut w, t);
+            str::from_utf8_owned(w.unwrap()).unwrap()
+        };
+        assert_eq!(format((0, 0)), ~"1970-01-01T00:00:00.000Z");
+        assert_eq!(format((951782400 + 3661, 123456789)),
+                   ~"2000-02-29T01:01:01.123Z");
+        assert_eq!(format((-1, 0)), ~"1969-12-31T23:59:59.000Z");
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn file_rotation() {
+        let dir = os::tmpdir().join(format!("rust-{}", rand::random::<u32>()));
+        fs::mkdir(&dir, io::UserRWX);
+        let path = dir.join("log");
+        {
+            let mut logger = FileLogger::new(&path, 1, 2);
+            for _ in range(0, 4) {
+                let _ = record_str(|r| { logger.log(r); ~[] });
+            }
+        }
+        assert!(path.exists());
+        assert!(dir.join("log.1").exists());
+        assert!(dir.join("log.2").exists());
+        assert!(!dir.join("log.3").exists());
+        let contents = File::open(&path).unwrap().read_to_end();
+        assert!(str::from_utf8(contents).unwrap().ends_with("INFO:foo::bar: hello world user=alice id=7\n"));
+        fs::rmdir_recursive(&dir);
+    }
+}
@@ -11,11 +11,35 @@
 
 #[macro_export]
 macro_rules! log(
+    ($lvl:expr, { $($key:expr => $value:expr),+ }, $($arg:tt)+) => ({
+        let lvl = $lvl;
+        if lvl <= __log_level() {
+            let fields: &[(&'static str, ~str)] =
+                &[$(($key, format!("{}", $value))),+];
+            format_args!(|args| {
+                ::std::logging::log(&::std::logging::LogRecord {
+                    level: lvl,
+                    module_path: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                    args: args,
+                    fields: fields,
+                })
+            }, $($arg)+)
+        }
+    });
     ($lvl:expr, $($arg:tt)+) => ({
         let lvl = $lvl;
         if lvl <= __log_level() {
             format_args!(|args| {
-                ::std::logging::log(lvl, args)
+                ::std::logging::log(&::std::logging::LogRecord {
+                    level: lvl,
+                    module_path: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                    args: args,
+                    fields: &[],
+                })
             }, $($arg)+)
         }
     })
@@ -868,7 +868,11 @@ pub enum MapOption {
     /// Create a memory mapping for a file with a given fd.
     MapFd(c_int),
     /// When using `MapFd`, the start of the map is `uint` bytes from the start of the file.
-    MapOffset(uint)
+    MapOffset(uint),
+    /// When using `MapFd`, writes to the map are carried through to the file and are visible to
+    /// other maps of the same file. Corresponds to `MAP_SHARED` on POSIX; file maps on Windows
+    /// are always shared.
+    MapShared
 }
 
 /// Possible errors when creating a map.
@@ -935,7 +939,8 @@ impl MemoryMap {
 
         let mut addr: *u8 = ptr::null();
         let mut prot = 0;
-        let mut flags = libc::MAP_PRIVATE;
+        let mut flags = 0;
+        let mut shared = false;
         let mut fd = -1;
         let mut offset = 0;
         let len = round_up(min_len, page_size());
@@ -954,9 +959,11 @@ impl MemoryMap {
                     fd = fd_;
                 },
                 MapOffset(offset_) => { offset = offset_ as off_t; }
+                MapShared => { shared = true; }
             }
         }
         if fd == -1 { flags |= libc::MAP_ANON; }
+        flags |= if shared { libc::MAP_SHARED } else { libc::MAP_PRIVATE };
 
         let r = unsafe {
             libc::mmap(addr as *c_void, len as size_t, prot, flags, fd, offset)
@@ -1028,6 +1035,7 @@ impl MemoryMap {
                 MapAddr(addr_) => { lpAddress = addr_ as LPVOID; },
                 MapFd(fd_) => { fd = fd_; },
                 MapOffset(offset_) => { offset = offset_; }
+                MapShared => {}
             }
         }
 
@@ -1084,7 +1092,7 @@ impl MemoryMap {
                 }
                 let r = libc::MapViewOfFile(mapping,
                                             dwDesiredAccess,
-                                            ((len as u64) >> 32) as DWORD,
+                                            ((offset as u64) >> 32) as DWORD,
                                             (offset & 0xffff_ffff) as DWORD,
                                             0);
                 match r as uint {
@@ -0,0 +1,304 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Backtraces of the current stack
+//!
+//! The stack is walked with the unwinder's `_Unwind_Backtrace`, and the
+//! addresses found on it are resolved to symbols with `dladdr`, which only
+//! knows about symbols which are exported dynamically (for instance when
+//! linking with `-rdynamic`). Symbol names are demangled from the scheme used
+//! by rustc.
+//!
+//! If the `RUST_BACKTRACE` environment variable is set, a backtrace is printed
+//! along with the message of every failing task.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::rt::backtrace::Backtrace;
+//!
+//! let bt = Backtrace::capture();
+//! println!("{}", bt);
+//! ```
+
+use cast;
+use char;
+use char::Char;
+use container::Container;
+use fmt;
+use from_str::from_str;
+use iter::Iterator;
+use libc::c_void;
+use num;
+use option::{Option, Some, None};
+use str::{StrSlice, StrVector};
+use vec::{ImmutableVector, OwnedVector};
+
+use uw = rt::unwind::libunwind;
+
+/// A single frame of a backtrace.
+#[deriving(Clone)]
+pub struct Frame {
+    /// The address of the instruction which the frame is executing.
+    ip: uint,
+    /// The demangled name of the function containing `ip`, if it is known.
+    name: Option<~str>,
+    /// The offset of `ip` from the start of that function.
+    offset: uint,
+}
+
+/// The frames of a stack, innermost first.
+#[deriving(Clone)]
+pub struct Backtrace {
+    priv frames: ~[Frame],
+}
+
+impl Backtrace {
+    /// Captures a backtrace of the stack of the current thread, starting at
+    /// the caller of this function.
+    #[inline(never)]
+    pub fn capture() -> Backtrace {
+        let mut ips: ~[uint] = ~[];
+        unsafe {
+            uw::_Unwind_Backtrace(trace_fn, &mut ips as *mut ~[uint] as *c_void);
+        }
+        // The first frame is this function
+        let frames = ips.iter().skip(1).map(|&ip| resolve(ip)).collect();
+        return Backtrace { frames: frames };
+
+        extern "C" fn trace_fn(context: *uw::_Unwind_Context,
+                               arg: *c_void) -> uw::_Unwind_Reason_Code {
+            unsafe {
+                let ips: &mut ~[uint] = cast::transmute(arg);
+                let ip = uw::_Unwind_GetIP(context);
+                if ip == 0 {
+                    return uw::_URC_END_OF_STACK
+                }
+                ips.push(ip as uint);
+            }
+            uw::_URC_NO_REASON
+        }
+    }
+
+    /// Returns the frames of this backtrace, innermost first.
+    pub fn frames<'a>(&'a self) -> &'a [Frame] {
+        let frames: &'a [Frame] = self.frames;
+        frames
+    }
+}
+
+impl fmt::Default for Backtrace {
+    fn fmt(bt: &Backtrace, f: &mut fmt::Formatter) {
+        write!(f.buf, "stack backtrace:");
+        for (i, frame) in bt.frames.iter().enumerate() {
+            write!(f.buf, "\n  {:2u}: {:#x} - ", i, frame.ip);
+            match frame.name {
+                Some(ref name) => write!(f.buf, "{} + {:#x}", *name, frame.offset),
+                None => write!(f.buf, "<unknown>"),
+            }
+        }
+    }
+}
+
+// Looks up the symbol containing an address
+#[cfg(unix)]
+fn resolve(ip: uint) -> Frame {
+    use c_str::CString;
+    use libc::c_int;
+    use ptr::RawPtr;
+
+    struct Dl_info {
+        dli_fname: *i8,
+        dli_fbase: *c_void,
+        dli_sname: *i8,
+        dli_saddr: *c_void,
+    }
+
+    extern {
+        fn dladdr(addr: *c_void, info: *mut Dl_info) -> c_int;
+    }
+
+    let mut info = Dl_info {
+        dli_fname: 0 as *i8,
+        dli_fbase: 0 as *c_void,
+        dli_sname: 0 as *i8,
+        dli_saddr: 0 as *c_void,
+    };
+    // The address is that of the instruction following the call, which may
+    // already belong to the next function
+    let found = unsafe {
+        dladdr((ip - 1) as *c_void, &mut info) != 0 && !info.dli_sname.is_null()
+    };
+    if !found {
+        return Frame { ip: ip, name: None, offset: 0 }
+    }
+
+    let sym = unsafe { CString::new(info.dli_sname, false) };
+    let name = sym.as_str().map(|s| demangle(s));
+    Frame { ip: ip, name: name, offset: ip - info.dli_saddr as uint }
+}
+
+#[cfg(windows)]
+fn resolve(ip: uint) -> Frame {
+    Frame { ip: ip, name: None, offset: 0 }
+}
+
+/// Demangles a symbol name produced by rustc, turning for instance
+/// `_ZN3std2rt6unwind12begin_unwind19h6f09ea4ad6e61bf6da4v0.9E` into
+/// `std::rt::unwind::begin_unwind`. Names which aren't mangled are returned
+/// as they are.
+pub fn demangle(s: &str) -> ~str {
+    match demangle_components(s) {
+        Some(mut components) => {
+            // Drop the trailing type hash and crate version
+            if components.len() > 1 && is_version(*components.last().unwrap()) {
+                components.pop();
+            }
+            if components.len() > 1 && is_hash(*components.last().unwrap()) {
+                components.pop();
+            }
+            components.map(|c| unescape(*c)).connect("::")
+        }
+        None => s.to_owned(),
+    }
+}
+
+// Splits `_ZN<len><name>...E` into its names
+fn demangle_components<'a>(s: &'a str) -> Option<~[&'a str]> {
+    // OSX prefixes all symbols with an extra underscore
+    let s = if s.starts_with("__ZN") { s.slice_from(1) } else { s };
+    if !s.starts_with("_ZN") || !s.ends_with("E") {
+        return None
+    }
+    let mut rest = s.slice(3, s.len() - 1);
+    let mut components = ~[];
+    while rest.len() > 0 {
+        let digits = rest.find(|c: char| !c.is_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return None
+        }
+        let len: uint = match from_str(rest.slice_to(digits)) {
+            Some(len) => len,
+            None => return None,
+        };
+        if digits + len > rest.len() {
+            return None
+        }
+        components.push(rest.slice(digits, digits + len));
+        rest = rest.slice_from(digits + len);
+    }
+    if components.is_empty() { None } else { Some(components) }
+}
+
+// The hash appended by `symbol_hash`, followed by the optional two characters
+// which distinguish the symbols of impls
+fn is_hash(s: &str) -> bool {
+    (s.len() == 17 || s.len() == 19) && s.starts_with("h") &&
+        s.slice(1, 17).chars().all(|c| char::is_digit_radix(c, 16))
+}
+
+fn is_version(s: &str) -> bool {
+    s.len() > 1 && s.starts_with("v") && s.char_at(1).is_digit()
+}
+
+// Reverses the escaping done by `back::link::sanitize`
+fn unescape(s: &str) -> ~str {
+    let s = if s.starts_with("_$") { s.slice_from(1) } else { s };
+    let mut result = ~"";
+    let mut rest = s;
+    while rest.len() > 0 {
+        if rest.starts_with("..") {
+            result.push_str("::");
+            rest = rest.slice_from(2);
+            continue
+        }
+        if !rest.starts_with("$") {
+            result.push_char(rest.char_at(0));
+            rest = rest.slice_from(rest.char_range_at(0).next);
+            continue
+        }
+        let escapes = [("$SP$", "@"), ("$UP$", "~"), ("$RP$", "*"),
+                       ("$BP$", "&"), ("$LT$", "<"), ("$GT$", ">"),
+                       ("$LP$", "("), ("$C$", ",")];
+        match escapes.iter().find(|&&(e, _)| rest.starts_with(e)) {
+            Some(&(e, c)) => {
+                result.push_str(c);
+                rest = rest.slice_from(e.len());
+                continue
+            }
+            None => {}
+        }
+        // Characters escaped with `char::escape_unicode`: $xNN, $uNNNN and
+        // $UNNNNNNNN
+        let digits = match rest.slice_from(1).chars().next() {
+            Some('x') => 2,
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => 0,
+        };
+        let c = if digits > 0 && rest.len() >= digits + 2 {
+            num::from_str_radix::<u32>(rest.slice(2, digits + 2), 16)
+                .and_then(|n| char::from_u32(n))
+        } else {
+            None
+        };
+        match c {
+            Some(c) => {
+                result.push_char(c);
+                rest = rest.slice_from(digits + 2);
+            }
+            None => {
+                result.push_char('$');
+                rest = rest.slice_from(1);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use prelude::*;
+    use super::{Backtrace, demangle};
+
+    #[test]
+    fn demangle_paths() {
+        assert_eq!(demangle("_ZN3std2rt6unwind12begin_unwind19h6f09ea4ad6e61bf6da4v0.9E"),
+                   ~"std::rt::unwind::begin_unwind");
+        assert_eq!(demangle("_ZN4main17h0123456789abcdefE"), ~"main");
+        assert_eq!(demangle("__ZN3foo3barE"), ~"foo::bar");
+        assert_eq!(demangle("_ZN3foo3barE"), ~"foo::bar");
+    }
+
+    #[test]
+    fn demangle_escapes() {
+        assert_eq!(demangle("_ZN14Foo$LT$int$GT$3new17h0123456789abcdefE"),
+                   ~"Foo<int>::new");
+        assert_eq!(demangle("_ZN20_$UP$$BP$$SP$$RP$$C$1xE"), ~"~&@*,::x");
+        assert_eq!(demangle("_ZN14vec..MoveItemsE"), ~"vec::MoveItems");
+        assert_eq!(demangle("_ZN7_$u03bb1fE"), ~"λ::f");
+    }
+
+    #[test]
+    fn demangle_not_mangled() {
+        assert_eq!(demangle("main"), ~"main");
+        assert_eq!(demangle("_ZN3fooE7"), ~"_ZN3fooE7");
+        assert_eq!(demangle("_ZN99fooE"), ~"_ZN99fooE");
+        assert_eq!(demangle("_ZNE"), ~"_ZNE");
+    }
+
+    #[test]
+    fn capture() {
+        let bt = Backtrace::capture();
+        assert!(bt.frames().len() > 0);
+        let s = format!("{}", bt);
+        assert!(s.starts_with("stack backtrace:"));
+    }
+}
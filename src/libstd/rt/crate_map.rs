@@ -14,6 +14,7 @@ use iter::Iterator;
 use option::{Some, None, Option};
 use ptr::RawPtr;
 use vec::ImmutableVector;
+use rt::global_heap::Allocator;
 use rt::rtio::EventLoop;
 
 // Need to tell the linker on OS X to not barf on undefined symbols
@@ -33,6 +34,8 @@ pub struct CrateMap<'a> {
     entries: &'a [ModEntry<'a>],
     children: &'a [&'a CrateMap<'a>],
     event_loop_factory: Option<fn() -> ~EventLoop>,
+    // Only present from version 3 on
+    global_allocator: Option<fn() -> &'static Allocator>,
 }
 
 #[cfg(not(windows))]
@@ -53,19 +56,20 @@ pub fn get_crate_map() -> Option<&'static CrateMap<'static>> {
 #[cfg(windows)]
 pub fn get_crate_map() -> Option<&'static CrateMap<'static>> {
     use cast::transmute;
-    use c_str::ToCStr;
+    use libc::c_char;
+    use str::StrSlice;
     use unstable::dynamic_lib::dl;
 
+    // This runs before the global allocator is known, so it must not
+    // allocate: the symbol names are nul-terminated by hand.
     let sym = unsafe {
         let module = dl::open_internal();
         let rust_crate_map_toplevel = if cfg!(target_arch = "x86") {
-            "__rust_crate_map_toplevel"
+            "__rust_crate_map_toplevel\0"
         } else {
-            "_rust_crate_map_toplevel"
+            "_rust_crate_map_toplevel\0"
         };
-        let sym = rust_crate_map_toplevel.with_c_str(|buf| {
-            dl::symbol(module, buf)
-        });
+        let sym = dl::symbol(module, rust_crate_map_toplevel.as_ptr() as *c_char);
         dl::close(module);
         sym
     };
@@ -82,17 +86,27 @@ pub fn get_crate_map() -> Option<&'static CrateMap<'static>> {
 fn version(crate_map: &CrateMap) -> i32 {
     match crate_map.version {
         2 => return 2,
+        3 => return 3,
         _ => return 0
     }
 }
 
+/// Returns the function designated by the `global_allocator` lang item, if
+/// the program has one.
+pub fn global_allocator(crate_map: &CrateMap) -> Option<fn() -> &'static Allocator> {
+    match version(crate_map) {
+        3 => crate_map.global_allocator,
+        _ => None,
+    }
+}
+
 fn do_iter_crate_map<'a>(
                      crate_map: &'a CrateMap<'a>,
                      f: |&ModEntry|,
                      visited: &mut HashSet<*CrateMap<'a>>) {
     if visited.insert(crate_map as *CrateMap) {
         match version(crate_map) {
-            2 => {
+            2 | 3 => {
                 let (entries, children) = (crate_map.entries, crate_map.children);
                 for entry in entries.iter() {
                     f(entry);
@@ -128,17 +142,19 @@ mod tests {
         ];
 
         let child_crate = CrateMap {
-            version: 2,
+            version: 3,
             entries: entries,
             children: &[],
             event_loop_factory: None,
+            global_allocator: None,
         };
 
         let root_crate = CrateMap {
-            version: 2,
+            version: 3,
             entries: &[],
             children: &[&child_crate, &child_crate],
             event_loop_factory: None,
+            global_allocator: None,
         };
 
         let mut cnt = 0;
@@ -156,31 +172,34 @@ mod tests {
         let mut level2: u32 = 2;
         let mut level3: u32 = 3;
         let child_crate2 = CrateMap {
-            version: 2,
+            version: 3,
             entries: &[
                 ModEntry { name: "c::m1", log_level: &mut level2},
                 ModEntry { name: "c::m2", log_level: &mut level3},
             ],
             children: &[],
             event_loop_factory: None,
+            global_allocator: None,
         };
 
         let child_crate1 = CrateMap {
-            version: 2,
+            version: 3,
             entries: &[
                 ModEntry { name: "t::f1", log_level: &mut 1},
             ],
             children: &[&child_crate2],
             event_loop_factory: None,
+            global_allocator: None,
         };
 
         let root_crate = CrateMap {
-            version: 2,
+            version: 3,
             entries: &[
                 ModEntry { name: "t::f2", log_level: &mut 0},
             ],
             children: &[&child_crate1],
             event_loop_factory: None,
+            global_allocator: None,
         };
 
         let mut cnt = 0;
@@ -20,6 +20,9 @@ use os;
 static mut MIN_STACK: uint = 2 * 1024 * 1024;
 static mut DEBUG_BORROW: bool = false;
 static mut POISON_ON_FREE: bool = false;
+static mut SCHED_STATS: uint = 0;
+static mut BACKTRACE: bool = false;
+static mut HEAP_STATS: bool = false;
 
 pub fn init() {
     unsafe {
@@ -38,6 +41,21 @@ pub fn init() {
             Some(_) => POISON_ON_FREE = true,
             None => ()
         }
+        match os::getenv("RUST_SCHED_STATS") {
+            Some(s) => match FromStr::from_str(s) {
+                Some(i) => SCHED_STATS = i,
+                None => ()
+            },
+            None => ()
+        }
+        match os::getenv("RUST_BACKTRACE") {
+            Some(_) => BACKTRACE = true,
+            None => ()
+        }
+        match os::getenv("RUST_HEAP_STATS") {
+            Some(_) => HEAP_STATS = true,
+            None => ()
+        }
     }
 }
 
@@ -52,3 +70,21 @@ pub fn debug_borrow() -> bool {
 pub fn poison_on_free() -> bool {
     unsafe { POISON_ON_FREE }
 }
+
+/// The interval, in milliseconds, at which pools of green schedulers should
+/// dump their statistics to stderr, or 0 if no dumping should happen.
+pub fn sched_stats() -> uint {
+    unsafe { SCHED_STATS }
+}
+
+/// Whether a backtrace should be printed along with the message of a failing
+/// task.
+pub fn backtrace() -> bool {
+    unsafe { BACKTRACE }
+}
+
+/// Whether heap allocations should be counted, and leaked managed boxes
+/// reported when tasks exit.
+pub fn heap_stats() -> bool {
+    unsafe { HEAP_STATS }
+}
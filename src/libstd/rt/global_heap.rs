@@ -8,12 +8,161 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+//! The global (exchange) heap
+//!
+//! All `~` allocations, owned vectors and the managed heap get their memory
+//! from a single global `Allocator`, which is the system's `malloc` unless a
+//! program designates another one. This is done by marking a function
+//! returning the allocator with the `global_allocator` lang item in any crate
+//! of the program:
+//!
+//! ```rust,ignore
+//! use std::rt::global_heap::Allocator;
+//!
+//! struct Counting;
+//! impl Allocator for Counting { ... }
+//!
+//! static COUNTING: Counting = Counting;
+//!
+//! #[lang = "global_allocator"]
+//! pub fn counting() -> &'static Allocator { &COUNTING as &'static Allocator }
+//! ```
+//!
+//! The compiler checks that the function has exactly this signature. The
+//! allocator is looked up in the crate map on the first allocation, and is
+//! used for the whole life of the program from then on.
+
 use libc::{c_void, size_t, free, malloc, realloc};
+use option::{Option, Some, None};
 use ptr::{RawPtr, mut_null};
+use rt::crate_map;
+use rt::heap_stats;
+use cast::transmute;
+use rt::thread::Thread;
+use sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, Acquire, Release, SeqCst};
 use unstable::intrinsics::{TyDesc, abort};
 use unstable::raw;
 use mem::size_of;
 
+/// A source of memory for the global heap.
+///
+/// An allocator is shared by all threads of the program, so its methods may be
+/// called concurrently. Blocks must be aligned for any type, as blocks
+/// returned by `malloc` are, and the methods must not allocate on the global
+/// heap themselves.
+#[lang="allocator"]
+pub trait Allocator {
+    /// Allocates a block of `size` bytes, which is never 0. Returns null if
+    /// there is no memory left.
+    unsafe fn alloc(&self, size: uint) -> *mut u8;
+
+    /// Resizes the non-null block at `ptr` to `size` bytes, which is never 0,
+    /// moving it if necessary. Returns null, leaving the block untouched, if
+    /// there is no memory left.
+    unsafe fn realloc(&self, ptr: *mut u8, size: uint) -> *mut u8;
+
+    /// Frees the non-null block at `ptr`.
+    unsafe fn free(&self, ptr: *mut u8);
+
+    /// Returns the size of the non-null block at `ptr`, which may be larger
+    /// than the size it was allocated with. This is only used for statistics,
+    /// and allocators which don't know the sizes of their blocks may return 0.
+    unsafe fn usable_size(&self, _ptr: *mut u8) -> uint { 0 }
+}
+
+/// The allocator of the C library, used unless a program designates another.
+pub struct SystemAllocator;
+
+impl Allocator for SystemAllocator {
+    #[inline]
+    unsafe fn alloc(&self, size: uint) -> *mut u8 {
+        malloc(size as size_t) as *mut u8
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, size: uint) -> *mut u8 {
+        realloc(ptr as *mut c_void, size as size_t) as *mut u8
+    }
+
+    #[inline]
+    unsafe fn free(&self, ptr: *mut u8) {
+        free(ptr as *mut c_void)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    #[cfg(target_os = "freebsd")]
+    unsafe fn usable_size(&self, ptr: *mut u8) -> uint {
+        extern { fn malloc_usable_size(p: *c_void) -> size_t; }
+        malloc_usable_size(ptr as *c_void) as uint
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn usable_size(&self, ptr: *mut u8) -> uint {
+        extern { fn malloc_size(p: *c_void) -> size_t; }
+        malloc_size(ptr as *c_void) as uint
+    }
+
+    #[cfg(windows)]
+    unsafe fn usable_size(&self, ptr: *mut u8) -> uint {
+        extern { fn _msize(p: *c_void) -> size_t; }
+        _msize(ptr as *c_void) as uint
+    }
+}
+
+static SYSTEM: SystemAllocator = SystemAllocator;
+
+static UNINIT: uint = 0;
+static INITIALIZING: uint = 1;
+static READY: uint = 2;
+
+static mut STATE: AtomicUint = INIT_ATOMIC_UINT;
+// The designated `fn() -> &'static Allocator`, or 0 for the system allocator.
+// Only meaningful once STATE is READY.
+static mut DESIGNATED: AtomicUint = INIT_ATOMIC_UINT;
+
+/// Returns the allocator of the global heap.
+#[inline]
+pub fn allocator() -> &'static Allocator {
+    unsafe {
+        if STATE.load(Acquire) != READY {
+            init();
+        }
+        match DESIGNATED.load(Acquire) {
+            0 => &SYSTEM as &'static Allocator,
+            f => {
+                let f: fn() -> &'static Allocator = transmute(f);
+                f()
+            }
+        }
+    }
+}
+
+// Looks up the designated allocator in the crate map. The lookup doesn't
+// allocate, so threads which find another thread in the middle of it simply
+// wait for it to finish instead of falling back to the system allocator.
+#[inline(never)] #[cold]
+unsafe fn init() {
+    match STATE.compare_and_swap(UNINIT, INITIALIZING, SeqCst) {
+        UNINIT => {}
+        _ => {
+            while STATE.load(Acquire) != READY {
+                Thread::yield_now();
+            }
+            return
+        }
+    }
+    let designated = match crate_map::get_crate_map() {
+        Some(map) => crate_map::global_allocator(map),
+        None => None,
+    };
+    match designated {
+        Some(f) => DESIGNATED.store(transmute(f), Release),
+        None => {}
+    }
+    STATE.store(READY, Release);
+}
+
 #[inline]
 pub fn get_box_size(body_size: uint, body_align: uint) -> uint {
     let header_size = size_of::<raw::Box<()>>();
@@ -29,7 +178,7 @@ fn align_to(size: uint, align: uint) -> uint {
     (size + align - 1) & !(align - 1)
 }
 
-/// A wrapper around libc::malloc, aborting on out-of-memory
+/// Allocates from the global allocator, aborting on out-of-memory
 #[inline]
 pub unsafe fn malloc_raw(size: uint) -> *mut u8 {
     // `malloc(0)` may allocate, but it may also return a null pointer
@@ -37,30 +186,43 @@ pub unsafe fn malloc_raw(size: uint) -> *mut u8 {
     if size == 0 {
         mut_null()
     } else {
-        let p = malloc(size as size_t);
+        let p = allocator().alloc(size);
         if p.is_null() {
             // we need a non-allocating way to print an error here
             abort();
         }
-        p as *mut u8
+        if heap_stats::tracking() {
+            heap_stats::record_alloc(p);
+        }
+        p
     }
 }
 
-/// A wrapper around libc::realloc, aborting on out-of-memory
+/// Reallocates with the global allocator, aborting on out-of-memory
 #[inline]
 pub unsafe fn realloc_raw(ptr: *mut u8, size: uint) -> *mut u8 {
     // `realloc(ptr, 0)` may allocate, but it may also return a null pointer
     // http://pubs.opengroup.org/onlinepubs/9699919799/functions/realloc.html
+    if ptr.is_null() {
+        return malloc_raw(size)
+    }
+    let tracking = heap_stats::tracking();
+    if tracking {
+        heap_stats::record_free(ptr);
+    }
     if size == 0 {
-        free(ptr as *mut c_void);
+        allocator().free(ptr);
         mut_null()
     } else {
-        let p = realloc(ptr as *mut c_void, size as size_t);
+        let p = allocator().realloc(ptr, size);
         if p.is_null() {
             // we need a non-allocating way to print an error here
             abort();
         }
-        p as *mut u8
+        if tracking {
+            heap_stats::record_alloc(p);
+        }
+        p
     }
 }
 
@@ -105,9 +267,52 @@ pub unsafe fn exchange_free_(ptr: *u8) {
     exchange_free(ptr)
 }
 
+/// Frees a block of the global heap, doing nothing if `ptr` is null
 #[inline]
 pub unsafe fn exchange_free(ptr: *u8) {
-    free(ptr as *mut c_void);
+    if ptr.is_null() {
+        return
+    }
+    if heap_stats::tracking() {
+        heap_stats::record_free(ptr as *mut u8);
+    }
+    allocator().free(ptr as *mut u8);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Allocator, SystemAllocator, allocator, malloc_raw, realloc_raw,
+                exchange_free};
+    use ptr::RawPtr;
+
+    #[test]
+    fn system_allocator() {
+        unsafe {
+            let a = SystemAllocator;
+            let p = a.alloc(10);
+            assert!(!p.is_null());
+            *p = 7;
+            let p = a.realloc(p, 1000);
+            assert!(!p.is_null());
+            assert_eq!(*p, 7);
+            assert!(a.usable_size(p) >= 1000);
+            a.free(p);
+        }
+    }
+
+    #[test]
+    fn raw_functions() {
+        unsafe {
+            assert!(malloc_raw(0).is_null());
+            let p = malloc_raw(16);
+            assert!(allocator().usable_size(p) >= 16);
+            let p = realloc_raw(p, 32);
+            assert!(!p.is_null());
+            assert!(realloc_raw(p, 0).is_null());
+            exchange_free(malloc_raw(8) as *u8);
+            exchange_free(0 as *u8);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,248 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Statistics about allocations on the exchange heap
+//!
+//! If the `RUST_HEAP_STATS` environment variable is set, the runtime counts
+//! the objects and bytes allocated by `rt::global_heap`, overall and per size
+//! class, prints a summary of them when it shuts down, and reports the managed
+//! boxes which are still alive when a task exits (which can only be kept
+//! alive by reference cycles).
+//!
+//! Independently of the counters, a hook can be installed with
+//! `set_alloc_hook` to be notified of every allocation and deallocation. The
+//! hook runs inside the allocator and must not allocate itself, so it can't
+//! capture a `Backtrace` or format a message; record what it needs into
+//! preallocated static storage instead, such as counters or a fixed-size
+//! ring buffer of events.
+//!
+//! Sizes are those reported by the global allocator's `usable_size`, which
+//! may be slightly larger than the sizes requested. Blocks allocated before the
+//! runtime was initialized aren't counted, so the live counters can be
+//! slightly off at the very start and end of a program.
+
+use cast;
+use fmt;
+use iter::{Iterator, range};
+use option::{Option, Some, None};
+use rt::env;
+use rt::global_heap;
+use sync::atomics::{AtomicInt, AtomicUint, INIT_ATOMIC_INT, INIT_ATOMIC_UINT};
+use sync::atomics::{Relaxed, SeqCst};
+
+/// The number of size classes. Class `i` counts the blocks of at most
+/// `16 << i` bytes which don't fit in the previous class, and the last class
+/// counts all larger blocks.
+pub static SIZE_CLASSES: uint = 16;
+
+/// A snapshot of the allocation counters.
+#[deriving(Clone)]
+pub struct HeapStats {
+    /// The number of blocks currently allocated.
+    live_objects: uint,
+    /// The number of bytes currently allocated.
+    live_bytes: uint,
+    /// The number of blocks allocated since the runtime started.
+    total_objects: uint,
+    /// The number of bytes allocated since the runtime started.
+    total_bytes: uint,
+    /// The number of blocks currently allocated in each size class.
+    live_by_class: [uint, ..SIZE_CLASSES],
+    /// The number of blocks allocated in each size class since the runtime
+    /// started.
+    total_by_class: [uint, ..SIZE_CLASSES],
+}
+
+/// An allocation or deallocation on the exchange heap, given to the hook
+/// installed with `set_alloc_hook`. Reallocations are reported as the
+/// deallocation of the old block followed by the allocation of the new one.
+pub enum AllocEvent {
+    /// A block of the given size was allocated at the given address.
+    Allocated(*u8, uint),
+    /// The block of the given size at the given address was freed.
+    Freed(*u8, uint),
+}
+
+/// A function notified of every allocation and deallocation.
+///
+/// The hook is called by the allocator itself, possibly outside of any task,
+/// so it must not allocate on the exchange heap (which would call it
+/// recursively) nor fail.
+pub type AllocHook = fn(event: AllocEvent);
+
+static mut LIVE_OBJECTS: AtomicInt = INIT_ATOMIC_INT;
+static mut LIVE_BYTES: AtomicInt = INIT_ATOMIC_INT;
+static mut TOTAL_OBJECTS: AtomicUint = INIT_ATOMIC_UINT;
+static mut TOTAL_BYTES: AtomicUint = INIT_ATOMIC_UINT;
+static mut LIVE_BY_CLASS: [AtomicInt, ..SIZE_CLASSES] = [
+    INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT,
+    INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT,
+    INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT,
+    INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT, INIT_ATOMIC_INT,
+];
+static mut TOTAL_BY_CLASS: [AtomicUint, ..SIZE_CLASSES] = [
+    INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT,
+    INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT,
+    INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT,
+    INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT, INIT_ATOMIC_UINT,
+];
+
+// The installed hook, transmuted to a word, or 0
+static mut ALLOC_HOOK: AtomicUint = INIT_ATOMIC_UINT;
+
+/// Whether the allocation counters are enabled, which is the case if
+/// `RUST_HEAP_STATS` is set.
+pub fn enabled() -> bool {
+    env::heap_stats()
+}
+
+/// Returns a snapshot of the allocation counters, which are all zero if they
+/// aren't enabled.
+pub fn stats() -> HeapStats {
+    fn live(n: &AtomicInt) -> uint {
+        let n = n.load(SeqCst);
+        if n < 0 { 0 } else { n as uint }
+    }
+
+    unsafe {
+        let mut stats = HeapStats {
+            live_objects: live(&LIVE_OBJECTS),
+            live_bytes: live(&LIVE_BYTES),
+            total_objects: TOTAL_OBJECTS.load(SeqCst),
+            total_bytes: TOTAL_BYTES.load(SeqCst),
+            live_by_class: [0, ..SIZE_CLASSES],
+            total_by_class: [0, ..SIZE_CLASSES],
+        };
+        for i in range(0, SIZE_CLASSES) {
+            stats.live_by_class[i] = live(&LIVE_BY_CLASS[i]);
+            stats.total_by_class[i] = TOTAL_BY_CLASS[i].load(SeqCst);
+        }
+        stats
+    }
+}
+
+/// Replaces the allocation hook, returning the previous hook if one was set.
+pub fn set_alloc_hook(hook: AllocHook) -> Option<AllocHook> {
+    unsafe { from_word(ALLOC_HOOK.swap(cast::transmute(hook), SeqCst)) }
+}
+
+/// Removes the allocation hook, returning it if one was set.
+pub fn take_alloc_hook() -> Option<AllocHook> {
+    unsafe { from_word(ALLOC_HOOK.swap(0, SeqCst)) }
+}
+
+unsafe fn from_word(word: uint) -> Option<AllocHook> {
+    if word == 0 { None } else { Some(cast::transmute(word)) }
+}
+
+/// Returns the size class of a block of `size` bytes.
+pub fn size_class(size: uint) -> uint {
+    let mut class = 0;
+    let mut limit = 16;
+    while class < SIZE_CLASSES - 1 && size > limit {
+        class += 1;
+        limit <<= 1;
+    }
+    class
+}
+
+/// Whether allocations need to be reported to `record_alloc` and
+/// `record_free`, either for the counters or for the hook.
+#[doc(hidden)]
+#[inline]
+pub fn tracking() -> bool {
+    unsafe { env::heap_stats() || ALLOC_HOOK.load(Relaxed) != 0 }
+}
+
+/// Records the allocation of the block at `p`.
+#[doc(hidden)]
+pub unsafe fn record_alloc(p: *mut u8) {
+    let size = global_heap::allocator().usable_size(p);
+    if env::heap_stats() {
+        let class = size_class(size);
+        LIVE_OBJECTS.fetch_add(1, Relaxed);
+        LIVE_BYTES.fetch_add(size as int, Relaxed);
+        TOTAL_OBJECTS.fetch_add(1, Relaxed);
+        TOTAL_BYTES.fetch_add(size, Relaxed);
+        LIVE_BY_CLASS[class].fetch_add(1, Relaxed);
+        TOTAL_BY_CLASS[class].fetch_add(1, Relaxed);
+    }
+    match from_word(ALLOC_HOOK.load(SeqCst)) {
+        Some(hook) => hook(Allocated(p as *u8, size)),
+        None => {}
+    }
+}
+
+/// Records that the block at `p` is about to be freed.
+#[doc(hidden)]
+pub unsafe fn record_free(p: *mut u8) {
+    let size = global_heap::allocator().usable_size(p);
+    if env::heap_stats() {
+        LIVE_OBJECTS.fetch_sub(1, Relaxed);
+        LIVE_BYTES.fetch_sub(size as int, Relaxed);
+        LIVE_BY_CLASS[size_class(size)].fetch_sub(1, Relaxed);
+    }
+    match from_word(ALLOC_HOOK.load(SeqCst)) {
+        Some(hook) => hook(Freed(p as *u8, size)),
+        None => {}
+    }
+}
+
+/// Prints a summary of the counters to stderr, called at runtime shutdown if
+/// they are enabled.
+#[doc(hidden)]
+pub fn dump() {
+    rterrln!("{}", stats());
+}
+
+impl fmt::Default for HeapStats {
+    fn fmt(s: &HeapStats, f: &mut fmt::Formatter) {
+        write!(f.buf, "heap statistics:\n  \
+                       live: {} objects, {} bytes\n  \
+                       total: {} objects, {} bytes\n  \
+                       size class      live     total",
+               s.live_objects, s.live_bytes, s.total_objects, s.total_bytes);
+        for class in range(0, SIZE_CLASSES) {
+            if s.total_by_class[class] == 0 {
+                continue
+            }
+            if class == SIZE_CLASSES - 1 {
+                write!(f.buf, "\n  > {:9u}", 8u << class);
+            } else {
+                write!(f.buf, "\n  <= {:8u}", 16u << class);
+            }
+            write!(f.buf, " {:9u} {:9u}",
+                   s.live_by_class[class], s.total_by_class[class]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prelude::*;
+    use super::{size_class, stats, SIZE_CLASSES};
+
+    #[test]
+    fn size_classes() {
+        assert_eq!(size_class(0), 0);
+        assert_eq!(size_class(16), 0);
+        assert_eq!(size_class(17), 1);
+        assert_eq!(size_class(32), 1);
+        assert_eq!(size_class(4096), 8);
+        assert_eq!(size_class(16 << (SIZE_CLASSES - 2)), SIZE_CLASSES - 2);
+        assert_eq!(size_class(uint::max_value), SIZE_CLASSES - 1);
+    }
+
+    #[test]
+    fn format_stats() {
+        let s = format!("{}", stats());
+        assert!(s.starts_with("heap statistics:"));
+    }
+}
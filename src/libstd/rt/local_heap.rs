@@ -11,6 +11,7 @@
 //! The local, garbage collected heap
 
 use cast;
+use cmp::TotalOrd;
 use iter::Iterator;
 use mem;
 use ops::Drop;
@@ -23,7 +24,8 @@ use rt::local::Local;
 use rt::task::Task;
 use unstable::intrinsics::TyDesc;
 use unstable::raw;
-use vec::ImmutableVector;
+use str::{Str, StrSlice};
+use vec::{ImmutableVector, MutableVector, OwnedVector};
 
 // This has no meaning with out rtdebug also turned on.
 #[cfg(rtdebug)]
@@ -35,7 +37,28 @@ pub type Box = raw::Box<()>;
 
 pub struct MemoryRegion {
     priv allocations: ~[*AllocHeader],
+    // Whether allocations carry a header recording their size
+    priv track_bytes: bool,
     priv live_allocations: uint,
+    priv live_bytes: uint,
+    priv total_allocations: uint,
+    priv total_bytes: uint,
+}
+
+/// Statistics about the managed heap of a task.
+///
+/// The byte counts are only kept when `RUST_HEAP_STATS` is set (or in debug
+/// builds of the runtime), and are 0 otherwise.
+#[deriving(Clone, Eq)]
+pub struct LocalHeapStats {
+    /// The number of boxes currently allocated.
+    live_boxes: uint,
+    /// The number of bytes currently allocated, including box headers.
+    live_bytes: uint,
+    /// The number of boxes allocated since the task started.
+    total_boxes: uint,
+    /// The number of bytes allocated since the task started.
+    total_bytes: uint,
 }
 
 pub struct LocalHeap {
@@ -50,7 +73,11 @@ impl LocalHeap {
     pub fn new() -> LocalHeap {
         let region = MemoryRegion {
             allocations: ~[],
+            track_bytes: cfg!(rtdebug) || env::heap_stats(),
             live_allocations: 0,
+            live_bytes: 0,
+            total_allocations: 0,
+            total_bytes: 0,
         };
         LocalHeap {
             memory_region: region,
@@ -135,6 +162,26 @@ impl LocalHeap {
     }
 }
 
+impl LocalHeap {
+    /// Returns the size of the allocation of a live box, including its
+    /// header, or 0 if the sizes of allocations aren't being tracked.
+    pub fn box_bytes(&self, a_box: *mut Box) -> uint {
+        let region = &self.memory_region;
+        region.bytes(region.header(a_box))
+    }
+
+    /// Returns the statistics of this heap.
+    pub fn stats(&self) -> LocalHeapStats {
+        let region = &self.memory_region;
+        LocalHeapStats {
+            live_boxes: region.live_allocations,
+            live_bytes: region.live_bytes,
+            total_boxes: region.total_allocations,
+            total_bytes: region.total_bytes,
+        }
+    }
+}
+
 impl Drop for LocalHeap {
     fn drop(&mut self) {
         assert!(self.live_allocs.is_null());
@@ -146,9 +193,15 @@ struct AllocHeader {
     magic: u32,
     index: i32,
     size: u32,
+    bytes: uint,
 }
 #[cfg(not(rtdebug))]
-struct AllocHeader;
+struct AllocHeader {
+    // The size of the allocation, for the statistics of the heap. Outside of
+    // debug builds this is all the header holds, so it's left out entirely
+    // unless the heap statistics are enabled, see `MemoryRegion::header_size`.
+    bytes: uint,
+}
 
 impl AllocHeader {
     #[cfg(rtdebug)]
@@ -180,27 +233,42 @@ impl AllocHeader {
     #[cfg(not(rtdebug))]
     fn update_size(&mut self, _size: u32) {}
 
-    fn as_box(&mut self) -> *mut Box {
-        let myaddr: uint = unsafe { cast::transmute(self) };
-        (myaddr + AllocHeader::size()) as *mut Box
-    }
+}
 
-    fn size() -> uint {
+impl MemoryRegion {
+    // The size of the header in front of each box, which is 0 when there is
+    // no header at all.
+    #[inline]
+    fn header_size(&self) -> uint {
+        if !self.track_bytes {
+            return 0
+        }
         // For some platforms, 16 byte alignment is required.
         let ptr_size = 16;
         let header_size = mem::size_of::<AllocHeader>();
         return (header_size + ptr_size - 1) / ptr_size * ptr_size;
     }
 
-    fn from(a_box: *mut Box) -> *mut AllocHeader {
-        (a_box as uint - AllocHeader::size()) as *mut AllocHeader
+    #[inline]
+    fn to_box(&self, alloc: *mut AllocHeader) -> *mut Box {
+        (alloc as uint + self.header_size()) as *mut Box
+    }
+
+    #[inline]
+    fn header(&self, a_box: *mut Box) -> *mut AllocHeader {
+        (a_box as uint - self.header_size()) as *mut AllocHeader
+    }
+
+    // The recorded size of an allocation. This must not be used for a
+    // headerless allocation, which is the box itself.
+    #[inline]
+    fn bytes(&self, alloc: *mut AllocHeader) -> uint {
+        if self.track_bytes { unsafe { (*alloc).bytes } } else { 0 }
     }
-}
 
-impl MemoryRegion {
     #[inline]
     fn malloc(&mut self, size: uint) -> *mut Box {
-        let total_size = size + AllocHeader::size();
+        let total_size = size + self.header_size();
         let alloc: *AllocHeader = unsafe {
             global_heap::malloc_raw(total_size) as *AllocHeader
         };
@@ -209,17 +277,24 @@ impl MemoryRegion {
         alloc.init(size as u32);
         self.claim(alloc);
         self.live_allocations += 1;
+        self.total_allocations += 1;
+        if self.track_bytes {
+            alloc.bytes = size;
+            self.live_bytes += size;
+            self.total_bytes += size;
+        }
 
-        return alloc.as_box();
+        return self.to_box(alloc);
     }
 
     #[inline]
     fn realloc(&mut self, alloc: *mut Box, size: uint) -> *mut Box {
         rtassert!(!alloc.is_null());
-        let orig_alloc = AllocHeader::from(alloc);
+        let orig_alloc = self.header(alloc);
         unsafe { (*orig_alloc).assert_sane(); }
+        let orig_size = self.bytes(orig_alloc);
 
-        let total_size = size + AllocHeader::size();
+        let total_size = size + self.header_size();
         let alloc: *AllocHeader = unsafe {
             global_heap::realloc_raw(orig_alloc as *mut u8,
                                      total_size) as *AllocHeader
@@ -228,19 +303,27 @@ impl MemoryRegion {
         let alloc: &mut AllocHeader = unsafe { cast::transmute(alloc) };
         alloc.assert_sane();
         alloc.update_size(size as u32);
+        if self.track_bytes {
+            alloc.bytes = size;
+            self.live_bytes = self.live_bytes - orig_size + size;
+            if size > orig_size {
+                self.total_bytes += size - orig_size;
+            }
+        }
         self.update(alloc, orig_alloc as *AllocHeader);
-        return alloc.as_box();
+        return self.to_box(alloc);
     }
 
     #[inline]
     fn free(&mut self, alloc: *mut Box) {
         rtassert!(!alloc.is_null());
-        let alloc = AllocHeader::from(alloc);
+        let alloc = self.header(alloc);
         unsafe {
             (*alloc).assert_sane();
             self.release(cast::transmute(alloc));
             rtassert!(self.live_allocations > 0);
             self.live_allocations -= 1;
+            self.live_bytes -= self.bytes(alloc);
             global_heap::exchange_free(alloc as *u8)
         }
     }
@@ -316,11 +399,64 @@ pub unsafe fn local_free(ptr: *u8) {
     }
 }
 
+/// Returns the statistics of the managed heap of the current task.
+pub fn stats() -> LocalHeapStats {
+    let mut task = Local::borrow(None::<Task>);
+    task.get().heap.stats()
+}
+
 pub fn live_allocs() -> *mut Box {
     let mut task = Local::borrow(None::<Task>);
     task.get().heap.live_allocs
 }
 
+/// Prints the number of boxes of each type which are still allocated in the
+/// heap of the current task, along with their size.
+pub fn report_leaks() {
+    // (type name, boxes, bytes)
+    let mut leaks: ~[(&'static str, uint, uint)] = ~[];
+    let (mut boxes, mut bytes) = (0u, 0u);
+    let heap: *LocalHeap = {
+        let mut task = Local::borrow(None::<Task>);
+        &task.get().heap as *LocalHeap
+    };
+    let mut alloc = live_allocs();
+    while !alloc.is_null() {
+        unsafe {
+            let tydesc = (*alloc).type_desc;
+            // The recorded size, which unlike the size of the type also
+            // covers the contents of managed vectors
+            let size = (*heap).box_bytes(alloc);
+            match leaks.iter().position(|&(name, _, _)| name == (*tydesc).name) {
+                Some(i) => {
+                    let (name, n, b) = leaks[i];
+                    leaks[i] = (name, n + 1, b + size);
+                }
+                None => leaks.push(((*tydesc).name, 1, size)),
+            }
+            boxes += 1;
+            bytes += size;
+            alloc = (*alloc).next;
+        }
+    }
+    if boxes == 0 {
+        return
+    }
+
+    let name = {
+        let mut task = Local::borrow(None::<Task>);
+        match task.get().name {
+            Some(ref name) => name.as_slice().to_owned(),
+            None => ~"<unnamed>",
+        }
+    };
+    rterrln!("task '{}' leaked {} managed boxes ({} bytes):", name, boxes, bytes);
+    leaks.sort_by(|&(_, _, a), &(_, _, b)| b.cmp(&a));
+    for &(name, n, b) in leaks.iter() {
+        rterrln!("  {} x {} ({} bytes)", n, name, b);
+    }
+}
+
 #[cfg(test)]
 mod bench {
     use extra::test::BenchHarness;
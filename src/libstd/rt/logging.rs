@@ -8,15 +8,23 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use cast;
 use container::Container;
+use fmt;
 use from_str::from_str;
-use iter::Iterator;
+use iter::{Iterator, range};
 use libc::exit;
 use option::{Some, None, Option};
+use ptr;
+use ptr::RawPtr;
 use rt::crate_map::{ModEntry, CrateMap, iter_crate_map, get_crate_map};
 use str::StrSlice;
-use vec::{ImmutableVector, MutableTotalOrdVector, OwnedVector};
+use sync::atomics::{AtomicBool, INIT_ATOMIC_BOOL, SeqCst};
+use unstable::mutex::{Mutex, MUTEX_INIT};
+use vec;
+use vec::{ImmutableVector, MutableVector, MutableTotalOrdVector, OwnedVector};
 #[cfg(test)] use cast::transmute;
+#[cfg(test)] use str::OwnedStr;
 
 struct LogDirective {
     name: Option<~str>,
@@ -132,24 +140,36 @@ fn update_entry(dirs: &[LogDirective], entry: &ModEntry) -> u32 {
     if longest_match >= 0 { return 1; } else { return 0; }
 }
 
+/// Split a logging specification into its directives and the optional
+/// message filter following a '/'
+fn split_filter<'a>(spec: &'a str) -> (&'a str, Option<~str>) {
+    match spec.find('/') {
+        Some(i) => (spec.slice_to(i), Some(spec.slice_from(i + 1).to_owned())),
+        None => (spec, None),
+    }
+}
+
+// Serializes updates of the logging spec. The log levels are read by the
+// logging macros without any synchronization, which is fine as each level is
+// a single aligned word, but two updates running at once could otherwise
+// leave a mix of both specs behind.
+static mut SPEC_LOCK: Mutex = MUTEX_INIT;
+
 /// Set log level for every entry in crate_map according to the sepecification
 /// in settings
-fn update_log_settings(crate_map: &CrateMap, settings: ~str) {
-    let mut dirs = ~[];
-    if settings.len() > 0 {
-        if settings == ~"::help" || settings == ~"?" {
-            rterrln!("\nCrate log map:\n");
+fn update_log_settings(crate_map: &CrateMap, settings: &str) {
+    unsafe { SPEC_LOCK.lock(); }
+    update_log_settings_locked(crate_map, settings);
+    unsafe { SPEC_LOCK.unlock(); }
+}
 
-            let mut entries = ~[];
-            iter_crate_map(crate_map, |entry| entries.push(entry.name.to_owned()));
-            entries.sort();
+fn update_log_settings_locked(crate_map: &CrateMap, settings: &str) {
+    let (settings, filter) = split_filter(settings);
+    set_filter(filter);
 
-            for name in entries.iter() {
-                rterrln!(" {}", *name);
-            }
-            unsafe { exit(1); }
-        }
-        dirs = parse_logging_spec(settings);
+    let mut dirs = ~[];
+    if settings.len() > 0 {
+        dirs = parse_logging_spec(settings.to_owned());
     }
 
     let mut n_matches: u32 = 0;
@@ -166,6 +186,281 @@ fn update_log_settings(crate_map: &CrateMap, settings: ~str) {
     }
 }
 
+/// Print the name of every module in the crate map and exit
+fn print_help(crate_map: &CrateMap) -> ! {
+    rterrln!("\nCrate log map:\n");
+
+    let mut entries = ~[];
+    iter_crate_map(crate_map, |entry| entries.push(entry.name.to_owned()));
+    entries.sort();
+
+    for name in entries.iter() {
+        rterrln!(" {}", *name);
+    }
+    unsafe { exit(1) }
+}
+
+/// A message filter, following the '/' of a logging spec. A filter enclosed
+/// in slashes (as in `hello=debug//conn.* re?set/`) is a regular expression,
+/// anything else is matched as a plain substring.
+enum MessageFilter {
+    Substring(~str),
+    Pattern(Regex),
+}
+
+impl MessageFilter {
+    fn parse(filter: ~str) -> Option<MessageFilter> {
+        if filter.len() >= 2 && filter.starts_with("/") && filter.ends_with("/") {
+            match Regex::parse(filter.slice(1, filter.len() - 1)) {
+                Some(re) => Some(Pattern(re)),
+                None => {
+                    rterrln!("warning: invalid regex in logging spec '{}', \
+                              ignoring it", filter);
+                    None
+                }
+            }
+        } else {
+            Some(Substring(filter))
+        }
+    }
+
+    fn matches(&self, msg: &str) -> bool {
+        match *self {
+            Substring(ref s) => msg.contains(*s),
+            Pattern(ref re) => re.is_match(msg),
+        }
+    }
+}
+
+/// A minimal regular expression: a sequence of literals, `.` and bracketed
+/// character classes (`[a-z_]`, `[^0-9]`), each optionally followed by `*`,
+/// `+` or `?`, and optionally anchored with a leading `^` and a trailing `$`.
+/// A backslash makes the character after it literal. There is no grouping or
+/// alternation, so matching can track all of the ways in which the pieces
+/// may line up with the text at once, see `is_match`.
+struct Regex {
+    start: bool,
+    end: bool,
+    pieces: ~[Piece],
+}
+
+struct Piece {
+    atom: Atom,
+    repeat: Repeat,
+}
+
+enum Atom {
+    Literal(char),
+    AnyChar,
+    // Inclusive ranges, and whether the class is negated
+    Class(~[(char, char)], bool),
+}
+
+enum Repeat {
+    Once,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Literal(l) => l == c,
+            AnyChar => true,
+            Class(ref ranges, negated) => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != negated
+            }
+        }
+    }
+}
+
+impl Regex {
+    /// Parses a regular expression, returning None if it is malformed.
+    fn parse(re: &str) -> Option<Regex> {
+        let chars: ~[char] = re.chars().collect();
+        let mut regex = Regex { start: false, end: false, pieces: ~[] };
+        let mut i = 0;
+        if chars.len() > 0 && chars[0] == '^' {
+            regex.start = true;
+            i = 1;
+        }
+        while i < chars.len() {
+            let atom = match chars[i] {
+                '$' if i == chars.len() - 1 => {
+                    regex.end = true;
+                    break
+                }
+                '.' => { i += 1; AnyChar }
+                '\\' => {
+                    if i + 1 == chars.len() { return None }
+                    i += 2;
+                    Literal(chars[i - 1])
+                }
+                '[' => match Regex::parse_class(chars, &mut i) {
+                    Some(class) => class,
+                    None => return None,
+                },
+                '*' | '+' | '?' => return None,
+                c => { i += 1; Literal(c) }
+            };
+            let repeat = if i < chars.len() {
+                match chars[i] {
+                    '*' => { i += 1; ZeroOrMore }
+                    '+' => { i += 1; OneOrMore }
+                    '?' => { i += 1; Optional }
+                    _ => Once,
+                }
+            } else {
+                Once
+            };
+            regex.pieces.push(Piece { atom: atom, repeat: repeat });
+        }
+        Some(regex)
+    }
+
+    // Parses the class starting at the '[' at `chars[*i]`, leaving `*i` just
+    // past its closing ']'.
+    fn parse_class(chars: &[char], i: &mut uint) -> Option<Atom> {
+        let mut j = *i + 1;
+        let negated = j < chars.len() && chars[j] == '^';
+        if negated { j += 1 }
+        let mut ranges = ~[];
+        let mut first = true;
+        loop {
+            if j >= chars.len() { return None }
+            let mut lo = chars[j];
+            if lo == ']' && !first { break }
+            if lo == '\\' {
+                if j + 1 >= chars.len() { return None }
+                j += 1;
+                lo = chars[j];
+            }
+            first = false;
+            if j + 2 < chars.len() && chars[j + 1] == '-' && chars[j + 2] != ']' {
+                let hi = chars[j + 2];
+                if hi < lo { return None }
+                ranges.push((lo, hi));
+                j += 3;
+            } else {
+                ranges.push((lo, lo));
+                j += 1;
+            }
+        }
+        *i = j + 1;
+        Some(Class(ranges, negated))
+    }
+
+    /// Returns whether this expression matches anywhere in `text`.
+    ///
+    /// Rather than trying the possible matches one after the other, which
+    /// can take time exponential in the number of repeated pieces, this
+    /// keeps track of all of them at once. State `p` is set while the text
+    /// read so far can end with a match of the first `p` pieces, so matching
+    /// takes time proportional to the length of the text times the number of
+    /// pieces.
+    fn is_match(&self, text: &str) -> bool {
+        let n = self.pieces.len();
+        let mut states = vec::from_elem(n + 1, false);
+        self.add_state(states.as_mut_slice(), 0);
+        for c in text.chars() {
+            if states[n] && !self.end { return true }
+            let mut next = vec::from_elem(n + 1, false);
+            for p in range(0, n) {
+                let piece = &self.pieces[p];
+                if !states[p] || !piece.atom.matches(c) { continue }
+                match piece.repeat {
+                    ZeroOrMore | OneOrMore => next[p] = true,
+                    Once | Optional => {}
+                }
+                self.add_state(next.as_mut_slice(), p + 1);
+            }
+            if !self.start {
+                self.add_state(next.as_mut_slice(), 0);
+            }
+            states = next;
+        }
+        states[n]
+    }
+
+    // Sets state `p`, along with the states which follow it by skipping
+    // pieces which may match nothing.
+    fn add_state(&self, states: &mut [bool], p: uint) {
+        let mut p = p;
+        loop {
+            states[p] = true;
+            if p == self.pieces.len() { break }
+            match self.pieces[p].repeat {
+                Optional | ZeroOrMore => p += 1,
+                Once | OneOrMore => break,
+            }
+        }
+    }
+}
+
+// The message filter of the current logging spec. `FILTER` is an owned
+// `MessageFilter` (or null) which is only touched with `FILTER_LOCK` held,
+// while `HAS_FILTER` lets messages skip the lock when there is no filter.
+static mut FILTER_LOCK: Mutex = MUTEX_INIT;
+static mut FILTER: *mut MessageFilter = 0 as *mut MessageFilter;
+static mut HAS_FILTER: AtomicBool = INIT_ATOMIC_BOOL;
+
+fn set_filter(filter: Option<~str>) {
+    let filter = filter.and_then(|f| MessageFilter::parse(f));
+    unsafe {
+        FILTER_LOCK.lock();
+        let prev = FILTER;
+        FILTER = match filter {
+            Some(filter) => cast::transmute(~filter),
+            None => ptr::mut_null(),
+        };
+        HAS_FILTER.store(!FILTER.is_null(), SeqCst);
+        FILTER_LOCK.unlock();
+
+        if !prev.is_null() {
+            let _prev: ~MessageFilter = cast::transmute(prev);
+        }
+    }
+}
+
+/// Returns whether a message passes the message filter of the current logging
+/// spec, which is true of all messages if there is no filter. The message is
+/// only formatted if there is a filter.
+pub fn filter_matches(args: &fmt::Arguments) -> bool {
+    unsafe {
+        if !HAS_FILTER.load(SeqCst) {
+            return true
+        }
+        let msg = fmt::format(args);
+        FILTER_LOCK.lock();
+        let matches = FILTER.is_null() || (*FILTER).matches(msg);
+        FILTER_LOCK.unlock();
+        matches
+    }
+}
+
+/// Applies a new logging specification to the running program, replacing the
+/// one read from `RUST_LOG` at startup or set by a previous call. Modules which
+/// none of the new directives match go back to the default level.
+///
+/// The specification has the same syntax as `RUST_LOG`, and may be followed by
+/// `/filter`, in which case only messages containing `filter` are logged. For
+/// instance `"error,hello=debug/connection"` logs errors of all modules and
+/// every message of `hello`, as long as they contain "connection". A filter
+/// enclosed in slashes, as in `"hello=debug//^conn[a-z]* re?set$/"`, is a
+/// regular expression instead; see the `std::logging` docs for its syntax.
+///
+/// Returns false, leaving the log levels untouched, if there is no crate map.
+pub fn set_log_spec(spec: &str) -> bool {
+    match get_crate_map() {
+        Some(crate_map) => {
+            update_log_settings(crate_map, spec);
+            true
+        }
+        None => false,
+    }
+}
+
 /// Configure logging by traversing the crate map and setting the
 /// per-module global logging flags based on the logging spec
 pub fn init() {
@@ -176,10 +471,13 @@ pub fn init() {
         Some(crate_map) => {
             match log_spec {
                 Some(spec) => {
+                    if spec == ~"::help" || spec == ~"?" {
+                        print_help(crate_map);
+                    }
                     update_log_settings(crate_map, spec);
                 }
                 None => {
-                    update_log_settings(crate_map, ~"");
+                    update_log_settings(crate_map, "");
                 }
             }
         },
@@ -247,6 +545,67 @@ fn parse_logging_spec_global() {
     assert_eq!(dirs[1].level, 4);
 }
 
+#[test]
+fn split_filter_spec() {
+    let (dirs, filter) = split_filter("crate1=info,crate2/foo bar");
+    assert_eq!(dirs, "crate1=info,crate2");
+    assert_eq!(filter, Some(~"foo bar"));
+
+    let (dirs, filter) = split_filter("/foo");
+    assert_eq!(dirs, "");
+    assert_eq!(filter, Some(~"foo"));
+
+    let (dirs, filter) = split_filter("crate1");
+    assert_eq!(dirs, "crate1");
+    assert_eq!(filter, None);
+}
+
+#[test]
+fn message_filter_substring() {
+    let filter = MessageFilter::parse(~"a.b").unwrap();
+    assert!(filter.matches("xa.by"));
+    assert!(!filter.matches("axb"));
+}
+
+#[test]
+fn message_filter_regex() {
+    let filter = MessageFilter::parse(~"/^conn[a-z]* re?set$/").unwrap();
+    assert!(filter.matches("connection reset"));
+    assert!(filter.matches("conn rset"));
+    assert!(!filter.matches("connection reset!"));
+    assert!(!filter.matches("lost connection reset"));
+
+    let filter = MessageFilter::parse(~"/[^0-9]+[0-9]+\\.x*/").unwrap();
+    assert!(filter.matches("port 8080."));
+    assert!(filter.matches("error 1.xx"));
+    assert!(!filter.matches("8080."));
+    assert!(!filter.matches("port 8080"));
+
+    let filter = MessageFilter::parse(~"/a.c/").unwrap();
+    assert!(filter.matches("xxabcxx"));
+    assert!(!filter.matches("ac"));
+}
+
+#[test]
+fn message_filter_regex_many_repeats() {
+    // A backtracking matcher would try every way of splitting the text
+    // between the repeated pieces before giving up.
+    let filter = MessageFilter::parse(~"/^a*a*a*a*a*a*a*a*a*a*a*a*b$/").unwrap();
+    let text = "a".repeat(200);
+    assert!(!filter.matches(text));
+    assert!(filter.matches(text.append("b")));
+}
+
+#[test]
+fn message_filter_invalid_regex() {
+    assert!(MessageFilter::parse(~"/*a/").is_none());
+    assert!(MessageFilter::parse(~"/[abc/").is_none());
+    assert!(MessageFilter::parse(~"/a\\/").is_none());
+    assert!(MessageFilter::parse(~"/[z-a]/").is_none());
+    // a single slash is just a substring
+    assert!(MessageFilter::parse(~"/").is_some());
+}
+
 // Tests for update_entry
 #[test]
 fn update_entry_match_full_path() {
@@ -69,7 +69,7 @@ use self::task::{Task, BlockedTask};
 pub use self::util::default_sched_threads;
 
 // Export unwinding facilities used by the failure macros
-pub use self::unwind::{begin_unwind, begin_unwind_raw};
+pub use self::unwind::{begin_unwind, begin_unwind_any, begin_unwind_raw};
 
 // XXX: these probably shouldn't be public...
 #[doc(hidden)]
@@ -104,6 +104,9 @@ pub mod env;
 /// The local, managed heap
 pub mod local_heap;
 
+/// Statistics about heap allocations
+pub mod heap_stats;
+
 /// The Logger trait and implementations
 pub mod logging;
 
@@ -119,6 +122,9 @@ mod thread_local_storage;
 /// Stack unwinding
 pub mod unwind;
 
+/// Capturing and printing backtraces of the current stack
+pub mod backtrace;
+
 /// Just stuff
 mod util;
 
@@ -204,6 +210,9 @@ pub fn at_exit(f: proc()) {
 /// undefined behavior.
 pub unsafe fn cleanup() {
     at_exit_imp::run();
+    if env::heap_stats() {
+        heap_stats::dump();
+    }
     args::cleanup();
     local_ptr::cleanup();
 }
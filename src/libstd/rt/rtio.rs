@@ -19,14 +19,17 @@ use path::Path;
 use result::{Result, Ok, Err};
 use rt::task::Task;
 use rt::local::Local;
+use task;
 
 use ai = io::net::addrinfo;
 use io;
 use io::IoError;
 use io::net::ip::{IpAddr, SocketAddr};
+use io::net::SocketOption;
+use io::net::unix::Credentials;
 use io::process::{ProcessConfig, ProcessExit};
 use io::signal::Signum;
-use io::{FileMode, FileAccess, FileStat, FilePermission};
+use io::{FileMode, FileAccess, FileStat, FilePermission, LockKind};
 use io::{SeekStyle};
 
 pub trait Callback {
@@ -119,6 +122,10 @@ impl<'a> LocalIo<'a> {
     pub fn maybe_raise<T>(f: |io: &mut IoFactory| -> Result<T, IoError>)
         -> Option<T>
     {
+        if task::cancelled() {
+            io::io_error::cond.raise(io::standard_error(io::Cancelled));
+            return None;
+        }
         match LocalIo::borrow() {
             None => {
                 io::io_error::cond.raise(io::standard_error(io::IoUnavailable));
@@ -153,12 +160,21 @@ impl<'a> LocalIo<'a> {
 
 pub trait IoFactory {
     // networking
-    fn tcp_connect(&mut self, addr: SocketAddr) -> Result<~RtioTcpStream, IoError>;
-    fn tcp_bind(&mut self, addr: SocketAddr) -> Result<~RtioTcpListener, IoError>;
-    fn udp_bind(&mut self, addr: SocketAddr) -> Result<~RtioUdpSocket, IoError>;
+    // The socket options are applied before binding or connecting, and the
+    // stream is bound to `local` (if any) before connecting.
+    fn tcp_connect(&mut self, addr: SocketAddr, local: Option<SocketAddr>,
+                   opts: &[SocketOption]) -> Result<~RtioTcpStream, IoError>;
+    fn tcp_bind(&mut self, addr: SocketAddr,
+                opts: &[SocketOption]) -> Result<~RtioTcpListener, IoError>;
+    fn udp_bind(&mut self, addr: SocketAddr,
+                opts: &[SocketOption]) -> Result<~RtioUdpSocket, IoError>;
     fn unix_bind(&mut self, path: &CString) ->
         Result<~RtioUnixListener, IoError>;
     fn unix_connect(&mut self, path: &CString) -> Result<~RtioPipe, IoError>;
+    fn unix_bind_abstract(&mut self, name: &[u8]) ->
+        Result<~RtioUnixListener, IoError>;
+    fn unix_connect_abstract(&mut self, name: &[u8]) ->
+        Result<~RtioPipe, IoError>;
     fn get_host_addresses(&mut self, host: Option<&str>, servname: Option<&str>,
                           hint: Option<ai::Hint>) -> Result<~[ai::Info], IoError>;
 
@@ -209,6 +225,13 @@ pub trait RtioTcpAcceptor : RtioSocket {
 pub trait RtioTcpStream : RtioSocket {
     fn read(&mut self, buf: &mut [u8]) -> Result<uint, IoError>;
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError>;
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError>;
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError>;
+    /// Sends up to `len` bytes of the file `fd`, starting at `offset`, to
+    /// the peer without copying them through userspace. Returns the number
+    /// of bytes sent, or an `IoUnavailable` error if the platform has no
+    /// such mechanism.
+    fn sendfile(&mut self, fd: c_int, offset: u64, len: u64) -> Result<u64, IoError>;
     fn peer_name(&mut self) -> Result<SocketAddr, IoError>;
     fn control_congestion(&mut self) -> Result<(), IoError>;
     fn nodelay(&mut self) -> Result<(), IoError>;
@@ -218,6 +241,11 @@ pub trait RtioTcpStream : RtioSocket {
 
 pub trait RtioSocket {
     fn socket_name(&mut self) -> Result<SocketAddr, IoError>;
+    /// Toggles non-blocking mode, in which operations which would otherwise
+    /// block fail immediately with a `ResourceUnavailable` error.
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), IoError>;
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError>;
+    fn fd(&self) -> c_int;
 }
 
 pub trait RtioUdpSocket : RtioSocket {
@@ -246,6 +274,8 @@ pub trait RtioTimer {
 pub trait RtioFileStream {
     fn read(&mut self, buf: &mut [u8]) -> Result<int, IoError>;
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError>;
+    fn readv(&mut self, bufs: &mut [&mut [u8]]) -> Result<uint, IoError>;
+    fn writev(&mut self, bufs: &[&[u8]]) -> Result<(), IoError>;
     fn pread(&mut self, buf: &mut [u8], offset: u64) -> Result<int, IoError>;
     fn pwrite(&mut self, buf: &[u8], offset: u64) -> Result<(), IoError>;
     fn seek(&mut self, pos: i64, whence: SeekStyle) -> Result<u64, IoError>;
@@ -253,6 +283,13 @@ pub trait RtioFileStream {
     fn fsync(&mut self) -> Result<(), IoError>;
     fn datasync(&mut self) -> Result<(), IoError>;
     fn truncate(&mut self, offset: i64) -> Result<(), IoError>;
+    /// Places an advisory lock on the whole file. If `block` is false and
+    /// the lock cannot be acquired immediately, a `ResourceUnavailable`
+    /// error is returned.
+    fn lock(&mut self, kind: LockKind, block: bool) -> Result<(), IoError>;
+    fn unlock(&mut self) -> Result<(), IoError>;
+    /// Returns the underlying OS file descriptor of this stream.
+    fn fd(&self) -> c_int;
 }
 
 pub trait RtioProcess {
@@ -264,6 +301,21 @@ pub trait RtioProcess {
 pub trait RtioPipe {
     fn read(&mut self, buf: &mut [u8]) -> Result<uint, IoError>;
     fn write(&mut self, buf: &[u8]) -> Result<(), IoError>;
+    /// Sets an option on the underlying socket, failing if this pipe isn't
+    /// backed by a socket.
+    fn set_option(&mut self, opt: SocketOption) -> Result<(), IoError>;
+    /// Returns the credentials of the peer process, failing if this pipe
+    /// isn't a Unix domain socket.
+    fn peer_credentials(&mut self) -> Result<Credentials, IoError>;
+    /// Writes all of `buf`, passing the file descriptors `fds` to the peer
+    /// along with it.
+    fn send_fds(&mut self, buf: &[u8], fds: &[c_int]) -> Result<(), IoError>;
+    /// Reads into `buf`, appending any file descriptors passed by the peer
+    /// along with the data to `fds`. Returns the number of bytes read and
+    /// whether the descriptors were truncated, in which case none of them
+    /// are appended.
+    fn recv_fds(&mut self, buf: &mut [u8],
+                fds: &mut ~[c_int]) -> Result<(uint, bool), IoError>;
 }
 
 pub trait RtioUnixListener {
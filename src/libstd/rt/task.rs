@@ -16,6 +16,7 @@
 use any::AnyOwnExt;
 use borrow;
 use cast;
+use cc;
 use cleanup;
 use clone::Clone;
 use io::Writer;
@@ -34,7 +35,7 @@ use rt::unwind::Unwinder;
 use send_str::SendStr;
 use sync::arc::UnsafeArc;
 use sync::atomics::{AtomicUint, SeqCst};
-use task::{TaskResult, TaskOpts};
+use task::{TaskResult, TaskOpts, CancelToken};
 use unstable::finally::Finally;
 
 /// The Task struct represents all state associated with a rust
@@ -55,6 +56,8 @@ pub struct Task {
     stdout: Option<~Writer>,
     stderr: Option<~Writer>,
 
+    cancel: Option<CancelToken>,
+
     priv imp: Option<~Runtime>,
 }
 
@@ -92,6 +95,7 @@ impl Task {
             logger: None,
             stdout: None,
             stderr: None,
+            cancel: None,
             imp: None,
         }
     }
@@ -135,6 +139,12 @@ impl Task {
                 // destructors can run arbitrary code.
                 close_outputs();
 
+                // The possible roots of `Cc` cycles are buffered in
+                // task-local storage, so the garbage cycles they lead to
+                // must be collected before it goes away. Collections may run
+                // user dtors which buffer more roots, hence the loop.
+                while cc::collect_cycles() > 0 {}
+
                 // First, destroy task-local storage. This may run user dtors.
                 //
                 // FIXME #8302: Dear diary. I'm so tired and confused.
@@ -162,6 +172,7 @@ impl Task {
                 };
                 drop(task);
                 drop(storage_map);
+                while cc::collect_cycles() > 0 {}
 
                 // Destroy remaining boxes. Also may run user dtors.
                 unsafe { cleanup::annihilate(); }
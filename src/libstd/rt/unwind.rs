@@ -59,26 +59,31 @@ use any::{Any, AnyRefExt};
 use c_str::CString;
 use cast;
 use kinds::Send;
+use local_data;
 use option::{Some, None, Option};
 use prelude::drop;
 use ptr::RawPtr;
 use result::{Err, Ok};
+use rt::backtrace::Backtrace;
+use rt::env;
 use rt::local::Local;
 use rt::task::Task;
 use str::Str;
+use sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
 use task::TaskResult;
 use unstable::intrinsics;
 use util;
 
 use uw = self::libunwind;
 
-mod libunwind {
+#[doc(hidden)] // also used by rt::backtrace
+pub mod libunwind {
     //! Unwind library interface
 
     #[allow(non_camel_case_types)];
     #[allow(dead_code)] // these are just bindings
 
-    use libc::{uintptr_t};
+    use libc::{uintptr_t, c_void};
 
     #[cfg(not(target_arch = "arm"))]
     #[repr(C)]
@@ -138,9 +143,51 @@ mod libunwind {
     pub type _Unwind_Exception_Cleanup_Fn = extern "C" fn(unwind_code: _Unwind_Reason_Code,
                                                           exception: *_Unwind_Exception);
 
+    pub type _Unwind_Trace_Fn = extern "C" fn(context: *_Unwind_Context,
+                                              arg: *c_void) -> _Unwind_Reason_Code;
+
     extern "C" {
         pub fn _Unwind_RaiseException(exception: *_Unwind_Exception) -> _Unwind_Reason_Code;
         pub fn _Unwind_DeleteException(exception: *_Unwind_Exception);
+        pub fn _Unwind_Backtrace(trace: _Unwind_Trace_Fn,
+                                 trace_argument: *c_void) -> _Unwind_Reason_Code;
+
+        #[cfg(not(target_arch = "arm"))]
+        pub fn _Unwind_GetIP(context: *_Unwind_Context) -> uintptr_t;
+    }
+
+    // ARM EHABI doesn't export _Unwind_GetIP, which is a macro in its headers
+    // reading the program counter out of the virtual register set.
+
+    #[cfg(target_arch = "arm")]
+    #[repr(C)]
+    enum _Unwind_VRS_RegClass {
+        _UVRSC_CORE = 0,
+    }
+
+    #[cfg(target_arch = "arm")]
+    #[repr(C)]
+    enum _Unwind_VRS_DataRepresentation {
+        _UVRSD_UINT32 = 0,
+    }
+
+    #[cfg(target_arch = "arm")]
+    extern "C" {
+        fn _Unwind_VRS_Get(context: *_Unwind_Context,
+                           regclass: _Unwind_VRS_RegClass,
+                           regno: _Unwind_Word,
+                           repr: _Unwind_VRS_DataRepresentation,
+                           data: *mut c_void) -> _Unwind_Reason_Code;
+    }
+
+    #[cfg(target_arch = "arm")]
+    pub unsafe fn _Unwind_GetIP(context: *_Unwind_Context) -> uintptr_t {
+        let mut val: _Unwind_Word = 0;
+        let ptr = &mut val as *mut _Unwind_Word;
+        _Unwind_VRS_Get(context, _UVRSC_CORE, 15, _UVRSD_UINT32,
+                        ptr as *mut c_void);
+        // The low bit only indicates the Thumb instruction set
+        val & !1
     }
 }
 
@@ -391,18 +438,17 @@ pub fn begin_unwind<M: Any + Send>(msg: M, file: &'static str, line: uint) -> !
     // we do start doing this, then we should propagate this allocation to
     // be performed in the parent of this task instead of the task that's
     // failing.
-    let msg = ~msg as ~Any;
+    begin_unwind_any(~msg as ~Any, file, line)
+}
 
+/// Fails the current task with a cause which is already boxed, such as the
+/// cause of the failure of another task. Unlike `begin_unwind`, the cause
+/// isn't wrapped in another box, so it can be inspected just like the
+/// original one.
+#[inline(never)] #[cold]
+pub fn begin_unwind_any(msg: ~Any, file: &'static str, line: uint) -> ! {
     let mut task;
     {
-        let msg_s = match msg.as_ref::<&'static str>() {
-            Some(s) => *s,
-            None => match msg.as_ref::<~str>() {
-                Some(s) => s.as_slice(),
-                None => "~Any",
-            }
-        };
-
         // It is assumed that all reasonable rust code will have a local task at
         // all times. This means that this `try_take` will succeed almost all of
         // the time. There are border cases, however, when the runtime has
@@ -414,42 +460,35 @@ pub fn begin_unwind<M: Any + Send>(msg: M, file: &'static str, line: uint) -> !
         task = match opt_task {
             Some(t) => t,
             None => {
-                rterrln!("failed at '{}', {}:{}", msg_s, file, line);
+                rterrln!("failed at '{}', {}:{}", cause_str(&*msg), file, line);
                 unsafe { intrinsics::abort() }
             }
         };
 
-        // See comments in io::stdio::with_task_stdout as to why we have to be
-        // careful when using an arbitrary I/O handle from the task. We
-        // essentially need to dance to make sure when a task is in TLS when
-        // running user code.
+        // The hook is user code, so it runs with the task in TLS. The name is
+        // taken out beforehand so that it can be lent to the hook.
         let name = task.name.take();
+        Local::put(task);
         {
-            let n = name.as_ref().map(|n| n.as_slice()).unwrap_or("<unnamed>");
-
-            match task.stderr.take() {
-                Some(mut stderr) => {
-                    Local::put(task);
-                    format_args!(|args| ::fmt::writeln(stderr, args),
-                                 "task '{}' failed at '{}', {}:{}",
-                                 n, msg_s, file, line);
-                    task = Local::take();
-
-                    match util::replace(&mut task.stderr, Some(stderr)) {
-                        Some(prev) => {
-                            Local::put(task);
-                            drop(prev);
-                            task = Local::take();
-                        }
-                        None => {}
-                    }
-                }
-                None => {
-                    rterrln!("task '{}' failed at '{}', {}:{}", n, msg_s,
-                             file, line);
+            let info = FailureInfo {
+                cause: &*msg,
+                file: file,
+                line: line,
+                task_name: name.as_ref().map(|n| n.as_slice()),
+            };
+            // A hook which fails itself would recurse forever, so failures
+            // from within the hook are reported by the default hook instead.
+            let in_hook = local_data::get(in_failure_hook, |b| b.is_some());
+            match failure_hook() {
+                Some(hook) if !in_hook => {
+                    local_data::set(in_failure_hook, ());
+                    run_failure_hook(hook, &info);
+                    local_data::pop(in_failure_hook);
                 }
+                _ => default_failure_hook(&info),
             }
         }
+        task = Local::take();
         task.name = name;
 
         if task.unwinder.unwinding {
@@ -472,3 +511,128 @@ pub fn begin_unwind<M: Any + Send>(msg: M, file: &'static str, line: uint) -> !
         (*task).unwinder.begin_unwind(msg);
     }
 }
+
+// Runs the failure hook of a task which is about to start unwinding. If the
+// hook fails itself, that failure (which has already been reported) is caught
+// here and forgotten, so that the original failure carries on as usual.
+fn run_failure_hook(hook: FailureHook, info: &FailureInfo) {
+    unsafe {
+        let task: *mut Task = Local::unsafe_borrow();
+        (*task).unwinder.try(|| hook(info));
+        if (*task).unwinder.unwinding {
+            (*task).unwinder.unwinding = false;
+            drop((*task).unwinder.cause.take());
+        }
+    }
+}
+
+/// The description of a task failure which is given to the failure hook.
+pub struct FailureInfo<'a> {
+    /// The value the task failed with, usually a `&'static str` or a `~str`.
+    cause: &'a Any,
+    /// The file in which the failure occurred.
+    file: &'static str,
+    /// The line on which the failure occurred.
+    line: uint,
+    /// The name of the failing task, if it has one.
+    task_name: Option<&'a str>,
+}
+
+/// A function which reports task failures, see `set_failure_hook`.
+pub type FailureHook = fn(&FailureInfo);
+
+// The installed hook, transmuted to a word, or 0 for the default hook
+static mut FAILURE_HOOK: AtomicUint = INIT_ATOMIC_UINT;
+
+local_data_key!(in_failure_hook: ())
+
+/// Replaces the process-wide failure hook, returning the previous hook if one
+/// was set.
+///
+/// The hook is called by every failing task before it starts unwinding, in
+/// place of printing the failure message to the task's stderr. It runs in the
+/// failing task, so it may use I/O and task-local data. A failure inside the
+/// hook is reported with `default_failure_hook` and then ignored: the task
+/// goes on to unwind with its original failure.
+pub fn set_failure_hook(hook: FailureHook) -> Option<FailureHook> {
+    unsafe { from_word(FAILURE_HOOK.swap(cast::transmute(hook), SeqCst)) }
+}
+
+/// Removes the process-wide failure hook, returning it if one was set.
+/// Failures are reported by `default_failure_hook` afterwards.
+pub fn take_failure_hook() -> Option<FailureHook> {
+    unsafe { from_word(FAILURE_HOOK.swap(0, SeqCst)) }
+}
+
+fn failure_hook() -> Option<FailureHook> {
+    unsafe { from_word(FAILURE_HOOK.load(SeqCst)) }
+}
+
+unsafe fn from_word(word: uint) -> Option<FailureHook> {
+    if word == 0 { None } else { Some(cast::transmute(word)) }
+}
+
+/// The failure reporting used when no hook is set: prints the failure message,
+/// and a backtrace if `RUST_BACKTRACE` is set, to the task's stderr.
+pub fn default_failure_hook(info: &FailureInfo) {
+    let n = info.task_name.unwrap_or("<unnamed>");
+    let msg = cause_str(info.cause);
+    let backtrace = if env::backtrace() {
+        Some(Backtrace::capture())
+    } else {
+        None
+    };
+
+    // See comments in io::stdio::with_task_stdout as to why we have to be
+    // careful when using an arbitrary I/O handle from the task. We
+    // essentially need to dance to make sure when a task is in TLS when
+    // running user code.
+    let opt_task: Option<~Task> = Local::try_take();
+    let stderr = match opt_task {
+        Some(mut task) => {
+            let stderr = task.stderr.take();
+            Local::put(task);
+            stderr
+        }
+        None => None,
+    };
+
+    match stderr {
+        Some(mut stderr) => {
+            format_args!(|args| ::fmt::writeln(stderr, args),
+                         "task '{}' failed at '{}', {}:{}",
+                         n, msg, info.file, info.line);
+            match backtrace {
+                Some(ref bt) => {
+                    format_args!(|args| ::fmt::writeln(stderr, args),
+                                 "{}", *bt);
+                }
+                None => {}
+            }
+
+            let mut task: ~Task = Local::take();
+            let prev = util::replace(&mut task.stderr, Some(stderr));
+            Local::put(task);
+            drop(prev);
+        }
+        None => {
+            rterrln!("task '{}' failed at '{}', {}:{}", n, msg,
+                     info.file, info.line);
+            match backtrace {
+                Some(ref bt) => rterrln!("{}", *bt),
+                None => {}
+            }
+        }
+    }
+}
+
+// The message of a failure, for causes which are strings
+fn cause_str<'a>(cause: &'a Any) -> &'a str {
+    match cause.as_ref::<&'static str>() {
+        Some(s) => *s,
+        None => match cause.as_ref::<~str>() {
+            Some(s) => s.as_slice(),
+            None => "~Any",
+        }
+    }
+}
@@ -20,13 +20,19 @@
 //! synchronization. The only guarantee provided to you by this class is that
 //! the underlying data will remain valid (not free'd) so long as the reference
 //! count is greater than one.
+//!
+//! This module also provides `AtomicArc`, a cell holding an `UnsafeArc` which
+//! can be read and replaced by many tasks at once without locking.
 
 use cast;
 use clone::Clone;
 use kinds::Send;
+use mem;
 use ops::Drop;
 use ptr::RawPtr;
+use result::{Result, Ok, Err};
 use sync::atomics::{AtomicUint, SeqCst, Relaxed, Acquire};
+use task;
 use vec;
 
 /// An atomically reference counted pointer.
@@ -127,11 +133,187 @@ impl<T> Drop for UnsafeArc<T>{
     }
 }
 
+/// A shared, mutable slot holding an `UnsafeArc`, which can be loaded from and
+/// swapped atomically.
+///
+/// The slot is a single word holding the address of a small node which owns a
+/// reference to the current value. As the node is at least word-aligned, the
+/// low bits of the word are free, and they count the loads which are in the
+/// middle of taking a new reference to the current value. Whoever swaps the
+/// node out takes over the count of those loads, which keeps the node alive
+/// until all of them have finished.
+///
+/// Every value stored gets a node of its own, so a load can always tell
+/// whether the node which it announced itself on was swapped out, even if the
+/// same value has been stored again since.
+///
+/// The number of loads which can be in progress at once is limited by the
+/// number of free bits: 3 loads on 32-bit platforms and 7 on 64-bit ones.
+/// Further loads yield to other tasks until one of those has finished, which
+/// takes a handful of instructions.
+pub struct AtomicArc<T> {
+    priv state: AtomicUint,
+}
+
+// The node which an `AtomicArc` points to
+struct Node<T> {
+    arc: UnsafeArc<T>,
+    // Once the node has been swapped out, the number of loads announced on it
+    // which are yet to finish. Loads may finish before the replacer has taken
+    // over their count, so this briefly wraps around below zero.
+    pending: AtomicUint,
+}
+
+impl<T: Send> AtomicArc<T> {
+    /// Creates a new slot holding `arc`.
+    pub fn new(arc: UnsafeArc<T>) -> AtomicArc<T> {
+        AtomicArc { state: AtomicUint::new(new_node(arc)) }
+    }
+
+    /// Returns a new reference to the current value of this slot.
+    pub fn load(&self) -> UnsafeArc<T> {
+        let word = self.announce();
+        let node = (word & !tag_mask()) as *mut Node<T>;
+        let arc = unsafe { (*node).arc.clone() };
+        self.retract(word);
+        arc
+    }
+
+    /// Replaces the value of this slot with `arc`, returning the old one.
+    pub fn swap(&self, arc: UnsafeArc<T>) -> UnsafeArc<T> {
+        let old = self.state().swap(new_node(arc), SeqCst);
+        unsafe { detach(old) }
+    }
+
+    /// Replaces the value of this slot with `arc`.
+    pub fn store(&self, arc: UnsafeArc<T>) {
+        let _old = self.swap(arc);
+    }
+
+    /// Replaces the value of this slot with `new` if it still holds the same
+    /// data as `current`. On success the old value is returned, otherwise
+    /// `new` is given back.
+    pub fn compare_and_swap(&self, current: &UnsafeArc<T>,
+                            new: UnsafeArc<T>) -> Result<UnsafeArc<T>, UnsafeArc<T>> {
+        let state = self.state();
+        let mask = tag_mask();
+        let new = new_node(new);
+        loop {
+            // Hold on to the current node like a load does, so that its value
+            // can be looked at
+            let word = self.announce();
+            let node = (word & !mask) as *mut Node<T>;
+            if unsafe { (*node).arc.data } != current.data {
+                self.retract(word);
+                let new: ~Node<T> = unsafe { cast::transmute(new) };
+                let Node { arc, .. } = *new;
+                return Err(arc)
+            }
+            loop {
+                let now = state.load(SeqCst);
+                if now & !mask != word & !mask { break }
+                // Retry if only the count of pending loads changed
+                if state.compare_and_swap(now, new, SeqCst) == now {
+                    // Our own announcement was handed over along with the
+                    // others, and the node stays alive until it's released
+                    let old = unsafe { detach(now) };
+                    unsafe { release::<T>(word & !mask) }
+                    return Ok(old)
+                }
+            }
+            // Someone else swapped the node out, so compare against the new one
+            unsafe { release::<T>(word & !mask) }
+        }
+    }
+
+    // Announces a load of the current node, which keeps the node alive until
+    // the announcement is retracted. Returns the word announced on.
+    fn announce(&self) -> uint {
+        let state = self.state();
+        let mask = tag_mask();
+        loop {
+            let cur = state.load(SeqCst);
+            if cur & mask == mask {
+                // Every bit of the count is taken by another load
+                task::deschedule();
+            } else if state.compare_and_swap(cur, cur + 1, SeqCst) == cur {
+                return cur
+            }
+        }
+    }
+
+    // Retracts an announcement made on the node of `word`. If the node has
+    // been swapped out in the meantime, the replacer took over the
+    // announcement, and it is released on the node instead. As the node can't
+    // be freed before that, it can't have been reused for another value.
+    fn retract(&self, word: uint) {
+        let state = self.state();
+        let mask = tag_mask();
+        loop {
+            let now = state.load(SeqCst);
+            if now & !mask != word & !mask {
+                unsafe { release::<T>(word & !mask) }
+                return
+            }
+            if state.compare_and_swap(now, now - 1, SeqCst) == now {
+                return
+            }
+        }
+    }
+
+    fn state<'a>(&'a self) -> &'a mut AtomicUint {
+        unsafe { cast::transmute_mut(&self.state) }
+    }
+}
+
+#[unsafe_destructor]
+impl<T: Send> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        // No loads can be in progress, so this just frees the current node
+        let _old: UnsafeArc<T> = unsafe { detach(self.state.load(SeqCst)) };
+    }
+}
+
+// The bits of a word holding a pointer to a `Node` which are always zero
+fn tag_mask() -> uint {
+    mem::min_align_of::<AtomicUint>() - 1
+}
+
+fn new_node<T: Send>(arc: UnsafeArc<T>) -> uint {
+    let node = ~Node { arc: arc, pending: AtomicUint::new(0) };
+    let word: uint = unsafe { cast::transmute(node) };
+    assert_eq!(word & tag_mask(), 0);
+    word
+}
+
+// Takes a node which was swapped out of an `AtomicArc` out of service,
+// returning a new reference to its value. The node itself is freed once the
+// loads which it counts have finished.
+unsafe fn detach<T: Send>(word: uint) -> UnsafeArc<T> {
+    let node = (word & !tag_mask()) as *mut Node<T>;
+    let loads = word & tag_mask();
+    let arc = (*node).arc.clone();
+    if (*node).pending.fetch_add(loads, SeqCst) + loads == 0 {
+        let _: ~Node<T> = cast::transmute(node);
+    }
+    arc
+}
+
+// Releases a load which was announced on a node that has since been swapped
+// out, freeing the node if it was the last one.
+unsafe fn release<T: Send>(node: uint) {
+    let node = node as *mut Node<T>;
+    if (*node).pending.fetch_sub(1, SeqCst) == 1 {
+        let _: ~Node<T> = cast::transmute(node);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use prelude::*;
-    use super::UnsafeArc;
+    use super::{UnsafeArc, AtomicArc};
     use mem::size_of;
+    use task;
 
     #[test]
     fn test_size() {
@@ -149,4 +331,104 @@ mod tests {
         let x = UnsafeArc::newN(~~"hello", 10);
         assert_eq!(x.len(), 10)
     }
+
+    #[test]
+    fn atomic_arc_smoke() {
+        let slot = AtomicArc::new(UnsafeArc::new(1));
+        let one = slot.load();
+        unsafe { assert_eq!(*one.get(), 1); }
+
+        let old = slot.swap(UnsafeArc::new(2));
+        unsafe {
+            assert_eq!(*old.get(), 1);
+            assert_eq!(*slot.load().get(), 2);
+        }
+
+        let two = slot.load();
+        assert!(slot.compare_and_swap(&one, UnsafeArc::new(3)).is_err());
+        assert!(slot.compare_and_swap(&two, UnsafeArc::new(4)).is_ok());
+        unsafe { assert_eq!(*slot.load().get(), 4); }
+    }
+
+    #[test]
+    fn atomic_arc_swap_restore() {
+        use sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+
+        static NTASKS: uint = 4;
+        static NITERS: uint = 1000;
+        static mut DROPS: AtomicUint = INIT_ATOMIC_UINT;
+
+        struct Canary { original: bool }
+        impl Drop for Canary {
+            fn drop(&mut self) {
+                if self.original { unsafe { DROPS.fetch_add(1, SeqCst); } }
+            }
+        }
+
+        let slot = UnsafeArc::new(AtomicArc::new(UnsafeArc::new(Canary {
+            original: true,
+        })));
+        let (port, chan) = SharedChan::new();
+        for _ in range(0, NTASKS) {
+            let slot = slot.clone();
+            let chan = chan.clone();
+            do task::spawn {
+                {
+                    let slot = unsafe { &*slot.get() };
+                    for _ in range(0, NITERS) {
+                        let _value = slot.load();
+                    }
+                }
+                drop(slot);
+                chan.send(());
+            }
+        }
+
+        // Swap the original value out and straight back in, which looks the
+        // same to a load which only compares addresses
+        {
+            let slot = unsafe { &*slot.get() };
+            for _ in range(0, NITERS) {
+                let old = slot.swap(UnsafeArc::new(Canary { original: false }));
+                slot.store(old);
+                task::deschedule();
+            }
+        }
+        for _ in range(0, NTASKS) {
+            port.recv();
+        }
+
+        // The original value must neither have been freed early nor leaked
+        unsafe { assert_eq!(DROPS.load(SeqCst), 0); }
+        drop(slot);
+        unsafe { assert_eq!(DROPS.load(SeqCst), 1); }
+    }
+
+    #[test]
+    fn atomic_arc_stress() {
+        static NTASKS: uint = 8;
+        static NITERS: uint = 1000;
+
+        let slot = UnsafeArc::new(AtomicArc::new(UnsafeArc::new(~0u)));
+        let (port, chan) = SharedChan::new();
+        for i in range(0, NTASKS) {
+            let slot = slot.clone();
+            let chan = chan.clone();
+            do task::spawn {
+                let slot = unsafe { &*slot.get() };
+                for j in range(0, NITERS) {
+                    if i % 2 == 0 {
+                        slot.store(UnsafeArc::new(~j));
+                    } else {
+                        let value = slot.load();
+                        unsafe { assert!(**value.get() < NITERS); }
+                    }
+                }
+                chan.send(());
+            }
+        }
+        for _ in range(0, NTASKS) {
+            port.recv();
+        }
+    }
 }
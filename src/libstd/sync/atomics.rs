@@ -16,7 +16,9 @@
  * operation. These orderings are the same as C++11 atomic orderings
  * [http://gcc.gnu.org/wiki/Atomic/GCCMM/AtomicSync]
  *
- * All atomic types are a single word in size.
+ * `AtomicInt`, `AtomicUint` and the other basic types are a single word in
+ * size, while the fixed-width integer types such as `AtomicU8` and `AtomicI64`
+ * have the size of the integer which they wrap.
  */
 
 #[allow(missing_doc)];
@@ -63,7 +65,9 @@ pub struct AtomicUint {
  * An unsafe atomic pointer. Only supports basic atomic operations
  */
 pub struct AtomicPtr<T> {
-    priv p: *mut T,
+    // Stored as an integer, as LLVM only supports read-modify-write
+    // operations on integers
+    priv p: uint,
     priv nocopy: NonCopyable
 }
 
@@ -72,7 +76,7 @@ pub struct AtomicPtr<T> {
  */
 #[unsafe_no_drop_flag]
 pub struct AtomicOption<T> {
-    priv p: *mut u8
+    priv p: uint
 }
 
 pub enum Ordering {
@@ -213,6 +217,44 @@ impl AtomicInt {
     pub fn fetch_sub(&mut self, val: int, order: Ordering) -> int {
         unsafe { atomic_sub(&mut self.v, val, order) }
     }
+
+    /// Returns the old value (like __sync_fetch_and_and).
+    #[inline]
+    pub fn fetch_and(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_and(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_nand).
+    #[inline]
+    pub fn fetch_nand(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_nand(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_or).
+    #[inline]
+    pub fn fetch_or(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_or(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_xor).
+    #[inline]
+    pub fn fetch_xor(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_xor(&mut self.v, val, order) }
+    }
+
+    /// Stores the maximum of the current value and `val`, returning the old
+    /// value.
+    #[inline]
+    pub fn fetch_max(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_max(&mut self.v, val, order) }
+    }
+
+    /// Stores the minimum of the current value and `val`, returning the old
+    /// value.
+    #[inline]
+    pub fn fetch_min(&mut self, val: int, order: Ordering) -> int {
+        unsafe { atomic_min(&mut self.v, val, order) }
+    }
 }
 
 impl AtomicUint {
@@ -251,31 +293,174 @@ impl AtomicUint {
     pub fn fetch_sub(&mut self, val: uint, order: Ordering) -> uint {
         unsafe { atomic_sub(&mut self.v, val, order) }
     }
+
+    /// Returns the old value (like __sync_fetch_and_and).
+    #[inline]
+    pub fn fetch_and(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_and(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_nand).
+    #[inline]
+    pub fn fetch_nand(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_nand(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_or).
+    #[inline]
+    pub fn fetch_or(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_or(&mut self.v, val, order) }
+    }
+
+    /// Returns the old value (like __sync_fetch_and_xor).
+    #[inline]
+    pub fn fetch_xor(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_xor(&mut self.v, val, order) }
+    }
+
+    /// Stores the maximum of the current value and `val`, returning the old
+    /// value.
+    #[inline]
+    pub fn fetch_max(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_umax(&mut self.v, val, order) }
+    }
+
+    /// Stores the minimum of the current value and `val`, returning the old
+    /// value.
+    #[inline]
+    pub fn fetch_min(&mut self, val: uint, order: Ordering) -> uint {
+        unsafe { atomic_umin(&mut self.v, val, order) }
+    }
 }
 
+// The fixed-width integer types. These are only available once the compiler
+// supports atomic intrinsics on types other than `int`.
+macro_rules! atomic_int(
+    ($name:ident, $init:ident, $T:ty, $max:ident, $min:ident) => (
+        #[cfg(not(stage0))]
+        pub struct $name {
+            priv v: $T,
+            priv nocopy: NonCopyable
+        }
+
+        #[cfg(not(stage0))]
+        pub static $init: $name = $name { v: 0, nocopy: NonCopyable };
+
+        #[cfg(not(stage0))]
+        impl $name {
+            pub fn new(v: $T) -> $name {
+                $name { v: v, nocopy: NonCopyable }
+            }
+
+            #[inline]
+            pub fn load(&self, order: Ordering) -> $T {
+                unsafe { atomic_load(&self.v, order) }
+            }
+
+            #[inline]
+            pub fn store(&mut self, val: $T, order: Ordering) {
+                unsafe { atomic_store(&mut self.v, val, order); }
+            }
+
+            #[inline]
+            pub fn swap(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_swap(&mut self.v, val, order) }
+            }
+
+            #[inline]
+            pub fn compare_and_swap(&mut self, old: $T, new: $T,
+                                    order: Ordering) -> $T {
+                unsafe { atomic_compare_and_swap(&mut self.v, old, new, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_add).
+            #[inline]
+            pub fn fetch_add(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_add(&mut self.v, val, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_sub).
+            #[inline]
+            pub fn fetch_sub(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_sub(&mut self.v, val, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_and).
+            #[inline]
+            pub fn fetch_and(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_and(&mut self.v, val, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_nand).
+            #[inline]
+            pub fn fetch_nand(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_nand(&mut self.v, val, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_or).
+            #[inline]
+            pub fn fetch_or(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_or(&mut self.v, val, order) }
+            }
+
+            /// Returns the old value (like __sync_fetch_and_xor).
+            #[inline]
+            pub fn fetch_xor(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { atomic_xor(&mut self.v, val, order) }
+            }
+
+            /// Stores the maximum of the current value and `val`, returning
+            /// the old value.
+            #[inline]
+            pub fn fetch_max(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { $max(&mut self.v, val, order) }
+            }
+
+            /// Stores the minimum of the current value and `val`, returning
+            /// the old value.
+            #[inline]
+            pub fn fetch_min(&mut self, val: $T, order: Ordering) -> $T {
+                unsafe { $min(&mut self.v, val, order) }
+            }
+        }
+    )
+)
+
+atomic_int!(AtomicI8,  INIT_ATOMIC_I8,  i8,  atomic_max,  atomic_min)
+atomic_int!(AtomicI16, INIT_ATOMIC_I16, i16, atomic_max,  atomic_min)
+atomic_int!(AtomicI32, INIT_ATOMIC_I32, i32, atomic_max,  atomic_min)
+atomic_int!(AtomicI64, INIT_ATOMIC_I64, i64, atomic_max,  atomic_min)
+atomic_int!(AtomicU8,  INIT_ATOMIC_U8,  u8,  atomic_umax, atomic_umin)
+atomic_int!(AtomicU16, INIT_ATOMIC_U16, u16, atomic_umax, atomic_umin)
+atomic_int!(AtomicU32, INIT_ATOMIC_U32, u32, atomic_umax, atomic_umin)
+atomic_int!(AtomicU64, INIT_ATOMIC_U64, u64, atomic_umax, atomic_umin)
+
 impl<T> AtomicPtr<T> {
     pub fn new(p: *mut T) -> AtomicPtr<T> {
-        AtomicPtr { p:p, nocopy: NonCopyable }
+        AtomicPtr { p: p as uint, nocopy: NonCopyable }
     }
 
     #[inline]
     pub fn load(&self, order: Ordering) -> *mut T {
-        unsafe { atomic_load(&self.p, order) }
+        unsafe { atomic_load(&self.p, order) as *mut T }
     }
 
     #[inline]
     pub fn store(&mut self, ptr: *mut T, order: Ordering) {
-        unsafe { atomic_store(&mut self.p, ptr, order); }
+        unsafe { atomic_store(&mut self.p, ptr as uint, order); }
     }
 
     #[inline]
     pub fn swap(&mut self, ptr: *mut T, order: Ordering) -> *mut T {
-        unsafe { atomic_swap(&mut self.p, ptr, order) }
+        unsafe { atomic_swap(&mut self.p, ptr as uint, order) as *mut T }
     }
 
     #[inline]
     pub fn compare_and_swap(&mut self, old: *mut T, new: *mut T, order: Ordering) -> *mut T {
-        unsafe { atomic_compare_and_swap(&mut self.p, old, new, order) }
+        unsafe {
+            atomic_compare_and_swap(&mut self.p, old as uint, new as uint,
+                                    order) as *mut T
+        }
     }
 }
 
@@ -289,22 +474,16 @@ impl<T> AtomicOption<T> {
     }
 
     pub fn empty() -> AtomicOption<T> {
-        unsafe {
-            AtomicOption {
-                p: cast::transmute(0)
-            }
-        }
+        AtomicOption { p: 0 }
     }
 
     #[inline]
     pub fn swap(&mut self, val: ~T, order: Ordering) -> Option<~T> {
         unsafe {
             let val = cast::transmute(val);
-
             let p = atomic_swap(&mut self.p, val, order);
-            let pv : &uint = cast::transmute(&p);
 
-            if *pv == 0 {
+            if p == 0 {
                 None
             } else {
                 Some(cast::transmute(p))
@@ -315,7 +494,13 @@ impl<T> AtomicOption<T> {
     #[inline]
     pub fn take(&mut self, order: Ordering) -> Option<~T> {
         unsafe {
-            self.swap(cast::transmute(0), order)
+            let p = atomic_swap(&mut self.p, 0, order);
+
+            if p == 0 {
+                None
+            } else {
+                Some(cast::transmute(p))
+            }
         }
     }
 
@@ -326,9 +511,8 @@ impl<T> AtomicOption<T> {
     pub fn fill(&mut self, val: ~T, order: Ordering) -> Option<~T> {
         unsafe {
             let val = cast::transmute(val);
-            let expected = cast::transmute(0);
-            let oldval = atomic_compare_and_swap(&mut self.p, expected, val, order);
-            if oldval == expected {
+            let oldval = atomic_compare_and_swap(&mut self.p, 0, val, order);
+            if oldval == 0 {
                 None
             } else {
                 Some(cast::transmute(val))
@@ -340,7 +524,7 @@ impl<T> AtomicOption<T> {
     /// result does not get invalidated by another task after this returns.
     #[inline]
     pub fn is_empty(&mut self, order: Ordering) -> bool {
-        unsafe { atomic_load(&self.p, order) == cast::transmute(0) }
+        unsafe { atomic_load(&self.p, order) == 0 }
     }
 }
 
@@ -352,6 +536,7 @@ impl<T> Drop for AtomicOption<T> {
 }
 
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_store<T>(dst: &mut T, val: T, order:Ordering) {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -364,6 +549,17 @@ pub unsafe fn atomic_store<T>(dst: &mut T, val: T, order:Ordering) {
 }
 
 #[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_store<T>(dst: &mut T, val: T, order:Ordering) {
+    match order {
+        Release => intrinsics::atomic_store_rel(dst, val),
+        Relaxed => intrinsics::atomic_store_relaxed(dst, val),
+        _       => intrinsics::atomic_store(dst, val)
+    }
+}
+
+#[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_load<T>(dst: &T, order:Ordering) -> T {
     let dst = cast::transmute(dst);
 
@@ -375,6 +571,17 @@ pub unsafe fn atomic_load<T>(dst: &T, order:Ordering) -> T {
 }
 
 #[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_load<T>(dst: &T, order:Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_load_acq(dst),
+        Relaxed => intrinsics::atomic_load_relaxed(dst),
+        _       => intrinsics::atomic_load(dst)
+    }
+}
+
+#[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_swap<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -388,8 +595,21 @@ pub unsafe fn atomic_swap<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_swap<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_xchg_acq(dst, val),
+        Release => intrinsics::atomic_xchg_rel(dst, val),
+        AcqRel  => intrinsics::atomic_xchg_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_xchg_relaxed(dst, val),
+        _       => intrinsics::atomic_xchg(dst, val)
+    }
+}
+
 /// Returns the old value (like __sync_fetch_and_add).
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_add<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -403,8 +623,22 @@ pub unsafe fn atomic_add<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+/// Returns the old value (like __sync_fetch_and_add).
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_add<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_xadd_acq(dst, val),
+        Release => intrinsics::atomic_xadd_rel(dst, val),
+        AcqRel  => intrinsics::atomic_xadd_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_xadd_relaxed(dst, val),
+        _       => intrinsics::atomic_xadd(dst, val)
+    }
+}
+
 /// Returns the old value (like __sync_fetch_and_sub).
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_sub<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -418,7 +652,21 @@ pub unsafe fn atomic_sub<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+/// Returns the old value (like __sync_fetch_and_sub).
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_sub<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_xsub_acq(dst, val),
+        Release => intrinsics::atomic_xsub_rel(dst, val),
+        AcqRel  => intrinsics::atomic_xsub_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_xsub_relaxed(dst, val),
+        _       => intrinsics::atomic_xsub(dst, val)
+    }
+}
+
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_compare_and_swap<T>(dst:&mut T, old:T, new:T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let old = cast::transmute(old);
@@ -434,6 +682,19 @@ pub unsafe fn atomic_compare_and_swap<T>(dst:&mut T, old:T, new:T, order: Orderi
 }
 
 #[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_compare_and_swap<T>(dst:&mut T, old:T, new:T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_cxchg_acq(dst, old, new),
+        Release => intrinsics::atomic_cxchg_rel(dst, old, new),
+        AcqRel  => intrinsics::atomic_cxchg_acqrel(dst, old, new),
+        Relaxed => intrinsics::atomic_cxchg_relaxed(dst, old, new),
+        _       => intrinsics::atomic_cxchg(dst, old, new),
+    }
+}
+
+#[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_and<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -447,8 +708,20 @@ pub unsafe fn atomic_and<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_and<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_and_acq(dst, val),
+        Release => intrinsics::atomic_and_rel(dst, val),
+        AcqRel  => intrinsics::atomic_and_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_and_relaxed(dst, val),
+        _       => intrinsics::atomic_and(dst, val)
+    }
+}
 
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_nand<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -462,8 +735,20 @@ pub unsafe fn atomic_nand<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_nand<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_nand_acq(dst, val),
+        Release => intrinsics::atomic_nand_rel(dst, val),
+        AcqRel  => intrinsics::atomic_nand_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_nand_relaxed(dst, val),
+        _       => intrinsics::atomic_nand(dst, val)
+    }
+}
 
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_or<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -477,8 +762,20 @@ pub unsafe fn atomic_or<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_or<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_or_acq(dst, val),
+        Release => intrinsics::atomic_or_rel(dst, val),
+        AcqRel  => intrinsics::atomic_or_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_or_relaxed(dst, val),
+        _       => intrinsics::atomic_or(dst, val)
+    }
+}
 
 #[inline]
+#[cfg(stage0)]
 pub unsafe fn atomic_xor<T>(dst: &mut T, val: T, order: Ordering) -> T {
     let dst = cast::transmute(dst);
     let val = cast::transmute(val);
@@ -492,6 +789,133 @@ pub unsafe fn atomic_xor<T>(dst: &mut T, val: T, order: Ordering) -> T {
     })
 }
 
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_xor<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_xor_acq(dst, val),
+        Release => intrinsics::atomic_xor_rel(dst, val),
+        AcqRel  => intrinsics::atomic_xor_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_xor_relaxed(dst, val),
+        _       => intrinsics::atomic_xor(dst, val)
+    }
+}
+
+/// Signed maximum. Returns the old value.
+#[inline]
+#[cfg(stage0)]
+pub unsafe fn atomic_max<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    let dst = cast::transmute(dst);
+    let val = cast::transmute(val);
+
+    cast::transmute(match order {
+        Acquire => intrinsics::atomic_max_acq(dst, val),
+        Release => intrinsics::atomic_max_rel(dst, val),
+        AcqRel  => intrinsics::atomic_max_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_max_relaxed(dst, val),
+        _       => intrinsics::atomic_max(dst, val)
+    })
+}
+
+/// Signed maximum. Returns the old value.
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_max<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_max_acq(dst, val),
+        Release => intrinsics::atomic_max_rel(dst, val),
+        AcqRel  => intrinsics::atomic_max_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_max_relaxed(dst, val),
+        _       => intrinsics::atomic_max(dst, val)
+    }
+}
+
+/// Signed minimum. Returns the old value.
+#[inline]
+#[cfg(stage0)]
+pub unsafe fn atomic_min<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    let dst = cast::transmute(dst);
+    let val = cast::transmute(val);
+
+    cast::transmute(match order {
+        Acquire => intrinsics::atomic_min_acq(dst, val),
+        Release => intrinsics::atomic_min_rel(dst, val),
+        AcqRel  => intrinsics::atomic_min_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_min_relaxed(dst, val),
+        _       => intrinsics::atomic_min(dst, val)
+    })
+}
+
+/// Signed minimum. Returns the old value.
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_min<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_min_acq(dst, val),
+        Release => intrinsics::atomic_min_rel(dst, val),
+        AcqRel  => intrinsics::atomic_min_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_min_relaxed(dst, val),
+        _       => intrinsics::atomic_min(dst, val)
+    }
+}
+
+/// Unsigned maximum. Returns the old value.
+#[inline]
+#[cfg(stage0)]
+pub unsafe fn atomic_umax<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    let dst = cast::transmute(dst);
+    let val = cast::transmute(val);
+
+    cast::transmute(match order {
+        Acquire => intrinsics::atomic_umax_acq(dst, val),
+        Release => intrinsics::atomic_umax_rel(dst, val),
+        AcqRel  => intrinsics::atomic_umax_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_umax_relaxed(dst, val),
+        _       => intrinsics::atomic_umax(dst, val)
+    })
+}
+
+/// Unsigned maximum. Returns the old value.
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_umax<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_umax_acq(dst, val),
+        Release => intrinsics::atomic_umax_rel(dst, val),
+        AcqRel  => intrinsics::atomic_umax_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_umax_relaxed(dst, val),
+        _       => intrinsics::atomic_umax(dst, val)
+    }
+}
+
+/// Unsigned minimum. Returns the old value.
+#[inline]
+#[cfg(stage0)]
+pub unsafe fn atomic_umin<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    let dst = cast::transmute(dst);
+    let val = cast::transmute(val);
+
+    cast::transmute(match order {
+        Acquire => intrinsics::atomic_umin_acq(dst, val),
+        Release => intrinsics::atomic_umin_rel(dst, val),
+        AcqRel  => intrinsics::atomic_umin_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_umin_relaxed(dst, val),
+        _       => intrinsics::atomic_umin(dst, val)
+    })
+}
+
+/// Unsigned minimum. Returns the old value.
+#[inline]
+#[cfg(not(stage0))]
+pub unsafe fn atomic_umin<T>(dst: &mut T, val: T, order: Ordering) -> T {
+    match order {
+        Acquire => intrinsics::atomic_umin_acq(dst, val),
+        Release => intrinsics::atomic_umin_rel(dst, val),
+        AcqRel  => intrinsics::atomic_umin_acqrel(dst, val),
+        Relaxed => intrinsics::atomic_umin_relaxed(dst, val),
+        _       => intrinsics::atomic_umin(dst, val)
+    }
+}
 
 /**
  * An atomic fence.
@@ -509,7 +933,8 @@ pub unsafe fn atomic_xor<T>(dst: &mut T, val: T, order: Ordering) -> T {
  * `Release` semantics, participates in the global program order of the other
  * `SeqCst` operations and/or fences.
  *
- * Accepts `Acquire`, `Release`, `AcqRel` and `SeqCst` orderings.
+ * Accepts `Acquire`, `Release`, `AcqRel` and `SeqCst` orderings. A `Relaxed`
+ * fence is treated as a `SeqCst` one.
  */
 #[inline]
 pub fn fence(order: Ordering) {
@@ -517,12 +942,45 @@ pub fn fence(order: Ordering) {
         match order {
             Acquire => intrinsics::atomic_fence_acq(),
             Release => intrinsics::atomic_fence_rel(),
-            AcqRel  => intrinsics::atomic_fence_rel(),
+            AcqRel  => intrinsics::atomic_fence_acqrel(),
             _       => intrinsics::atomic_fence(),
         }
     }
 }
 
+/**
+ * A compiler-only memory barrier.
+ *
+ * This has the same ordering semantics as `fence`, but only with respect to
+ * code running on the current thread: it keeps the compiler from reordering
+ * memory accesses across it, but emits no instructions to keep the processor
+ * from doing so. This makes it suitable for synchronizing with signal
+ * handlers.
+ *
+ * Accepts `Acquire`, `Release`, `AcqRel` and `SeqCst` orderings. As with
+ * `fence`, a `Relaxed` fence is treated as a `SeqCst` one.
+ */
+#[inline]
+#[cfg(not(stage0))]
+pub fn compiler_fence(order: Ordering) {
+    unsafe {
+        match order {
+            Acquire => intrinsics::atomic_singlethreadfence_acq(),
+            Release => intrinsics::atomic_singlethreadfence_rel(),
+            AcqRel  => intrinsics::atomic_singlethreadfence_acqrel(),
+            _       => intrinsics::atomic_singlethreadfence(),
+        }
+    }
+}
+
+/// A stronger fence stands in for the compiler fence when building with a
+/// compiler which doesn't support it.
+#[inline]
+#[cfg(stage0)]
+pub fn compiler_fence(order: Ordering) {
+    fence(order)
+}
+
 #[cfg(test)]
 mod test {
     use option::*;
@@ -585,10 +1043,94 @@ mod test {
         assert_eq!(a.load(SeqCst),false);
     }
 
+    #[test]
+    fn int_bitwise() {
+        let mut a = AtomicInt::new(0b1100);
+        assert_eq!(a.fetch_and(0b1010, SeqCst), 0b1100);
+        assert_eq!(a.fetch_or(0b0001, SeqCst), 0b1000);
+        assert_eq!(a.fetch_xor(0b1111, SeqCst), 0b1001);
+        assert_eq!(a.fetch_nand(0b0110, SeqCst), 0b0110);
+        assert_eq!(a.load(SeqCst), !0b0110);
+    }
+
+    #[test]
+    fn int_max_min() {
+        let mut a = AtomicInt::new(-5);
+        assert_eq!(a.fetch_max(-10, SeqCst), -5);
+        assert_eq!(a.fetch_max(3, SeqCst), -5);
+        assert_eq!(a.fetch_min(-7, SeqCst), 3);
+        assert_eq!(a.load(SeqCst), -7);
+
+        let mut u = AtomicUint::new(5);
+        assert_eq!(u.fetch_max(10, Relaxed), 5);
+        assert_eq!(u.fetch_min(1, Relaxed), 10);
+        assert_eq!(u.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn fixed_width() {
+        let mut a = AtomicU8::new(250);
+        assert_eq!(a.fetch_add(10, SeqCst), 250);
+        assert_eq!(a.load(SeqCst), 4);
+        assert_eq!(a.fetch_max(200, SeqCst), 4);
+        assert_eq!(a.compare_and_swap(200, 7, SeqCst), 200);
+        assert_eq!(a.load(SeqCst), 7);
+
+        let mut b = AtomicI16::new(-1);
+        assert_eq!(b.fetch_min(-300, AcqRel), -1);
+        assert_eq!(b.swap(12, SeqCst), -300);
+        assert_eq!(b.fetch_sub(13, SeqCst), 12);
+        assert_eq!(b.load(SeqCst), -1);
+
+        let mut c = AtomicU64::new(1 << 40);
+        assert_eq!(c.fetch_or(1, SeqCst), 1 << 40);
+        assert_eq!(c.fetch_and(1, SeqCst), (1 << 40) | 1);
+        c.store(-1i64 as u64, Release);
+        assert_eq!(c.load(Acquire), -1i64 as u64);
+
+        let mut d = AtomicI32::new(0);
+        assert_eq!(d.fetch_xor(-1, SeqCst), 0);
+        assert_eq!(d.fetch_max(0, SeqCst), -1);
+        assert_eq!(d.load(SeqCst), 0);
+    }
+
+    #[test]
+    fn ptr() {
+        let mut x = 1;
+        let mut y = 2;
+        let px = &mut x as *mut int;
+        let py = &mut y as *mut int;
+        let mut p = AtomicPtr::new(px);
+        assert_eq!(p.swap(py, SeqCst), px);
+        assert_eq!(p.compare_and_swap(px, px, SeqCst), py);
+        assert_eq!(p.compare_and_swap(py, px, SeqCst), py);
+        assert_eq!(p.load(SeqCst), px);
+    }
+
+    #[test]
+    fn fences() {
+        fence(Acquire);
+        fence(Release);
+        fence(AcqRel);
+        fence(SeqCst);
+        compiler_fence(Acquire);
+        compiler_fence(Release);
+        compiler_fence(AcqRel);
+        compiler_fence(SeqCst);
+    }
+
+    #[test]
+    fn relaxed_fence() {
+        fence(Relaxed);
+        compiler_fence(Relaxed);
+    }
+
     static mut S_FLAG : AtomicFlag = INIT_ATOMIC_FLAG;
     static mut S_BOOL : AtomicBool = INIT_ATOMIC_BOOL;
     static mut S_INT  : AtomicInt  = INIT_ATOMIC_INT;
     static mut S_UINT : AtomicUint = INIT_ATOMIC_UINT;
+    static mut S_U8   : AtomicU8   = INIT_ATOMIC_U8;
+    static mut S_I64  : AtomicI64  = INIT_ATOMIC_I64;
 
     #[test]
     fn static_init() {
@@ -597,6 +1139,8 @@ mod test {
             assert!(!S_BOOL.load(SeqCst));
             assert!(S_INT.load(SeqCst) == 0);
             assert!(S_UINT.load(SeqCst) == 0);
+            assert!(S_U8.load(SeqCst) == 0);
+            assert!(S_I64.load(SeqCst) == 0);
         }
     }
 }
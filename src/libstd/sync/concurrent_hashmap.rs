@@ -0,0 +1,371 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A hash map which can be shared and modified by many tasks at once
+//!
+//! `ConcurrentHashMap` splits its entries into a number of stripes, each of
+//! which is a `HashMap` guarded by its own `RwLock`. A key always lives in the
+//! same stripe, chosen by a keyed hash of the key, so operations on keys in
+//! different stripes never wait for each other, and lookups in the same stripe
+//! only wait for writers. As `RwLock` is task-aware, a task waiting for a
+//! stripe never blocks the thread of a green scheduler.
+//!
+//! Handles to a map are cloned to share it between tasks, and all of the
+//! operations take `&self`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::ConcurrentHashMap;
+//!
+//! let map = ConcurrentHashMap::new();
+//! for i in range(0, 4) {
+//!     let map = map.clone();
+//!     do spawn {
+//!         map.insert(i, i * i);
+//!     }
+//! }
+//! ```
+
+use clone::Clone;
+use container::{Container, Map, Mutable, MutableMap};
+use hash::Hash;
+use hashmap::HashMap;
+use iter::Iterator;
+use kinds::{Freeze, Send};
+use option::{Option, Some, None};
+use rand;
+use sync::arc::UnsafeArc;
+use sync::rwlock::RwLock;
+use vec;
+use vec::{ImmutableVector, OwnedVector};
+
+/// The number of stripes of a map created with `ConcurrentHashMap::new`.
+pub static DEFAULT_STRIPES: uint = 64;
+
+struct Stripes<K, V> {
+    stripes: ~[RwLock<HashMap<K, V>>],
+    // The keys of the hash which picks the stripe of a key
+    k0: u64,
+    k1: u64,
+}
+
+/// A striped, task-safe hash map. See the module documentation.
+///
+/// A task which fails while modifying the map poisons the stripe it was
+/// modifying. This happens if a closure passed to `find_mut_with` or
+/// `find_or_insert_with` fails, or if the `Eq` or `Hash` implementation of the
+/// keys fails during a modification. Poisoning is permanent: every later
+/// operation on that stripe fails as well, and so do the operations which
+/// visit all of the stripes, such as `snapshot`, `len` and `clear`. A map with
+/// a poisoned stripe should be dropped and rebuilt.
+pub struct ConcurrentHashMap<K, V> {
+    priv inner: UnsafeArc<Stripes<K, V>>,
+}
+
+impl<K: Hash + Eq + Freeze + Send, V: Freeze + Send> ConcurrentHashMap<K, V> {
+    /// Creates an empty map with `DEFAULT_STRIPES` stripes.
+    pub fn new() -> ConcurrentHashMap<K, V> {
+        ConcurrentHashMap::with_stripes(DEFAULT_STRIPES)
+    }
+
+    /// Creates an empty map with `n` stripes. More stripes allow more tasks
+    /// to modify the map at once.
+    pub fn with_stripes(n: uint) -> ConcurrentHashMap<K, V> {
+        assert!(n > 0);
+        let stripes = Stripes {
+            stripes: vec::from_fn(n, |_| RwLock::new(HashMap::new())),
+            k0: rand::random(),
+            k1: rand::random(),
+        };
+        ConcurrentHashMap { inner: UnsafeArc::new(stripes) }
+    }
+
+    fn stripe<'a>(&'a self, k: &K) -> &'a RwLock<HashMap<K, V>> {
+        let inner = unsafe { &*self.inner.get() };
+        let hash = k.hash_keyed(inner.k0, inner.k1);
+        &inner.stripes[(hash % inner.stripes.len() as u64) as uint]
+    }
+
+    fn stripes<'a>(&'a self) -> &'a [RwLock<HashMap<K, V>>] {
+        let inner = unsafe { &*self.inner.get() };
+        let stripes: &'a [RwLock<HashMap<K, V>>] = inner.stripes;
+        stripes
+    }
+
+    /// Inserts a key-value pair into the map. Returns true if the key did not
+    /// already exist in the map.
+    pub fn insert(&self, k: K, v: V) -> bool {
+        self.stripe(&k).write().get_mut().insert(k, v)
+    }
+
+    /// Inserts a key-value pair into the map, returning the previous value of
+    /// the key, if any.
+    pub fn swap(&self, k: K, v: V) -> Option<V> {
+        self.stripe(&k).write().get_mut().swap(k, v)
+    }
+
+    /// Removes a key from the map. Returns true if the key was present.
+    pub fn remove(&self, k: &K) -> bool {
+        self.stripe(k).write().get_mut().remove(k)
+    }
+
+    /// Removes a key from the map, returning its value, if any.
+    pub fn pop(&self, k: &K) -> Option<V> {
+        self.stripe(k).write().get_mut().pop(k)
+    }
+
+    /// Returns true if the map contains a value for the given key.
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.stripe(k).read().get().contains_key(k)
+    }
+
+    /// Calls `f` with a reference to the value of the given key, if any,
+    /// while other tasks are kept from modifying it.
+    ///
+    /// `f` must not access this map, as it may deadlock otherwise.
+    pub fn find_with<R>(&self, k: &K, f: |Option<&V>| -> R) -> R {
+        let guard = self.stripe(k).read();
+        f(guard.get().find(k))
+    }
+
+    /// Calls `f` with a mutable reference to the value of the given key, if
+    /// any, while other tasks are kept from accessing it.
+    ///
+    /// `f` must not access this map, as it may deadlock otherwise.
+    ///
+    /// # Failure
+    ///
+    /// If `f` fails, the stripe of the key is poisoned for good. See
+    /// `ConcurrentHashMap`.
+    pub fn find_mut_with<R>(&self, k: &K, f: |Option<&mut V>| -> R) -> R {
+        let mut guard = self.stripe(k).write();
+        f(guard.get_mut().find_mut(k))
+    }
+
+    /// Removes all entries from the map. Entries which are inserted into
+    /// stripes which have already been cleared while this runs are kept.
+    pub fn clear(&self) {
+        for stripe in self.stripes().iter() {
+            stripe.write().get_mut().clear();
+        }
+    }
+}
+
+impl<K: Hash + Eq + Freeze + Send + Clone,
+     V: Freeze + Send + Clone> ConcurrentHashMap<K, V> {
+    /// Returns a copy of the value of the given key, if any.
+    pub fn find(&self, k: &K) -> Option<V> {
+        self.find_with(k, |v| v.map(|v| v.clone()))
+    }
+
+    /// Returns a copy of the value of the given key, first inserting the value
+    /// computed by `f` if the key isn't in the map yet.
+    ///
+    /// Other tasks are kept from accessing the stripe of the key while `f`
+    /// runs, so it must not access this map, as it may deadlock otherwise.
+    ///
+    /// # Failure
+    ///
+    /// If `f` fails, the stripe of the key is poisoned for good. See
+    /// `ConcurrentHashMap`.
+    pub fn find_or_insert_with(&self, k: K, f: |&K| -> V) -> V {
+        match self.find(&k) {
+            Some(v) => return v,
+            None => {}
+        }
+        let mut guard = self.stripe(&k).write();
+        guard.get_mut().find_or_insert_with(k, f).clone()
+    }
+
+    /// Returns a copy of the entries of the map, in no particular order.
+    ///
+    /// The stripes of the map are copied one after the other, so the snapshot
+    /// is consistent within each stripe, but it may only contain some of the
+    /// modifications which other tasks made while it was being taken.
+    pub fn snapshot(&self) -> ~[(K, V)] {
+        let mut entries = ~[];
+        for stripe in self.stripes().iter() {
+            let guard = stripe.read();
+            for (k, v) in guard.get().iter() {
+                entries.push((k.clone(), v.clone()));
+            }
+        }
+        entries
+    }
+
+    /// Returns an iterator over a snapshot of the entries of the map. See
+    /// `snapshot`.
+    pub fn iter(&self) -> vec::MoveItems<(K, V)> {
+        self.snapshot().move_iter()
+    }
+}
+
+impl<K: Hash + Eq + Freeze + Send,
+     V: Freeze + Send> Container for ConcurrentHashMap<K, V> {
+    /// Returns the number of entries in the map. Other tasks may change it
+    /// while the stripes are being counted.
+    fn len(&self) -> uint {
+        self.stripes().iter().fold(0, |n, stripe| n + stripe.read().get().len())
+    }
+}
+
+impl<K: Send, V: Send> Clone for ConcurrentHashMap<K, V> {
+    /// Returns a new handle to the same map.
+    fn clone(&self) -> ConcurrentHashMap<K, V> {
+        ConcurrentHashMap { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+
+    use native;
+    use super::ConcurrentHashMap;
+    use task;
+
+    #[test]
+    fn smoke() {
+        let map = ConcurrentHashMap::with_stripes(4);
+        assert!(map.is_empty());
+        assert!(map.insert(1, 2));
+        assert!(!map.insert(1, 3));
+        assert_eq!(map.find(&1), Some(3));
+        assert_eq!(map.swap(1, 4), Some(3));
+        assert_eq!(map.swap(2, 5), None);
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key(&2));
+        assert_eq!(map.pop(&2), Some(5));
+        assert!(!map.remove(&2));
+        assert!(map.remove(&1));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn find_with() {
+        let map = ConcurrentHashMap::new();
+        map.insert(~"a", ~[1]);
+        map.find_mut_with(&~"a", |v| v.unwrap().push(2));
+        assert_eq!(map.find_with(&~"a", |v| v.map(|v| v.len())), Some(2));
+        assert_eq!(map.find_with(&~"b", |v| v.is_none()), true);
+        assert_eq!(map.find_or_insert_with(~"b", |k| ~[k.len()]), ~[1]);
+        assert_eq!(map.find_or_insert_with(~"b", |_| fail!()), ~[1]);
+    }
+
+    #[test]
+    fn snapshot() {
+        let map = ConcurrentHashMap::with_stripes(3);
+        for i in range(0, 100) {
+            map.insert(i, i * 2);
+        }
+        let mut entries = map.snapshot();
+        entries.sort();
+        assert_eq!(entries.len(), 100);
+        for (i, &(k, v)) in entries.iter().enumerate() {
+            assert_eq!((k, v), (i, i * 2));
+        }
+        assert_eq!(map.iter().fold(0, |n, (_, v)| n + v), 9900);
+        map.clear();
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn poisoned_stripe() {
+        let map = ConcurrentHashMap::with_stripes(1);
+        map.insert(1, 1);
+        let map2 = map.clone();
+        let ret = do task::try {
+            map2.find_mut_with(&1, |_| fail!());
+        };
+        assert!(ret.is_err());
+        let ret = do task::try {
+            map.find(&1);
+        };
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn stress_insert_find() {
+        let nthreads = 8u;
+        let nkeys = 1000u;
+        let map = ConcurrentHashMap::new();
+        let (port, chan) = SharedChan::new();
+
+        for t in range(0, nthreads) {
+            let map = map.clone();
+            let chan = chan.clone();
+            do native::task::spawn {
+                for i in range(0, nkeys) {
+                    let k = t * nkeys + i;
+                    assert!(map.insert(k, k + 1));
+                    assert_eq!(map.find(&k), Some(k + 1));
+                }
+                chan.send(());
+            }
+        }
+        for _ in range(0, nthreads) {
+            port.recv();
+        }
+
+        assert_eq!(map.len(), nthreads * nkeys);
+        for k in range(0, nthreads * nkeys) {
+            assert_eq!(map.find(&k), Some(k + 1));
+        }
+    }
+
+    #[test]
+    fn stress_contended() {
+        let nthreads = 8u;
+        let niters = 1000u;
+        let nkeys = 16u;
+        let map = ConcurrentHashMap::with_stripes(4);
+        for k in range(0, nkeys) {
+            map.insert(k, 0u);
+        }
+        let (port, chan) = SharedChan::new();
+
+        // Every task bumps all of the shared counters, and inserts and removes
+        // a scratch key of its own. With only a few stripes the scratch keys
+        // share their stripes with the counters, so each stripe is contended.
+        for t in range(0, nthreads) {
+            let map = map.clone();
+            let chan = chan.clone();
+            let f = proc() {
+                for i in range(0, niters) {
+                    let k = i % nkeys;
+                    map.find_mut_with(&k, |v| match v {
+                        Some(v) => *v += 1,
+                        None => fail!("counter {} went missing", k),
+                    });
+                    let scratch = nkeys + t;
+                    map.insert(scratch, i);
+                    assert_eq!(map.pop(&scratch), Some(i));
+                }
+                chan.send(());
+            };
+            if t % 2 == 0 { native::task::spawn(f) } else { spawn(f) }
+        }
+
+        // Snapshots taken concurrently only ever see whole entries
+        for _ in range(0, 10) {
+            for (k, _) in map.iter() {
+                assert!(k < nkeys + nthreads);
+            }
+        }
+
+        for _ in range(0, nthreads) {
+            port.recv();
+        }
+        let total = map.iter().fold(0, |n, (_, v)| n + v);
+        assert_eq!(total, nthreads * niters);
+        assert_eq!(map.len(), nkeys);
+    }
+}
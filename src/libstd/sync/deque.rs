@@ -193,6 +193,12 @@ impl<T: Send> Worker<T> {
         unsafe { (*self.deque.get()).pop() }
     }
 
+    /// Returns the number of elements currently in this work queue. This is
+    /// only a snapshot, the queue may be concurrently modified by stealers.
+    pub fn len(&self) -> uint {
+        unsafe { (*self.deque.get()).len() }
+    }
+
     /// Gets access to the buffer pool that this worker is attached to. This can
     /// be used to create more deques which share the same buffer pool as this
     /// deque.
@@ -207,6 +213,13 @@ impl<T: Send> Stealer<T> {
         unsafe { (*self.deque.get()).steal() }
     }
 
+    /// Returns an approximation of the number of elements in the queue. The
+    /// owning worker and other stealers may concurrently modify the queue, so
+    /// this is only suitable for heuristics and statistics.
+    pub fn len(&self) -> uint {
+        unsafe { (*self.deque.get()).len() }
+    }
+
     /// Gets access to the buffer pool that this stealer is attached to. This
     /// can be used to create more deques which share the same buffer pool as
     /// this deque.
@@ -297,6 +310,12 @@ impl<T: Send> Deque<T> {
         }
     }
 
+    unsafe fn len(&self) -> uint {
+        let t = self.top.load(SeqCst);
+        let b = self.bottom.load(SeqCst);
+        if b > t { (b - t) as uint } else { 0 }
+    }
+
     unsafe fn maybe_shrink(&mut self, b: int, t: int) {
         let a = self.array.load(SeqCst);
         if b - t < (*a).size() / K && b - t > (1 << MIN_BITS) {
@@ -420,6 +439,22 @@ mod tests {
         assert_eq!(s.clone().steal(), Data(1));
     }
 
+    #[test]
+    fn len() {
+        let mut pool = BufferPool::new();
+        let (mut w, mut s) = pool.deque();
+        assert_eq!(w.len(), 0);
+        for i in range(0, 100) {
+            w.push(i);
+        }
+        assert_eq!(w.len(), 100);
+        assert_eq!(s.len(), 100);
+        assert_eq!(s.steal(), Data(0));
+        assert_eq!(w.pop(), Some(99));
+        assert_eq!(w.len(), 98);
+        assert_eq!(s.len(), 98);
+    }
+
     #[test]
     fn stealpush() {
         static AMT: int = 100000;
@@ -14,10 +14,26 @@
 //! Most of the primitives in this module do not provide any sort of locking
 //! and/or blocking at all, but rather provide the necessary tools to build
 //! other types of concurrent primitives.
+//!
+//! The exceptions are `Mutex`, `RwLock`, `Condvar` and `Once`, which are
+//! blocking primitives aware of the task they're running on: green tasks are
+//! parked without blocking their scheduler's thread, and native tasks block
+//! in the OS. `ConcurrentHashMap` is built on top of `RwLock`.
+
+pub use self::concurrent_hashmap::ConcurrentHashMap;
+pub use self::mutex::{Mutex, MutexGuard, Condvar};
+pub use self::once::{Once, ONCE_INIT};
+pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub mod arc;
 pub mod atomics;
+pub mod concurrent_hashmap;
 pub mod deque;
 pub mod mpmc_bounded_queue;
 pub mod mpsc_queue;
+pub mod mutex;
+pub mod once;
+pub mod rwlock;
 pub mod spsc_queue;
+
+mod wait;
@@ -0,0 +1,538 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A task-aware mutual exclusion lock and condition variable
+//!
+//! Unlike `unstable::mutex::Mutex`, the `Mutex` in this module never blocks
+//! the OS thread of a green task. A task which finds the lock held parks
+//! itself until the lock is handed to it, allowing its scheduler to run other
+//! tasks in the meantime. Native tasks block their thread in the OS as usual.
+//!
+//! The uncontended path of both locking and unlocking is a single atomic
+//! compare-and-swap. Only contended operations touch the queue of blocked
+//! tasks (a short critical section guarded by a native lock), in the same
+//! spirit as a futex. Blocking doesn't allocate.
+//!
+//! If a task fails while holding the lock, the mutex is *poisoned* and all
+//! future attempts to lock it will fail as well. This prevents other tasks
+//! from observing data which was left in an inconsistent state.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::mutex::{Mutex, Condvar};
+//!
+//! let mutex = Mutex::new(0);
+//! let cond = Condvar::new();
+//!
+//! let (m, c) = (mutex.clone(), cond.clone());
+//! do spawn {
+//!     let mut guard = m.lock();
+//!     *guard.get_mut() = 1;
+//!     c.signal();
+//! }
+//!
+//! let mut guard = mutex.lock();
+//! while *guard.get() == 0 {
+//!     cond.wait(&mut guard);
+//! }
+//! ```
+
+use clone::Clone;
+use kinds::Send;
+use ops::Drop;
+use option::{Option, Some, None};
+use sync::arc::UnsafeArc;
+use sync::atomics::{AtomicUint, SeqCst};
+use sync::wait::{WaitNode, WaitQueue};
+use task;
+use unstable::mutex;
+
+static UNLOCKED: uint = 0;
+static LOCKED: uint = 1;
+static CONTENDED: uint = 2;
+
+// The lock underlying `Mutex`, without any data or poisoning.
+//
+// Blocked tasks are queued up in `waiters` (see `sync::wait`). Ownership of
+// the lock is handed directly to the first blocked task when the lock is
+// released, so a woken task never has to race for the lock again.
+//
+// Waiting can't be interrupted by task cancellation, as a waiter is handed
+// the lock whether it's still interested or not.
+struct RawMutex {
+    state: AtomicUint,
+    queue_lock: mutex::Mutex, // protects `waiters`
+    waiters: WaitQueue,
+}
+
+impl RawMutex {
+    fn new() -> RawMutex {
+        RawMutex {
+            state: AtomicUint::new(UNLOCKED),
+            queue_lock: unsafe { mutex::Mutex::new() },
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    fn try_lock(&mut self) -> bool {
+        self.state.compare_and_swap(UNLOCKED, LOCKED, SeqCst) == UNLOCKED
+    }
+
+    fn lock(&mut self) {
+        if self.try_lock() { return }
+
+        let mut node = WaitNode::new();
+        unsafe {
+            self.queue_lock.lock();
+            // Flag the lock as contended so the holder takes the slow path in
+            // `unlock`. If the lock was released in the meantime then we now
+            // own it (and the next `unlock` just finds an empty queue).
+            if self.state.swap(CONTENDED, SeqCst) == UNLOCKED {
+                self.queue_lock.unlock();
+                return
+            }
+            self.waiters.push(&mut node);
+            self.queue_lock.unlock();
+        }
+        node.wait();
+    }
+
+    fn unlock(&mut self) {
+        if self.state.compare_and_swap(LOCKED, UNLOCKED, SeqCst) == LOCKED {
+            return
+        }
+
+        let node = unsafe {
+            self.queue_lock.lock();
+            let node = self.waiters.pop();
+            match node {
+                // Hand the lock over, leaving it contended if there are more
+                // tasks behind the one we're waking up.
+                Some(..) if self.waiters.is_empty() => {
+                    self.state.store(LOCKED, SeqCst)
+                }
+                Some(..) => {}
+                None => self.state.store(UNLOCKED, SeqCst),
+            }
+            self.queue_lock.unlock();
+            node
+        };
+        // Waking a task may reschedule, so it's done outside of the lock
+        match node {
+            Some(node) => unsafe { WaitNode::wake(node) },
+            None => {}
+        }
+    }
+}
+
+impl Drop for RawMutex {
+    fn drop(&mut self) {
+        assert!(self.waiters.is_empty());
+        unsafe { self.queue_lock.destroy() }
+    }
+}
+
+struct MutexInner<T> {
+    lock: RawMutex,
+    poisoned: bool,
+    data: T,
+}
+
+/// A mutual exclusion lock protecting some shared data of type `T`.
+///
+/// A `Mutex` is a handle to the shared lock; cloning it produces another
+/// handle to the same lock which can be sent to other tasks.
+pub struct Mutex<T> {
+    priv inner: UnsafeArc<MutexInner<T>>,
+}
+
+/// An RAII guard for a locked `Mutex`. The data protected by the mutex can be
+/// accessed through the guard, and the lock is released when the guard falls
+/// out of scope.
+pub struct MutexGuard<'a, T> {
+    priv mutex: &'a Mutex<T>,
+    // Whether the task was already failing when the lock was acquired, in
+    // which case failure while holding the lock doesn't poison it.
+    priv failing: bool,
+}
+
+impl<T: Send> Mutex<T> {
+    /// Creates a new unlocked mutex protecting `data`.
+    pub fn new(data: T) -> Mutex<T> {
+        let inner = MutexInner {
+            lock: RawMutex::new(),
+            poisoned: false,
+            data: data,
+        };
+        Mutex { inner: UnsafeArc::new(inner) }
+    }
+
+    /// Acquires this lock, blocking the current task until it is available.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if the mutex has been poisoned by a task which
+    /// failed while holding the lock.
+    pub fn lock<'a>(&'a self) -> MutexGuard<'a, T> {
+        unsafe { (*self.inner.get()).lock.lock() }
+        self.guard()
+    }
+
+    /// Attempts to acquire this lock without blocking, returning `None` if the
+    /// lock is currently held by another task.
+    ///
+    /// # Failure
+    ///
+    /// Like `lock`, this function will fail if the mutex has been poisoned.
+    pub fn try_lock<'a>(&'a self) -> Option<MutexGuard<'a, T>> {
+        if unsafe { (*self.inner.get()).lock.try_lock() } {
+            Some(self.guard())
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether a task has failed while holding this lock.
+    pub fn is_poisoned(&self) -> bool {
+        unsafe { (*self.inner.get()).poisoned }
+    }
+
+    // Must only be called with the lock held. The guard is created before
+    // checking for poison so that the lock is released while unwinding.
+    fn guard<'a>(&'a self) -> MutexGuard<'a, T> {
+        let guard = MutexGuard { mutex: self, failing: task::failing() };
+        if self.is_poisoned() {
+            fail!("poisoned Mutex - another task failed inside!");
+        }
+        guard
+    }
+}
+
+impl<T: Send> Clone for Mutex<T> {
+    fn clone(&self) -> Mutex<T> {
+        Mutex { inner: self.inner.clone() }
+    }
+}
+
+impl<'a, T: Send> MutexGuard<'a, T> {
+    /// Returns a reference to the data protected by the mutex.
+    pub fn get<'b>(&'b self) -> &'b T {
+        unsafe { &(*self.mutex.inner.get()).data }
+    }
+
+    /// Returns a mutable reference to the data protected by the mutex.
+    pub fn get_mut<'b>(&'b mut self) -> &'b mut T {
+        unsafe { &mut (*self.mutex.inner.get()).data }
+    }
+
+    fn raw(&mut self) -> &'a mut RawMutex {
+        unsafe { &mut (*self.mutex.inner.get()).lock }
+    }
+}
+
+#[unsafe_destructor]
+impl<'a, T: Send> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let inner = self.mutex.inner.get();
+            if !self.failing && task::failing() {
+                (*inner).poisoned = true;
+            }
+            (*inner).lock.unlock();
+        }
+    }
+}
+
+struct CondvarInner {
+    queue_lock: mutex::Mutex, // protects `waiters`
+    waiters: WaitQueue,
+}
+
+impl Drop for CondvarInner {
+    fn drop(&mut self) {
+        assert!(self.waiters.is_empty());
+        unsafe { self.queue_lock.destroy() }
+    }
+}
+
+/// A condition variable which allows tasks to block until signalled while
+/// atomically releasing a `Mutex`.
+///
+/// Like `Mutex`, a `Condvar` is a handle which can be cloned and sent to other
+/// tasks, and waiting on it only parks the current green task.
+pub struct Condvar {
+    priv inner: UnsafeArc<CondvarInner>,
+}
+
+impl Condvar {
+    /// Creates a new condition variable with no waiters.
+    pub fn new() -> Condvar {
+        let inner = CondvarInner {
+            queue_lock: unsafe { mutex::Mutex::new() },
+            waiters: WaitQueue::new(),
+        };
+        Condvar { inner: UnsafeArc::new(inner) }
+    }
+
+    /// Atomically releases the lock held by `guard` and blocks the current
+    /// task until this condition variable is signalled, re-acquiring the lock
+    /// before returning.
+    ///
+    /// As is usual for condition variables, the condition being waited for
+    /// should be re-checked in a loop after this returns.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if the mutex was poisoned while this task was
+    /// waiting.
+    pub fn wait<'a, T: Send>(&self, guard: &mut MutexGuard<'a, T>) {
+        // Enqueue ourselves before dropping the lock so that a signal sent by
+        // whichever task acquires it next can't be missed.
+        let mut node = WaitNode::new();
+        unsafe {
+            let inner = self.inner.get();
+            (*inner).queue_lock.lock();
+            (*inner).waiters.push(&mut node);
+            (*inner).queue_lock.unlock();
+        }
+        guard.raw().unlock();
+        node.wait();
+        guard.raw().lock();
+
+        if guard.mutex.is_poisoned() {
+            fail!("poisoned Mutex - another task failed inside!");
+        }
+    }
+
+    /// Wakes up one task blocked in `wait`, returning whether there was a task
+    /// to wake up.
+    pub fn signal(&self) -> bool {
+        let node = unsafe {
+            let inner = self.inner.get();
+            (*inner).queue_lock.lock();
+            let node = (*inner).waiters.pop();
+            (*inner).queue_lock.unlock();
+            node
+        };
+        match node {
+            Some(node) => { unsafe { WaitNode::wake(node) } true }
+            None => false,
+        }
+    }
+
+    /// Wakes up all tasks blocked in `wait`, returning how many were woken.
+    pub fn broadcast(&self) -> uint {
+        let mut waiters = unsafe {
+            let inner = self.inner.get();
+            (*inner).queue_lock.lock();
+            let waiters = (*inner).waiters.take();
+            (*inner).queue_lock.unlock();
+            waiters
+        };
+        unsafe { waiters.wake_all() }
+    }
+}
+
+impl Clone for Condvar {
+    fn clone(&self) -> Condvar {
+        Condvar { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prelude::*;
+
+    use extra::arc::MutexArc;
+    use extra::test::BenchHarness;
+    use native;
+    use task;
+    use super::{Mutex, Condvar};
+
+    #[test]
+    fn smoke() {
+        let m = Mutex::new(1);
+        {
+            let mut guard = m.lock();
+            assert_eq!(*guard.get(), 1);
+            *guard.get_mut() = 2;
+        }
+        assert_eq!(*m.lock().get(), 2);
+    }
+
+    #[test]
+    fn try_lock() {
+        let m = Mutex::new(());
+        let guard = m.try_lock();
+        assert!(guard.is_some());
+        assert!(m.try_lock().is_none());
+        drop(guard);
+        assert!(m.try_lock().is_some());
+    }
+
+    fn lots_and_lots(spawn: |proc()|) {
+        static J: uint = 1000;
+        static K: uint = 3;
+        let m = Mutex::new(0u);
+        let (p, c) = SharedChan::new();
+
+        for _ in range(0, K) {
+            let (m1, c1) = (m.clone(), c.clone());
+            spawn(proc() {
+                for _ in range(0, J) { *m1.lock().get_mut() += 1; }
+                c1.send(());
+            });
+            let (m2, c2) = (m.clone(), c.clone());
+            spawn(proc() {
+                for _ in range(0, J) {
+                    *m2.lock().get_mut() += 1;
+                    task::deschedule();
+                }
+                c2.send(());
+            });
+        }
+        for _ in range(0, 2 * K) { p.recv(); }
+        assert_eq!(*m.lock().get(), J * K * 2);
+    }
+
+    #[test]
+    fn lots_and_lots_green() {
+        lots_and_lots(|f| task::spawn(f));
+    }
+
+    #[test]
+    fn lots_and_lots_native() {
+        lots_and_lots(|f| native::task::spawn(f));
+    }
+
+    #[test]
+    fn condvar() {
+        let m = Mutex::new(false);
+        let cond = Condvar::new();
+        let (m2, cond2) = (m.clone(), cond.clone());
+        do spawn {
+            let mut guard = m2.lock();
+            *guard.get_mut() = true;
+            cond2.signal();
+        }
+        let mut guard = m.lock();
+        while !*guard.get() {
+            cond.wait(&mut guard);
+        }
+    }
+
+    #[test]
+    fn condvar_broadcast() {
+        static N: uint = 5;
+        let m = Mutex::new(0u);
+        let cond = Condvar::new();
+        let (p, c) = SharedChan::new();
+
+        for _ in range(0, N) {
+            let (m, cond, c) = (m.clone(), cond.clone(), c.clone());
+            do native::task::spawn {
+                let mut guard = m.lock();
+                *guard.get_mut() += 1;
+                c.send(());
+                while *guard.get() != 0 {
+                    cond.wait(&mut guard);
+                }
+                c.send(());
+            }
+        }
+        for _ in range(0, N) { p.recv(); }
+
+        // Everyone has bumped the count, so they're all waiting now
+        *m.lock().get_mut() = 0;
+        let mut woken = 0;
+        while woken < N {
+            woken += cond.broadcast();
+            task::deschedule();
+        }
+        for _ in range(0, N) { p.recv(); }
+        assert!(!cond.signal());
+    }
+
+    #[test]
+    fn poison() {
+        let m = Mutex::new(1);
+        let m2 = m.clone();
+        let ret = do task::try {
+            let _guard = m2.lock();
+            fail!();
+        };
+        assert!(ret.is_err());
+        assert!(m.is_poisoned());
+    }
+
+    #[test] #[should_fail]
+    fn poison_fails_lock() {
+        let m = Mutex::new(1);
+        let m2 = m.clone();
+        let _ = do task::try {
+            let _guard = m2.lock();
+            fail!();
+        };
+        m.lock();
+    }
+
+    #[test]
+    fn failure_while_unwinding_does_not_poison() {
+        struct Unwinder { m: Mutex<int> }
+        impl Drop for Unwinder {
+            fn drop(&mut self) {
+                *self.m.lock().get_mut() += 1;
+            }
+        }
+
+        let m = Mutex::new(1);
+        let m2 = m.clone();
+        let _ = do task::try {
+            let _u = Unwinder { m: m2.clone() };
+            fail!();
+        };
+        assert!(!m.is_poisoned());
+        assert_eq!(*m.lock().get(), 2);
+    }
+
+    #[test]
+    fn cancel_blocked_lock() {
+        let m = Mutex::new(0);
+        let m2 = m.clone();
+        let guard = m.lock();
+        let (p, c) = Chan::new();
+        let child = do task::task().spawn_cancellable {
+            c.send(());
+            *m2.lock().get_mut() += 1;
+            task::check_cancelled();
+        };
+        p.recv();
+        child.cancel();
+        drop(guard);
+
+        // The child still gets the lock, and releases it again on its way out
+        assert!(child.join().is_err());
+        assert_eq!(*m.lock().get(), 1);
+        assert!(!m.is_poisoned());
+    }
+
+    #[bench]
+    fn bench_uncontended(bh: &mut BenchHarness) {
+        let m = Mutex::new(0);
+        bh.iter(|| { *m.lock().get_mut() += 1; });
+    }
+
+    #[bench]
+    fn bench_uncontended_mutex_arc(bh: &mut BenchHarness) {
+        let m = MutexArc::new(0);
+        bh.iter(|| unsafe { m.unsafe_access(|x| *x += 1) });
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A "once initialization" primitive
+//!
+//! This primitive is meant to be used to run one-time initialization. An
+//! example use case would be for initializing an FFI library.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::once::{Once, ONCE_INIT};
+//!
+//! static mut START: Once = ONCE_INIT;
+//! unsafe {
+//!     START.doit(|| {
+//!         // run initialization here
+//!     });
+//! }
+//! ```
+
+use cast;
+use ops::Drop;
+use sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+use sync::wait::WaitNode;
+use task;
+
+// The low two bits of the state hold one of these values. While the state is
+// RUNNING, the remaining bits point to the list of blocked tasks.
+static INCOMPLETE: uint = 0;
+static RUNNING: uint = 1;
+static COMPLETE: uint = 2;
+static POISONED: uint = 3;
+static STATE_MASK: uint = 3;
+
+// A task waiting for the initialization routine to finish. Waiters live on
+// the stack of the blocked task and are linked together through `next`.
+struct Waiter {
+    node: WaitNode,
+    next: uint,
+}
+
+/// A type which can be used to run a one-time global initialization, such as
+/// the setup of an FFI library. This type can only be statically constructed
+/// with the `ONCE_INIT` value.
+///
+/// Tasks which find another task in the middle of the initialization routine
+/// block until it finishes. Blocked green tasks are descheduled, so waiting
+/// never blocks a green task's scheduler.
+pub struct Once {
+    priv state: AtomicUint,
+}
+
+/// Initialization value for static `Once` values.
+pub static ONCE_INIT: Once = Once { state: INIT_ATOMIC_UINT };
+
+impl Once {
+    /// Perform an initialization routine once and only once. The given closure
+    /// will be executed if this is the first time `doit` has been called, and
+    /// otherwise the routine will *not* be invoked.
+    ///
+    /// This method will block the calling task if another initialization
+    /// routine is currently running. Green tasks are descheduled while they
+    /// wait, so the routine is free to block or perform I/O.
+    ///
+    /// When this function returns, it is guaranteed that some initialization
+    /// has run and completed (it may not be the closure specified).
+    ///
+    /// # Failure
+    ///
+    /// If the initialization routine fails, the `Once` is poisoned and this
+    /// function (along with any other task blocked in it) will fail.
+    pub fn doit(&self, f: ||) {
+        let this = unsafe { cast::transmute_mut(self) };
+        loop {
+            let state = this.state.load(SeqCst);
+            match state & STATE_MASK {
+                COMPLETE => return,
+                POISONED => fail!("Once instance has previously been poisoned"),
+                RUNNING => this.wait(state),
+                _ => {
+                    let prev = this.state.compare_and_swap(INCOMPLETE, RUNNING,
+                                                           SeqCst);
+                    if prev == INCOMPLETE {
+                        let _finish = Finish { state: &mut this.state };
+                        f();
+                        return
+                    }
+                }
+            }
+        }
+    }
+
+    // Blocks until the running initialization routine finishes. Returns
+    // without blocking if it has already finished by the time we would have
+    // been queued up.
+    fn wait(&mut self, mut state: uint) {
+        let mut me = Waiter { node: WaitNode::new(), next: 0 };
+        let ptr = &mut me as *mut Waiter as uint;
+        loop {
+            if state & STATE_MASK != RUNNING { return }
+            me.next = state & !STATE_MASK;
+            let prev = self.state.compare_and_swap(state, ptr | RUNNING,
+                                                   SeqCst);
+            if prev == state { break }
+            state = prev;
+        }
+        // Our node must stay put until the routine's task has woken us up
+        me.node.wait();
+    }
+
+    /// Returns whether an initialization routine has run to completion.
+    pub fn is_completed(&self) -> bool {
+        self.state.load(SeqCst) == COMPLETE
+    }
+}
+
+// Publishes the outcome of the initialization routine, even if it fails
+struct Finish<'a> {
+    state: &'a mut AtomicUint,
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for Finish<'a> {
+    fn drop(&mut self) {
+        let state = if task::failing() {POISONED} else {COMPLETE};
+        let mut queue = self.state.swap(state, SeqCst) & !STATE_MASK;
+
+        // Wake up everyone who was waiting. A waiter may return (and free its
+        // node) as soon as it is woken up, so the node must not be touched
+        // afterwards.
+        while queue != 0 {
+            unsafe {
+                let waiter = queue as *mut Waiter;
+                queue = (*waiter).next;
+                WaitNode::wake(&mut (*waiter).node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prelude::*;
+
+    use native;
+    use task;
+    use super::{ONCE_INIT, Once};
+
+    #[test]
+    fn smoke_once() {
+        static mut o: Once = ONCE_INIT;
+        let mut a = 0;
+        unsafe { o.doit(|| a += 1); }
+        assert_eq!(a, 1);
+        unsafe { o.doit(|| a += 1); }
+        assert_eq!(a, 1);
+        unsafe { assert!(o.is_completed()); }
+    }
+
+    #[test]
+    fn stampede_once() {
+        static mut o: Once = ONCE_INIT;
+        static mut run: bool = false;
+
+        let (p, c) = SharedChan::new();
+        let mut starts = ~[];
+        for i in range(0, 10) {
+            let c = c.clone();
+            let (start, start_chan) = Chan::new();
+            starts.push(start_chan);
+            let f = proc() {
+                start.recv();
+                unsafe {
+                    o.doit(|| {
+                        assert!(!run);
+                        run = true;
+                    });
+                    assert!(run);
+                }
+                c.send(());
+            };
+            if i % 2 == 0 { spawn(f) } else { native::task::spawn(f) }
+        }
+
+        // Let everyone loose at once
+        for start in starts.move_iter() {
+            start.send(());
+        }
+        unsafe {
+            o.doit(|| {
+                assert!(!run);
+                run = true;
+            });
+            assert!(run);
+        }
+
+        for _ in range(0, 10) {
+            p.recv();
+        }
+    }
+
+    #[test]
+    fn wait_for_running_once() {
+        static mut o: Once = ONCE_INIT;
+
+        let (started, started_chan) = Chan::new();
+        let (finish, finish_chan) = Chan::new();
+        do native::task::spawn {
+            unsafe {
+                o.doit(|| {
+                    started_chan.send(());
+                    finish.recv();
+                });
+            }
+        }
+        started.recv();
+
+        // The routine is running now, so these must block until it's done
+        let (p, c) = SharedChan::new();
+        for i in range(0, 4) {
+            let c = c.clone();
+            let f = proc() {
+                unsafe {
+                    o.doit(|| fail!("ran twice"));
+                    assert!(o.is_completed());
+                }
+                c.send(());
+            };
+            if i % 2 == 0 { spawn(f) } else { native::task::spawn(f) }
+        }
+        finish_chan.send(());
+        for _ in range(0, 4) {
+            p.recv();
+        }
+    }
+
+    #[test]
+    fn poison_once() {
+        static mut o: Once = ONCE_INIT;
+        let ret = do task::try {
+            unsafe { o.doit(|| fail!()); }
+        };
+        assert!(ret.is_err());
+
+        let ret = do task::try {
+            unsafe { o.doit(|| {}); }
+        };
+        assert!(ret.is_err());
+    }
+}
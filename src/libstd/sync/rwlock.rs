@@ -0,0 +1,441 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A task-aware reader-writer lock
+//!
+//! `RwLock` allows any number of readers or a single writer to access the
+//! protected data at once. Like `sync::mutex::Mutex`, tasks which have to wait
+//! for the lock are parked rather than blocking their scheduler's thread.
+//!
+//! Once a writer is waiting for the lock, new readers queue up behind it so
+//! that writers can't be starved by a steady stream of readers. When a writer
+//! releases the lock, all readers which queued up in the meantime are let in
+//! at once.
+//!
+//! A task which fails while holding the write lock poisons the `RwLock`, and
+//! all future attempts to acquire it will fail. Failing while holding a read
+//! lock does not poison it, as readers can't have modified the data.
+
+use clone::Clone;
+use kinds::{Freeze, Send};
+use ops::Drop;
+use option::{Option, Some, None};
+use sync::arc::UnsafeArc;
+use sync::wait::{WaitNode, WaitQueue};
+use task;
+use unstable::mutex;
+
+// The lock underlying `RwLock`. All of its state is guarded by a native lock
+// which is only held for a handful of instructions at a time. Blocked tasks
+// are queued up (see `sync::wait`), and are woken up once the lock has
+// already been granted to them. Waiting can't be interrupted by task
+// cancellation.
+struct RawRwLock {
+    state_lock: mutex::Mutex,
+    readers: uint,
+    writer: bool,
+    read_waiters: WaitQueue,
+    write_waiters: WaitQueue,
+}
+
+impl RawRwLock {
+    fn new() -> RawRwLock {
+        RawRwLock {
+            state_lock: unsafe { mutex::Mutex::new() },
+            readers: 0,
+            writer: false,
+            read_waiters: WaitQueue::new(),
+            write_waiters: WaitQueue::new(),
+        }
+    }
+
+    fn try_read(&mut self) -> bool {
+        unsafe {
+            self.state_lock.lock();
+            let ret = self.read_available();
+            if ret { self.readers += 1; }
+            self.state_lock.unlock();
+            ret
+        }
+    }
+
+    fn read(&mut self) {
+        let mut node = WaitNode::new();
+        unsafe {
+            self.state_lock.lock();
+            if self.read_available() {
+                self.readers += 1;
+                self.state_lock.unlock();
+                return
+            }
+            self.read_waiters.push(&mut node);
+            self.state_lock.unlock();
+        }
+        node.wait();
+    }
+
+    fn read_unlock(&mut self) {
+        let node = unsafe {
+            self.state_lock.lock();
+            assert!(self.readers > 0);
+            self.readers -= 1;
+            let node = if self.readers == 0 {
+                self.grant_write()
+            } else {
+                None
+            };
+            self.state_lock.unlock();
+            node
+        };
+        match node {
+            Some(node) => unsafe { WaitNode::wake(node) },
+            None => {}
+        }
+    }
+
+    fn try_write(&mut self) -> bool {
+        unsafe {
+            self.state_lock.lock();
+            let ret = self.write_available();
+            if ret { self.writer = true; }
+            self.state_lock.unlock();
+            ret
+        }
+    }
+
+    fn write(&mut self) {
+        let mut node = WaitNode::new();
+        unsafe {
+            self.state_lock.lock();
+            if self.write_available() {
+                self.writer = true;
+                self.state_lock.unlock();
+                return
+            }
+            self.write_waiters.push(&mut node);
+            self.state_lock.unlock();
+        }
+        node.wait();
+    }
+
+    fn write_unlock(&mut self) {
+        let (mut readers, writer) = unsafe {
+            self.state_lock.lock();
+            assert!(self.writer);
+            self.writer = false;
+            let ret = if self.read_waiters.len() > 0 {
+                let readers = self.read_waiters.take();
+                self.readers += readers.len();
+                (readers, None)
+            } else {
+                (WaitQueue::new(), self.grant_write())
+            };
+            self.state_lock.unlock();
+            ret
+        };
+        // Waking a task may reschedule, so it's done outside of the lock
+        unsafe { readers.wake_all(); }
+        match writer {
+            Some(node) => unsafe { WaitNode::wake(node) },
+            None => {}
+        }
+    }
+
+    // The following must be called with `state_lock` held
+
+    fn read_available(&self) -> bool {
+        !self.writer && self.write_waiters.len() == 0
+    }
+
+    fn write_available(&self) -> bool {
+        !self.writer && self.readers == 0
+    }
+
+    fn grant_write(&mut self) -> Option<*mut WaitNode> {
+        let node = self.write_waiters.pop();
+        if node.is_some() { self.writer = true; }
+        node
+    }
+}
+
+impl Drop for RawRwLock {
+    fn drop(&mut self) {
+        assert!(self.read_waiters.is_empty());
+        assert!(self.write_waiters.is_empty());
+        unsafe { self.state_lock.destroy() }
+    }
+}
+
+struct RwLockInner<T> {
+    lock: RawRwLock,
+    poisoned: bool,
+    data: T,
+}
+
+/// A reader-writer lock protecting some shared data of type `T`.
+///
+/// An `RwLock` is a handle to the shared lock; cloning it produces another
+/// handle to the same lock which can be sent to other tasks.
+pub struct RwLock<T> {
+    priv inner: UnsafeArc<RwLockInner<T>>,
+}
+
+/// An RAII guard for a shared read lock on an `RwLock`.
+pub struct RwLockReadGuard<'a, T> {
+    priv lock: &'a RwLock<T>,
+}
+
+/// An RAII guard for the exclusive write lock on an `RwLock`.
+pub struct RwLockWriteGuard<'a, T> {
+    priv lock: &'a RwLock<T>,
+    // Whether the task was already failing when the lock was acquired, in
+    // which case failure while holding the lock doesn't poison it.
+    priv failing: bool,
+}
+
+impl<T: Freeze + Send> RwLock<T> {
+    /// Creates a new unlocked reader-writer lock protecting `data`.
+    pub fn new(data: T) -> RwLock<T> {
+        let inner = RwLockInner {
+            lock: RawRwLock::new(),
+            poisoned: false,
+            data: data,
+        };
+        RwLock { inner: UnsafeArc::new(inner) }
+    }
+
+    /// Acquires a shared read lock, blocking the current task until there is
+    /// no writer holding or waiting for the lock.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if the lock has been poisoned.
+    pub fn read<'a>(&'a self) -> RwLockReadGuard<'a, T> {
+        unsafe { (*self.inner.get()).lock.read() }
+        self.read_guard()
+    }
+
+    /// Attempts to acquire a shared read lock without blocking.
+    pub fn try_read<'a>(&'a self) -> Option<RwLockReadGuard<'a, T>> {
+        if unsafe { (*self.inner.get()).lock.try_read() } {
+            Some(self.read_guard())
+        } else {
+            None
+        }
+    }
+
+    /// Acquires the exclusive write lock, blocking the current task until all
+    /// other readers and writers have released the lock.
+    ///
+    /// # Failure
+    ///
+    /// This function will fail if the lock has been poisoned.
+    pub fn write<'a>(&'a self) -> RwLockWriteGuard<'a, T> {
+        unsafe { (*self.inner.get()).lock.write() }
+        self.write_guard()
+    }
+
+    /// Attempts to acquire the exclusive write lock without blocking.
+    pub fn try_write<'a>(&'a self) -> Option<RwLockWriteGuard<'a, T>> {
+        if unsafe { (*self.inner.get()).lock.try_write() } {
+            Some(self.write_guard())
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether a task has failed while holding the write lock.
+    pub fn is_poisoned(&self) -> bool {
+        unsafe { (*self.inner.get()).poisoned }
+    }
+
+    fn read_guard<'a>(&'a self) -> RwLockReadGuard<'a, T> {
+        let guard = RwLockReadGuard { lock: self };
+        if self.is_poisoned() {
+            fail!("poisoned RwLock - another task failed inside!");
+        }
+        guard
+    }
+
+    fn write_guard<'a>(&'a self) -> RwLockWriteGuard<'a, T> {
+        let guard = RwLockWriteGuard { lock: self, failing: task::failing() };
+        if self.is_poisoned() {
+            fail!("poisoned RwLock - another task failed inside!");
+        }
+        guard
+    }
+}
+
+impl<T: Freeze + Send> Clone for RwLock<T> {
+    fn clone(&self) -> RwLock<T> {
+        RwLock { inner: self.inner.clone() }
+    }
+}
+
+impl<'a, T: Freeze + Send> RwLockReadGuard<'a, T> {
+    /// Returns a reference to the data protected by the lock.
+    pub fn get<'b>(&'b self) -> &'b T {
+        unsafe { &(*self.lock.inner.get()).data }
+    }
+}
+
+#[unsafe_destructor]
+impl<'a, T: Freeze + Send> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe { (*self.lock.inner.get()).lock.read_unlock() }
+    }
+}
+
+impl<'a, T: Freeze + Send> RwLockWriteGuard<'a, T> {
+    /// Returns a reference to the data protected by the lock.
+    pub fn get<'b>(&'b self) -> &'b T {
+        unsafe { &(*self.lock.inner.get()).data }
+    }
+
+    /// Returns a mutable reference to the data protected by the lock.
+    pub fn get_mut<'b>(&'b mut self) -> &'b mut T {
+        unsafe { &mut (*self.lock.inner.get()).data }
+    }
+}
+
+#[unsafe_destructor]
+impl<'a, T: Freeze + Send> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let inner = self.lock.inner.get();
+            if !self.failing && task::failing() {
+                (*inner).poisoned = true;
+            }
+            (*inner).lock.write_unlock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prelude::*;
+
+    use extra::arc::RWArc;
+    use extra::test::BenchHarness;
+    use native;
+    use task;
+    use super::RwLock;
+
+    #[test]
+    fn smoke() {
+        let l = RwLock::new(1);
+        {
+            let r1 = l.read();
+            let r2 = l.read();
+            assert_eq!(*r1.get(), 1);
+            assert_eq!(*r2.get(), 1);
+            assert!(l.try_write().is_none());
+        }
+        {
+            let mut w = l.write();
+            *w.get_mut() = 2;
+            assert!(l.try_read().is_none());
+            assert!(l.try_write().is_none());
+        }
+        assert_eq!(*l.read().get(), 2);
+    }
+
+    #[test]
+    fn writer_blocks_new_readers() {
+        let l = RwLock::new(0);
+        let r = l.read();
+        let (p, c) = Chan::new();
+        let l2 = l.clone();
+        do spawn {
+            *l2.write().get_mut() = 1;
+            c.send(());
+        }
+        // Wait for the writer to queue up behind our read lock
+        while l.try_read().is_some() { task::deschedule(); }
+        drop(r);
+        p.recv();
+        assert_eq!(*l.read().get(), 1);
+    }
+
+    fn frob(spawn: |proc()|) {
+        static N: uint = 10;
+        static M: uint = 1000;
+        let l = RwLock::new(0u);
+        let (p, c) = SharedChan::new();
+        for i in range(0, N) {
+            let (l, c) = (l.clone(), c.clone());
+            spawn(proc() {
+                for _ in range(0, M) {
+                    if i % 2 == 0 {
+                        *l.write().get_mut() += 1;
+                    } else {
+                        let r = l.read();
+                        assert!(*r.get() <= N * M / 2);
+                    }
+                }
+                c.send(());
+            });
+        }
+        for _ in range(0, N) { p.recv(); }
+        assert_eq!(*l.read().get(), N * M / 2);
+    }
+
+    #[test]
+    fn frob_green() { frob(|f| task::spawn(f)); }
+
+    #[test]
+    fn frob_native() { frob(|f| native::task::spawn(f)); }
+
+    #[test]
+    fn poison_write() {
+        let l = RwLock::new(1);
+        let l2 = l.clone();
+        let _ = do task::try {
+            let _w = l2.write();
+            fail!();
+        };
+        assert!(l.is_poisoned());
+    }
+
+    #[test]
+    fn no_poison_read() {
+        let l = RwLock::new(1);
+        let l2 = l.clone();
+        let _ = do task::try {
+            let _r = l2.read();
+            fail!();
+        };
+        assert!(!l.is_poisoned());
+        assert_eq!(*l.read().get(), 1);
+    }
+
+    #[test] #[should_fail]
+    fn poison_fails_read() {
+        let l = RwLock::new(1);
+        let l2 = l.clone();
+        let _ = do task::try {
+            let _w = l2.write();
+            fail!();
+        };
+        l.read();
+    }
+
+    #[bench]
+    fn bench_read(bh: &mut BenchHarness) {
+        let l = RwLock::new(0);
+        bh.iter(|| { l.read(); });
+    }
+
+    #[bench]
+    fn bench_read_rwarc(bh: &mut BenchHarness) {
+        let l = RWArc::new(0);
+        bh.iter(|| { l.read(|_| ()); });
+    }
+}
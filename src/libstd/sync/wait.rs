@@ -0,0 +1,133 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Blocking support for the task-aware primitives in this module
+//!
+//! A blocked task is represented by a `WaitNode` which lives on the blocked
+//! task's own stack, so blocking never allocates. The task is descheduled
+//! through the runtime: green tasks are parked with their scheduler, and
+//! native tasks sleep on the native mutex and condition variable which their
+//! runtime keeps for each task.
+//!
+//! Waiting on a node isn't a cancellation point. A task which is cancelled
+//! while it waits is only woken up by `WaitNode::wake`.
+
+use option::{Option, Some, None};
+use ptr::RawPtr;
+use rt::local::Local;
+use rt::task::{Task, BlockedTask};
+use sync::atomics::{AtomicUint, SeqCst};
+
+// The state of a node is either one of these, or a `BlockedTask` cast to a
+// uint while the task is blocked.
+static EMPTY: uint = 0;
+static WOKEN: uint = 1;
+
+pub struct WaitNode {
+    priv state: AtomicUint,
+    priv next: *mut WaitNode,
+}
+
+impl WaitNode {
+    pub fn new() -> WaitNode {
+        WaitNode { state: AtomicUint::new(EMPTY), next: 0 as *mut WaitNode }
+    }
+
+    /// Blocks the current task until `wake` has been called on this node,
+    /// returning immediately if that has already happened.
+    pub fn wait(&mut self) {
+        if self.state.load(SeqCst) == WOKEN { return }
+        let task: ~Task = Local::take();
+        task.deschedule(1, |task| {
+            let n = unsafe { task.cast_to_uint() };
+            match self.state.compare_and_swap(EMPTY, n, SeqCst) {
+                EMPTY => Ok(()),
+                _ => Err(unsafe { BlockedTask::cast_from_uint(n) }),
+            }
+        });
+    }
+
+    /// Wakes up the task waiting on `node`. The waiting task may return (and
+    /// free the node) as soon as this is called, so the node must not be
+    /// touched afterwards.
+    pub unsafe fn wake(node: *mut WaitNode) {
+        match (*node).state.swap(WOKEN, SeqCst) {
+            EMPTY => {}
+            n => match BlockedTask::cast_from_uint(n).wake() {
+                Some(task) => task.reawaken(true),
+                None => {}
+            }
+        }
+    }
+}
+
+/// A FIFO queue of blocked tasks, linked together through their nodes. The
+/// queue needs to be protected by a lock of its own.
+pub struct WaitQueue {
+    priv head: *mut WaitNode,
+    priv tail: *mut WaitNode,
+    priv len: uint,
+}
+
+impl WaitQueue {
+    pub fn new() -> WaitQueue {
+        WaitQueue { head: 0 as *mut WaitNode, tail: 0 as *mut WaitNode, len: 0 }
+    }
+
+    pub fn len(&self) -> uint { self.len }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Appends `node` to the queue. The node must stay put until it has been
+    /// popped off of the queue again and woken up.
+    pub unsafe fn push(&mut self, node: *mut WaitNode) {
+        (*node).next = 0 as *mut WaitNode;
+        if self.tail.is_null() {
+            self.head = node;
+        } else {
+            (*self.tail).next = node;
+        }
+        self.tail = node;
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<*mut WaitNode> {
+        if self.head.is_null() { return None }
+        let node = self.head;
+        unsafe {
+            self.head = (*node).next;
+        }
+        if self.head.is_null() {
+            self.tail = 0 as *mut WaitNode;
+        }
+        self.len -= 1;
+        Some(node)
+    }
+
+    /// Removes all of the nodes from this queue, returning them in a queue of
+    /// their own.
+    pub fn take(&mut self) -> WaitQueue {
+        let ret = WaitQueue { head: self.head, tail: self.tail, len: self.len };
+        *self = WaitQueue::new();
+        ret
+    }
+
+    /// Wakes up all of the tasks in this queue, returning how many there
+    /// were.
+    pub unsafe fn wake_all(&mut self) -> uint {
+        let mut n = 0;
+        loop {
+            match self.pop() {
+                Some(node) => { WaitNode::wake(node); n += 1; }
+                None => return n,
+            }
+        }
+    }
+}
@@ -54,22 +54,35 @@
 #[allow(missing_doc)];
 
 use any::Any;
-use comm::{Chan, Port};
+use cast;
+use clone::Clone;
+use comm::{Chan, Port, SharedChan, Data, Empty, Disconnected};
+use container::Container;
 use io::Writer;
-use kinds::Send;
+use iter::{Iterator, OrdIterator};
+use kinds::{Freeze, Send};
+use logging;
 use logging::Logger;
 use option::{None, Some, Option};
+use ptr::RawPtr;
 use result::{Result, Ok, Err};
 use rt::local::Local;
 use rt::task::Task;
 use send_str::{SendStr, IntoSendStr};
 use str::Str;
+use unstable::finally::Finally;
+use unstable::mutex::{Mutex, MUTEX_INIT};
+use unstable::raw;
+use unstable::sync::Exclusive;
 use util;
+use vec::{OwnedVector, ImmutableVector, MutableVector};
 
 #[cfg(test)] use any::{AnyOwnExt, AnyRefExt};
-#[cfg(test)] use comm::SharedChan;
+#[cfg(test)] use comm::Select;
+#[cfg(test)] use iter::range;
 #[cfg(test)] use ptr;
 #[cfg(test)] use result;
+#[cfg(test)] use vec;
 
 /// Indicates the manner in which a task exited.
 ///
@@ -100,6 +113,8 @@ pub type TaskResult = Result<(), ~Any>;
  *           in. This is of particular importance for libraries which want to call
  *           into foreign code that blocks. Without doing so in a different
  *           scheduler other tasks will be impeded or even blocked indefinitely.
+ *
+ * * cancel - A token through which the task can be cooperatively cancelled.
  */
 pub struct TaskOpts {
     watched: bool,
@@ -109,6 +124,7 @@ pub struct TaskOpts {
     logger: Option<~Logger>,
     stdout: Option<~Writer>,
     stderr: Option<~Writer>,
+    cancel: Option<CancelToken>,
 }
 
 /**
@@ -195,6 +211,56 @@ impl TaskBuilder {
         self.opts.name = Some(name.into_send_str());
     }
 
+    /// Attach a cancellation token to the task-to-be. Cancelling the token
+    /// will cause the task to fail the next time it blocks.
+    pub fn cancel_token(&mut self, token: CancelToken) {
+        self.opts.cancel = Some(token);
+    }
+
+    /// Give the task-to-be a deadline of `msecs` milliseconds from the time it
+    /// starts running. Once the deadline expires, the task's cancellation
+    /// token is cancelled with `DeadlineExpired` (a token is created if one
+    /// has not already been attached).
+    ///
+    /// Deadlines are enforced by a helper task which uses a `Timer`, so the
+    /// new task must be spawned into a runtime with I/O available.
+    pub fn deadline(&mut self, msecs: u64) {
+        let token = self.get_cancel_token();
+        do self.add_wrapper |body| {
+            proc() {
+                let _deadline = Deadline::new(msecs, token);
+                body();
+            }
+        }
+    }
+
+    /**
+     * Spawn a child task which can be cancelled by the parent.
+     *
+     * This is like `spawn`, but the returned handle can be used to cancel the
+     * child and to wait for it to exit. If no cancellation token was attached
+     * to this builder, a new one is created.
+     *
+     * # Failure
+     * Fails if a future_result was already set for this task.
+     */
+    pub fn spawn_cancellable(mut self, f: proc()) -> CancellableTask {
+        let token = self.get_cancel_token();
+        let result = self.future_result();
+        self.spawn(f);
+        CancellableTask { token: token, result: result }
+    }
+
+    fn get_cancel_token(&mut self) -> CancelToken {
+        match self.opts.cancel {
+            Some(ref token) => return token.clone(),
+            None => {}
+        }
+        let token = CancelToken::new();
+        self.opts.cancel = Some(token.clone());
+        token
+    }
+
     /**
      * Add a wrapper to the body of the spawned task.
      *
@@ -295,6 +361,7 @@ impl TaskOpts {
             logger: None,
             stdout: None,
             stderr: None,
+            cancel: None,
         }
     }
 }
@@ -357,6 +424,509 @@ pub fn failing() -> bool {
     local.get().unwinder.unwinding()
 }
 
+/* Cancellation */
+
+/// The reason for which a task was cancelled.
+///
+/// A task which notices that it has been cancelled fails with a value of this
+/// type, so a cancelled task can be told apart from one which failed on its
+/// own by checking the cause of failure with `is::<CancelReason>()`.
+#[deriving(Eq, Clone)]
+pub enum CancelReason {
+    /// The token was cancelled with `CancelToken::cancel`.
+    Requested,
+    /// The deadline given to the task expired.
+    DeadlineExpired,
+}
+
+struct CancelState {
+    reason: Option<CancelReason>,
+    // Whether the cancellers taken out of `waiters` are still being run
+    cancelling: bool,
+    next_id: uint,
+    waiters: ~[(uint, ~Canceller)],
+}
+
+/// An action which interrupts a blocking operation when the token of the task
+/// performing it is cancelled.
+///
+/// Cancellers are run by the task which cancels the token, so they must not
+/// block.
+#[doc(hidden)]
+pub trait Canceller {
+    fn cancel(~self);
+}
+
+/// A token used to cooperatively cancel tasks.
+///
+/// A token is attached to a task through its `TaskOpts`. Cancellation is
+/// cooperative: a task is never interrupted at an arbitrary point, instead the
+/// blocking operations it performs notice the cancellation. Receiving on a
+/// port and `Timer::sleep` fail the task with the `CancelReason`, and I/O
+/// operations raise an `io_error` of kind `Cancelled`. Tasks which compute
+/// for a long time without blocking can call `task::check_cancelled`.
+///
+/// All clones of a token share the same state, so one token can be used to
+/// cancel a whole group of tasks.
+#[deriving(Clone)]
+pub struct CancelToken {
+    priv state: Exclusive<CancelState>,
+}
+
+impl CancelToken {
+    /// Creates a new token which has not been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken {
+            state: Exclusive::new(CancelState {
+                reason: None,
+                cancelling: false,
+                next_id: 0,
+                waiters: ~[],
+            })
+        }
+    }
+
+    /// Cancels every task using this token, waking up those which are
+    /// currently blocked. Cancelling a token more than once has no effect.
+    ///
+    /// Tasks blocked receiving on a port or sleeping on a timer are woken up
+    /// in every runtime. Blocking reads, writes and accepts on TCP and UDP
+    /// sockets are interrupted by shutting the socket down, after which the
+    /// socket can't be used for I/O any more. This isn't possible for sockets
+    /// of the libuv runtime on Windows, and other kinds of I/O (such as pipes
+    /// and Unix domain sockets) are not interrupted either. Those tasks notice
+    /// the cancellation when they next start an I/O operation or otherwise
+    /// reach a cancellation point.
+    pub fn cancel(&self) {
+        self.cancel_with(Requested)
+    }
+
+    /// Returns whether this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Returns why this token was cancelled, if it has been.
+    pub fn reason(&self) -> Option<CancelReason> {
+        unsafe { self.state.with(|s| s.reason) }
+    }
+
+    /// Fails the current task with the `CancelReason` if this token has been
+    /// cancelled.
+    pub fn check(&self) {
+        match self.reason() {
+            Some(reason) => fail!(reason),
+            None => {}
+        }
+    }
+
+    fn cancel_with(&self, reason: CancelReason) {
+        let waiters = unsafe {
+            self.state.with(|s| {
+                if s.reason.is_none() {
+                    s.reason = Some(reason);
+                    s.cancelling = true;
+                    Some(util::replace(&mut s.waiters, ~[]))
+                } else {
+                    None
+                }
+            })
+        };
+        let waiters = match waiters {
+            Some(waiters) => waiters,
+            None => return,
+        };
+        // Running a canceller may reschedule, which isn't allowed while
+        // holding the lock.
+        for (_, canceller) in waiters.move_iter() {
+            canceller.cancel();
+        }
+        unsafe { self.state.with(|s| s.cancelling = false) }
+    }
+
+    /// Registers a canceller to be run when this token is cancelled, for use
+    /// by blocking operations. The returned id must be passed to
+    /// `unregister` once the operation is complete. If the token has already
+    /// been cancelled, the canceller is handed back instead.
+    #[doc(hidden)]
+    pub fn register<C: Canceller + Send>(&self, canceller: C) -> Result<uint, C> {
+        unsafe {
+            self.state.with(|s| {
+                if s.reason.is_some() {
+                    Err(canceller)
+                } else {
+                    let id = s.next_id;
+                    s.next_id += 1;
+                    s.waiters.push((id, ~canceller as ~Canceller));
+                    Ok(id)
+                }
+            })
+        }
+    }
+
+    /// Removes the canceller registered as `id`, returning whether it was
+    /// still registered. If it wasn't, the canceller has been run by the time
+    /// that this returns, so the resources that it refers to can be freed.
+    #[doc(hidden)]
+    pub fn unregister(&self, id: uint) -> bool {
+        loop {
+            // The canceller is dropped outside of the lock for the same
+            // reason that cancellers are run outside of it.
+            let (found, _canceller) = unsafe {
+                self.state.with(|s| {
+                    match s.waiters.iter().position(|&(i, _)| i == id) {
+                        Some(i) => (Some(true), Some(s.waiters.swap_remove(i))),
+                        None if s.cancelling => (None, None),
+                        None => (Some(false), None),
+                    }
+                })
+            };
+            match found {
+                Some(found) => return found,
+                None => deschedule(),
+            }
+        }
+    }
+}
+
+/* Deadlines */
+
+// All deadlines are enforced by a single helper task. It is spawned when a
+// deadline is set while none are outstanding, and it exits once all of the
+// tasks with deadlines have finished (which closes its port), so it never
+// keeps the runtime alive on its own.
+enum DeadlineMsg {
+    NewDeadline(uint, u64, CancelToken),
+    RemoveDeadline(uint),
+}
+
+struct DeadlineState {
+    chan: Option<SharedChan<DeadlineMsg>>,
+    outstanding: uint,
+    next_id: uint,
+}
+
+static mut DEADLINE_LOCK: Mutex = MUTEX_INIT;
+static mut DEADLINE_STATE: *mut DeadlineState = 0 as *mut DeadlineState;
+
+// Runs `f` on the global deadline state. Sending and spawning may reschedule,
+// so neither may happen inside of `f`.
+fn with_deadline_state<T>(f: |&mut DeadlineState| -> T) -> T {
+    unsafe {
+        DEADLINE_LOCK.lock();
+        if DEADLINE_STATE.is_null() {
+            DEADLINE_STATE = cast::transmute(~DeadlineState {
+                chan: None,
+                outstanding: 0,
+                next_id: 0,
+            });
+        }
+        let ret = f(&mut *DEADLINE_STATE);
+        DEADLINE_LOCK.unlock();
+        ret
+    }
+}
+
+// The deadline of the current task, which is removed when the task's body
+// returns or unwinds
+struct Deadline {
+    id: uint,
+}
+
+impl Deadline {
+    fn new(msecs: u64, token: CancelToken) -> Deadline {
+        let (id, chan, port) = with_deadline_state(|state| {
+            let port = match state.chan {
+                Some(..) => None,
+                None => {
+                    let (port, chan) = SharedChan::new();
+                    state.chan = Some(chan);
+                    Some(port)
+                }
+            };
+            let id = state.next_id;
+            state.next_id += 1;
+            state.outstanding += 1;
+            (id, state.chan.get_ref().clone(), port)
+        });
+        match port {
+            Some(port) => {
+                let mut t = task();
+                t.name("<deadline helper>");
+                t.unwatched();
+                do t.spawn { deadline_helper(port) }
+            }
+            None => {}
+        }
+        chan.send(NewDeadline(id, now_ms() + msecs, token));
+        Deadline { id: id }
+    }
+}
+
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        let chan = with_deadline_state(|state| {
+            state.outstanding -= 1;
+            if state.outstanding == 0 {
+                state.chan.take_unwrap()
+            } else {
+                state.chan.get_ref().clone()
+            }
+        });
+        chan.send(RemoveDeadline(self.id));
+    }
+}
+
+fn deadline_helper(mut port: Port<DeadlineMsg>) {
+    let mut active: ~[(uint, u64, CancelToken)] = ~[];
+    loop {
+        // Cancel the tokens of the deadlines which have expired, and work out
+        // how long to wait for the next one.
+        let now = now_ms();
+        let mut i = 0;
+        while i < active.len() {
+            let expired = match active[i] { (_, expiry, _) => expiry <= now };
+            if expired {
+                let (_, _, token) = active.swap_remove(i);
+                token.cancel_with(DeadlineExpired);
+            } else {
+                i += 1;
+            }
+        }
+        let next = active.iter().map(|&(_, expiry, _)| expiry).min();
+
+        let msg = match next {
+            None => port.recv_opt(),
+            Some(expiry) => match port.recv_timeout(expiry - now) {
+                Data(msg) => Some(msg),
+                Empty => continue,
+                Disconnected => None,
+            },
+        };
+        match msg {
+            Some(NewDeadline(id, expiry, token)) => {
+                active.push((id, expiry, token));
+            }
+            Some(RemoveDeadline(id)) => {
+                match active.iter().position(|&(i, _, _)| i == id) {
+                    Some(i) => { active.swap_remove(i); }
+                    None => {}
+                }
+            }
+            None => break,
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    let (secs, nsecs) = logging::now();
+    secs as u64 * 1000 + nsecs as u64 / 1000000
+}
+
+/// A handle to a task spawned with `TaskBuilder::spawn_cancellable`.
+pub struct CancellableTask {
+    priv token: CancelToken,
+    priv result: Port<TaskResult>,
+}
+
+impl CancellableTask {
+    /// Returns a handle to the cancellation token of the task.
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    /// Requests that the task be cancelled. This does not wait for it to exit.
+    pub fn cancel(&self) {
+        self.token.cancel()
+    }
+
+    /// Waits for the task to exit, returning its result. A task which exited
+    /// due to cancellation fails with its `CancelReason`.
+    pub fn join(self) -> TaskResult {
+        let CancellableTask { result, .. } = self;
+        result.recv()
+    }
+
+    /// Cancels the task and then waits for it to exit.
+    pub fn cancel_and_join(self) -> TaskResult {
+        self.cancel();
+        self.join()
+    }
+}
+
+/// Returns a handle to the cancellation token of the current task, if it has
+/// one.
+///
+/// A task which is unwinding has no token, so that destructors can still
+/// block and perform I/O without failing a second time (which would abort
+/// the whole process).
+pub fn cancel_token() -> Option<CancelToken> {
+    unsafe {
+        let task: Option<*mut Task> = Local::try_unsafe_borrow();
+        match task {
+            Some(task) if !(*task).unwinder.unwinding() => {
+                (*task).cancel.clone()
+            }
+            Some(..) | None => None,
+        }
+    }
+}
+
+/// Returns whether the current task has been cancelled. This is always false
+/// while the task is unwinding.
+pub fn cancelled() -> bool {
+    match cancel_token() {
+        Some(token) => token.is_cancelled(),
+        None => false,
+    }
+}
+
+/// Fails the current task if it has been cancelled. This can be used as a
+/// cancellation point in code which doesn't otherwise block.
+pub fn check_cancelled() {
+    match cancel_token() {
+        Some(token) => token.check(),
+        None => {}
+    }
+}
+
+/* Scoped tasks */
+
+/**
+ * A scope for spawning tasks which borrow from the stack of their parent.
+ *
+ * Tasks spawned through a `Scope` run `proc`s, so everything that they
+ * capture must be sendable, but each of them is also handed a slice which
+ * the parent has borrowed for the lifetime `'a` of the scope. Slices with
+ * shared access can be handed to any number of tasks, as long as their
+ * elements have no interior mutability. Slices with exclusive access can be
+ * split up between tasks with `mut_split_at` or `mut_chunks`. Every task
+ * spawned in a scope is joined when the scope is dropped, which happens
+ * before the borrows end. This is true even if the parent task fails: the
+ * scope is dropped during unwinding, and it waits for its children to finish
+ * before unwinding continues.
+ *
+ * If any of the tasks in a scope fails, the parent fails once all of the
+ * tasks have been joined.
+ *
+ * Tasks are spawned into the same runtime as the parent, so scopes can be
+ * used from both green and native tasks.
+ *
+ * # Example
+ *
+ * ```rust
+ * use std::task::Scope;
+ *
+ * let mut data = [1, 2, 3, 4, 5, 6];
+ * {
+ *     let mut scope = Scope::new();
+ *     for chunk in data.mut_chunks(2) {
+ *         scope.spawn_mut(chunk, proc(chunk) {
+ *             for x in chunk.mut_iter() { *x *= 2 }
+ *         });
+ *     }
+ * }
+ * assert_eq!(data.to_owned(), ~[2, 4, 6, 8, 10, 12]);
+ * ```
+ */
+pub struct Scope<'a> {
+    priv results: ~[Port<TaskResult>],
+    priv marker: Option<&'a ()>,
+}
+
+impl<'a> Scope<'a> {
+    /// Creates a new scope with no tasks in it.
+    pub fn new() -> Scope<'a> {
+        Scope { results: ~[], marker: None }
+    }
+
+    /// Spawns a task in this scope which will run `f` with shared access to
+    /// `data`.
+    pub fn spawn<T: Freeze + Send>(&mut self, data: &'a [T], f: proc(&[T])) {
+        self.spawn_with(task(), data, f)
+    }
+
+    /// Spawns a task in this scope which will run `f` with exclusive access
+    /// to `data`.
+    pub fn spawn_mut<T: Send>(&mut self, data: &'a mut [T], f: proc(&mut [T])) {
+        self.spawn_mut_with(task(), data, f)
+    }
+
+    /// Like `spawn`, but uses `builder` to configure the task.
+    ///
+    /// # Failure
+    ///
+    /// Fails if a future_result was already set for the builder.
+    pub fn spawn_with<T: Freeze + Send>(&mut self, builder: TaskBuilder,
+                                        data: &'a [T], f: proc(&[T])) {
+        let (ptr, len) = (data.as_ptr() as uint, data.len());
+        self.spawn_raw(builder, proc() {
+            f(unsafe { cast::transmute(raw::Slice { data: ptr as *T, len: len }) })
+        })
+    }
+
+    /// Like `spawn_mut`, but uses `builder` to configure the task.
+    ///
+    /// # Failure
+    ///
+    /// Fails if a future_result was already set for the builder.
+    pub fn spawn_mut_with<T: Send>(&mut self, builder: TaskBuilder,
+                                   data: &'a mut [T], f: proc(&mut [T])) {
+        let (ptr, len) = (data.as_mut_ptr() as uint, data.len());
+        self.spawn_raw(builder, proc() {
+            f(unsafe { cast::transmute(raw::Slice { data: ptr as *T, len: len }) })
+        })
+    }
+
+    // The slices can't be sent to the children directly as they borrow from
+    // the parent, so they are passed as raw parts instead. This is sound
+    // because the bounds on the element types make it safe to access them
+    // from the child, and the child is joined before the borrow ends.
+    fn spawn_raw(&mut self, mut builder: TaskBuilder, f: proc()) {
+        self.results.push(builder.future_result());
+        builder.spawn(f);
+    }
+
+    /// Waits for all of the tasks spawned so far in this scope to exit,
+    /// returning whether all of them succeeded.
+    pub fn join(&mut self) -> bool {
+        let results = util::replace(&mut self.results, ~[]);
+        // Joining must not be interrupted, or the children could outlive the
+        // data that they borrow.
+        uncancellable(|| {
+            results.move_iter().fold(true, |ok, port| port.recv().is_ok() && ok)
+        })
+    }
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for Scope<'a> {
+    fn drop(&mut self) {
+        if !self.join() && !failing() {
+            fail!("a task spawned in a scope failed");
+        }
+    }
+}
+
+/// Runs `f` with the cancellation token of the current task detached, so
+/// that the blocking operations it performs can't be interrupted.
+///
+/// This is meant for code which must wait for other tasks to finish before it
+/// can safely return (or unwind), such as joining tasks which borrow from the
+/// current stack frame.
+pub fn uncancellable<T>(f: || -> T) -> T {
+    let token = {
+        let mut task = Local::borrow(None::<Task>);
+        task.get().cancel.take()
+    };
+    if token.is_none() { return f() }
+    let mut token = token;
+    f.finally(|| {
+        let mut task = Local::borrow(None::<Task>);
+        task.get().cancel = token.take();
+    })
+}
+
 // The following 8 tests test the following 2^3 combinations:
 // {un,}linked {un,}supervised failure propagation {up,down}wards.
 
@@ -628,3 +1198,257 @@ fn test_try_fail_message_unit_struct() {
         Err(_) | Ok(()) => fail!()
     }
 }
+
+#[test]
+fn test_cancel_blocked_task() {
+    let (port, _chan) = Chan::<()>::new();
+    let child = do task().spawn_cancellable {
+        port.recv();
+    };
+    deschedule();
+    match child.cancel_and_join() {
+        Err(ref e) if e.is::<CancelReason>() => {
+            assert_eq!(*e.as_ref::<CancelReason>().unwrap(), Requested);
+        }
+        Err(_) | Ok(()) => fail!()
+    }
+}
+
+#[test]
+fn test_cancel_task_blocked_in_select_set() {
+    let (port, _chan) = Chan::<()>::new();
+    let (started, start) = Chan::new();
+    let child = do task().spawn_cancellable {
+        let mut port = port;
+        let sel = Select::new();
+        let mut handle = sel.add(&mut port);
+        start.send(());
+        handle.recv();
+    };
+    started.recv();
+    assert!(child.cancel_and_join().is_err());
+}
+
+#[test]
+fn test_cancel_before_spawn() {
+    let token = CancelToken::new();
+    token.cancel();
+    let mut t = task();
+    t.cancel_token(token.clone());
+    let child = do t.spawn_cancellable {
+        assert!(cancelled());
+        check_cancelled();
+    };
+    let res = child.join();
+    assert!(res.is_err());
+    assert!(token.is_cancelled());
+}
+
+#[test]
+fn test_cancellable_task_finishes() {
+    let child = do task().spawn_cancellable {
+        assert!(cancel_token().is_some());
+        assert!(!cancelled());
+        let (port, chan) = Chan::new();
+        chan.send(1);
+        assert_eq!(port.recv(), 1);
+    };
+    assert!(child.join().is_ok());
+    assert!(cancel_token().is_none());
+}
+
+#[test]
+fn test_cancel_many_tasks() {
+    let token = CancelToken::new();
+    let (port, chan) = SharedChan::<()>::new();
+    let mut results = ~[];
+    for _ in range(0, 5) {
+        let mut t = task();
+        t.cancel_token(token.clone());
+        results.push(t.future_result());
+        let chan = chan.clone();
+        do t.spawn {
+            let (p, _c) = Chan::<()>::new();
+            chan.send(());
+            p.recv();
+        }
+    }
+    for _ in range(0, 5) { port.recv(); }
+    token.cancel();
+    for r in results.iter() {
+        assert!(r.recv().is_err());
+    }
+}
+
+#[test]
+fn test_deadline_expires() {
+    use io::timer;
+
+    let mut t = task();
+    t.deadline(10);
+    match do t.try { timer::sleep(100000) } {
+        Err(ref e) if e.is::<CancelReason>() => {
+            assert_eq!(*e.as_ref::<CancelReason>().unwrap(), DeadlineExpired);
+        }
+        Err(_) | Ok(()) => fail!()
+    }
+}
+
+#[test]
+fn test_many_deadlines() {
+    use io::timer;
+
+    // All of these deadlines are enforced by the same helper task
+    let results = vec::from_fn(5, |i| {
+        let mut t = task();
+        t.deadline(10 * (i as u64 + 1));
+        let result = t.future_result();
+        do t.spawn { timer::sleep(100000) }
+        result
+    });
+    let mut t = task();
+    t.deadline(100000);
+    assert_eq!((do t.try { 3 }).unwrap(), 3);
+    for result in results.iter() {
+        assert!(result.recv().is_err());
+    }
+}
+
+#[test]
+fn test_deadline_not_reached() {
+    let mut t = task();
+    t.deadline(100000);
+    let ret = do t.try { 3 };
+    assert_eq!(ret.unwrap(), 3);
+}
+
+#[test]
+fn test_cancelled_io() {
+    use io;
+    use io::Timer;
+
+    let token = CancelToken::new();
+    token.cancel();
+    let mut t = task();
+    t.cancel_token(token);
+    let ret = do t.try {
+        let mut kind = None;
+        io::io_error::cond.trap(|e| kind = Some(e.kind)).inside(|| {
+            assert!(Timer::new().is_none());
+        });
+        kind
+    };
+    assert_eq!(ret.unwrap(), Some(io::Cancelled));
+}
+
+#[test]
+fn test_scoped_borrow() {
+    let data = [1, 2, 3, 4, 5, 6];
+    let (port, chan) = SharedChan::new();
+    {
+        let mut scope = Scope::new();
+        let chan2 = chan.clone();
+        scope.spawn(data.slice_to(3), proc(data) {
+            chan2.send(data.iter().fold(0, |a, &b| a + b));
+        });
+        scope.spawn(data.slice_from(3), proc(data) {
+            chan.send(data.iter().fold(0, |a, &b| a + b));
+        });
+    }
+    assert_eq!(port.recv() + port.recv(), 21);
+}
+
+#[test]
+fn test_scoped_mut_split() {
+    let mut data = vec::from_elem(100, 0u);
+    {
+        let (a, b) = data.mut_split_at(50);
+        let mut scope = Scope::new();
+        scope.spawn_mut(a, proc(a) { for x in a.mut_iter() { *x = 1 } });
+        scope.spawn_mut(b, proc(b) { for x in b.mut_iter() { *x = 2 } });
+        assert!(scope.join());
+    }
+    assert!(data.slice_to(50).iter().all(|&x| x == 1));
+    assert!(data.slice_from(50).iter().all(|&x| x == 2));
+}
+
+#[test]
+fn test_scoped_native() {
+    use native;
+
+    let (port, chan) = Chan::new();
+    do native::task::spawn {
+        let data = [1, 2, 3];
+        let mut sum = [0];
+        {
+            let mut scope = Scope::new();
+            scope.spawn_mut(sum.as_mut_slice(), proc(sum) {
+                sum[0] = data.iter().fold(0, |a, &b| a + b);
+            });
+        }
+        chan.send(sum[0]);
+    }
+    assert_eq!(port.recv(), 6);
+}
+
+#[test]
+fn test_scoped_failure_propagates() {
+    let ret = do try {
+        let data = [1];
+        let mut scope = Scope::new();
+        scope.spawn(data.as_slice(), proc(_) fail!());
+    };
+    assert!(ret.is_err());
+}
+
+#[test]
+fn test_scope_joins_on_parent_failure() {
+    use sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+    static mut DONE: AtomicUint = INIT_ATOMIC_UINT;
+
+    let ret = do try {
+        let data = [1];
+        let mut scope = Scope::new();
+        scope.spawn(data.as_slice(), proc(_) {
+            for _ in range(0, 10) { deschedule() }
+            unsafe { DONE.store(1, SeqCst); }
+        });
+        fail!();
+    };
+    assert!(ret.is_err());
+    assert_eq!(unsafe { DONE.load(SeqCst) }, 1);
+}
+
+#[test]
+fn test_scope_ignores_cancellation() {
+    let token = CancelToken::new();
+    let mut t = task();
+    t.cancel_token(token.clone());
+    let child = do t.spawn_cancellable {
+        let data = [1];
+        let mut scope = Scope::new();
+        scope.spawn(data.as_slice(), proc(_) token.cancel());
+        assert!(scope.join());
+        assert!(cancelled());
+    };
+    assert!(child.join().is_ok());
+}
+
+#[test]
+fn test_uncancellable() {
+    let token = CancelToken::new();
+    token.cancel();
+    let mut t = task();
+    t.cancel_token(token);
+    let child = do t.spawn_cancellable {
+        let (port, chan) = Chan::new();
+        chan.send(1);
+        let got = uncancellable(|| {
+            assert!(!cancelled());
+            port.recv()
+        });
+        assert_eq!(got, 1);
+        assert!(cancelled());
+    };
+    assert!(child.join().is_ok());
+}
@@ -23,8 +23,8 @@ across other volatile intrinsics. See the LLVM documentation on
 
 # Atomics
 
-The atomic intrinsics provide common atomic operations on machine
-words, with multiple possible memory orderings. They obey the same
+The atomic intrinsics provide common atomic operations on integers of
+any width, with multiple possible memory orderings. They obey the same
 semantics as C++11. See the LLVM documentation on [[atomics]].
 
 [atomics]: http://llvm.org/docs/Atomics.html
@@ -177,7 +177,10 @@ extern "rust-intrinsic" {
 
     pub fn volatile_load<T>(src: *T) -> T;
     pub fn volatile_store<T>(dst: *mut T, val: T);
+}
 
+#[cfg(stage0)]
+extern "rust-intrinsic" {
     /// Atomic compare and exchange, sequentially consistent.
     pub fn atomic_cxchg(dst: &mut int, old: int, src: int) -> int;
     /// Atomic compare and exchange, acquire ordering.
@@ -282,6 +285,126 @@ extern "rust-intrinsic" {
     pub fn atomic_fence_acq();
     pub fn atomic_fence_rel();
     pub fn atomic_fence_acqrel();
+}
+
+#[cfg(not(stage0))]
+extern "rust-intrinsic" {
+    /// Atomic compare and exchange, sequentially consistent.
+    pub fn atomic_cxchg<T>(dst: &mut T, old: T, src: T) -> T;
+    /// Atomic compare and exchange, acquire ordering.
+    pub fn atomic_cxchg_acq<T>(dst: &mut T, old: T, src: T) -> T;
+    /// Atomic compare and exchange, release ordering.
+    pub fn atomic_cxchg_rel<T>(dst: &mut T, old: T, src: T) -> T;
+
+    pub fn atomic_cxchg_acqrel<T>(dst: &mut T, old: T, src: T) -> T;
+    pub fn atomic_cxchg_relaxed<T>(dst: &mut T, old: T, src: T) -> T;
+
+
+    /// Atomic load, sequentially consistent.
+    pub fn atomic_load<T>(src: &T) -> T;
+    /// Atomic load, acquire ordering.
+    pub fn atomic_load_acq<T>(src: &T) -> T;
+
+    pub fn atomic_load_relaxed<T>(src: &T) -> T;
+
+    /// Atomic store, sequentially consistent.
+    pub fn atomic_store<T>(dst: &mut T, val: T);
+    /// Atomic store, release ordering.
+    pub fn atomic_store_rel<T>(dst: &mut T, val: T);
+
+    pub fn atomic_store_relaxed<T>(dst: &mut T, val: T);
+
+    /// Atomic exchange, sequentially consistent.
+    pub fn atomic_xchg<T>(dst: &mut T, src: T) -> T;
+    /// Atomic exchange, acquire ordering.
+    pub fn atomic_xchg_acq<T>(dst: &mut T, src: T) -> T;
+    /// Atomic exchange, release ordering.
+    pub fn atomic_xchg_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xchg_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xchg_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    /// Atomic addition, sequentially consistent.
+    pub fn atomic_xadd<T>(dst: &mut T, src: T) -> T;
+    /// Atomic addition, acquire ordering.
+    pub fn atomic_xadd_acq<T>(dst: &mut T, src: T) -> T;
+    /// Atomic addition, release ordering.
+    pub fn atomic_xadd_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xadd_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xadd_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    /// Atomic subtraction, sequentially consistent.
+    pub fn atomic_xsub<T>(dst: &mut T, src: T) -> T;
+    /// Atomic subtraction, acquire ordering.
+    pub fn atomic_xsub_acq<T>(dst: &mut T, src: T) -> T;
+    /// Atomic subtraction, release ordering.
+    pub fn atomic_xsub_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xsub_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xsub_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_and<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_and_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_and_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_and_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_and_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_nand<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_nand_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_nand_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_nand_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_nand_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_or<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_or_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_or_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_or_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_or_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_xor<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xor_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xor_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xor_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_xor_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_max<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_max_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_max_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_max_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_max_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_min<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_min_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_min_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_min_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_min_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_umin<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umin_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umin_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umin_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umin_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_umax<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umax_acq<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umax_rel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umax_acqrel<T>(dst: &mut T, src: T) -> T;
+    pub fn atomic_umax_relaxed<T>(dst: &mut T, src: T) -> T;
+
+    pub fn atomic_fence();
+    pub fn atomic_fence_acq();
+    pub fn atomic_fence_rel();
+    pub fn atomic_fence_acqrel();
+
+    /// A compiler-only memory barrier, sequentially consistent. This prevents
+    /// the compiler from reordering memory accesses across it, but emits no
+    /// machine instructions, which makes it useful for synchronizing with
+    /// signal handlers running on the same thread.
+    pub fn atomic_singlethreadfence();
+    pub fn atomic_singlethreadfence_acq();
+    pub fn atomic_singlethreadfence_rel();
+    pub fn atomic_singlethreadfence_acqrel();
+}
+
+extern "rust-intrinsic" {
 
     /// The size of a type in bytes.
     ///
@@ -48,6 +48,9 @@
 #[allow(non_camel_case_types)];
 
 use int;
+use libc;
+use ptr::RawPtr;
+use unstable::intrinsics;
 use sync::atomics;
 
 pub struct Mutex {
@@ -161,11 +164,21 @@ impl Mutex {
     }
 }
 
+// The blocks of the OS primitives come from the C allocator rather than the
+// global heap, so that a designated global allocator may use these locks.
+unsafe fn malloc_block(size: uint) -> *mut libc::c_void {
+    let block = libc::malloc(size as libc::size_t);
+    if block.is_null() {
+        intrinsics::abort();
+    }
+    block
+}
+
 #[cfg(unix)]
 mod imp {
     use libc;
     use ptr;
-    use rt::global_heap::malloc_raw;
+    use super::malloc_block;
 
     type pthread_mutex_t = libc::c_void;
     type pthread_mutexattr_t = libc::c_void;
@@ -173,14 +186,14 @@ mod imp {
     type pthread_condattr_t = libc::c_void;
 
     pub unsafe fn init_lock() -> uint {
-        let block = malloc_raw(rust_pthread_mutex_t_size() as uint) as *mut pthread_mutex_t;
+        let block = malloc_block(rust_pthread_mutex_t_size() as uint) as *mut pthread_mutex_t;
         let n = pthread_mutex_init(block, ptr::null());
         assert_eq!(n, 0);
         return block as uint;
     }
 
     pub unsafe fn init_cond() -> uint {
-        let block = malloc_raw(rust_pthread_cond_t_size() as uint) as *mut pthread_cond_t;
+        let block = malloc_block(rust_pthread_cond_t_size() as uint) as *mut pthread_cond_t;
         let n = pthread_cond_init(block, ptr::null());
         assert_eq!(n, 0);
         return block as uint;
@@ -245,13 +258,13 @@ mod imp {
     use libc;
     use libc::{HANDLE, BOOL, LPSECURITY_ATTRIBUTES, c_void, DWORD, LPCSTR};
     use ptr;
-    use rt::global_heap::malloc_raw;
+    use super::malloc_block;
 
     type LPCRITICAL_SECTION = *c_void;
     static SPIN_COUNT: DWORD = 4000;
 
     pub unsafe fn init_lock() -> uint {
-        let block = malloc_raw(rust_crit_section_size() as uint) as *c_void;
+        let block = malloc_block(rust_crit_section_size() as uint) as *c_void;
         InitializeCriticalSectionAndSpinCount(block, SPIN_COUNT);
         return block as uint;
     }
@@ -15,11 +15,10 @@ use prelude::*;
 use container::Container;
 use mem::size_of;
 use cast::{forget, transmute};
-use rt::global_heap::{malloc_raw, realloc_raw};
+use rt::global_heap::{malloc_raw, realloc_raw, exchange_free};
 use vec::Items;
 use unstable::raw::Slice;
 use ptr::{offset, read_ptr};
-use libc::{free, c_void};
 use unstable::intrinsics::move_val_init;
 
 pub struct Vec<T> {
@@ -94,7 +93,7 @@ impl<T> Vec<T> {
 
     pub fn shrink_to_fit(&mut self) {
         if self.len == 0 {
-            unsafe { free(self.ptr as *mut c_void) };
+            unsafe { exchange_free(self.ptr as *u8) };
             self.cap = 0;
             self.ptr = 0 as *mut T;
         } else {
@@ -166,7 +165,7 @@ impl<T> Vec<T> {
     pub fn move_iter(self) -> MoveItems<T> {
         unsafe {
             let iter = transmute(self.as_slice().iter());
-            let ptr = self.ptr as *mut c_void;
+            let ptr = self.ptr as *mut u8;
             forget(self);
             MoveItems { allocation: ptr, iter: iter }
         }
@@ -186,13 +185,13 @@ impl<T> Drop for Vec<T> {
             for x in self.as_mut_slice().iter() {
                 read_ptr(x);
             }
-            free(self.ptr as *mut c_void)
+            exchange_free(self.ptr as *u8)
         }
     }
 }
 
 pub struct MoveItems<T> {
-    priv allocation: *mut c_void, // the block of memory allocated for the vector
+    priv allocation: *mut u8, // the block of memory allocated for the vector
     priv iter: Items<'static, T>
 }
 
@@ -225,7 +224,7 @@ impl<T> Drop for MoveItems<T> {
         // destroy the remaining elements
         for _x in *self {}
         unsafe {
-            free(self.allocation)
+            exchange_free(self.allocation as *u8)
         }
     }
 }
@@ -32,6 +32,7 @@ pub mod to_str;
 pub mod zero;
 pub mod default;
 pub mod primitive;
+pub mod trace;
 
 #[path="cmp/eq.rs"]
 pub mod eq;
@@ -103,6 +104,8 @@ pub fn expand_meta_deriving(cx: &ExtCtxt,
 
                             "FromPrimitive" => expand!(primitive::expand_deriving_from_primitive),
 
+                            "Trace" => expand!(trace::expand_deriving_trace),
+
                             ref tname => {
                                 cx.span_err(titem.span, format!("unknown \
                                     `deriving` trait: `{}`", *tname));
@@ -0,0 +1,62 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use ast::{MetaItem, Item, Expr, MutMutable};
+use codemap::Span;
+use ext::base::ExtCtxt;
+use ext::build::AstBuilder;
+use ext::deriving::generic::*;
+
+pub fn expand_deriving_trace(cx: &ExtCtxt,
+                             span: Span,
+                             mitem: @MetaItem,
+                             in_items: ~[@Item]) -> ~[@Item] {
+    let trait_def = TraitDef {
+        cx: cx, span: span,
+
+        path: Path::new(~["std", "cc", "Trace"]),
+        additional_bounds: ~[],
+        generics: LifetimeBounds::empty(),
+        methods: ~[
+            MethodDef {
+                name: "trace",
+                generics: LifetimeBounds::empty(),
+                explicit_self: borrowed_explicit_self(),
+                args: ~[Ptr(~Literal(Path::new(~["std", "cc", "Tracer"])),
+                            Borrowed(None, MutMutable))],
+                ret_ty: nil_ty(),
+                inline: false,
+                const_nonmatching: false,
+                combine_substructure: trace_substructure
+            }
+        ]
+    };
+
+    trait_def.expand(mitem, in_items)
+}
+
+// Traces every field in turn: `{ self.a.trace(tracer); self.b.trace(tracer); }`
+fn trace_substructure(cx: &ExtCtxt, span: Span, substr: &Substructure) -> @Expr {
+    let tracer = match substr.nonself_args {
+        [tracer] => tracer,
+        _ => cx.span_bug(span, "incorrect number of arguments in `deriving(Trace)`")
+    };
+    let trace = substr.method_ident;
+
+    let fields = match *substr.fields {
+        Struct(ref fs) | EnumMatching(_, _, ref fs) => fs,
+        _ => cx.span_bug(span, "impossible substructure in `deriving(Trace)`")
+    };
+
+    let stmts = fields.iter().map(|field| {
+        cx.stmt_expr(cx.expr_method_call(span, field.self_, trace, ~[tracer]))
+    }).collect();
+    cx.expr_block(cx.block(span, stmts, None))
+}
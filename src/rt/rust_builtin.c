@@ -358,6 +358,115 @@ rust_valgrind_stack_deregister(unsigned int id) {
   VALGRIND_STACK_DEREGISTER(id);
 }
 
+// The guard page of the green task currently running on this thread. The
+// scheduler gets a pointer to its thread's copy from
+// rust_stack_guard_install and updates it directly on every context switch.
+struct rust_stack_guard {
+    uintptr_t lo;
+    uintptr_t hi;
+};
+
+#if defined(__WIN32__)
+
+struct rust_stack_guard *
+rust_stack_guard_install() {
+    // guard page faults are reported by the OS as access violations, so
+    // there's nothing to keep track of
+    return NULL;
+}
+
+void
+rust_stack_guard_uninstall() {
+}
+
+#else
+
+// Both of these are per-thread. They're __thread variables rather than hung
+// off of a pthread key because pthread_getspecific isn't async-signal-safe.
+static __thread struct rust_stack_guard rust_stack_guard;
+// The alternate stack the fault handler runs on (we can't run it on the
+// stack that just overflowed).
+static __thread void *rust_stack_guard_altstack;
+
+static pthread_once_t rust_stack_guard_once = PTHREAD_ONCE_INIT;
+static struct sigaction rust_stack_guard_old_segv;
+static struct sigaction rust_stack_guard_old_bus;
+
+static void
+rust_stack_guard_handler(int signum, siginfo_t *info, void *context) {
+    uintptr_t addr = (uintptr_t) info->si_addr;
+    if (addr >= rust_stack_guard.lo && addr < rust_stack_guard.hi) {
+        static const char msg[] =
+            "fatal runtime error: task has overflowed its stack "
+            "(hit the stack guard page)\n";
+        // only async-signal-safe functions from here on out
+        write(2, msg, sizeof(msg) - 1);
+        abort();
+    }
+
+    // Not a guard page, so hand the fault to whatever was installed before
+    // us, leaving our handler in place for every other thread.
+    struct sigaction *old = signum == SIGSEGV ? &rust_stack_guard_old_segv
+                                              : &rust_stack_guard_old_bus;
+    if (old->sa_flags & SA_SIGINFO) {
+        old->sa_sigaction(signum, info, context);
+    } else if (old->sa_handler == SIG_DFL || old->sa_handler == SIG_IGN) {
+        // The default action terminates the process, so it's fine to restore
+        // it and let the faulting instruction run again. Ignoring the fault
+        // would just run it again forever, so that gets the default action
+        // as well.
+        signal(signum, SIG_DFL);
+    } else {
+        old->sa_handler(signum);
+    }
+}
+
+static void
+rust_stack_guard_init() {
+    struct sigaction action;
+    memset(&action, 0, sizeof(action));
+    sigemptyset(&action.sa_mask);
+    action.sa_flags = SA_SIGINFO | SA_ONSTACK;
+    action.sa_sigaction = rust_stack_guard_handler;
+    sigaction(SIGSEGV, &action, &rust_stack_guard_old_segv);
+    sigaction(SIGBUS, &action, &rust_stack_guard_old_bus);
+}
+
+struct rust_stack_guard *
+rust_stack_guard_install() {
+    pthread_once(&rust_stack_guard_once, rust_stack_guard_init);
+    if (rust_stack_guard_altstack != NULL) return &rust_stack_guard;
+
+    stack_t stack;
+    stack.ss_sp = malloc(SIGSTKSZ);
+    stack.ss_size = SIGSTKSZ;
+    stack.ss_flags = 0;
+    if (stack.ss_sp == NULL) return &rust_stack_guard;
+    if (sigaltstack(&stack, NULL) != 0) {
+        free(stack.ss_sp);
+        return &rust_stack_guard;
+    }
+    rust_stack_guard_altstack = stack.ss_sp;
+    return &rust_stack_guard;
+}
+
+void
+rust_stack_guard_uninstall() {
+    rust_stack_guard.lo = 0;
+    rust_stack_guard.hi = 0;
+    if (rust_stack_guard_altstack == NULL) return;
+
+    stack_t stack;
+    stack.ss_sp = NULL;
+    stack.ss_size = SIGSTKSZ;
+    stack.ss_flags = SS_DISABLE;
+    sigaltstack(&stack, NULL);
+    free(rust_stack_guard_altstack);
+    rust_stack_guard_altstack = NULL;
+}
+
+#endif
+
 #if defined(__WIN32__)
 
 void
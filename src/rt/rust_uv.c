@@ -167,3 +167,4 @@ int
 rust_uv_guess_handle(int fd) {
   return uv_guess_handle(fd);
 }
+
@@ -128,8 +128,11 @@ extern "C" LLVMValueRef LLVMBuildAtomicCmpXchg(LLVMBuilderRef B,
     return wrap(unwrap(B)->CreateAtomicCmpXchg(unwrap(target), unwrap(old),
                                                unwrap(source), order));
 }
-extern "C" LLVMValueRef LLVMBuildAtomicFence(LLVMBuilderRef B, AtomicOrdering order) {
-    return wrap(unwrap(B)->CreateFence(order));
+extern "C" LLVMValueRef LLVMBuildAtomicFence(LLVMBuilderRef B,
+                                             AtomicOrdering order,
+                                             LLVMBool isSingleThreaded) {
+    return wrap(unwrap(B)->CreateFence(order,
+                isSingleThreaded ? SingleThread : CrossThread));
 }
 
 extern "C" void LLVMSetDebug(int Enabled) {
@@ -0,0 +1,71 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// This benchmark has a number of tasks hammer on a single shared counter,
+// comparing the task-aware std::sync::Mutex against extra::arc::MutexArc
+// (which is built on semaphores over channels).
+
+extern mod extra;
+
+use extra::arc::MutexArc;
+use extra::time;
+use std::os;
+use std::sync::Mutex;
+
+fn run(name: &str, num_tasks: uint, f: |SharedChan<()>|) {
+    let (port, chan) = SharedChan::new();
+    let start = time::precise_time_s();
+    for _ in range(0, num_tasks) {
+        f(chan.clone());
+    }
+    for _ in range(0, num_tasks) {
+        port.recv();
+    }
+    let elapsed = time::precise_time_s() - start;
+    println!("{}: {} seconds", name, elapsed);
+}
+
+fn main() {
+    let args = os::args();
+    let args = if os::getenv("RUST_BENCH").is_some() {
+        ~[~"", ~"20", ~"100000"]
+    } else if args.len() <= 1u {
+        ~[~"", ~"4", ~"1000"]
+    } else {
+        args.clone()
+    };
+
+    let num_tasks = from_str::<uint>(args[1]).unwrap();
+    let iters = from_str::<uint>(args[2]).unwrap();
+
+    let m = Mutex::new(0u);
+    run("std::sync::Mutex", num_tasks, |chan| {
+        let m = m.clone();
+        do spawn {
+            for _ in range(0, iters) {
+                *m.lock().get_mut() += 1;
+            }
+            chan.send(());
+        }
+    });
+    assert_eq!(*m.lock().get(), num_tasks * iters);
+
+    let arc = MutexArc::new(0u);
+    run("extra::arc::MutexArc", num_tasks, |chan| {
+        let arc = arc.clone();
+        do spawn {
+            for _ in range(0, iters) {
+                arc.access(|n| *n += 1);
+            }
+            chan.send(());
+        }
+    });
+    arc.access(|n| assert_eq!(*n, num_tasks * iters));
+}
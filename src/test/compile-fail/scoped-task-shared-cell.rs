@@ -0,0 +1,21 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// ensure that tasks in a scope can't share data with interior mutability
+
+use std::cell::Cell;
+use std::task::Scope;
+
+pub fn main() {
+    let cells = [Cell::new(1)];
+    let mut scope = Scope::new();
+    scope.spawn(cells.as_slice(), proc(cells) cells[0].set(2));
+    //~^ ERROR: incompatible type `std::cell::Cell<int>`, which does not fulfill `Freeze`
+}
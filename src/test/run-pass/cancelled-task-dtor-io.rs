@@ -0,0 +1,56 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// xfail-win32
+
+// Tests that the destructors of a cancelled task can still do I/O and receive
+// on ports while it unwinds, instead of failing again and aborting
+
+use std::any::AnyRefExt;
+use std::io::PipeStream;
+use std::os;
+use std::task;
+use std::task::{CancelReason, Requested};
+
+struct Flush {
+    pipe: PipeStream,
+    ack: Port<int>,
+}
+
+impl Drop for Flush {
+    fn drop(&mut self) {
+        assert!(task::failing());
+        assert!(!task::cancelled());
+        self.pipe.write(bytes!("flushed"));
+        assert_eq!(self.ack.recv(), 1);
+    }
+}
+
+fn main() {
+    let fds = os::pipe();
+    let mut reader = PipeStream::open(fds.input).unwrap();
+    let writer = PipeStream::open(fds.out).unwrap();
+
+    let (ack_port, ack_chan) = Chan::new();
+    ack_chan.send(1);
+    let (started_port, started_chan) = Chan::new();
+    let (never_port, _never_chan) = Chan::<()>::new();
+
+    let child = do task::task().spawn_cancellable {
+        let _flush = Flush { pipe: writer, ack: ack_port };
+        started_chan.send(());
+        never_port.recv();
+    };
+    started_port.recv();
+
+    let err = child.cancel_and_join().unwrap_err();
+    assert_eq!(err.as_ref::<CancelReason>(), Some(&Requested));
+    assert_eq!(reader.read_to_end(), bytes!("flushed").to_owned());
+}
@@ -0,0 +1,74 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[feature(struct_variant)];
+
+use std::cc::{Cc, collect_cycles};
+use std::cell::RefCell;
+
+#[deriving(Trace)]
+struct Graph {
+    name: ~str,
+    nodes: ~[Cc<Vertex>]
+}
+
+#[deriving(Trace)]
+struct Vertex {
+    id: uint,
+    edges: RefCell<~[Cc<Vertex>]>,
+    label: Label
+}
+
+#[deriving(Trace)]
+enum Label {
+    Plain,
+    Tagged(~str, Option<Cc<Vertex>>),
+    Pair { first: Cc<Vertex>, second: Cc<Vertex> }
+}
+
+#[deriving(Trace)]
+struct Wrapper(Cc<Vertex>);
+
+fn vertex(id: uint, label: Label) -> Cc<Vertex> {
+    Cc::new(Vertex { id: id, edges: RefCell::new(~[]), label: label })
+}
+
+fn link(from: &Cc<Vertex>, to: &Cc<Vertex>) {
+    from.borrow().edges.with_mut(|edges| edges.push(to.clone()));
+}
+
+pub fn main() {
+    // A ring of vertices owned by a graph
+    let a = vertex(0, Plain);
+    let b = vertex(1, Plain);
+    let c = vertex(2, Plain);
+    link(&a, &b);
+    link(&b, &c);
+    link(&c, &a);
+    let graph = Graph { name: ~"ring", nodes: ~[a, b, c] };
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(collect_cycles(), 0);
+    drop(graph);
+    assert_eq!(collect_cycles(), 3);
+
+    // Cycles passing through enum variants and tuple structs
+    let d = vertex(3, Plain);
+    let e = vertex(4, Tagged(~"back", Some(d.clone())));
+    let f = vertex(5, Pair { first: d.clone(), second: e.clone() });
+    link(&d, &f);
+    let w = Cc::new(Wrapper(d.clone()));
+    assert_eq!(f.borrow().id, 5);
+    drop(d);
+    drop(e);
+    drop(f);
+    assert_eq!(collect_cycles(), 0);
+    drop(w);
+    assert_eq!(collect_cycles(), 3);
+}
@@ -0,0 +1,68 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Tests that the process-wide failure hook sees the cause, location and name
+// of failing tasks
+
+use std::any::AnyRefExt;
+use std::rt::unwind::{FailureInfo, set_failure_hook, take_failure_hook};
+use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+use std::task;
+
+static mut CALLS: AtomicUint = INIT_ATOMIC_UINT;
+static mut NAME_CHECKS: AtomicUint = INIT_ATOMIC_UINT;
+
+// Checks that the task still has its name while it unwinds
+struct CheckName;
+
+impl Drop for CheckName {
+    fn drop(&mut self) {
+        task::with_task_name(|name| assert_eq!(name, Some("failing hook")));
+        unsafe { NAME_CHECKS.fetch_add(1, SeqCst); }
+    }
+}
+
+fn hook(info: &FailureInfo) {
+    assert_eq!(info.task_name, Some("hooked"));
+    assert_eq!(info.cause.as_ref::<&'static str>(), Some(&"boom"));
+    assert!(info.file.ends_with("failure-hook.rs"));
+    assert!(info.line > 0);
+    unsafe { CALLS.fetch_add(1, SeqCst); }
+}
+
+fn hook_which_fails(_info: &FailureInfo) {
+    fail!("failure in the hook");
+}
+
+fn main() {
+    assert!(set_failure_hook(hook).is_none());
+
+    let mut t = task::task();
+    t.name("hooked");
+    assert!(t.try(proc() { fail!("boom") }).is_err());
+    unsafe { assert_eq!(CALLS.load(SeqCst), 1); }
+
+    // A failing hook falls back to the default reporting, and the task goes on
+    // to fail with its original cause
+    assert!(set_failure_hook(hook_which_fails).is_some());
+    let mut t = task::task();
+    t.name("failing hook");
+    let res = t.try(proc() {
+        let _check = CheckName;
+        fail!("boom")
+    });
+    assert_eq!(res.unwrap_err().as_ref::<&'static str>(), Some(&"boom"));
+    unsafe { assert_eq!(NAME_CHECKS.load(SeqCst), 1); }
+
+    assert!(take_failure_hook().is_some());
+    assert!(take_failure_hook().is_none());
+    assert!(task::try(proc() { fail!("boom") }).is_err());
+    unsafe { assert_eq!(CALLS.load(SeqCst), 1); }
+}
@@ -0,0 +1,62 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Tests that the allocator designated by the `global_allocator` lang item
+// serves the allocations of the program
+
+// xfail-fast
+
+use std::rt::global_heap::{Allocator, SystemAllocator};
+use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+
+static mut ALLOCS: AtomicUint = INIT_ATOMIC_UINT;
+static mut FREES: AtomicUint = INIT_ATOMIC_UINT;
+
+struct Counting;
+
+impl Allocator for Counting {
+    unsafe fn alloc(&self, size: uint) -> *mut u8 {
+        ALLOCS.fetch_add(1, SeqCst);
+        SystemAllocator.alloc(size)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, size: uint) -> *mut u8 {
+        SystemAllocator.realloc(ptr, size)
+    }
+    unsafe fn free(&self, ptr: *mut u8) {
+        FREES.fetch_add(1, SeqCst);
+        SystemAllocator.free(ptr)
+    }
+    unsafe fn usable_size(&self, ptr: *mut u8) -> uint {
+        SystemAllocator.usable_size(ptr)
+    }
+}
+
+static COUNTING: Counting = Counting;
+
+#[lang = "global_allocator"]
+pub fn counting() -> &'static Allocator {
+    &COUNTING as &'static Allocator
+}
+
+fn main() {
+    let (allocs, frees) = unsafe { (ALLOCS.load(SeqCst), FREES.load(SeqCst)) };
+    assert!(allocs > 0);
+
+    let mut v = ~[1];
+    for i in range(0, 100) {
+        v.push(i);
+    }
+    drop(v);
+
+    unsafe {
+        assert!(ALLOCS.load(SeqCst) > allocs);
+        assert!(FREES.load(SeqCst) > frees);
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Tests that a global logger which fails only fails the task which logged the
+// message, and that it is removed so that other tasks keep on logging
+
+// xfail-fast
+// exec-env:RUST_LOG=info
+
+use std::fmt;
+use std::logging::{set_global_logger, take_global_logger, Logger};
+use std::task;
+
+struct FailingLogger;
+
+impl Logger for FailingLogger {
+    fn log(&mut self, _level: u32, _args: &fmt::Arguments) {
+        fail!("the global logger failed");
+    }
+}
+
+fn main() {
+    assert!(set_global_logger(~FailingLogger as ~Logger:Send).is_none());
+
+    assert!(task::try(proc() { info!("first") }).is_err());
+    assert!(task::try(proc() { info!("second") }).is_ok());
+
+    // The failed logger is gone, and a new one can be installed
+    assert!(take_global_logger().is_none());
+    assert!(set_global_logger(~FailingLogger as ~Logger:Send).is_none());
+    assert!(task::try(proc() { info!("third") }).is_err());
+}
@@ -0,0 +1,61 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// xfail-fast
+// exec-env:RUST_HEAP_STATS=1
+
+use std::rt::heap_stats;
+use std::rt::heap_stats::{AllocEvent, Allocated, Freed};
+use std::rt::local_heap;
+use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+
+static mut ALLOCS: AtomicUint = INIT_ATOMIC_UINT;
+static mut FREES: AtomicUint = INIT_ATOMIC_UINT;
+
+fn hook(event: AllocEvent) {
+    unsafe {
+        match event {
+            Allocated(..) => { ALLOCS.fetch_add(1, SeqCst); }
+            Freed(..) => { FREES.fetch_add(1, SeqCst); }
+        }
+    }
+}
+
+fn main() {
+    assert!(heap_stats::enabled());
+
+    let before = heap_stats::stats();
+    let v = ~[0u8, ..1000];
+    let during = heap_stats::stats();
+    assert!(during.total_objects > before.total_objects);
+    assert!(during.total_bytes >= before.total_bytes + 1000);
+    assert!(during.live_bytes >= 1000);
+    let class = heap_stats::size_class(1000);
+    assert!(during.total_by_class[class] > before.total_by_class[class]);
+    drop(v);
+
+    assert!(heap_stats::set_alloc_hook(hook).is_none());
+    let b = ~1;
+    drop(b);
+    assert!(heap_stats::take_alloc_hook().is_some());
+    unsafe {
+        assert!(ALLOCS.load(SeqCst) >= 1);
+        assert!(FREES.load(SeqCst) >= 1);
+    }
+
+    let before = local_heap::stats();
+    let m = @[1, 2, 3];
+    let during = local_heap::stats();
+    assert_eq!(during.live_boxes, before.live_boxes + 1);
+    assert_eq!(during.total_boxes, before.total_boxes + 1);
+    assert!(during.live_bytes > before.live_bytes);
+    drop(m);
+    assert_eq!(local_heap::stats().live_boxes, before.live_boxes);
+}
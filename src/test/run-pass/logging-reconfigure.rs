@@ -0,0 +1,66 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Tests that log levels and message filters can be changed while running
+
+// xfail-fast
+
+use std::comm::Data;
+use std::fmt;
+use std::logging::{set_logger, Logger};
+use std::rt::logging::set_log_spec;
+
+struct ChanLogger(Chan<~str>);
+
+impl Logger for ChanLogger {
+    fn log(&mut self, _level: u32, args: &fmt::Arguments) {
+        let ChanLogger(ref chan) = *self;
+        chan.send(fmt::format(args));
+    }
+}
+
+fn main() {
+    let (port, chan) = Chan::new();
+    set_logger(~ChanLogger(chan) as ~Logger);
+
+    info!("before");
+    assert!(!log_enabled!(std::logging::INFO));
+
+    assert!(set_log_spec("info"));
+    assert!(log_enabled!(std::logging::INFO));
+    info!("enabled");
+    debug!("still disabled");
+
+    assert!(set_log_spec("error"));
+    info!("disabled again");
+    error!("error");
+
+    assert!(set_log_spec("info/keep"));
+    info!("dropped");
+    info!("keep me");
+
+    assert!(set_log_spec("info//^ke+p [0-9]+$/"));
+    info!("keep 1 more");
+    info!("keep 42");
+
+    assert!(set_log_spec(""));
+    info!("back to defaults");
+    error!("last");
+
+    let mut msgs = ~[];
+    loop {
+        match port.try_recv() {
+            Data(msg) => msgs.push(msg),
+            _ => break,
+        }
+    }
+    assert_eq!(msgs, ~[~"enabled", ~"error", ~"keep me", ~"keep 42",
+                      ~"last"]);
+}
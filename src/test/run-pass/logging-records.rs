@@ -0,0 +1,58 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Tests that the global logger receives the metadata and fields of messages
+// logged by tasks without a logger of their own
+
+// xfail-fast
+// exec-env:RUST_LOG=info
+
+use std::fmt;
+use std::logging::{set_global_logger, take_global_logger, Logger, LogRecord};
+
+struct ChanLogger(Chan<(u32, ~str, uint, ~str, ~[(~str, ~str)])>);
+
+impl Logger for ChanLogger {
+    fn log(&mut self, _level: u32, _args: &fmt::Arguments) {
+        fail!("the record should have been logged with `log_record`");
+    }
+
+    fn log_record(&mut self, record: &LogRecord) {
+        let ChanLogger(ref chan) = *self;
+        let fields = record.fields.map(|&(k, ref v)| (k.to_owned(), v.clone()));
+        chan.send((record.level, record.module_path.to_owned(), record.line,
+                   fmt::format(record.args), fields));
+    }
+}
+
+fn main() {
+    let (port, chan) = Chan::new();
+    assert!(set_global_logger(~ChanLogger(chan) as ~Logger:Send).is_none());
+
+    do spawn {
+        info!({"user" => "alice", "attempt" => 3}, "login {}", "failed");
+        debug!("not logged");
+        error!("oops");
+    }
+
+    let (level, module, line, msg, fields) = port.recv();
+    assert_eq!(level, 3);
+    assert!(module.len() > 0);
+    assert!(line > 0);
+    assert_eq!(msg, ~"login failed");
+    assert_eq!(fields, ~[(~"user", ~"alice"), (~"attempt", ~"3")]);
+
+    let (level, _, _, msg, fields) = port.recv();
+    assert_eq!(level, 1);
+    assert_eq!(msg, ~"oops");
+    assert!(fields.is_empty());
+
+    assert!(take_global_logger().is_some());
+}